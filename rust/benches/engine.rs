@@ -0,0 +1,87 @@
+/*
+Criterion benchmarks of the engine's hot paths
+
+at_issue_projection  - one projection to maturity at a fixed premium, monthly and annual steps
+solve_for_premium    - the full premium solve, by root finding method
+lockstep             - the premium solves of LANES policies one at a time and together in lockstep
+get_rates            - assembling a cell's rates from the csv tables, and from the tables parsed once
+load_tables          - parsing every table into memory, RateTables::load, which the coi file dominates
+
+Rates are read from ./data so run from the directory above src.
+
+Run: cargo bench --bench engine
+Compare against a saved run: cargo bench --bench engine -- --save-baseline before, then after a change
+cargo bench --bench engine -- --baseline before
+
+*/
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use illus1::{
+    age::IssueAge, at_issue_projection, at_issue_projection_with, get_rates, lanes::{self, Lanes, LANES}, rates::DataDir, solve_for_premium, solve_for_premium_using, solver::{Method, SolverOptions}, ProjectionConfig, RateTables, SolveTarget, Timestep
+};
+
+fn projection(c: &mut Criterion) {
+    let issue_age = IssueAge::new(35).unwrap();
+    let rates = get_rates("M", "NS", issue_age).unwrap();
+    let mut group = c.benchmark_group("at_issue_projection");
+    group.bench_function("monthly", |b| b.iter(|| at_issue_projection(black_box(&rates), issue_age, 100000.0, black_box(1255.03)).unwrap()));
+    let annual = ProjectionConfig { timestep: Timestep::Annual, ..Default::default() };
+    group.bench_function("annual", |b| b.iter(|| at_issue_projection_with(black_box(&rates), issue_age, 100000.0, black_box(1255.03), &annual).unwrap()));
+    group.finish();
+}
+
+fn solve(c: &mut Criterion) {
+    let issue_age = IssueAge::new(35).unwrap();
+    let rates = get_rates("M", "NS", issue_age).unwrap();
+    let config = ProjectionConfig::default();
+    let mut group = c.benchmark_group("solve_for_premium");
+    group.bench_function("default", |b| b.iter(|| solve_for_premium(black_box(&rates), issue_age, 100000.0).unwrap().premium));
+    for method in [Method::Bisection, Method::Brent, Method::Secant] {
+        let options = SolverOptions::new(method, 0.005);
+        group.bench_with_input(BenchmarkId::new("method", format!("{:?}", method)), &options, |b, options| {
+            b.iter(|| solve_for_premium_using(black_box(&rates), issue_age, 100000.0, &config, SolveTarget::EndowAtMaturity, options).unwrap().premium)
+        });
+    }
+    group.finish();
+}
+
+fn lockstep(c: &mut Criterion) {
+    let issue_age = IssueAge::new(35).unwrap();
+    let rates = get_rates("M", "NS", issue_age).unwrap();
+    let config = ProjectionConfig::default();
+    let options = SolverOptions::new(Method::Bisection, 0.005);
+    let faces: Vec<f64> = (0..LANES).map(|l| 100000.0 + 25000.0 * l as f64).collect();
+    let all_rates = vec![&rates; LANES];
+    let mut group = c.benchmark_group("lockstep");
+    group.bench_function("one_at_a_time", |b| b.iter(|| {
+        faces.iter().map(|face| solve_for_premium_using(black_box(&rates), issue_age, *face, &config, SolveTarget::EndowAtMaturity, &options).unwrap().premium).sum::<f64>()
+    }));
+    group.bench_function("lanes", |b| b.iter(|| {
+        let lanes = Lanes::new(black_box(&all_rates), &faces, issue_age, &config).unwrap();
+        lanes::solve_for_premiums(&lanes, &options).unwrap().into_iter().map(|solve| solve.unwrap().premium).sum::<f64>()
+    }));
+    group.finish();
+}
+
+fn rates(c: &mut Criterion) {
+    let issue_age = IssueAge::new(35).unwrap();
+    let mut group = c.benchmark_group("get_rates");
+    // every table is read and parsed on each call
+    group.sample_size(20);
+    group.bench_function("csv", |b| b.iter(|| get_rates(black_box("M"), black_box("NS"), black_box(issue_age)).unwrap()));
+    let tables = RateTables::load(&DataDir::default()).unwrap();
+    group.bench_function("tables", |b| b.iter(|| tables.get_rates(black_box("M"), black_box("NS"), black_box(issue_age)).unwrap()));
+    group.finish();
+}
+
+fn load_tables(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_tables");
+    group.sample_size(10);
+    group.bench_function("load", |b| b.iter(|| RateTables::load(&DataDir::default()).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, projection, solve, lockstep, rates, load_tables);
+criterion_main!(benches);
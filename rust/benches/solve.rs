@@ -0,0 +1,132 @@
+/*
+Timing of the 1000 policy solve loop from run() in main.rs
+
+Compares the engine as it is now (RateSet borrowed by the projection) to the
+old approach where the rates were a HashMap cloned for every projection and
+looked up by string key every month. The old projection and solver are
+reproduced here since the library no longer has a way to express them.
+
+Also prints how many projections each root finding method takes for the solve.
+
+Run: cargo bench
+
+*/
+use std::{
+    collections::HashMap, hint::black_box, time::{Duration, Instant}
+};
+
+use illus1::{
+    age::{AttainedAge, IssueAge}, get_rates, solve_for_premium, solve_for_premium_using, solver::{Method, SolverOptions}, ProjectionConfig, RateSet, SolveTarget
+};
+
+const POLICIES: usize = 1000;
+
+// rates come back sized from issue to age 121, the old arrays always had 121 entries but only that many were read
+fn to_map(rates: &RateSet) -> HashMap<&'static str, [f64;121]> {
+    let fixed = |rates: &Vec<f64>| -> [f64;121] {
+        let mut padded = [0.0;121];
+        padded[..rates.len()].copy_from_slice(rates);
+        return padded;
+    };
+    let mut map: HashMap<&'static str, [f64;121]> = HashMap::new();
+    map.insert("premium_loads", fixed(&rates.premium_loads));
+    map.insert("policy_fees", fixed(&rates.policy_fees));
+    map.insert("unit_loads", fixed(&rates.unit_loads));
+    map.insert("corr_facts", fixed(&rates.corr_facts));
+    map.insert("naar_discs", fixed(&rates.naar_discs));
+    map.insert("coi_rates", fixed(&rates.coi_rates));
+    map.insert("interest_rates", fixed(&rates.interest_rates));
+    return map;
+}
+
+// projection as it was before RateSet
+fn at_issue_projection_map(rates: HashMap<&'static str, [f64;121]>, issue_age: i8, face_amount: f64, annual_premium: f64) -> f64 {
+    let maturity_age: i8 = 121;
+    let projection_years: i8 = maturity_age - issue_age;
+    let mut end_value = 0.0;
+    let mut policy_year = 0;
+
+    for i in 0..(12 * i32::from(projection_years)) {
+        policy_year += if (i % 12) == 0 {1} else {0};
+        let start_value = end_value;
+        let premium = if (i % 12) == 0 {annual_premium} else {0.0};
+        let premium_load = premium * rates["premium_loads"][policy_year-1];
+        let expense_charge = (rates["policy_fees"][policy_year-1] + rates["unit_loads"][policy_year-1] * face_amount / 1000.0) / 12.0;
+        let av_for_db = start_value + premium - premium_load - expense_charge;
+        let db = face_amount.max(rates["corr_facts"][policy_year-1] * av_for_db);
+        let naar = (db * rates["naar_discs"][policy_year-1] - av_for_db.max(0.0)).max(0.0);
+        let coi = (naar / 1000.0) * (rates["coi_rates"][policy_year-1] / 12.0);
+        let av_for_interest = av_for_db - coi;
+        let interest = (av_for_interest * rates["interest_rates"][policy_year - 1]).max(0.0);
+        end_value = av_for_interest + interest;
+    }
+
+    return end_value;
+}
+
+// solver as it was before RateSet, cloning the map for every projection
+fn solve_for_premium_map(rates: HashMap<&'static str, [f64;121]>, issue_age: i8, face_amount: f64) -> f64 {
+    let mut guess_lo = 0.0;
+    let mut guess_hi = face_amount / 100.0;
+    let mut guess_md = 0.0;
+
+    loop {
+        let end_value = at_issue_projection_map(rates.clone(), issue_age, face_amount, guess_hi);
+        if end_value <= 0.0 {
+            guess_lo = guess_hi;
+            guess_hi *= 2.0;
+        } else {
+            break;
+        }
+    }
+
+    while (guess_hi - guess_lo) > 0.005 {
+        guess_md = (guess_lo + guess_hi) / 2.0;
+        let end_value = at_issue_projection_map(rates.clone(), issue_age, face_amount, guess_md);
+        if end_value <= 0.0 {
+            guess_lo = guess_md;
+        } else {
+            guess_hi = guess_md;
+        }
+    }
+
+    let mut result = (guess_md * 100.0).round() / 100.0;
+    let end_value = at_issue_projection_map(rates.clone(), issue_age, face_amount, result);
+    if end_value <= 0.0 {result += 0.01}
+
+    return result;
+}
+
+fn time<F: FnMut() -> f64>(label: &str, mut f: F) -> Duration {
+    let now = Instant::now();
+    let mut x = 0.0;
+    for _i in 0..POLICIES {
+        x = black_box(f());
+    }
+    let elapsed = now.elapsed();
+    println!("{:<10} premium: {:.2}  elapsed: {:.2?}", label, x, elapsed);
+    return elapsed;
+}
+
+fn main() {
+    // rates loaded once so the timing is the solve and not csv reading
+    let issue_age = IssueAge::new(35).unwrap();
+    let rates = get_rates("M", "NS", issue_age).unwrap();
+    let map = to_map(&rates);
+
+    let cloned = time("hashmap", || solve_for_premium_map(black_box(map.clone()), 35, 100000.0));
+    let borrowed = time("borrowed", || solve_for_premium(black_box(&rates), issue_age, 100000.0).unwrap().premium);
+
+    println!("speedup: {:.2}x", cloned.as_secs_f64() / borrowed.as_secs_f64());
+
+    // projections each root finding method needs for the same solve
+    // end value at maturity jumps as the premium crosses the endowing premium, value at 65 is smooth in the premium
+    let config = ProjectionConfig::default();
+    for target in [SolveTarget::EndowAtMaturity, SolveTarget::CashValueAtAge { age: AttainedAge::of(65), amount: 100000.0 }] {
+        for method in [Method::Bisection, Method::Brent, Method::Secant] {
+            let options = SolverOptions::new(method, 0.005);
+            let solved = solve_for_premium_using(&rates, issue_age, 100000.0, &config, target, &options).unwrap();
+            println!("{:<10} premium: {:.2}  iterations: {}  projections: {}  target: {:?}", format!("{:?}", method), solved.premium, solved.iterations, solved.evaluations, target);
+        }
+    }
+}
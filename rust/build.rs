@@ -0,0 +1,13 @@
+// generates the grpc service from proto/valact.proto when built with the grpc feature, nothing to do otherwise
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    {
+        // the vendored protoc so building does not need one installed, unless PROTOC already points at one
+        if std::env::var_os("PROTOC").is_none() {
+            // build scripts are single threaded here so nothing else is reading the environment
+            unsafe { std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?) };
+        }
+        tonic_prost_build::compile_protos("proto/valact.proto")?;
+    }
+    return Ok(());
+}
@@ -0,0 +1,83 @@
+/*
+C api of the valact engine, built into the illus1 cdylib with the ffi feature
+cargo build --release --lib --features ffi
+gives target/release/libillus1.so (illus1.dll on windows, libillus1.dylib on macos)
+
+Every function returns VALACT_OK or VALACT_ERROR, valact_last_error gives the
+message for the last failure on the calling thread.
+
+    ValactRepository *repo = valact_repository_new(NULL);
+    ValactPolicy policy = {"M", "NS", 35, 100000.0, 1};
+    ValactSolve solve;
+    if (valact_solve_premium(repo, &policy, &solve) != VALACT_OK) {
+        char message[256];
+        valact_last_error(message, sizeof message);
+    }
+    valact_repository_free(repo);
+*/
+#ifndef VALACT_H
+#define VALACT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define VALACT_OK 0
+#define VALACT_ERROR -1
+
+/* rate tables parsed once, share one across calls and threads */
+typedef struct ValactRepository ValactRepository;
+
+typedef struct {
+    const char *gender;
+    const char *risk_class;
+    int32_t issue_age;
+    double face_amount;
+    /* 1 level (or 0), 2 increasing, 3 return of premium */
+    int32_t death_benefit_option;
+} ValactPolicy;
+
+typedef struct {
+    double premium;
+    int32_t iterations;
+    int32_t converged;
+} ValactSolve;
+
+typedef struct {
+    int32_t policy_month;
+    int32_t policy_year;
+    double value_start;
+    double premium;
+    double premium_load;
+    double expense_charge;
+    double death_benefit;
+    double naar;
+    double coi_charge;
+    double interest;
+    double value_end;
+    double cash_surrender_value;
+} ValactMonth;
+
+/* length of the message, copied into buffer truncated to length - 1 bytes and nul terminated */
+size_t valact_last_error(char *buffer, size_t length);
+
+/* data_dir NULL reads ./data, returns NULL on failure
+   each repository keeps its own data_dir, so more than one can be open at once */
+ValactRepository *valact_repository_new(const char *data_dir);
+void valact_repository_free(ValactRepository *repository);
+
+int32_t valact_solve_premium(const ValactRepository *repository, const ValactPolicy *policy, ValactSolve *result);
+int32_t valact_end_value(const ValactRepository *repository, const ValactPolicy *policy, double annual_premium, double *end_value);
+/* count is set to the months in the projection, call with capacity 0 to size months first */
+int32_t valact_project(const ValactRepository *repository, const ValactPolicy *policy, double annual_premium, ValactMonth *months, size_t capacity, size_t *count);
+/* threads 0 uses one per core */
+int32_t valact_solve_batch(const ValactRepository *repository, const ValactPolicy *policies, size_t count, size_t threads, double *premiums);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,237 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ValactError;
+
+// ages and policy years as their own types so an issue age can't be passed where an attained age is wanted
+// and nothing wraps past 127 the way i8 did, each is checked when it is made and then always in range
+// they read and write as plain numbers in csv, json and toml
+
+// oldest age a table or policy can reach, well short of where u8 arithmetic on ages could wrap
+pub const MAX_AGE: u8 = 150;
+
+// last policy year a table or rider can run to
+pub const MAX_POLICY_YEAR: u16 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(try_from = "i64", into = "u8")]
+pub struct IssueAge(u8);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(try_from = "i64", into = "u8")]
+pub struct AttainedAge(u8);
+
+// 1 is the first year
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(try_from = "i64", into = "u16")]
+pub struct PolicyYear(u16);
+
+impl IssueAge {
+    pub fn new(age: u8) -> Result<IssueAge, ValactError> {
+        if age > MAX_AGE {
+            return Err(format!("issue age {} is over {}", age, MAX_AGE).into());
+        }
+        return Ok(IssueAge(age));
+    }
+
+    pub fn get(self) -> u8 {
+        return self.0;
+    }
+
+    // attained age in a policy year
+    pub fn attained_age(self, policy_year: PolicyYear) -> Result<AttainedAge, ValactError> {
+        let age = u16::from(self.0) + policy_year.0 - 1;
+        return u8::try_from(age).map_err(|_| ValactError::from(format!("attained age {} is over {}", age, MAX_AGE))).and_then(AttainedAge::new);
+    }
+
+    // the same age as an attained age, for comparing with maturity and table ages
+    pub fn at_issue(self) -> AttainedAge {
+        return AttainedAge(self.0);
+    }
+}
+
+impl AttainedAge {
+    pub fn new(age: u8) -> Result<AttainedAge, ValactError> {
+        if age > MAX_AGE {
+            return Err(format!("attained age {} is over {}", age, MAX_AGE).into());
+        }
+        return Ok(AttainedAge(age));
+    }
+
+    // for ages fixed in the code, out of range is a compile error when used for a const
+    pub const fn of(age: u8) -> AttainedAge {
+        assert!(age <= MAX_AGE, "attained age is out of range");
+        return AttainedAge(age);
+    }
+
+    pub fn get(self) -> u8 {
+        return self.0;
+    }
+
+    // the issue age of cover starting at this age, as when a test restarts part way through a policy
+    pub fn as_issue_age(self) -> IssueAge {
+        return IssueAge(self.0);
+    }
+
+    // whole years from issue to this age, None when it is before issue
+    pub fn years_since(self, issue_age: IssueAge) -> Option<usize> {
+        return self.0.checked_sub(issue_age.0).map(usize::from);
+    }
+}
+
+impl PolicyYear {
+    pub const FIRST: PolicyYear = PolicyYear(1);
+
+    pub fn new(year: u16) -> Result<PolicyYear, ValactError> {
+        if !(1..=MAX_POLICY_YEAR).contains(&year) {
+            return Err(format!("policy year {} must be from 1 to {}", year, MAX_POLICY_YEAR).into());
+        }
+        return Ok(PolicyYear(year));
+    }
+
+    // the year a 0 based index into rates by policy year is for
+    pub fn from_index(index: usize) -> Result<PolicyYear, ValactError> {
+        let year = u16::try_from(index + 1).map_err(|_| format!("policy year {} is over {}", index + 1, MAX_POLICY_YEAR))?;
+        return PolicyYear::new(year);
+    }
+
+    pub fn get(self) -> u16 {
+        return self.0;
+    }
+
+    // index into rates by policy year
+    pub fn index(self) -> usize {
+        return usize::from(self.0 - 1);
+    }
+}
+
+impl TryFrom<i64> for IssueAge {
+    type Error = ValactError;
+
+    fn try_from(age: i64) -> Result<IssueAge, ValactError> {
+        return IssueAge::new(u8::try_from(age).map_err(|_| format!("issue age {} is out of range", age))?);
+    }
+}
+
+impl TryFrom<i64> for AttainedAge {
+    type Error = ValactError;
+
+    fn try_from(age: i64) -> Result<AttainedAge, ValactError> {
+        return AttainedAge::new(u8::try_from(age).map_err(|_| format!("attained age {} is out of range", age))?);
+    }
+}
+
+impl TryFrom<i64> for PolicyYear {
+    type Error = ValactError;
+
+    fn try_from(year: i64) -> Result<PolicyYear, ValactError> {
+        return PolicyYear::new(u16::try_from(year).map_err(|_| format!("policy year {} is out of range", year))?);
+    }
+}
+
+impl From<IssueAge> for u8 {
+    fn from(age: IssueAge) -> u8 {
+        return age.0;
+    }
+}
+
+impl From<AttainedAge> for u8 {
+    fn from(age: AttainedAge) -> u8 {
+        return age.0;
+    }
+}
+
+impl From<PolicyYear> for u16 {
+    fn from(year: PolicyYear) -> u16 {
+        return year.0;
+    }
+}
+
+// parsed from the command line and config values as plain numbers
+impl FromStr for IssueAge {
+    type Err = ValactError;
+
+    fn from_str(s: &str) -> Result<IssueAge, ValactError> {
+        return IssueAge::try_from(s.trim().parse::<i64>()?);
+    }
+}
+
+impl FromStr for AttainedAge {
+    type Err = ValactError;
+
+    fn from_str(s: &str) -> Result<AttainedAge, ValactError> {
+        return AttainedAge::try_from(s.trim().parse::<i64>()?);
+    }
+}
+
+impl FromStr for PolicyYear {
+    type Err = ValactError;
+
+    fn from_str(s: &str) -> Result<PolicyYear, ValactError> {
+        return PolicyYear::try_from(s.trim().parse::<i64>()?);
+    }
+}
+
+impl fmt::Display for IssueAge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.0);
+    }
+}
+
+impl fmt::Display for AttainedAge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.0);
+    }
+}
+
+impl fmt::Display for PolicyYear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.0);
+    }
+}
+
+// how insurance age is determined from date of birth
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AgeBasis {
+    // age at the nearest birthday, what the coi tables in ./data are on
+    #[default]
+    #[serde(alias="ANB")]
+    NearestBirthday,
+    // age at the last birthday
+    #[serde(alias="ALB")]
+    LastBirthday,
+}
+
+// birthday in a given year, Feb 29 birthdays fall on Feb 28 in other years
+fn birthday_in(dob: NaiveDate, year: i32) -> NaiveDate {
+    return NaiveDate::from_ymd_opt(year, dob.month(), dob.day())
+        .or_else(|| NaiveDate::from_ymd_opt(year, 2, 28))
+        .unwrap();
+}
+
+pub fn age_last_birthday(dob: NaiveDate, as_of: NaiveDate) -> i32 {
+    let mut age = as_of.year() - dob.year();
+    if birthday_in(dob, as_of.year()) > as_of {
+        age -= 1;
+    }
+    return age;
+}
+
+// insurance age on as_of (usually the issue date), exactly half way between birthdays rounds up on ANB
+pub fn insurance_age(dob: NaiveDate, as_of: NaiveDate, basis: AgeBasis) -> Result<IssueAge, ValactError> {
+    if as_of < dob {
+        return Err(format!("date {} is before date of birth {}", as_of, dob).into());
+    }
+    let alb = age_last_birthday(dob, as_of);
+    let age = match basis {
+        AgeBasis::LastBirthday => alb,
+        AgeBasis::NearestBirthday => {
+            let last = birthday_in(dob, dob.year() + alb);
+            let next = birthday_in(dob, dob.year() + alb + 1);
+            if (as_of - last) >= (next - as_of) {alb + 1} else {alb}
+        }
+    };
+    return IssueAge::try_from(i64::from(age)).map_err(|_| format!("insurance age {} is out of range", age).into());
+}
@@ -0,0 +1,169 @@
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    age::IssueAge, error::ValactError, projection::Timestep, rates::{read_py, read_py_carried}, schedule::{scheduled_in_year, withdrawals_in_year, ScheduledAmount, Withdrawal}
+};
+
+// deferred fixed annuity accumulation from issue to the annuitization age
+// premiums and withdrawals are at the start of each contract year, with withdrawals up to the free percent of the
+// value at the start of the year free of surrender charge and the charge taken from the value on anything above it
+// interest is credited each step at the declared rate for the contract year, never below the minimum guaranteed rate
+// the cash surrender value is the value less the charge on a full surrender, and the annuitization value is the
+// full value with no charge, what would be applied to buy income at the annuitization age
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnnuityProduct {
+    // fraction of the amount surrendered above the free amount, by contract year, nil past the end
+    pub surrender_charges: Vec<f64>,
+    // fraction of the value at the start of each contract year that can be withdrawn without charge
+    pub free_withdrawal_percent: f64,
+    // declared rates by contract year, annual effective, years past the end stay at the last rate
+    pub credited_rates: Vec<f64>,
+    pub minimum_rate: f64,
+    pub annuitization_age: i32,
+    pub timestep: Timestep,
+}
+
+impl Default for AnnuityProduct {
+    fn default() -> Self {
+        return AnnuityProduct {
+            surrender_charges: vec![0.07, 0.06, 0.05, 0.04, 0.03, 0.02, 0.01],
+            free_withdrawal_percent: 0.10,
+            credited_rates: vec![0.03],
+            minimum_rate: 0.01,
+            annuitization_age: 95,
+            timestep: Timestep::Monthly,
+        };
+    }
+}
+
+impl AnnuityProduct {
+    pub fn surrender_charge_rate(&self, contract_year: i32) -> f64 {
+        return self.surrender_charges.get((contract_year - 1) as usize).copied().unwrap_or(0.0);
+    }
+
+    pub fn credited_rate(&self, contract_year: i32) -> f64 {
+        let declared = match self.credited_rates.get((contract_year - 1) as usize) {
+            Some(rate) => *rate,
+            None => self.credited_rates.last().copied().unwrap_or(0.0),
+        };
+        return declared.max(self.minimum_rate);
+    }
+}
+
+// surrender charge rates by contract year, Policy_Year and Rate columns
+pub fn read_surrender_schedule(path: &str, years: usize) -> Result<Vec<f64>, ValactError> {
+    return read_py(path, 0.0, years);
+}
+
+// declared rates by contract year, Policy_Year and Rate columns
+pub fn read_credited_rates(path: &str, years: usize) -> Result<Vec<f64>, ValactError> {
+    return read_py_carried(path, years);
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct AnnuityMonth {
+    #[serde(rename="Contract_Month")]
+    pub contract_month: i32,
+    #[serde(rename="Contract_Year")]
+    pub contract_year: i32,
+    #[serde(rename="Attained_Age")]
+    pub attained_age: i32,
+    #[serde(rename="Value_Start")]
+    pub value_start: f64,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Withdrawal")]
+    pub withdrawal: f64,
+    // part of the withdrawal within the free amount
+    #[serde(rename="Free_Withdrawal")]
+    pub free_withdrawal: f64,
+    // charged on the part of the withdrawal above the free amount
+    #[serde(rename="Withdrawal_Charge")]
+    pub withdrawal_charge: f64,
+    #[serde(rename="Credited_Rate")]
+    pub credited_rate: f64,
+    #[serde(rename="Interest")]
+    pub interest: f64,
+    #[serde(rename="Value_End")]
+    pub value_end: f64,
+    #[serde(rename="Surrender_Charge")]
+    pub surrender_charge: f64,
+    #[serde(rename="Cash_Surrender_Value")]
+    pub cash_surrender_value: f64,
+    #[serde(rename="Death_Benefit")]
+    pub death_benefit: f64,
+    #[serde(rename="Annuitization_Value")]
+    pub annuitization_value: f64,
+}
+
+pub fn project_annuity(issue_age: IssueAge, product: &AnnuityProduct, premiums: &[ScheduledAmount], withdrawals: &[Withdrawal]) -> Result<Vec<AnnuityMonth>, ValactError> {
+    let years = product.annuitization_age - i32::from(issue_age.get());
+    if years < 1 {
+        return Err(format!("annuitization age {} must be after the issue age {}", product.annuitization_age, issue_age).into());
+    }
+    let steps_per_year = product.timestep.steps_per_year();
+    let months_per_step = 12 / steps_per_year;
+    let mut months: Vec<AnnuityMonth> = Vec::with_capacity((years * steps_per_year) as usize);
+    let mut value = 0.0;
+    // free amount still available in the contract year
+    let mut free_left = 0.0;
+
+    for i in 0..years * steps_per_year {
+        let contract_year = i / steps_per_year + 1;
+        let year_start = i % steps_per_year == 0;
+        let value_start = value;
+        let sc_rate = product.surrender_charge_rate(contract_year);
+
+        let (premium, withdrawal, free_withdrawal, withdrawal_charge) = if year_start {
+            let premium = scheduled_in_year(premiums, contract_year);
+            free_left = product.free_withdrawal_percent * value_start;
+            let available = value_start + premium;
+            // the charge comes out of the value too, so the most that can be withdrawn leaves room for it
+            let requested = withdrawals_in_year(withdrawals, contract_year, value_start);
+            let most = free_left.min(available) + (available - free_left).max(0.0) / (1.0 + sc_rate);
+            let withdrawal = requested.min(most);
+            let free_withdrawal = withdrawal.min(free_left);
+            free_left -= free_withdrawal;
+            (premium, withdrawal, free_withdrawal, sc_rate * (withdrawal - free_withdrawal))
+        } else {
+            (0.0, 0.0, 0.0, 0.0)
+        };
+
+        let credited_rate = product.credited_rate(contract_year);
+        let base = (value_start + premium - withdrawal - withdrawal_charge).max(0.0);
+        let interest = base * ((1.0 + credited_rate).powf(1.0 / f64::from(steps_per_year)) - 1.0);
+        value = base + interest;
+        let surrender_charge = sc_rate * (value - free_left).max(0.0);
+
+        months.push(AnnuityMonth {
+            contract_month: i * months_per_step + 1,
+            contract_year,
+            attained_age: i32::from(issue_age.get()) + contract_year - 1,
+            value_start,
+            premium,
+            withdrawal,
+            free_withdrawal,
+            withdrawal_charge,
+            credited_rate,
+            interest,
+            value_end: value,
+            surrender_charge,
+            cash_surrender_value: value - surrender_charge,
+            death_benefit: value,
+            annuitization_value: value,
+        });
+    }
+    return Ok(months);
+}
+
+pub fn write_annuity_csv(path: &str, months: &[AnnuityMonth]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for month in months {
+        wtr.serialize(month)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
@@ -0,0 +1,99 @@
+use std::{
+    fs::File, sync::Arc
+};
+
+use arrow_array::{ArrayRef, Float64Array, Int8Array, RecordBatch, StringArray, UInt64Array, UInt8Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+
+use crate::{batch::PolicyResult, error::ValactError};
+
+// batch results as arrow record batches, for going straight into a dataframe (polars, pandas through pyarrow,
+// duckdb) rather than through a csv file
+// a .arrow results file is the arrow ipc file format, which polars reads with pl.read_ipc and pyarrow with
+// pyarrow.ipc.open_file, the columns and types are those of the parquet file
+
+// rows per record batch, results are converted and written this many at a time
+pub const DEFAULT_BATCH_SIZE: usize = 8192;
+
+// same columns as the csv results file, Census_Row is null but on streamed results
+pub fn results_schema() -> SchemaRef {
+    return Arc::new(Schema::new(vec![
+        Field::new("Gender", DataType::Utf8, false),
+        Field::new("Risk_Class", DataType::Utf8, false),
+        Field::new("Issue_Age", DataType::UInt8, false),
+        Field::new("Face_Amount", DataType::Float64, false),
+        Field::new("Death_Benefit_Option", DataType::Int8, false),
+        Field::new("Premium", DataType::Float64, false),
+        Field::new("Census_Row", DataType::UInt64, true),
+    ]));
+}
+
+pub fn to_record_batch(schema: SchemaRef, results: &[PolicyResult]) -> Result<RecordBatch, ValactError> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(results.iter().map(|r| r.gender.as_str()))),
+        Arc::new(StringArray::from_iter_values(results.iter().map(|r| r.risk_class.as_str()))),
+        Arc::new(UInt8Array::from_iter_values(results.iter().map(|r| r.issue_age.get()))),
+        Arc::new(Float64Array::from_iter_values(results.iter().map(|r| r.face_amount))),
+        Arc::new(Int8Array::from_iter_values(results.iter().map(|r| r.death_benefit_option.number()))),
+        Arc::new(Float64Array::from_iter_values(results.iter().map(|r| r.premium))),
+        Arc::new(UInt64Array::from_iter(results.iter().map(|r| r.census_row.map(|row| row as u64)))),
+    ];
+    return Ok(RecordBatch::try_new(schema, columns)?);
+}
+
+// the results batch_size rows to a record batch, at least one batch so an empty run still has the schema
+pub fn to_record_batches(results: &[PolicyResult], batch_size: usize) -> Result<Vec<RecordBatch>, ValactError> {
+    let schema = results_schema();
+    if results.is_empty() {
+        return Ok(vec![RecordBatch::new_empty(schema)]);
+    }
+    return results.chunks(batch_size.max(1)).map(|chunk| to_record_batch(schema.clone(), chunk)).collect();
+}
+
+// writes batch results to an arrow ipc file one record batch at a time
+pub struct IpcResultsWriter {
+    schema: SchemaRef,
+    writer: FileWriter<File>,
+}
+
+impl IpcResultsWriter {
+    pub fn create(path: &str) -> Result<IpcResultsWriter, ValactError> {
+        let schema = results_schema();
+        let file = File::create(path).map_err(|source| ValactError::File { path: path.to_string(), source })?;
+        let writer = FileWriter::try_new(file, &schema)?;
+        return Ok(IpcResultsWriter { schema, writer });
+    }
+
+    pub fn write(&mut self, results: &[PolicyResult]) -> Result<(), ValactError> {
+        let batch = to_record_batch(self.schema.clone(), results)?;
+        self.writer.write(&batch)?;
+        return Ok(());
+    }
+
+    // writes the ipc footer, the file is not readable until this is called
+    pub fn finish(mut self) -> Result<(), ValactError> {
+        self.writer.finish()?;
+        return Ok(());
+    }
+}
+
+pub fn write_results_ipc(path: &str, results: &[PolicyResult], batch_size: usize) -> Result<(), ValactError> {
+    let mut writer = IpcResultsWriter::create(path)?;
+    for chunk in results.chunks(batch_size.max(1)) {
+        writer.write(chunk)?;
+    }
+    writer.finish()?;
+    return Ok(());
+}
+
+// the results as the bytes of an arrow ipc file, e.g. to hand across to python without touching disk
+pub fn to_ipc_bytes(results: &[PolicyResult], batch_size: usize) -> Result<Vec<u8>, ValactError> {
+    let schema = results_schema();
+    let mut writer = FileWriter::try_new(Vec::new(), &schema)?;
+    for batch in to_record_batches(results, batch_size)? {
+        writer.write(&batch)?;
+    }
+    writer.finish()?;
+    return Ok(writer.into_inner()?);
+}
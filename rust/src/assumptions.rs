@@ -0,0 +1,277 @@
+use std::{
+    collections::BTreeMap, fs::File, io::BufReader, path::Path
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::ValactError, rates::DataDir};
+
+// company assumptions for pricing cash flows and profit testing, expenses, commissions, taxes, earned rates,
+// mortality improvement and the statutory valuation basis, as opposed to the charges the policy makes which are in the rate tables
+// read from a json file laid out like the structs below, anything left out takes its default and a misspelt
+// field is an error rather than silently ignored
+
+// costs the company incurs, none of them reach the account value
+// per policy amounts are per policy issued or in force, percent of premium is a fraction of each premium collected
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExpenseAssumptions {
+    // at issue, per policy and per 1000 of face
+    pub acquisition_per_policy: f64,
+    pub acquisition_per_1000: f64,
+    // per policy in force each year, increasing by maintenance_inflation a year from the second policy year
+    pub maintenance_per_policy: f64,
+    pub maintenance_inflation: f64,
+    pub first_year_percent_of_premium: f64,
+    pub renewal_percent_of_premium: f64,
+}
+
+impl Default for ExpenseAssumptions {
+    fn default() -> Self {
+        return ExpenseAssumptions {
+            acquisition_per_policy: 400.0,
+            acquisition_per_1000: 1.0,
+            maintenance_per_policy: 75.0,
+            maintenance_inflation: 0.025,
+            first_year_percent_of_premium: 0.05,
+            renewal_percent_of_premium: 0.02,
+        };
+    }
+}
+
+impl ExpenseAssumptions {
+    pub fn acquisition(&self, face_amount: f64) -> f64 {
+        return self.acquisition_per_policy + self.acquisition_per_1000 * face_amount / 1000.0;
+    }
+
+    // for a whole policy year
+    pub fn maintenance(&self, policy_year: i32) -> f64 {
+        return self.maintenance_per_policy * (1.0 + self.maintenance_inflation).powi(policy_year - 1);
+    }
+
+    pub fn percent_of_premium(&self, policy_year: i32) -> f64 {
+        return if policy_year == 1 {self.first_year_percent_of_premium} else {self.renewal_percent_of_premium};
+    }
+}
+
+// heaped commission schedule, rates are fractions of premium
+// premium in a policy year up to the target premium is paid at the target rate and the rest at the excess rate,
+// renewal rates apply from policy year 2 to renewal_years + 1 and nothing is paid after that
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommissionSchedule {
+    pub first_year_target_rate: f64,
+    pub first_year_excess_rate: f64,
+    pub renewal_target_rate: f64,
+    pub renewal_excess_rate: f64,
+    pub renewal_years: i32,
+    // in place of the target_premium table
+    pub target_premium_per_1000: Option<f64>,
+}
+
+impl Default for CommissionSchedule {
+    fn default() -> Self {
+        return CommissionSchedule {
+            first_year_target_rate: 0.9,
+            first_year_excess_rate: 0.03,
+            renewal_target_rate: 0.03,
+            renewal_excess_rate: 0.02,
+            renewal_years: 9,
+            target_premium_per_1000: None,
+        };
+    }
+}
+
+impl CommissionSchedule {
+    // (target rate, excess rate) for a policy year
+    pub fn rates(&self, policy_year: i32) -> (f64, f64) {
+        if policy_year == 1 {
+            return (self.first_year_target_rate, self.first_year_excess_rate);
+        }
+        if policy_year <= self.renewal_years + 1 {
+            return (self.renewal_target_rate, self.renewal_excess_rate);
+        }
+        return (0.0, 0.0);
+    }
+
+    // commission on a premium given what was already paid earlier in the policy year
+    pub fn commission(&self, policy_year: i32, premium: f64, paid_in_year: f64, target_premium: f64) -> f64 {
+        let (target_rate, excess_rate) = self.rates(policy_year);
+        let to_target = premium.min((target_premium - paid_in_year).max(0.0));
+        return to_target * target_rate + (premium - to_target) * excess_rate;
+    }
+}
+
+// taxes on premiums collected
+// premium tax is at the rate for the state the policy is issued in, or the default rate for a state not listed
+// the dac tax proxy follows IRC 848, a share of premium is capitalized and amortized straight line over
+// dac_amortization_years with half a year's amortization in the first and last years, and the cash flow is
+// the corporate tax on the capitalized amount less the amortization, so it turns into a saving in later years
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaxAssumptions {
+    pub state: Option<String>,
+    pub premium_tax_rates: BTreeMap<String, f64>,
+    pub default_premium_tax_rate: f64,
+    pub dac_rate: f64,
+    pub dac_amortization_years: i32,
+    pub corporate_tax_rate: f64,
+}
+
+impl Default for TaxAssumptions {
+    fn default() -> Self {
+        return TaxAssumptions {
+            state: None,
+            premium_tax_rates: BTreeMap::new(),
+            default_premium_tax_rate: 0.02,
+            dac_rate: 0.092,
+            dac_amortization_years: 15,
+            corporate_tax_rate: 0.21,
+        };
+    }
+}
+
+impl TaxAssumptions {
+    pub fn premium_tax_rate(&self) -> f64 {
+        return self.state.as_ref().and_then(|state| self.premium_tax_rates.get(state)).copied().unwrap_or(self.default_premium_tax_rate);
+    }
+
+    // fraction of an amount capitalized in capitalized_year that is amortized in policy_year
+    pub fn dac_amortization(&self, capitalized_year: i32, policy_year: i32) -> f64 {
+        let years = f64::from(self.dac_amortization_years);
+        let elapsed = policy_year - capitalized_year;
+        if elapsed == 0 || elapsed == self.dac_amortization_years {
+            return 0.5 / years;
+        }
+        if elapsed > 0 && elapsed < self.dac_amortization_years {
+            return 1.0 / years;
+        }
+        return 0.0;
+    }
+}
+
+// profit testing, both annual effective
+// earned_rate is what the assets backing the reserve earn, hurdle_rate discounts profits for the present value
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfitAssumptions {
+    pub earned_rate: f64,
+    pub hurdle_rate: f64,
+}
+
+impl Default for ProfitAssumptions {
+    fn default() -> Self {
+        return ProfitAssumptions { earned_rate: 0.05, hurdle_rate: 0.10 };
+    }
+}
+
+// mortality improvement on the expected mortality, rates fall by rate a year compounded, e.g. 0.01 for 1%
+// the table is taken as being for table_year and improves from there to the calendar year of each policy year,
+// the first policy year being start_year, so improvement before issue is allowed for; without both years it runs
+// from the first policy year; improvement stops after max_years when given
+// the expected mortality falls back to the coi rates without a mortality table and is improved the same, the coi
+// rates charged to the policy are left as they are
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MortalityImprovement {
+    pub rate: f64,
+    pub table_year: Option<i32>,
+    pub start_year: Option<i32>,
+    pub max_years: Option<i32>,
+}
+
+impl MortalityImprovement {
+    // years of improvement in a policy year
+    pub fn years(&self, policy_year: i32) -> i32 {
+        let before_issue = match (self.table_year, self.start_year) {
+            (Some(table_year), Some(start_year)) => start_year - table_year,
+            _ => 0,
+        };
+        let years = (before_issue + policy_year - 1).max(0);
+        return self.max_years.map_or(years, |max| years.min(max));
+    }
+
+    pub fn factor(&self, policy_year: i32) -> f64 {
+        return (1.0 - self.rate).powi(self.years(policy_year));
+    }
+
+    // mortality by policy year, improved in place
+    pub fn apply(&self, mortality: &mut [f64]) {
+        if self.rate == 0.0 {
+            return;
+        }
+        for (i, rate) in mortality.iter_mut().enumerate() {
+            *rate *= self.factor(i as i32 + 1);
+        }
+    }
+}
+
+// illustration actuary tests, see illustration.rs
+// self-support is tested at the end of each policy year from self_support_from_year, and the lapse-support test
+// replaces the lapse rates from lapse_support_from_year on with lapse_support_rate
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IllustrationAssumptions {
+    pub self_support_from_year: i32,
+    pub lapse_support_from_year: i32,
+    pub lapse_support_rate: f64,
+}
+
+impl Default for IllustrationAssumptions {
+    fn default() -> Self {
+        return IllustrationAssumptions { self_support_from_year: 15, lapse_support_from_year: 6, lapse_support_rate: 0.0 };
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ReserveMethod {
+    // commissioners reserve valuation method, taken as full preliminary term
+    #[default]
+    Crvm,
+    NetLevel,
+}
+
+// statutory valuation basis, interest annual effective
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValuationAssumptions {
+    pub method: ReserveMethod,
+    pub interest: f64,
+}
+
+impl Default for ValuationAssumptions {
+    fn default() -> Self {
+        return ValuationAssumptions { method: ReserveMethod::Crvm, interest: 0.035 };
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Assumptions {
+    pub expenses: ExpenseAssumptions,
+    pub commissions: CommissionSchedule,
+    pub taxes: TaxAssumptions,
+    pub profit: ProfitAssumptions,
+    pub valuation: ValuationAssumptions,
+    pub mortality_improvement: MortalityImprovement,
+    pub illustration: IllustrationAssumptions,
+}
+
+impl Assumptions {
+    pub fn from_json_file(path: &str) -> Result<Assumptions, ValactError> {
+        let file = File::open(path)?;
+        return Ok(serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))?);
+    }
+
+    // the given file, else assumptions.json in the data directory if there is one, else the defaults
+    pub fn load(data_dir: &DataDir, path: Option<&str>) -> Result<Assumptions, ValactError> {
+        if let Some(path) = path {
+            return Assumptions::from_json_file(path);
+        }
+        let default_path = data_dir.path("assumptions.json");
+        if Path::new(&default_path).exists() {
+            return Assumptions::from_json_file(&default_path);
+        }
+        return Ok(Assumptions::default());
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+
+use crate::{
+    age::{IssueAge, PolicyYear}, error::ValactError, rates::{table_records, RateSet}
+};
+
+// charges that vary by face amount band, read once from a csv (or json) of
+//   Charge, Min_Face, Issue_Age, Policy_Year, Rate
+// Charge is premium_load, policy_fee or unit_load in the units of the rate set, and Min_Face the smallest face
+// amount in the band, e.g. bands from 0, 100000 and 250000 for under 100k, 100k to 250k and 250k and over
+// a policy takes each charge from the band with the largest Min_Face not above its face amount, rows with
+// Issue_Age left blank apply at every issue age and rows for the issue age take precedence over them
+// policy years, charges and face amounts the table does not cover keep the product's rates
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BandedCharge {
+    PremiumLoad,
+    PolicyFee,
+    UnitLoad,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BandRecord {
+    #[serde(alias="Charge")]
+    pub charge: BandedCharge,
+    #[serde(alias="Min_Face")]
+    pub min_face: f64,
+    #[serde(alias="Issue_Age")]
+    pub issue_age: Option<IssueAge>,
+    #[serde(alias="Policy_Year")]
+    pub policy_year: PolicyYear,
+    #[serde(alias="Rate")]
+    pub rate: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FaceBands {
+    records: Vec<BandRecord>,
+}
+
+impl FaceBands {
+    pub fn from_file(path: &str) -> Result<FaceBands, ValactError> {
+        let records = table_records(path)?.collect::<Result<Vec<BandRecord>, ValactError>>()?;
+        if let Some(record) = records.iter().find(|r| r.min_face < 0.0) {
+            return Err(ValactError::Table { path: path.to_string(), message: format!("band minimum face {} is negative", record.min_face) });
+        }
+        return Ok(FaceBands { records });
+    }
+
+    // the band a face amount falls in for a charge, None if the table has no band for it
+    pub fn band(&self, charge: BandedCharge, face_amount: f64) -> Option<f64> {
+        return self.records.iter()
+            .filter(|r| r.charge == charge && r.min_face <= face_amount)
+            .map(|r| r.min_face)
+            .max_by(f64::total_cmp);
+    }
+
+    // the smallest face of every band of any charge, in order
+    pub fn min_faces(&self) -> Vec<f64> {
+        let mut faces: Vec<f64> = self.records.iter().map(|r| r.min_face).collect();
+        faces.sort_by(f64::total_cmp);
+        faces.dedup();
+        return faces;
+    }
+
+    fn rates_for(&self, charge: BandedCharge, issue_age: IssueAge, face_amount: f64, rates: &mut [f64]) {
+        let Some(band) = self.band(charge, face_amount) else {
+            return;
+        };
+        let in_band = self.records.iter().filter(|r| r.charge == charge && r.min_face == band);
+        // every issue age rows first so the issue age's own rows overwrite them
+        let (all_ages, own_age): (Vec<&BandRecord>, Vec<&BandRecord>) = in_band.filter(|r| r.issue_age.is_none_or(|age| age == issue_age)).partition(|r| r.issue_age.is_none());
+        for record in all_ages.into_iter().chain(own_age) {
+            if let Some(rate) = rates.get_mut(record.policy_year.index()) {
+                *rate = record.rate;
+            }
+        }
+    }
+
+    // the policy's band rates in place of the product's
+    pub fn apply(&self, rates: &mut RateSet, issue_age: IssueAge, face_amount: f64) {
+        self.rates_for(BandedCharge::PremiumLoad, issue_age, face_amount, &mut rates.premium_loads);
+        self.rates_for(BandedCharge::PolicyFee, issue_age, face_amount, &mut rates.policy_fees);
+        self.rates_for(BandedCharge::UnitLoad, issue_age, face_amount, &mut rates.unit_loads);
+    }
+}
@@ -0,0 +1,252 @@
+use std::{borrow::Borrow, fs::File};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info_span, warn, Span};
+
+use crate::{
+    age::{insurance_age, AgeBasis, IssueAge}, error::ValactError, lanes::{self, Lanes, LANES}, projection::{solve_for_premium_using, DeathBenefitOption, InforceState, ProjectionConfig, SolveResult, SolveTarget}, rates::{get_rates, RateSet}, solver::SolverOptions
+};
+
+// one row of an inforce/census file
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PolicyRecord {
+    #[serde(alias="Gender")]
+    pub gender: String,
+    #[serde(alias="Risk_Class")]
+    pub risk_class: String,
+    #[serde(alias="Issue_Age")]
+    pub issue_age: IssueAge,
+    #[serde(alias="Face_Amount")]
+    pub face_amount: f64,
+    #[serde(alias="Death_Benefit_Option", alias="DB_Option", default)]
+    pub death_benefit_option: DeathBenefitOption,
+    // where the policy stands at the valuation date of an inforce extract, None projects it from issue
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inforce: Option<InforceState>,
+    // picks the version of versioned rate tables the policy's rates come from
+    #[serde(alias="Issue_Date", default, skip_serializing_if = "Option::is_none")]
+    pub issue_date: Option<NaiveDate>,
+}
+
+// one row of the results file, the inputs are echoed back so rows can be matched up
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PolicyResult {
+    #[serde(rename="Gender")]
+    pub gender: String,
+    #[serde(rename="Risk_Class")]
+    pub risk_class: String,
+    #[serde(rename="Issue_Age")]
+    pub issue_age: IssueAge,
+    #[serde(rename="Face_Amount")]
+    pub face_amount: f64,
+    #[serde(rename="Death_Benefit_Option")]
+    pub death_benefit_option: DeathBenefitOption,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    // position of the policy in the census from 0, set on streamed results as they come in the order policies
+    // finish rather than census order
+    #[serde(rename="Census_Row", default, skip_serializing_if = "Option::is_none")]
+    pub census_row: Option<usize>,
+}
+
+// census rows can give Issue_Age directly or Date_Of_Birth and Issue_Date to work it out from
+#[derive(Deserialize)]
+struct CensusRow {
+    #[serde(alias="Gender")]
+    gender: String,
+    #[serde(alias="Risk_Class")]
+    risk_class: String,
+    #[serde(alias="Issue_Age", default)]
+    issue_age: Option<IssueAge>,
+    #[serde(alias="Date_Of_Birth", default)]
+    date_of_birth: Option<NaiveDate>,
+    #[serde(alias="Issue_Date", default)]
+    issue_date: Option<NaiveDate>,
+    #[serde(alias="Face_Amount")]
+    face_amount: f64,
+    #[serde(alias="Death_Benefit_Option", alias="DB_Option", default)]
+    death_benefit_option: DeathBenefitOption,
+}
+
+pub fn read_policies(path: &str) -> Result<Vec<PolicyRecord>, ValactError> {
+    return read_policies_with_basis(path, AgeBasis::default());
+}
+
+// basis is used for rows without an Issue_Age
+pub fn read_policies_with_basis(path: &str, basis: AgeBasis) -> Result<Vec<PolicyRecord>, ValactError> {
+    let file = File::open(path).map_err(|source| ValactError::File { path: path.to_string(), source })?;
+    let mut rdr = csv::Reader::from_reader(file);
+    let headers = rdr.headers().map_err(|e| ValactError::csv(path, None, e))?.clone();
+
+    let mut policies = Vec::new();
+    for result in rdr.records() {
+        let record = result.map_err(|e| ValactError::csv(path, None, e))?;
+        let line = record.position().map_or(0, |p| p.line());
+        let row_error = |message: String| ValactError::Row { path: path.to_string(), line, column: None, message };
+        let row: CensusRow = record.deserialize(Some(&headers)).map_err(|e| ValactError::csv(path, Some(&headers), e))?;
+        let issue_age = match (row.issue_age, row.date_of_birth, row.issue_date) {
+            (Some(age), _, _) => age,
+            (None, Some(dob), Some(issue_date)) => insurance_age(dob, issue_date, basis).map_err(|e| row_error(e.to_string()))?,
+            _ => return Err(row_error("needs Issue_Age or both Date_Of_Birth and Issue_Date".to_string())),
+        };
+        policies.push(PolicyRecord {
+            gender: row.gender,
+            risk_class: row.risk_class,
+            issue_age,
+            face_amount: row.face_amount,
+            death_benefit_option: row.death_benefit_option,
+            inforce: None,
+            issue_date: row.issue_date,
+        });
+    }
+    return Ok(policies);
+}
+
+pub fn solve_policy(policy: &PolicyRecord) -> Result<PolicyResult, ValactError> {
+    return solve_policy_with(policy, &|policy: &PolicyRecord| get_rates(&policy.gender, &policy.risk_class, policy.issue_age));
+}
+
+// rates_for looks up the rates for the policy so other rate backends can be used, and rates resolved per policy
+// rather than per cell, e.g. face amount bands; it can hand back the rates or a shared Arc of them
+pub fn solve_policy_with<F, S>(policy: &PolicyRecord, rates_for: &F) -> Result<PolicyResult, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError>,
+    S: Borrow<RateSet>,
+{
+    let target = SolveTarget::EndowAtMaturity;
+    return solve_policy_using(policy, rates_for, &SolverOptions::new(target.default_method(), 0.005));
+}
+
+// options sets the root finding method and tolerance of the solve, which is always to endow at maturity
+pub fn solve_policy_using<F, S>(policy: &PolicyRecord, rates_for: &F, options: &SolverOptions) -> Result<PolicyResult, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError>,
+    S: Borrow<RateSet>,
+{
+    let _entered = policy_span(policy).entered();
+    let config = policy_config(policy);
+    let solved = rates_for(policy)
+        .and_then(|rates| solve_for_premium_using(rates.borrow(), policy.issue_age, policy.face_amount, &config, SolveTarget::EndowAtMaturity, options));
+    return policy_result(policy, solved);
+}
+
+// a span per policy so everything logged during the rate lookup and solve says which policy it was for
+pub(crate) fn policy_span(policy: &PolicyRecord) -> Span {
+    return info_span!("policy", gender = %policy.gender, risk_class = %policy.risk_class, issue_age = policy.issue_age.get(), face_amount = policy.face_amount);
+}
+
+pub(crate) fn policy_config(policy: &PolicyRecord) -> ProjectionConfig {
+    return ProjectionConfig { death_benefit_option: policy.death_benefit_option, inforce: policy.inforce, ..Default::default() };
+}
+
+pub(crate) fn policy_result(policy: &PolicyRecord, solved: Result<SolveResult, ValactError>) -> Result<PolicyResult, ValactError> {
+    let solve = match solved {
+        Ok(solve) => solve,
+        Err(e) => {
+            warn!(error = %e, "solve failed");
+            return Err(e.for_cell(&policy.gender, &policy.risk_class, policy.issue_age));
+        }
+    };
+    debug!(premium = solve.premium, iterations = solve.iterations, converged = solve.converged, "solved");
+    let premium = solve.premium;
+    return Ok(PolicyResult {
+        gender: policy.gender.clone(),
+        risk_class: policy.risk_class.clone(),
+        issue_age: policy.issue_age,
+        face_amount: policy.face_amount,
+        death_benefit_option: policy.death_benefit_option,
+        premium,
+        census_row: None,
+    });
+}
+
+// policies of the same issue age and death benefit option solved together in lockstep (lanes.rs), up to LANES of
+// them, with the result of each in the same order as solve_policy_using gives it; policies whose rates cannot be
+// projected together, or solves lockstep does not cover, are solved one at a time
+pub fn solve_policies_lockstep<F, S>(policies: &[&PolicyRecord], rates_for: &F, options: &SolverOptions) -> Vec<Result<PolicyResult, ValactError>>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError>,
+    S: Borrow<RateSet>,
+{
+    let one_at_a_time = |policies: &[&PolicyRecord]| -> Vec<Result<PolicyResult, ValactError>> {
+        return policies.iter().map(|policy| solve_policy_using(policy, rates_for, options)).collect();
+    };
+    let Some(first) = policies.first() else {
+        return Vec::new();
+    };
+    let config = policy_config(first);
+    let alike = policies.iter().all(|p| p.issue_age == first.issue_age && p.death_benefit_option == first.death_benefit_option && p.inforce.is_none());
+    if policies.len() == 1 || policies.len() > LANES || !alike || !lanes::supported(&config, options) {
+        return one_at_a_time(policies);
+    }
+
+    let mut results: Vec<Option<Result<PolicyResult, ValactError>>> = policies.iter().map(|_| None).collect();
+    let mut rates = Vec::with_capacity(policies.len());
+    let mut in_lanes = Vec::with_capacity(policies.len());
+    for (i, policy) in policies.iter().enumerate() {
+        let _entered = policy_span(policy).entered();
+        match rates_for(policy) {
+            Ok(policy_rates) => {
+                rates.push(policy_rates);
+                in_lanes.push(i);
+            }
+            Err(e) => results[i] = Some(policy_result(policy, Err(e))),
+        }
+    }
+    let lane_rates: Vec<&RateSet> = rates.iter().map(|r| r.borrow()).collect();
+    let face_amounts: Vec<f64> = in_lanes.iter().map(|&i| policies[i].face_amount).collect();
+    let solved = Lanes::new(&lane_rates, &face_amounts, first.issue_age, &config).and_then(|lanes| lanes::solve_for_premiums(&lanes, options));
+    match solved {
+        Ok(solves) => {
+            for (&i, solve) in in_lanes.iter().zip(solves) {
+                let _entered = policy_span(policies[i]).entered();
+                results[i] = Some(policy_result(policies[i], solve.map_err(ValactError::from)));
+            }
+        }
+        Err(_) => {
+            let lane_policies: Vec<&PolicyRecord> = in_lanes.iter().map(|&i| policies[i]).collect();
+            for (&i, result) in in_lanes.iter().zip(one_at_a_time(&lane_policies)) {
+                results[i] = Some(result);
+            }
+        }
+    }
+    return results.into_iter().flatten().collect();
+}
+
+pub fn solve_policies(policies: &[PolicyRecord]) -> Result<Vec<PolicyResult>, ValactError> {
+    let mut results = Vec::with_capacity(policies.len());
+    for policy in policies {
+        results.push(solve_policy(policy)?);
+    }
+    return Ok(results);
+}
+
+pub fn write_results(path: &str, results: &[PolicyResult]) -> Result<(), ValactError> {
+    let mut writer = ResultsWriter::create(path)?;
+    for result in results {
+        writer.write(result)?;
+    }
+    return writer.finish();
+}
+
+// writes batch results to a csv file a row at a time, for results streamed out as they are solved
+pub struct ResultsWriter {
+    wtr: csv::Writer<File>,
+}
+
+impl ResultsWriter {
+    pub fn create(path: &str) -> Result<ResultsWriter, ValactError> {
+        return Ok(ResultsWriter { wtr: csv::Writer::from_path(path)? });
+    }
+
+    pub fn write(&mut self, result: &PolicyResult) -> Result<(), ValactError> {
+        self.wtr.serialize(result)?;
+        return Ok(());
+    }
+
+    pub fn finish(mut self) -> Result<(), ValactError> {
+        self.wtr.flush()?;
+        return Ok(());
+    }
+}
@@ -0,0 +1,150 @@
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ValactError, projection::MonthResult
+};
+
+// calendar dates for the projection of a policy issued on a given date
+// each policy month starts on its monthiversary, the issue date's day of the month or the last day of a month too
+// short for it, so a policy issued Jan 31 has monthiversaries on Feb 28 (29), Mar 31, Apr 30 and so on
+// a valuation date part way through a policy year makes an inforce projection (ProjectionConfig::inforce) whose
+// first policy year is partial, the policy picking up at the month in progress on the valuation date with the
+// account value at its monthiversary before that month's deductions
+// flows are booked on the monthiversary of the step they fall in, with an annual step that is the anniversary
+
+pub fn monthiversary(issue_date: NaiveDate, policy_month: i32) -> Result<NaiveDate, ValactError> {
+    let months = u32::try_from(policy_month - 1).map_err(|_| format!("policy month {} is before issue", policy_month))?;
+    return issue_date.checked_add_months(Months::new(months))
+        .ok_or_else(|| format!("policy month {} from issue on {} is out of range", policy_month, issue_date).into());
+}
+
+// policy months completed by the valuation date, the inforce duration the projection starts after
+pub fn duration_at(issue_date: NaiveDate, valuation_date: NaiveDate) -> Result<i32, ValactError> {
+    if valuation_date < issue_date {
+        return Err(format!("valuation date {} is before the issue date {}", valuation_date, issue_date).into());
+    }
+    let mut months = (valuation_date.year() - issue_date.year()) * 12 + valuation_date.month() as i32 - issue_date.month() as i32;
+    if monthiversary(issue_date, months + 1)? > valuation_date {
+        months -= 1;
+    }
+    return Ok(months);
+}
+
+#[derive(Serialize)]
+struct Dated {
+    #[serde(rename="Date")]
+    date: NaiveDate,
+}
+
+// the monthly projection csv with each step's monthiversary in front
+pub fn write_dated_projection_csv(path: &str, months: &[MonthResult], issue_date: NaiveDate) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for month in months {
+        wtr.serialize((Dated { date: monthiversary(issue_date, month.policy_month)? }, month))?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
+
+// calendar periods flows are rolled up to for financial reporting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ReportingPeriod {
+    #[default]
+    Month,
+    Quarter,
+    Year,
+}
+
+impl ReportingPeriod {
+    fn months(self) -> u32 {
+        return match self {
+            ReportingPeriod::Month => 1,
+            ReportingPeriod::Quarter => 3,
+            ReportingPeriod::Year => 12,
+        };
+    }
+
+    // first day of the period a date falls in
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        let month = (date.month() - 1) / self.months() * self.months() + 1;
+        return date.with_day(1).and_then(|first| first.with_month(month)).unwrap_or(date);
+    }
+
+    pub fn end(self, date: NaiveDate) -> NaiveDate {
+        let start = self.start(date);
+        return start.checked_add_months(Months::new(self.months())).and_then(|next| next.pred_opt()).unwrap_or(start);
+    }
+}
+
+// flows of the steps whose monthiversaries fall in a calendar period, and values after the last of them
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct CalendarPeriod {
+    #[serde(rename="Period_Start")]
+    pub period_start: NaiveDate,
+    #[serde(rename="Period_End")]
+    pub period_end: NaiveDate,
+    #[serde(rename="Policy_Year")]
+    pub policy_year: i32,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Premium_Load")]
+    pub premium_load: f64,
+    #[serde(rename="Withdrawal")]
+    pub withdrawal: f64,
+    #[serde(rename="Expense_Charge")]
+    pub expense_charge: f64,
+    #[serde(rename="COI_Charge")]
+    pub coi_charge: f64,
+    #[serde(rename="Rider_Charge")]
+    pub rider_charge: f64,
+    #[serde(rename="Interest")]
+    pub interest: f64,
+    #[serde(rename="Loan")]
+    pub loan: f64,
+    #[serde(rename="Loan_Repayment")]
+    pub loan_repayment: f64,
+    #[serde(rename="Value_End")]
+    pub value_end: f64,
+    #[serde(rename="Loan_Balance")]
+    pub loan_balance: f64,
+    #[serde(rename="Cash_Surrender_Value")]
+    pub cash_surrender_value: f64,
+}
+
+// periods no step falls in, between the anniversaries of an annual step, are left out; policy_year is the one in
+// force at the end of the period's last step
+pub fn calendar_summary(months: &[MonthResult], issue_date: NaiveDate, period: ReportingPeriod) -> Result<Vec<CalendarPeriod>, ValactError> {
+    let mut periods: Vec<CalendarPeriod> = Vec::new();
+    for month in months {
+        let date = monthiversary(issue_date, month.policy_month)?;
+        let period_start = period.start(date);
+        if periods.last().is_none_or(|last| last.period_start != period_start) {
+            periods.push(CalendarPeriod { period_start, period_end: period.end(date), ..Default::default() });
+        }
+        let Some(entry) = periods.last_mut() else { continue };
+        entry.policy_year = month.policy_year;
+        entry.premium += month.premium;
+        entry.premium_load += month.premium_load;
+        entry.withdrawal += month.withdrawal;
+        entry.expense_charge += month.expense_charge;
+        entry.coi_charge += month.coi_charge;
+        entry.rider_charge += month.rider_charge;
+        entry.interest += month.interest;
+        entry.loan += month.loan;
+        entry.loan_repayment += month.loan_repayment;
+        entry.value_end = month.value_end;
+        entry.loan_balance = month.loan_balance;
+        entry.cash_surrender_value = month.cash_surrender_value;
+    }
+    return Ok(periods);
+}
+
+pub fn write_calendar_csv(path: &str, periods: &[CalendarPeriod]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for period in periods {
+        wtr.serialize(period)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
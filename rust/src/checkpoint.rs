@@ -0,0 +1,224 @@
+use std::{
+    fs::{self, File, OpenOptions}, io::{self, BufRead, BufReader, BufWriter, Write}, path::{Path, PathBuf}, sync::Mutex, time::{Duration, Instant, SystemTime}
+};
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::error::ValactError;
+
+// checkpoints of long batch and scenario runs so one interrupted overnight can be picked up where it stopped
+// rather than started again
+// a run checkpointed under run id X keeps X/run.json, the manifest below, and X/results.jsonl, a line per
+// completed model point, policy or path with its position in the run, in the checkpoint directory
+// results are held in memory as they finish and appended to results.jsonl every interval, then the manifest is
+// replaced (written to the side and renamed over the old one) with the count and cursor they bring it to, so a
+// run killed part way through loses at most an interval of work; a line cut short by the kill is dropped when
+// the run is resumed
+// only results are saved, failed solves are tried again on a resume
+// the fingerprint is a hash of everything that decides the results, the census or scenarios and the options, a
+// resume with anything different is refused rather than mixing results from two different runs
+
+pub const DEFAULT_DIR: &str = "checkpoints";
+
+const MANIFEST: &str = "run.json";
+const RESULTS: &str = "results.jsonl";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RunManifest {
+    pub run_id: String,
+    // the subcommand the run is for, a batch checkpoint cannot be resumed as scenarios
+    pub command: String,
+    // the census or scenario file, for the operator's benefit
+    pub input: String,
+    pub fingerprint: String,
+    pub total: usize,
+    pub completed: usize,
+    // everything before this position in the run is done, results past it are in results.jsonl too
+    pub cursor: usize,
+    pub started_at: DateTime<Utc>,
+    pub saved_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Entry<T> {
+    index: usize,
+    result: T,
+}
+
+// fnv-1a over the json of what the run was started with
+struct Fingerprint(u64);
+
+impl Write for Fingerprint {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100000001b3);
+        }
+        return Ok(bytes.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+pub fn fingerprint<T: Serialize>(inputs: &T) -> Result<String, ValactError> {
+    let mut hasher = Fingerprint(0xcbf29ce484222325);
+    serde_json::to_writer(&mut hasher, inputs)?;
+    return Ok(format!("{:016x}", hasher.0));
+}
+
+fn now() -> DateTime<Utc> {
+    return DateTime::<Utc>::from(SystemTime::now());
+}
+
+fn write_manifest(dir: &Path, manifest: &RunManifest) -> Result<(), ValactError> {
+    let path = dir.join(MANIFEST);
+    let side = dir.join(format!("{}.tmp", MANIFEST));
+    fs::write(&side, serde_json::to_string_pretty(manifest)?).map_err(|source| ValactError::File { path: side.display().to_string(), source })?;
+    fs::rename(&side, &path).map_err(|source| ValactError::File { path: path.display().to_string(), source })?;
+    return Ok(());
+}
+
+struct State<T> {
+    manifest: RunManifest,
+    done: Vec<Option<T>>,
+    // finished since the last save
+    pending: Vec<(usize, T)>,
+    saved: Instant,
+}
+
+pub struct Checkpoint<T> {
+    dir: PathBuf,
+    interval: Duration,
+    state: Mutex<State<T>>,
+}
+
+impl<T: Clone + Serialize + DeserializeOwned> Checkpoint<T> {
+    // a new run of total results under a run id from the time and process id, saved every interval
+    pub fn start(dir: &str, command: &str, input: &str, total: usize, fingerprint: String, interval: Duration) -> Result<Checkpoint<T>, ValactError> {
+        let started_at = now();
+        let run_id = format!("{}-{}", started_at.format("%Y%m%d-%H%M%S"), std::process::id());
+        let run_dir = Path::new(dir).join(&run_id);
+        fs::create_dir_all(&run_dir).map_err(|source| ValactError::File { path: run_dir.display().to_string(), source })?;
+        File::create(run_dir.join(RESULTS))?;
+        let manifest = RunManifest { run_id, command: command.to_string(), input: input.to_string(), fingerprint, total, completed: 0, cursor: 0, started_at, saved_at: started_at };
+        write_manifest(&run_dir, &manifest)?;
+        info!(run_id = %manifest.run_id, total, "checkpointing the run to {} every {:?}, resume it with --resume {}", run_dir.display(), interval, manifest.run_id);
+        return Ok(Checkpoint::with_state(run_dir, interval, manifest, (0..total).map(|_| None).collect()));
+    }
+
+    // the run saved under run_id, which has to have been started by command with the same fingerprint
+    pub fn resume(dir: &str, run_id: &str, command: &str, fingerprint: &str, interval: Duration) -> Result<Checkpoint<T>, ValactError> {
+        let run_dir = Path::new(dir).join(run_id);
+        let manifest_path = run_dir.join(MANIFEST);
+        let text = fs::read_to_string(&manifest_path).map_err(|e| format!("no checkpoint of run {} in {}: {}", run_id, dir, e))?;
+        let manifest: RunManifest = serde_json::from_str(&text)?;
+        if manifest.command != command {
+            return Err(format!("run {} is a {} run and cannot be resumed as {}", run_id, manifest.command, command).into());
+        }
+        if manifest.fingerprint != fingerprint {
+            return Err(format!("run {} was started from {} with different inputs or options, start a new run rather than resuming it", run_id, manifest.input).into());
+        }
+
+        let results_path = run_dir.join(RESULTS);
+        let file = File::open(&results_path).map_err(|source| ValactError::File { path: results_path.display().to_string(), source })?;
+        let mut done: Vec<Option<T>> = (0..manifest.total).map(|_| None).collect();
+        let lines = BufReader::new(file).lines().collect::<Result<Vec<String>, _>>()?;
+        let mut entries = Vec::with_capacity(lines.len());
+        for (number, line) in lines.iter().enumerate() {
+            let entry = match serde_json::from_str::<Entry<T>>(line) {
+                Ok(entry) => entry,
+                // the last line can be cut short by whatever stopped the run, it is solved again
+                Err(_) if number + 1 == lines.len() => break,
+                Err(e) => return Err(ValactError::Row { path: results_path.display().to_string(), line: number as u64 + 1, column: None, message: e.to_string() }),
+            };
+            if entry.index >= manifest.total {
+                return Err(format!("{} has a result for position {} of a run of {}", results_path.display(), entry.index, manifest.total).into());
+            }
+            entries.push(entry);
+        }
+        // written again without the partial line so the lines saved from here on are read back as well, to the side
+        // and renamed over the old file so a resume killed part way through still has every saved result
+        let side = run_dir.join(format!("{}.tmp", RESULTS));
+        let mut wtr = BufWriter::new(File::create(&side).map_err(|source| ValactError::File { path: side.display().to_string(), source })?);
+        for entry in entries {
+            serde_json::to_writer(&mut wtr, &entry)?;
+            wtr.write_all(b"\n")?;
+            done[entry.index] = Some(entry.result);
+        }
+        wtr.flush()?;
+        drop(wtr);
+        fs::rename(&side, &results_path).map_err(|source| ValactError::File { path: results_path.display().to_string(), source })?;
+        let completed = done.iter().filter(|result| result.is_some()).count();
+        info!(run_id, completed, total = manifest.total, "resuming the run from {}", run_dir.display());
+        return Ok(Checkpoint::with_state(run_dir, interval, manifest, done));
+    }
+
+    fn with_state(dir: PathBuf, interval: Duration, manifest: RunManifest, done: Vec<Option<T>>) -> Checkpoint<T> {
+        let state = State { manifest, done, pending: Vec::new(), saved: Instant::now() };
+        return Checkpoint { dir, interval, state: Mutex::new(state) };
+    }
+
+    pub fn run_id(&self) -> String {
+        return self.state.lock().unwrap().manifest.run_id.clone();
+    }
+
+    // positions of the run still to be solved, in order
+    pub fn remaining(&self) -> Vec<usize> {
+        let state = self.state.lock().unwrap();
+        return state.done.iter().enumerate().filter(|(_, result)| result.is_none()).map(|(i, _)| i).collect();
+    }
+
+    // results so far by position in the run, including those from before a resume
+    pub fn results(&self) -> Vec<Option<T>> {
+        let state = self.state.lock().unwrap();
+        let mut results = state.done.clone();
+        for (index, result) in &state.pending {
+            results[*index] = Some(result.clone());
+        }
+        return results;
+    }
+
+    // the result at a position in the run, called from the worker threads as each one finishes; the thread that
+    // finds the interval has passed saves, a save that fails is logged and tried again next interval
+    pub fn record(&self, index: usize, result: &T) {
+        let mut state = self.state.lock().unwrap();
+        state.pending.push((index, result.clone()));
+        if state.saved.elapsed() >= self.interval && let Err(e) = self.save_state(&mut state) {
+            warn!("checkpoint of run {} not saved: {}", state.manifest.run_id, e);
+        }
+    }
+
+    // everything recorded so far, at the end of a run or before it stops on an error
+    pub fn save(&self) -> Result<(), ValactError> {
+        return self.save_state(&mut self.state.lock().unwrap());
+    }
+
+    fn save_state(&self, state: &mut State<T>) -> Result<(), ValactError> {
+        state.saved = Instant::now();
+        let results_path = self.dir.join(RESULTS);
+        let file = OpenOptions::new().append(true).open(&results_path).map_err(|source| ValactError::File { path: results_path.display().to_string(), source })?;
+        let mut wtr = BufWriter::new(file);
+        for (index, result) in &state.pending {
+            serde_json::to_writer(&mut wtr, &Entry { index: *index, result })?;
+            wtr.write_all(b"\n")?;
+        }
+        wtr.into_inner().map_err(|e| e.into_error())?.sync_data()?;
+        for (index, result) in std::mem::take(&mut state.pending) {
+            state.done[index] = Some(result);
+        }
+        let manifest = &mut state.manifest;
+        manifest.completed = state.done.iter().filter(|result| result.is_some()).count();
+        manifest.cursor = state.done.iter().position(|result| result.is_none()).unwrap_or(manifest.total);
+        manifest.saved_at = now();
+        return write_manifest(&self.dir, manifest);
+    }
+
+    // the run finished and its results are written, its checkpoint is no longer needed
+    pub fn finish(self) -> Result<(), ValactError> {
+        fs::remove_dir_all(&self.dir).map_err(|source| ValactError::File { path: self.dir.display().to_string(), source })?;
+        return Ok(());
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs;
+
+use toml::{Table, Value};
+
+use crate::error::ValactError;
+
+// run configuration, the options for a run kept in a toml file so a batch run can be repeated exactly without a
+// long command line
+// top level keys are the global options (data_dir, product, rates_db, maturity_age, ...) and a table named after a
+// command holds the options for that command, e.g. [batch] with input, output_file, threads, method and tolerance
+// or [scenarios] with the scenario_file and output_file
+// keys are the long option names with underscores for the dashes, switches are true or false and options that can
+// be repeated take an array
+// the options are turned into command line arguments so they are checked exactly as if typed, and anything also
+// given on the command line is taken from the command line
+//
+//   data_dir = "./data/2025q4"
+//   product = "products/ul2025.toml"
+//   maturity_age = 121
+//
+//   [batch]
+//   input = "census.csv"
+//   output_file = "results.parquet"
+//   threads = 8
+//   method = "brent"
+//   tolerance = 0.001
+
+pub struct RunConfig {
+    options: Table,
+}
+
+impl RunConfig {
+    pub fn from_file(path: &str) -> Result<RunConfig, ValactError> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        return Ok(RunConfig { options: text.parse::<Table>().map_err(|e| format!("{}: {}", path, e))? });
+    }
+
+    // arguments for the global options and those of command, leaving out any option already in given
+    pub fn args(&self, command: Option<&str>, given: &[String]) -> Result<Vec<String>, ValactError> {
+        let mut args = Vec::new();
+        for (key, value) in &self.options {
+            match value {
+                Value::Table(section) => {
+                    if command.is_some_and(|name| option_name(key) == name) {
+                        for (key, value) in section {
+                            push_option(&mut args, key, value, given)?;
+                        }
+                    }
+                }
+                value => push_option(&mut args, key, value, given)?,
+            }
+        }
+        return Ok(args);
+    }
+}
+
+fn option_name(key: &str) -> String {
+    return key.replace('_', "-");
+}
+
+fn push_option(args: &mut Vec<String>, key: &str, value: &Value, given: &[String]) -> Result<(), ValactError> {
+    let flag = format!("--{}", option_name(key));
+    if given.iter().any(|arg| *arg == flag || arg.starts_with(&format!("{}=", flag))) {
+        return Ok(());
+    }
+    match value {
+        Value::Boolean(true) => args.push(flag),
+        Value::Boolean(false) => {}
+        Value::Array(values) => {
+            for value in values {
+                args.push(flag.clone());
+                args.push(scalar(key, value)?);
+            }
+        }
+        value => {
+            args.push(flag);
+            args.push(scalar(key, value)?);
+        }
+    }
+    return Ok(());
+}
+
+fn scalar(key: &str, value: &Value) -> Result<String, ValactError> {
+    return match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Datetime(d) => Ok(d.to_string()),
+        _ => Err(format!("run config option {} must be a string, number, date or an array of them", key).into()),
+    };
+}
@@ -0,0 +1,39 @@
+
+use crate::{age::IssueAge, error::ValactError, rates::{read_cell_coi_table, RateSet}};
+
+// cash value accumulation test corridor, IRC 7702(b)
+// the death benefit has to be at least the account value over the net single premium for $1 of benefit, so the
+// corridor factor at each policy year is 1 / nsp with the nsp for a benefit to maturity, endowing at maturity
+
+// statutory minimum interest for the net single premium, annual effective
+pub const CVAT_INTEREST: f64 = 0.04;
+
+// net single premium for $1 at the start of each policy year, mortality per 1000 by policy year with annual deaths
+// paid at the end of the year, the last year ends with the $1 paid as an endowment
+pub fn net_single_premiums(mortality: &[f64], interest: f64) -> Vec<f64> {
+    let v = 1.0 / (1.0 + interest);
+    let mut nsp = vec![0.0; mortality.len()];
+    let mut next = 1.0;
+    for (i, q) in mortality.iter().enumerate().rev() {
+        let q = (q / 1000.0).clamp(0.0, 1.0);
+        nsp[i] = v * (q + (1.0 - q) * next);
+        next = nsp[i];
+    }
+    return nsp;
+}
+
+pub fn corridor_factors(mortality: &[f64], interest: f64) -> Vec<f64> {
+    return net_single_premiums(mortality, interest).iter().map(|nsp| 1.0 / nsp).collect();
+}
+
+// corridor factors for a cell in place of the corridor table
+// mortality is an XTbML or select and ultimate csv table when given, the cell's coi rates otherwise
+pub fn apply_cvat_corridor(rates: &mut RateSet, issue_age: IssueAge, mortality_path: Option<&str>, interest: f64) -> Result<(), ValactError> {
+    let years = rates.corr_facts.len();
+    let mortality = match mortality_path {
+        Some(path) => read_cell_coi_table(path)?.rates_for_issue_age(issue_age, 1000.0, years),
+        None => rates.coi_rates.clone(),
+    };
+    rates.corr_facts = corridor_factors(&mortality, interest);
+    return Ok(());
+}
@@ -0,0 +1,250 @@
+use rust_decimal::{prelude::{FromPrimitive, ToPrimitive}, Decimal, MathematicalOps, RoundingStrategy};
+use serde::Serialize;
+
+use crate::{
+    age::IssueAge,
+    error::ValactError,
+    projection::{solve_for_premium_with, DeathBenefitOption, PremiumPattern, ProjectionConfig},
+    rates::{projection_years, RateSet},
+    rounding::{RoundingMode, RoundingPolicy},
+};
+
+// the base projection worked in decimal rather than f64, for tying premiums and values out to an admin system
+// to the cent without binary floating point artifacts
+// every money amount is rounded by the config's rounding policy as it is worked out, whether or not it sets
+// amounts, so the account value is always a whole number of units, rates are taken from the rate set at the precision f64 holds them and kept
+// unrounded (a month's coi rate, discount and interest are full precision, the charge they give is rounded)
+// covers the timestep, death benefit options, premium mode, modal factor and premium pattern of the config,
+// anything else (withdrawals, loans, face and option changes, interest paths, index and fund accounts, riders, inforce) is an error
+
+// always shown with the unit's places, 2850.00 rather than 2850.0
+fn round(policy: &RoundingPolicy, amount: Decimal) -> Decimal {
+    let strategy = match policy.mode {
+        RoundingMode::Truncate => RoundingStrategy::ToZero,
+        RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+        RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+    };
+    let places = policy.unit.decimal_places();
+    let mut rounded = amount.round_dp_with_strategy(places, strategy);
+    rounded.rescale(places);
+    return rounded;
+}
+
+pub fn to_decimal(value: f64, what: &str) -> Result<Decimal, ValactError> {
+    return Decimal::from_f64(value).ok_or_else(|| format!("{} {} does not fit a decimal", what, value).into());
+}
+
+// one step of the decimal projection, columns named as in the monthly projection csv
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct DecimalMonth {
+    #[serde(rename="Policy_Month")]
+    pub policy_month: i32,
+    #[serde(rename="Policy_Year")]
+    pub policy_year: i32,
+    #[serde(rename="Month_In_Policy_Year")]
+    pub month_in_policy_year: i32,
+    #[serde(rename="Value_Start")]
+    pub value_start: Decimal,
+    #[serde(rename="Premium")]
+    pub premium: Decimal,
+    #[serde(rename="Premium_Load")]
+    pub premium_load: Decimal,
+    #[serde(rename="Expense_Charge")]
+    pub expense_charge: Decimal,
+    #[serde(rename="Death_Benefit")]
+    pub death_benefit: Decimal,
+    #[serde(rename="NAAR")]
+    pub naar: Decimal,
+    #[serde(rename="COI_Charge")]
+    pub coi_charge: Decimal,
+    #[serde(rename="Interest")]
+    pub interest: Decimal,
+    #[serde(rename="Value_End")]
+    pub value_end: Decimal,
+    #[serde(rename="Surrender_Charge")]
+    pub surrender_charge: Decimal,
+    #[serde(rename="Cash_Surrender_Value")]
+    pub cash_surrender_value: Decimal,
+}
+
+fn check_supported(config: &ProjectionConfig) -> Result<(), ValactError> {
+    let unsupported = [
+        ("withdrawals", !config.withdrawals.is_empty()),
+        ("loans", !config.loans.is_empty() || !config.loan_repayments.is_empty()),
+        ("face changes", !config.face_changes.is_empty()),
+        ("death benefit option changes", !config.option_changes.is_empty()),
+        ("interest paths", !config.interest_path.is_empty()),
+        ("index and fund accounts", !config.accounts.is_empty()),
+        ("riders", !config.riders.is_empty()),
+        ("inforce projections", config.inforce.is_some()),
+    ];
+    let used: Vec<&str> = unsupported.iter().filter(|(_, used)| *used).map(|(name, _)| *name).collect();
+    if !used.is_empty() {
+        return Err(format!("the decimal projection does not support {}", used.join(", ")).into());
+    }
+    return Ok(());
+}
+
+// a rate set's vectors as decimals, converted once rather than every step
+struct DecimalRates {
+    premium_loads: Vec<Decimal>,
+    policy_fees: Vec<Decimal>,
+    unit_loads: Vec<Decimal>,
+    corr_facts: Vec<Decimal>,
+    naar_discs: Vec<Decimal>,
+    coi_rates: Vec<Decimal>,
+    interest_rates: Vec<Decimal>,
+    surrender_charges: Vec<Decimal>,
+}
+
+impl DecimalRates {
+    fn new(rates: &RateSet) -> Result<DecimalRates, ValactError> {
+        let convert = |values: &[f64], what: &str| -> Result<Vec<Decimal>, ValactError> {
+            return values.iter().map(|value| to_decimal(*value, what)).collect();
+        };
+        return Ok(DecimalRates {
+            premium_loads: convert(&rates.premium_loads, "premium load")?,
+            policy_fees: convert(&rates.policy_fees, "policy fee")?,
+            unit_loads: convert(&rates.unit_loads, "unit load")?,
+            corr_facts: convert(&rates.corr_facts, "corridor factor")?,
+            naar_discs: convert(&rates.naar_discs, "naar discount")?,
+            coi_rates: convert(&rates.coi_rates, "coi rate")?,
+            interest_rates: convert(&rates.interest_rates, "interest rate")?,
+            surrender_charges: convert(&rates.surrender_charges, "surrender charge")?,
+        });
+    }
+}
+
+// same order of operations as projection::project, on_month gets every step
+fn project<F: FnMut(&DecimalMonth)>(rates: &DecimalRates, issue_age: IssueAge, years: usize, face_amount: Decimal, annual_premium: Decimal, config: &ProjectionConfig, mut on_month: F) -> Result<Decimal, ValactError> {
+    let steps_per_year = config.timestep.steps_per_year();
+    let months_per_step = 12 / steps_per_year;
+    let months_per_payment = 12 / config.premium_mode.payments_per_year();
+    let steps = Decimal::from(steps_per_year);
+    let per_1000 = Decimal::ONE_THOUSAND;
+    let round_amount = |amount: Decimal| round(&config.rounding, amount);
+    // modal premium rounded, the annual premium split evenly unless a modal factor is given
+    let modal_premium = match config.modal_factor {
+        Some(factor) => round_amount(annual_premium * to_decimal(factor, "modal factor")?),
+        None => round_amount(annual_premium / Decimal::from(config.premium_mode.payments_per_year())),
+    };
+    let mut end_value = round_amount(Decimal::ZERO);
+    let mut premiums_paid = Decimal::ZERO;
+    let mut policy_year = 0;
+
+    for i in 0..(steps_per_year * years as i32) {
+        policy_year += if (i % steps_per_year) == 0 {1} else {0};
+        let year = policy_year - 1;
+        let start_value = end_value;
+        let month_in_year = (i % steps_per_year) * months_per_step;
+        let payments = (month_in_year..month_in_year + months_per_step).filter(|m| m % months_per_payment == 0).count();
+        let premium = round_amount(match config.premium_pattern {
+            PremiumPattern::SinglePay if i == 0 => annual_premium,
+            PremiumPattern::SinglePay => Decimal::ZERO,
+            pattern if pattern.pays_in_year(issue_age, policy_year) => modal_premium * Decimal::from(payments),
+            _ => Decimal::ZERO,
+        });
+        premiums_paid += premium;
+        let premium_load = round_amount(premium * rates.premium_loads[year]);
+        let expense_charge = round_amount((rates.policy_fees[year] + rates.unit_loads[year] * face_amount / per_1000) / steps);
+        let av_for_db = start_value + premium - premium_load - expense_charge;
+        let specified_amount = match config.death_benefit_option {
+            DeathBenefitOption::Level => face_amount,
+            DeathBenefitOption::Increasing => face_amount + av_for_db.max(Decimal::ZERO),
+            DeathBenefitOption::ReturnOfPremium => face_amount + premiums_paid,
+        };
+        let db = round_amount(specified_amount.max(rates.corr_facts[year] * av_for_db));
+        let naar_disc = rates.naar_discs[year].powi(i64::from(months_per_step));
+        let naar = round_amount((db * naar_disc - av_for_db.max(Decimal::ZERO)).max(Decimal::ZERO));
+        let coi = round_amount(naar / per_1000 * (rates.coi_rates[year] / steps));
+        let av_for_interest = av_for_db - coi;
+        let interest_rate = (Decimal::ONE + rates.interest_rates[year]).powi(i64::from(months_per_step)) - Decimal::ONE;
+        let interest = round_amount((av_for_interest * interest_rate).max(Decimal::ZERO));
+        end_value = av_for_interest + interest;
+        let surrender_charge = round_amount(rates.surrender_charges[year] * face_amount / per_1000);
+
+        on_month(&DecimalMonth {
+            policy_month: i * months_per_step + 1,
+            policy_year: policy_year as i32,
+            month_in_policy_year: month_in_year + 1,
+            value_start: start_value,
+            premium,
+            premium_load,
+            expense_charge,
+            death_benefit: db,
+            naar,
+            coi_charge: coi,
+            interest,
+            value_end: end_value,
+            surrender_charge,
+            cash_surrender_value: (end_value - surrender_charge).max(round_amount(Decimal::ZERO)),
+        });
+    }
+    return Ok(end_value);
+}
+
+fn prepare(rates: &RateSet, issue_age: IssueAge, config: &ProjectionConfig) -> Result<(DecimalRates, usize), ValactError> {
+    check_supported(config)?;
+    let years = projection_years(issue_age, rates.maturity_age)?;
+    if rates.years() < years {
+        return Err(format!("rates cover {} policy years but issue age {} to maturity age {} needs {}", rates.years(), issue_age, rates.maturity_age, years).into());
+    }
+    return Ok((DecimalRates::new(rates)?, years));
+}
+
+pub fn decimal_end_value(rates: &RateSet, issue_age: IssueAge, face_amount: Decimal, annual_premium: Decimal, config: &ProjectionConfig) -> Result<Decimal, ValactError> {
+    let (decimal_rates, years) = prepare(rates, issue_age, config)?;
+    return project(&decimal_rates, issue_age, years, face_amount, annual_premium, config, |_| {});
+}
+
+pub fn decimal_projection_detail(rates: &RateSet, issue_age: IssueAge, face_amount: Decimal, annual_premium: Decimal, config: &ProjectionConfig) -> Result<Vec<DecimalMonth>, ValactError> {
+    let (decimal_rates, years) = prepare(rates, issue_age, config)?;
+    let mut months = Vec::with_capacity(config.timestep.steps_per_year() as usize * years);
+    project(&decimal_rates, issue_age, years, face_amount, annual_premium, config, |month| months.push(*month))?;
+    return Ok(months);
+}
+
+// smallest annual premium in whole units of the rounding policy that leaves a positive value at maturity
+// the floating point solve gives the starting point and the answer is found exactly by bisecting on units,
+// the end value rising with the premium
+pub fn solve_for_premium_decimal(rates: &RateSet, issue_age: IssueAge, face_amount: Decimal, config: &ProjectionConfig) -> Result<Decimal, ValactError> {
+    let (decimal_rates, years) = prepare(rates, issue_age, config)?;
+    let places = config.rounding.unit.decimal_places();
+    let endows = |premium_units: i64| -> Result<bool, ValactError> {
+        return Ok(project(&decimal_rates, issue_age, years, face_amount, Decimal::new(premium_units, places), config, |_| {})? > Decimal::ZERO);
+    };
+    let face = face_amount.to_f64().ok_or_else(|| format!("face amount {} is out of range", face_amount))?;
+    let guess = (solve_for_premium_with(rates, issue_age, face, config)?.premium / config.rounding.unit.size()).round() as i64;
+
+    // high endows and low does not, widening from the guess by doubling steps
+    let mut high = guess.max(0);
+    let mut step = 1;
+    while !endows(high)? {
+        if step > 1 << 40 {
+            return Err(format!("no premium endows a face amount of {}", face_amount).into());
+        }
+        high += step;
+        step *= 2;
+    }
+    let mut low = high - 1;
+    step = 1;
+    while low >= 0 && endows(low)? {
+        high = low;
+        low = (low - step).max(-1);
+        step *= 2;
+    }
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if endows(mid)? {high = mid} else {low = mid}
+    }
+    return Ok(Decimal::new(high, places));
+}
+
+pub fn write_decimal_projection_csv(path: &str, months: &[DecimalMonth]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for month in months {
+        wtr.serialize(month)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
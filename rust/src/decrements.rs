@@ -0,0 +1,182 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{
+    age::IssueAge, assumptions::Assumptions, error::ValactError, projection::{MonthResult, Timestep}, rates::{read_cell_coi_table, read_gen_rc_ia_py, read_py_carried, DataDir}
+};
+
+// decrement weighted projection, the single policy path from the projection scaled by the expected number
+// of policies still in force, so each row is the expected cash flow per policy issued
+// deaths are at the expected mortality assumption and lapses from the lapse table, both annual rates spread evenly
+// over the steps of the year, with deaths taken first and lapses from the survivors at the end of each step
+// expected mortality is kept apart from the coi rates, which are a charge to the policy rather than what the
+// company expects to pay in claims
+
+// expected values for one step of the projection per policy issued
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ExpectedMonth {
+    #[serde(rename="Policy_Month")]
+    pub policy_month: i32,
+    #[serde(rename="Policy_Year")]
+    pub policy_year: i32,
+    #[serde(rename="Inforce_Start")]
+    pub inforce_start: f64,
+    #[serde(rename="Deaths")]
+    pub deaths: f64,
+    #[serde(rename="Lapses")]
+    pub lapses: f64,
+    #[serde(rename="Inforce_End")]
+    pub inforce_end: f64,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Premium_Load")]
+    pub premium_load: f64,
+    #[serde(rename="Expense_Charge")]
+    pub expense_charge: f64,
+    #[serde(rename="COI_Charge")]
+    pub coi_charge: f64,
+    #[serde(rename="Withdrawal")]
+    pub withdrawal: f64,
+    #[serde(rename="Death_Benefits")]
+    pub death_benefits: f64,
+    #[serde(rename="Surrender_Benefits")]
+    pub surrender_benefits: f64,
+    // cash surrender value paid to the policies still in force at maturity, in the last step only
+    #[serde(rename="Maturity_Benefits")]
+    pub maturity_benefits: f64,
+    // company expenses from the assumptions, acquisition in the first step of a projection from issue only
+    #[serde(rename="Acquisition_Expense")]
+    pub acquisition_expense: f64,
+    #[serde(rename="Maintenance_Expense")]
+    pub maintenance_expense: f64,
+    #[serde(rename="Premium_Expense")]
+    pub premium_expense: f64,
+    #[serde(rename="Commission")]
+    pub commission: f64,
+    #[serde(rename="Premium_Tax")]
+    pub premium_tax: f64,
+    // tax on the change in the 848 dac proxy for the year, in the last step of each policy year
+    #[serde(rename="DAC_Tax")]
+    pub dac_tax: f64,
+    // account value held at the end of the step for the policies still in force
+    #[serde(rename="Account_Value")]
+    pub account_value: f64,
+}
+
+// annual lapse rates by policy year from the lapse table in the data directory, years past the end of the table
+// stay at its last rate
+// no lapses when there is no lapse table
+pub fn read_lapse_rates(data_dir: &DataDir, path: Option<&str>, years: usize) -> Result<Vec<f64>, ValactError> {
+    let path = path.map_or_else(|| data_dir.table_path("lapse"), str::to_string);
+    if !Path::new(&path).exists() {
+        return Ok(vec![0.0; years]);
+    }
+    return read_py_carried(&path, years);
+}
+
+// expected mortality per 1000 by policy year for a cell, times multiple
+// the table is an XTbML or select and ultimate csv (ending _su.csv) table, or laid out like the coi table by
+// gender, risk class, issue age and policy year; the mortality table in the data directory is used when no table
+// is given and the coi rates when there is no mortality table either
+pub fn read_mortality_rates(data_dir: &DataDir, path: Option<&str>, gender: &str, risk_class: &str, issue_age: IssueAge, coi_rates: &[f64], multiple: f64) -> Result<Vec<f64>, ValactError> {
+    return read_cell_mortality(path, &data_dir.table_path("mortality"), gender, risk_class, issue_age, coi_rates, multiple);
+}
+
+// same for any mortality table, with default_path the table in the data directory used when no path is given
+pub fn read_cell_mortality(path: Option<&str>, default_path: &str, gender: &str, risk_class: &str, issue_age: IssueAge, coi_rates: &[f64], multiple: f64) -> Result<Vec<f64>, ValactError> {
+    let years = coi_rates.len();
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(default_path).exists() => default_path,
+        None => return Ok(coi_rates.iter().map(|rate| rate * multiple).collect()),
+    };
+    let rates = if path.ends_with(".xml") || path.ends_with("_su.csv") {
+        read_cell_coi_table(path)?.rates_for_issue_age(issue_age, 1000.0, years)
+    } else {
+        read_gen_rc_ia_py(path, 1000.0, gender, risk_class, issue_age, years)?
+    };
+    return Ok(rates.iter().map(|rate| rate * multiple).collect());
+}
+
+// rate for a step of the year from an annual rate
+fn step_rate(annual_rate: f64, steps_per_year: i32) -> f64 {
+    return 1.0 - (1.0 - annual_rate.clamp(0.0, 1.0)).powf(1.0 / f64::from(steps_per_year));
+}
+
+// mortality per 1000 and lapse rates, both annual and by policy year
+// target_premium is the commission target for the policy, premium per year
+pub fn expected_cash_flows(months: &[MonthResult], mortality: &[f64], lapses: &[f64], timestep: Timestep, assumptions: &Assumptions, target_premium: f64) -> Vec<ExpectedMonth> {
+    let steps_per_year = timestep.steps_per_year();
+    let expenses = &assumptions.expenses;
+    // premium the single policy has paid so far in the policy year, for splitting commission at the target
+    let mut paid_in_year = 0.0;
+    let taxes = &assumptions.taxes;
+    let premium_tax_rate = taxes.premium_tax_rate();
+    // expected premium collected by policy year, what the dac proxy is capitalized from
+    let mut collected_by_year: Vec<f64> = Vec::new();
+    let mut expected: Vec<ExpectedMonth> = Vec::with_capacity(months.len());
+    let mut inforce = 1.0;
+
+    for (i, month) in months.iter().enumerate() {
+        let year = (month.policy_year - 1) as usize;
+        let inforce_start = inforce;
+        let deaths = inforce_start * step_rate(mortality[year] / 1000.0, steps_per_year);
+        let lapses = (inforce_start - deaths) * step_rate(lapses[year], steps_per_year);
+        inforce = inforce_start - deaths - lapses;
+        let maturity_benefits = if i + 1 == months.len() {inforce * month.cash_surrender_value} else {0.0};
+        if month.month_in_policy_year == 1 {
+            paid_in_year = 0.0;
+        }
+        let commission = assumptions.commissions.commission(month.policy_year, month.premium, paid_in_year, target_premium);
+        paid_in_year += month.premium;
+        // an inforce projection starts part way through the policy, its earlier years collected nothing here
+        if collected_by_year.len() < month.policy_year as usize {
+            collected_by_year.resize(month.policy_year as usize, 0.0);
+        }
+        collected_by_year[year] += inforce_start * month.premium;
+        let year_end = months.get(i + 1).is_none_or(|next| next.policy_year != month.policy_year);
+        let dac_tax = if year_end {
+            let amortized: f64 = collected_by_year.iter().enumerate()
+                .map(|(y, collected)| collected * taxes.dac_rate * taxes.dac_amortization(y as i32 + 1, month.policy_year))
+                .fold(0.0, |total, a| total + a);
+            taxes.corporate_tax_rate * (collected_by_year[year] * taxes.dac_rate - amortized)
+        } else {
+            0.0
+        };
+
+        expected.push(ExpectedMonth {
+            policy_month: month.policy_month,
+            policy_year: month.policy_year,
+            inforce_start,
+            deaths,
+            lapses,
+            inforce_end: inforce,
+            premium: inforce_start * month.premium,
+            premium_load: inforce_start * month.premium_load,
+            expense_charge: inforce_start * month.expense_charge,
+            coi_charge: inforce_start * month.coi_charge,
+            withdrawal: inforce_start * month.withdrawal,
+            death_benefits: deaths * month.net_death_benefit,
+            surrender_benefits: lapses * month.cash_surrender_value,
+            maturity_benefits,
+            acquisition_expense: if month.policy_month == 1 {inforce_start * expenses.acquisition(month.face_amount)} else {0.0},
+            maintenance_expense: inforce_start * expenses.maintenance(month.policy_year) / f64::from(steps_per_year),
+            premium_expense: inforce_start * month.premium * expenses.percent_of_premium(month.policy_year),
+            commission: inforce_start * commission,
+            premium_tax: inforce_start * month.premium * premium_tax_rate,
+            dac_tax,
+            account_value: inforce * month.value_end,
+        });
+    }
+    return expected;
+}
+
+pub fn write_expected_csv(path: &str, expected: &[ExpectedMonth]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for month in expected {
+        wtr.serialize(month)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
@@ -0,0 +1,110 @@
+use std::{
+    io, num::{ParseFloatError, ParseIntError}
+};
+
+use thiserror::Error;
+
+use crate::age::IssueAge;
+
+// the one error type the library returns, so a bad table says which file, row and cell it came from
+// the table readers fill in File, Row and Cell, errors from the libraries underneath convert through ? as they are
+// and everything else is a Message
+#[derive(Debug, Error)]
+pub enum ValactError {
+    // a table or input file that could not be opened or read
+    #[error("{path}: {source}")]
+    File { path: String, source: io::Error },
+    // a row of a table that did not parse, line is the line of the file (a csv header is line 1) and column the
+    // header of the field that was wrong, when the reader knows it
+    #[error("{path} line {line}{}: {message}", column.as_ref().map_or(String::new(), |c| format!(", column {}", c)))]
+    Row { path: String, line: u64, column: Option<String>, message: String },
+    // a file that parsed but is not a table the engine can use
+    #[error("{path}: {message}")]
+    Table { path: String, message: String },
+    // rates for a cell that could not be assembled, message is the underlying error
+    #[error("{gender} {risk_class} issue age {issue_age}: {message}")]
+    Cell { gender: String, risk_class: String, issue_age: IssueAge, message: String },
+    #[error("{0}")]
+    Message(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+    #[error(transparent)]
+    Xml(#[from] quick_xml::Error),
+    #[error(transparent)]
+    XmlAttribute(#[from] quick_xml::events::attributes::AttrError),
+    #[error(transparent)]
+    ParseInt(#[from] ParseIntError),
+    #[error(transparent)]
+    ParseFloat(#[from] ParseFloatError),
+    #[error(transparent)]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[cfg(feature = "arrow")]
+    #[error(transparent)]
+    Arrow(#[from] arrow_schema::ArrowError),
+    #[cfg(feature = "xlsx")]
+    #[error(transparent)]
+    Xlsx(#[from] rust_xlsxwriter::XlsxError),
+}
+
+impl From<String> for ValactError {
+    fn from(message: String) -> ValactError {
+        return ValactError::Message(message);
+    }
+}
+
+impl From<&str> for ValactError {
+    fn from(message: &str) -> ValactError {
+        return ValactError::Message(message.to_string());
+    }
+}
+
+impl ValactError {
+    // a csv reader error for the file at path, with the line and column if it was a bad field
+    // headers give the column its name rather than its position
+    pub fn csv(path: &str, headers: Option<&csv::StringRecord>, e: csv::Error) -> ValactError {
+        let line = e.position().map(|p| p.line());
+        let message = e.to_string();
+        return match (e.into_kind(), line) {
+            (csv::ErrorKind::Io(source), _) => ValactError::File { path: path.to_string(), source },
+            (csv::ErrorKind::Deserialize { err, .. }, Some(line)) => {
+                let column = err.field().map(|i| match headers.and_then(|h| h.get(i as usize)) {
+                    Some(name) => name.to_string(),
+                    None => (i + 1).to_string(),
+                });
+                ValactError::Row { path: path.to_string(), line, column, message: err.kind().to_string() }
+            }
+            (_, Some(line)) => ValactError::Row { path: path.to_string(), line, column: None, message },
+            (_, None) => ValactError::Table { path: path.to_string(), message },
+        };
+    }
+
+    // the file an error came from, unless it already says
+    pub fn in_file(self, path: &str) -> ValactError {
+        return match self {
+            ValactError::File { .. } | ValactError::Row { .. } | ValactError::Table { .. } => self,
+            ValactError::Io(source) => ValactError::File { path: path.to_string(), source },
+            ValactError::Json(e) if e.line() > 0 => ValactError::Row { path: path.to_string(), line: e.line() as u64, column: None, message: e.to_string() },
+            e => ValactError::Table { path: path.to_string(), message: e.to_string() },
+        };
+    }
+
+    // the cell whose rates were being assembled or solved, unless it already says
+    pub fn for_cell(self, gender: &str, risk_class: &str, issue_age: IssueAge) -> ValactError {
+        if let ValactError::Cell { .. } = self {
+            return self;
+        }
+        return ValactError::Cell { gender: gender.to_string(), risk_class: risk_class.to_string(), issue_age, message: self.to_string() };
+    }
+}
@@ -0,0 +1,111 @@
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::ValactError, scenarios::ScenarioSet};
+
+// simple one factor short rate generator for rough scenario work, monthly paths of annual rates that can go
+// straight to the scenario runner
+// vasicek: dr = reversion * (mean_rate - r) dt + volatility dW, rates can go negative
+// lognormal: the log of the rate follows the same process around the log of mean_rate, rates stay positive
+// both are stepped monthly with an euler step, the first month of every path is at initial_rate
+// the same seed always gives the same paths
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ShortRateModel {
+    #[default]
+    Vasicek,
+    Lognormal,
+}
+
+impl ShortRateModel {
+    // volatility is absolute for vasicek and of the log rate for lognormal so they need different defaults
+    pub fn default_volatility(self) -> f64 {
+        return match self {
+            ShortRateModel::Vasicek => 0.01,
+            ShortRateModel::Lognormal => 0.2,
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct GeneratorParams {
+    pub model: ShortRateModel,
+    pub initial_rate: f64,
+    pub mean_rate: f64,
+    // speed of mean reversion per year
+    pub reversion: f64,
+    // per sqrt(year)
+    pub volatility: f64,
+    pub seed: u64,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        let model = ShortRateModel::default();
+        return GeneratorParams { model, initial_rate: 0.03, mean_rate: 0.04, reversion: 0.1, volatility: model.default_volatility(), seed: 1 };
+    }
+}
+
+// splitmix64, small and good enough for scenario generation without pulling in a rand crate
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        return Rng { state: seed };
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        return z ^ (z >> 31);
+    }
+
+    // uniform on (0, 1], never zero so its log is finite
+    fn uniform(&mut self) -> f64 {
+        return ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64;
+    }
+
+    // standard normal by box-muller, the second variate is thrown away to keep the stream simple
+    fn normal(&mut self) -> f64 {
+        let u1 = self.uniform();
+        let u2 = self.uniform();
+        return (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+    }
+}
+
+// a set of scenarios paths, each months long and named 1 up to scenarios
+pub fn generate_scenarios(params: &GeneratorParams, scenarios: usize, months: usize) -> Result<ScenarioSet, ValactError> {
+    if scenarios == 0 || months == 0 {
+        return Err("need at least one scenario of at least one month".into());
+    }
+    if params.model == ShortRateModel::Lognormal && (params.initial_rate <= 0.0 || params.mean_rate <= 0.0) {
+        return Err("lognormal rates need positive initial and mean rates".into());
+    }
+    let dt = 1.0 / 12.0;
+    let shock = params.volatility * f64::sqrt(dt);
+    let mut rng = Rng::new(params.seed);
+    let mut paths: Vec<Vec<f64>> = Vec::with_capacity(scenarios);
+
+    for _ in 0..scenarios {
+        let mut path = Vec::with_capacity(months);
+        let mut rate = params.initial_rate;
+        for _ in 0..months {
+            path.push(rate);
+            let z = rng.normal();
+            rate = match params.model {
+                ShortRateModel::Vasicek => rate + params.reversion * (params.mean_rate - rate) * dt + shock * z,
+                ShortRateModel::Lognormal => {
+                    let x = rate.ln();
+                    (x + params.reversion * (params.mean_rate.ln() - x) * dt + shock * z).exp()
+                }
+            };
+        }
+        paths.push(path);
+    }
+    let names = (1..=scenarios).map(|n| n.to_string()).collect();
+    return Ok(ScenarioSet { names, paths });
+}
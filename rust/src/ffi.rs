@@ -0,0 +1,241 @@
+use std::{
+    cell::RefCell, ffi::{c_char, CStr}, panic::{catch_unwind, AssertUnwindSafe}, ptr, slice
+};
+
+use crate::{
+    age::IssueAge, batch::PolicyRecord, error::ValactError, projection::{at_issue_projection_detail_with, at_issue_projection_with, solve_for_premium_with, DeathBenefitOption, ProjectionConfig}, rates::{DataDir, RateSet, DEFAULT_MATURITY_AGE}, repository::RateRepository, runner
+};
+
+// c api for calling the engine from c#, vba, excel add-ins and anything else that can load a dll
+// declared in include/valact.h, the structs there must be kept in step with the repr(C) ones here
+// the rate repository is an opaque handle, made once and shared by every call (and thread) until it is freed
+// every function returns 0 on success and -1 on failure, with the message from valact_last_error, and a panic
+// is caught and reported as a failure rather than unwinding into the caller
+// strings are nul terminated utf-8
+
+pub const VALACT_OK: i32 = 0;
+pub const VALACT_ERROR: i32 = -1;
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+pub struct ValactRepository {
+    repository: RateRepository,
+}
+
+#[repr(C)]
+pub struct ValactPolicy {
+    pub gender: *const c_char,
+    pub risk_class: *const c_char,
+    pub issue_age: i32,
+    pub face_amount: f64,
+    // 1 level (or 0 for a zeroed struct), 2 increasing, 3 return of premium
+    pub death_benefit_option: i32,
+}
+
+#[repr(C)]
+pub struct ValactSolve {
+    pub premium: f64,
+    pub iterations: i32,
+    pub converged: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct ValactMonth {
+    pub policy_month: i32,
+    pub policy_year: i32,
+    pub value_start: f64,
+    pub premium: f64,
+    pub premium_load: f64,
+    pub expense_charge: f64,
+    pub death_benefit: f64,
+    pub naar: f64,
+    pub coi_charge: f64,
+    pub interest: f64,
+    pub value_end: f64,
+    pub cash_surrender_value: f64,
+}
+
+// runs f, recording any error or panic for valact_last_error
+fn guarded<F>(f: F) -> i32
+where
+    F: FnOnce() -> Result<(), ValactError>,
+{
+    let message = match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => return VALACT_OK,
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "panic in valact".to_string(),
+    };
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    return VALACT_ERROR;
+}
+
+unsafe fn to_str<'a>(s: *const c_char, name: &str) -> Result<&'a str, ValactError> {
+    if s.is_null() {
+        return Err(format!("{} is null", name).into());
+    }
+    return Ok(unsafe { CStr::from_ptr(s) }.to_str().map_err(|e| format!("{} is not utf-8: {}", name, e))?);
+}
+
+unsafe fn to_record(policy: *const ValactPolicy) -> Result<PolicyRecord, ValactError> {
+    let policy = unsafe { policy.as_ref() }.ok_or("policy is null")?;
+    let death_benefit_option = match policy.death_benefit_option {
+        0 | 1 => DeathBenefitOption::Level,
+        2 => DeathBenefitOption::Increasing,
+        3 => DeathBenefitOption::ReturnOfPremium,
+        option => return Err(format!("death benefit option {} must be 1, 2 or 3", option).into()),
+    };
+    return Ok(PolicyRecord {
+        gender: unsafe { to_str(policy.gender, "gender") }?.to_string(),
+        risk_class: unsafe { to_str(policy.risk_class, "risk_class") }?.to_string(),
+        issue_age: IssueAge::try_from(i64::from(policy.issue_age))?,
+        face_amount: policy.face_amount,
+        death_benefit_option,
+        inforce: None,
+        issue_date: None,
+    });
+}
+
+unsafe fn cell_rates(repository: *const ValactRepository, policy: &PolicyRecord) -> Result<RateSet, ValactError> {
+    let repository = unsafe { repository.as_ref() }.ok_or("repository is null")?;
+    return repository.repository.get_rates(&policy.gender, &policy.risk_class, policy.issue_age);
+}
+
+fn config_for(policy: &PolicyRecord) -> ProjectionConfig {
+    return ProjectionConfig { death_benefit_option: policy.death_benefit_option, ..Default::default() };
+}
+
+// copies the last error on this thread into buffer, truncated to fit with a nul, and returns its full length
+/// # Safety
+/// buffer must point to at least length writable bytes, or be null to just get the length
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn valact_last_error(buffer: *mut c_char, length: usize) -> usize {
+    return LAST_ERROR.with(|last| {
+        let message = last.borrow();
+        if !buffer.is_null() && length > 0 {
+            let n = message.len().min(length - 1);
+            unsafe {
+                ptr::copy_nonoverlapping(message.as_ptr() as *const c_char, buffer, n);
+                *buffer.add(n) = 0;
+            }
+        }
+        message.len()
+    });
+}
+
+// rate tables read from data_dir (./data when null) and parsed once, null on failure
+// the handle keeps its own data directory, another handle can read a different one
+/// # Safety
+/// data_dir must be null or a nul terminated string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn valact_repository_new(data_dir: *const c_char) -> *mut ValactRepository {
+    let mut handle = ptr::null_mut();
+    guarded(|| {
+        let data_dir = if data_dir.is_null() {DataDir::default()} else {DataDir::new(unsafe { to_str(data_dir, "data_dir") }?)};
+        handle = Box::into_raw(Box::new(ValactRepository { repository: RateRepository::in_memory_to_maturity(&data_dir, DEFAULT_MATURITY_AGE)? }));
+        return Ok(());
+    });
+    return handle;
+}
+
+/// # Safety
+/// repository must come from valact_repository_new and not be used again
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn valact_repository_free(repository: *mut ValactRepository) {
+    if !repository.is_null() {
+        drop(unsafe { Box::from_raw(repository) });
+    }
+}
+
+// level annual premium that endows the policy at maturity
+/// # Safety
+/// repository, policy and result must be valid pointers
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn valact_solve_premium(repository: *const ValactRepository, policy: *const ValactPolicy, result: *mut ValactSolve) -> i32 {
+    return guarded(|| {
+        let policy = unsafe { to_record(policy) }?;
+        let rates = unsafe { cell_rates(repository, &policy) }?;
+        let solve = solve_for_premium_with(&rates, policy.issue_age, policy.face_amount, &config_for(&policy))?;
+        let result = unsafe { result.as_mut() }.ok_or("result is null")?;
+        *result = ValactSolve { premium: solve.premium, iterations: solve.iterations as i32, converged: i32::from(solve.converged) };
+        return Ok(());
+    });
+}
+
+// account value at maturity at an annual premium
+/// # Safety
+/// repository, policy and end_value must be valid pointers
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn valact_end_value(repository: *const ValactRepository, policy: *const ValactPolicy, annual_premium: f64, end_value: *mut f64) -> i32 {
+    return guarded(|| {
+        let policy = unsafe { to_record(policy) }?;
+        let rates = unsafe { cell_rates(repository, &policy) }?;
+        let value = at_issue_projection_with(&rates, policy.issue_age, policy.face_amount, annual_premium, &config_for(&policy))?;
+        *unsafe { end_value.as_mut() }.ok_or("end_value is null")? = value;
+        return Ok(());
+    });
+}
+
+// monthly projection into months, up to capacity rows, with the number of months the projection has in count
+// call with capacity 0 to size the buffer first
+/// # Safety
+/// repository, policy and count must be valid pointers, months must have room for capacity rows
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn valact_project(repository: *const ValactRepository, policy: *const ValactPolicy, annual_premium: f64, months: *mut ValactMonth, capacity: usize, count: *mut usize) -> i32 {
+    return guarded(|| {
+        let policy = unsafe { to_record(policy) }?;
+        let rates = unsafe { cell_rates(repository, &policy) }?;
+        let detail = at_issue_projection_detail_with(&rates, policy.issue_age, policy.face_amount, annual_premium, &config_for(&policy))?;
+        *unsafe { count.as_mut() }.ok_or("count is null")? = detail.len();
+        if capacity == 0 {
+            return Ok(());
+        }
+        if months.is_null() {
+            return Err("months is null".into());
+        }
+        let out = unsafe { slice::from_raw_parts_mut(months, capacity) };
+        for (row, month) in out.iter_mut().zip(&detail) {
+            *row = ValactMonth {
+                policy_month: month.policy_month,
+                policy_year: month.policy_year,
+                value_start: month.value_start,
+                premium: month.premium,
+                premium_load: month.premium_load,
+                expense_charge: month.expense_charge,
+                death_benefit: month.death_benefit,
+                naar: month.naar,
+                coi_charge: month.coi_charge,
+                interest: month.interest,
+                value_end: month.value_end,
+                cash_surrender_value: month.cash_surrender_value,
+            };
+        }
+        return Ok(());
+    });
+}
+
+// premiums for count policies into premiums, solved across threads (0 for one per core)
+/// # Safety
+/// repository must be valid, policies and premiums must each have count entries
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn valact_solve_batch(repository: *const ValactRepository, policies: *const ValactPolicy, count: usize, threads: usize, premiums: *mut f64) -> i32 {
+    return guarded(|| {
+        let repository = unsafe { repository.as_ref() }.ok_or("repository is null")?;
+        if count == 0 {
+            return Ok(());
+        }
+        if policies.is_null() || premiums.is_null() {
+            return Err("policies or premiums is null".into());
+        }
+        let records = unsafe { slice::from_raw_parts(policies, count) }.iter().map(|policy| unsafe { to_record(policy) }).collect::<Result<Vec<PolicyRecord>, ValactError>>()?;
+        let threads = if threads == 0 {None} else {Some(threads)};
+        let results = runner::solve_policies_parallel_with(&records, threads, |policy| repository.repository.get_shared(&policy.gender, &policy.risk_class, policy.issue_age))?;
+        let out = unsafe { slice::from_raw_parts_mut(premiums, count) };
+        for (premium, result) in out.iter_mut().zip(&results) {
+            *premium = result.premium;
+        }
+        return Ok(());
+    });
+}
@@ -0,0 +1,378 @@
+use std::{
+    borrow::Borrow, collections::HashMap, sync::mpsc
+};
+
+use bytemuck::{Pod, Zeroable};
+use tracing::info;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    age::IssueAge, batch::{policy_config, policy_result, policy_span, solve_policy_using, PolicyRecord, PolicyResult}, error::ValactError, lanes::{self, solve_in_lockstep}, projection::{path_rate, DeathBenefitOption, PremiumPattern, ProjectionConfig, SolveResult}, rates::{projection_years, RateSet}, scenarios::{ScenarioResult, ScenarioSet}, solver::{Method, SolverOptions}
+};
+
+// experimental projection of a batch or of scenario paths on a compute shader (gpu.wgsl) through wgpu, for
+// --backend gpu on large stochastic runs
+// each invocation projects one cell, a policy or a scenario path, to maturity at the premium it is given, so a
+// dispatch is every cell at once; a premium solve is the bisection of lanes::solve_for_premiums with each of its
+// steps one dispatch across all of the cells
+// the kernel works in single precision, solved premiums agree with the cpu's to about a part in a hundred
+// thousand, a cent or two, and end values typically to a part in a million, further on paths where the account
+// value runs negative; fine for the distribution of a stochastic run but not for tying out a single policy
+// only what lanes covers can run on the gpu, with interest paths passed per cell and no rounding of amounts (see
+// supported), anything else is left to the cpu
+
+// cells in one dispatch at most, fewer if their tables would run past the device's largest storage buffer
+pub const MAX_CELLS: usize = 65536;
+
+const WORKGROUP_SIZE: u32 = 64;
+const NO_PATH: u32 = u32::MAX;
+
+// layouts of the structs in gpu.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Year {
+    premium_load: f32,
+    policy_fee: f32,
+    unit_load: f32,
+    corridor_factor: f32,
+    naar_discount: f32,
+    coi_rate: f32,
+    interest_rate: f32,
+    unused: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuCell {
+    year: u32,
+    schedule: u32,
+    path: u32,
+    steps: u32,
+    face_amount: f32,
+    option: u32,
+    unused: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Params {
+    cells: u32,
+    steps_per_year: u32,
+    unused: [u32; 2],
+}
+
+// what the kernel can stand in for, a plain at issue projection solved by bisection without rounded amounts
+// the config's own interest path has to be empty, paths are given per cell
+pub fn supported(config: &ProjectionConfig, options: &SolverOptions) -> bool {
+    return lanes::supported(config, options) && !config.rounding.amounts;
+}
+
+pub struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    adapter: String,
+    max_binding: u64,
+}
+
+impl Gpu {
+    // the first adapter wgpu finds, an error if there is none or it cannot run compute shaders
+    pub fn new() -> Result<Gpu, ValactError> {
+        let instance = wgpu::Instance::default();
+        let options = wgpu::RequestAdapterOptions { power_preference: wgpu::PowerPreference::HighPerformance, force_fallback_adapter: false, compatible_surface: None, ..Default::default() };
+        let adapter = pollster::block_on(instance.request_adapter(&options)).map_err(|e| format!("no gpu adapter: {}", e))?;
+        let info = adapter.get_info();
+        let adapter_name = format!("{} ({:?})", info.name, info.backend);
+        if !adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS) {
+            return Err(format!("gpu adapter {} cannot run compute shaders", adapter_name).into());
+        }
+        let limits = adapter.limits();
+        let descriptor = wgpu::DeviceDescriptor { label: Some("valact"), required_limits: limits.clone(), ..Default::default() };
+        let (device, queue) = pollster::block_on(adapter.request_device(&descriptor)).map_err(|e| format!("gpu adapter {}: {}", adapter_name, e))?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor { label: Some("projection"), source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()) });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("projection"),
+            layout: None,
+            module: &module,
+            entry_point: Some("project"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        info!(adapter = %adapter_name, "projecting on the gpu");
+        let max_binding = limits.max_storage_buffer_binding_size.min(limits.max_buffer_size);
+        return Ok(Gpu { device, queue, pipeline, adapter: adapter_name, max_binding });
+    }
+
+    // name and backend of the adapter, e.g. "NVIDIA GeForce RTX 4070 (Vulkan)"
+    pub fn adapter(&self) -> &str {
+        return &self.adapter;
+    }
+
+    // cells in a dispatch when each needs this many bytes of tables
+    pub fn cells_per_dispatch(&self, bytes_per_cell: u64) -> usize {
+        return usize::try_from(self.max_binding / bytes_per_cell.max(1)).unwrap_or(MAX_CELLS).clamp(1, MAX_CELLS);
+    }
+}
+
+// a cell of a dispatch, rates cover the issue age to the maturity age; an empty interest path credits the rates'
+// interest table
+#[derive(Clone, Copy)]
+pub struct Cell<'a> {
+    pub rates: &'a RateSet,
+    pub issue_age: IssueAge,
+    pub face_amount: f64,
+    pub death_benefit_option: DeathBenefitOption,
+    pub interest_path: &'a [f64],
+}
+
+impl Cell<'_> {
+    // bytes of tables the cell adds to a dispatch, at most
+    pub fn bytes(&self) -> u64 {
+        let years = self.rates.years() as u64;
+        return years * (size_of::<Year>() as u64 + 12 * 4) + if self.interest_path.is_empty() {0} else {years * 12 * 4};
+    }
+}
+
+// cells laid out on the device ready to be projected at any premiums, tables are uploaded once and only the
+// premiums go up with each dispatch
+pub struct Projections<'g> {
+    gpu: &'g Gpu,
+    config: ProjectionConfig,
+    count: usize,
+    face_amounts: Vec<f64>,
+    bind_group: wgpu::BindGroup,
+    premiums: wgpu::Buffer,
+    values: wgpu::Buffer,
+    readback: wgpu::Buffer,
+}
+
+impl<'g> Projections<'g> {
+    // the cells' death benefit options are used in place of config's
+    pub fn new(gpu: &'g Gpu, cells: &[Cell], config: &ProjectionConfig) -> Result<Projections<'g>, ValactError> {
+        if cells.is_empty() || cells.len() > MAX_CELLS {
+            return Err(format!("{} cells for a dispatch of at most {}", cells.len(), MAX_CELLS).into());
+        }
+        if !supported(config, &SolverOptions::new(Method::Bisection, 0.005)) {
+            return Err("only a plain at issue projection without rounded amounts can be run on the gpu".into());
+        }
+        let steps_per_year = config.timestep.steps_per_year();
+        let months_per_step = 12 / steps_per_year;
+        let months_per_payment = 12 / config.premium_mode.payments_per_year();
+        let steps = f64::from(steps_per_year);
+
+        let mut years: Vec<Year> = Vec::new();
+        let mut schedules: Vec<f32> = Vec::new();
+        let mut paths: Vec<f32> = Vec::new();
+        let mut gpu_cells = Vec::with_capacity(cells.len());
+        // cells sharing rates or an issue age share their years and premium schedule
+        let mut year_offsets: HashMap<*const RateSet, u32> = HashMap::new();
+        let mut schedule_offsets: HashMap<(IssueAge, usize), u32> = HashMap::new();
+        let offset = |len: usize| u32::try_from(len).map_err(|_| ValactError::from("gpu tables past 4 billion entries".to_string()));
+        for cell in cells {
+            let rates = cell.rates;
+            let cell_years = projection_years(cell.issue_age, rates.maturity_age)?;
+            if rates.years() < cell_years {
+                return Err(format!("rates covering {} policy years for a projection of {}", rates.years(), cell_years).into());
+            }
+            let cell_steps = cell_years * steps_per_year as usize;
+            let year = match year_offsets.get(&std::ptr::from_ref(rates)) {
+                Some(&year) => year,
+                None => {
+                    let year = offset(years.len())?;
+                    years.extend((0..rates.years()).map(|y| Year {
+                        premium_load: rates.premium_loads[y] as f32,
+                        policy_fee: (rates.policy_fees[y] / steps) as f32,
+                        unit_load: (rates.unit_loads[y] / 1000.0 / steps) as f32,
+                        corridor_factor: rates.corr_facts[y] as f32,
+                        naar_discount: rates.naar_discs[y].powi(months_per_step) as f32,
+                        coi_rate: (rates.coi_rates[y] / steps / 1000.0) as f32,
+                        interest_rate: ((1.0 + rates.interest_rates[y]).powi(months_per_step) - 1.0) as f32,
+                        unused: 0.0,
+                    }));
+                    year_offsets.insert(std::ptr::from_ref(rates), year);
+                    year
+                }
+            };
+            let schedule = match schedule_offsets.get(&(cell.issue_age, cell_steps)) {
+                Some(&schedule) => schedule,
+                None => {
+                    let schedule = offset(schedules.len())?;
+                    schedules.extend((0..cell_steps as i32).map(|i| {
+                        let policy_year = (i / steps_per_year) as usize + 1;
+                        let month_in_year = (i % steps_per_year) * months_per_step;
+                        let payments = (month_in_year..month_in_year + months_per_step).filter(|m| m % months_per_payment == 0).count();
+                        let multiple = match config.premium_pattern {
+                            PremiumPattern::SinglePay if i == 0 => 1.0,
+                            PremiumPattern::SinglePay => 0.0,
+                            pattern if pattern.pays_in_year(cell.issue_age, policy_year) => config.modal_factor() * payments as f64,
+                            _ => 0.0,
+                        };
+                        return multiple as f32;
+                    }));
+                    schedule_offsets.insert((cell.issue_age, cell_steps), schedule);
+                    schedule
+                }
+            };
+            let path = match cell.interest_path {
+                [] => NO_PATH,
+                path => {
+                    let start = offset(paths.len())?;
+                    paths.extend((0..cell_steps).map(|i| path_rate(path, i * months_per_step as usize, months_per_step as usize) as f32));
+                    start
+                }
+            };
+            let (face_amount, option) = match cell.death_benefit_option {
+                DeathBenefitOption::Level => (cell.face_amount.max(0.0), 0),
+                DeathBenefitOption::Increasing => (cell.face_amount, 1),
+                DeathBenefitOption::ReturnOfPremium => (cell.face_amount, 2),
+            };
+            gpu_cells.push(GpuCell { year, schedule, path, steps: offset(cell_steps)?, face_amount: face_amount as f32, option, unused: [0; 2] });
+        }
+        // a binding cannot be empty
+        if paths.is_empty() {
+            paths.push(0.0);
+        }
+        let largest = [size_of_val(&years[..]), size_of_val(&schedules[..]), size_of_val(&paths[..])].into_iter().max().unwrap_or(0) as u64;
+        if largest > gpu.max_binding {
+            return Err(format!("{} bytes of tables for one storage buffer of at most {}, dispatch fewer cells", largest, gpu.max_binding).into());
+        }
+
+        let device = &gpu.device;
+        let storage = |label: &str, contents: &[u8]| -> wgpu::Buffer {
+            return device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some(label), contents, usage: wgpu::BufferUsages::STORAGE });
+        };
+        let params = Params { cells: offset(cells.len())?, steps_per_year: steps_per_year as u32, unused: [0; 2] };
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some("params"), contents: bytemuck::bytes_of(&params), usage: wgpu::BufferUsages::UNIFORM });
+        let cell_buffer = storage("cells", bytemuck::cast_slice(&gpu_cells));
+        let year_buffer = storage("years", bytemuck::cast_slice(&years));
+        let schedule_buffer = storage("schedules", bytemuck::cast_slice(&schedules));
+        let path_buffer = storage("paths", bytemuck::cast_slice(&paths));
+        let size = (cells.len() * size_of::<f32>()) as u64;
+        let premiums = device.create_buffer(&wgpu::BufferDescriptor { label: Some("premiums"), size, usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false });
+        let values = device.create_buffer(&wgpu::BufferDescriptor { label: Some("values"), size, usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC, mapped_at_creation: false });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor { label: Some("readback"), size, usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false });
+        let buffers = [&params, &cell_buffer, &year_buffer, &schedule_buffer, &path_buffer, &premiums, &values];
+        let entries: Vec<wgpu::BindGroupEntry> = buffers.iter().enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry { binding: binding as u32, resource: buffer.as_entire_binding() })
+            .collect();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor { label: Some("projection"), layout: &gpu.pipeline.get_bind_group_layout(0), entries: &entries });
+
+        return Ok(Projections {
+            gpu,
+            config: config.clone(),
+            count: cells.len(),
+            face_amounts: cells.iter().map(|cell| cell.face_amount).collect(),
+            bind_group,
+            premiums,
+            values,
+            readback,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        return self.count;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.count == 0;
+    }
+
+    // account value at maturity of every cell at its annual premium, one dispatch
+    pub fn end_values(&self, annual_premiums: &[f64]) -> Result<Vec<f64>, ValactError> {
+        if annual_premiums.len() != self.count {
+            return Err(format!("{} premiums for {} cells", annual_premiums.len(), self.count).into());
+        }
+        let Gpu { device, queue, pipeline, .. } = self.gpu;
+        let premiums: Vec<f32> = annual_premiums.iter().map(|premium| *premium as f32).collect();
+        queue.write_buffer(&self.premiums, 0, bytemuck::cast_slice(&premiums));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("projection") });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("projection"), timestamp_writes: None });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups((self.count as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.values, 0, &self.readback, 0, self.readback.size());
+        queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = mpsc::channel();
+        self.readback.map_async(wgpu::MapMode::Read, .., move |mapped| {
+            let _ = sender.send(mapped);
+        });
+        device.poll(wgpu::PollType::wait_indefinitely()).map_err(|e| format!("gpu projection: {}", e))?;
+        receiver.recv().map_err(|e| e.to_string())?.map_err(|e| format!("reading gpu results: {}", e))?;
+        let mapped = self.readback.get_mapped_range(..).map_err(|e| format!("reading gpu results: {}", e))?;
+        let values = bytemuck::cast_slice::<u8, f32>(&mapped).iter().map(|value| f64::from(*value)).collect();
+        drop(mapped);
+        self.readback.unmap();
+        return Ok(values);
+    }
+
+    // the premium each cell endows at maturity for, solved by bisection as solve_for_premium_using would
+    pub fn solve_for_premiums(&self, options: &SolverOptions) -> Result<Vec<Result<SolveResult, String>>, ValactError> {
+        if !supported(&self.config, options) {
+            return Err("only a bisection solve can be run on the gpu".into());
+        }
+        return solve_in_lockstep(&self.face_amounts, &self.config, options, |premiums| self.end_values(premiums));
+    }
+}
+
+// run_scenarios on the gpu, every path projected and solved at once a dispatch of paths at a time
+pub fn run_scenarios(gpu: &Gpu, rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig, scenarios: &ScenarioSet) -> Result<Vec<ScenarioResult>, ValactError> {
+    // the solver solve_for_premium_with uses
+    let options = SolverOptions::new(Method::Bisection, 0.005);
+    let cells: Vec<Cell> = scenarios.paths.iter()
+        .map(|path| Cell { rates, issue_age, face_amount, death_benefit_option: config.death_benefit_option, interest_path: path })
+        .collect();
+    let per_dispatch = gpu.cells_per_dispatch(cells.iter().map(|cell| cell.bytes()).max().unwrap_or(0));
+    let mut results = Vec::with_capacity(cells.len());
+    for (cells, names) in cells.chunks(per_dispatch).zip(scenarios.names.chunks(per_dispatch)) {
+        let projections = Projections::new(gpu, cells, config)?;
+        let end_values = projections.end_values(&vec![annual_premium; cells.len()])?;
+        for ((name, end_value), solved) in names.iter().zip(end_values).zip(projections.solve_for_premiums(&options)?) {
+            results.push(ScenarioResult { name: name.clone(), end_value, premium: solved?.premium });
+        }
+    }
+    return Ok(results);
+}
+
+// the batch's premiums solved on the gpu a dispatch of policies at a time, in the order of policies as
+// solve_policy_using gives them; policies the kernel does not cover, inforce ones or policies whose options
+// need rounded amounts, are solved on the cpu
+pub fn solve_policies<F, S>(gpu: &Gpu, policies: &[PolicyRecord], rates_for: &F, options: &SolverOptions) -> Result<Vec<Result<PolicyResult, ValactError>>, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError>,
+    S: Borrow<RateSet>,
+{
+    let mut results: Vec<Option<Result<PolicyResult, ValactError>>> = policies.iter().map(|_| None).collect();
+    let mut rates = Vec::new();
+    let mut on_gpu = Vec::new();
+    for (i, policy) in policies.iter().enumerate() {
+        if policy.inforce.is_some() || !supported(&policy_config(policy), options) {
+            results[i] = Some(solve_policy_using(policy, rates_for, options));
+            continue;
+        }
+        let _entered = policy_span(policy).entered();
+        match rates_for(policy) {
+            Ok(policy_rates) => {
+                rates.push(policy_rates);
+                on_gpu.push(i);
+            }
+            Err(e) => results[i] = Some(policy_result(policy, Err(e))),
+        }
+    }
+
+    let cells: Vec<Cell> = on_gpu.iter().zip(&rates).map(|(&i, rates)| {
+        let policy = &policies[i];
+        return Cell { rates: rates.borrow(), issue_age: policy.issue_age, face_amount: policy.face_amount, death_benefit_option: policy.death_benefit_option, interest_path: &[] };
+    }).collect();
+    let per_dispatch = gpu.cells_per_dispatch(cells.iter().map(|cell| cell.bytes()).max().unwrap_or(0));
+    for (cells, indexes) in cells.chunks(per_dispatch).zip(on_gpu.chunks(per_dispatch)) {
+        let projections = Projections::new(gpu, cells, &policy_config(&policies[indexes[0]]))?;
+        for (&i, solved) in indexes.iter().zip(projections.solve_for_premiums(options)?) {
+            let _entered = policy_span(&policies[i]).entered();
+            results[i] = Some(policy_result(&policies[i], solved.map_err(ValactError::from)));
+        }
+    }
+    return Ok(results.into_iter().flatten().collect());
+}
@@ -0,0 +1,81 @@
+// end value of the at issue projection for one cell (policy or scenario path) per invocation, see gpu.rs
+// the steps are project()'s with amounts unrounded and no schedules, riders or accounts, in single precision
+
+struct Year {
+    premium_load: f32,
+    // policy fee and unit load per 1000 of face for one step
+    policy_fee: f32,
+    unit_load: f32,
+    corridor_factor: f32,
+    // naar discount over one step
+    naar_discount: f32,
+    // coi per 1 of naar for one step
+    coi_rate: f32,
+    // interest over one step
+    interest_rate: f32,
+    unused: f32,
+}
+
+struct Cell {
+    // offsets of the cell's first policy year, premium multiple and path rate
+    year: u32,
+    schedule: u32,
+    path: u32,
+    steps: u32,
+    face_amount: f32,
+    // 0 level, 1 increasing, 2 return of premium
+    option: u32,
+    unused: vec2<u32>,
+}
+
+struct Params {
+    cells: u32,
+    steps_per_year: u32,
+    unused: vec2<u32>,
+}
+
+const NO_PATH: u32 = 0xffffffffu;
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> cells: array<Cell>;
+@group(0) @binding(2) var<storage, read> years: array<Year>;
+// the premium paid each step as a multiple of the annual premium
+@group(0) @binding(3) var<storage, read> schedules: array<f32>;
+// interest over each step of the cells credited a scenario path
+@group(0) @binding(4) var<storage, read> paths: array<f32>;
+@group(0) @binding(5) var<storage, read> premiums: array<f32>;
+@group(0) @binding(6) var<storage, read_write> values: array<f32>;
+
+@compute @workgroup_size(64)
+fn project(@builtin(global_invocation_id) id: vec3<u32>) {
+    let c = id.x;
+    if (c >= params.cells) {
+        return;
+    }
+    let cell = cells[c];
+    let annual_premium = premiums[c];
+    var value = 0.0;
+    var premiums_paid = 0.0;
+    for (var i = 0u; i < cell.steps; i++) {
+        let year = years[cell.year + i / params.steps_per_year];
+        let premium = annual_premium * schedules[cell.schedule + i];
+        premiums_paid += premium;
+        let expense_charge = year.policy_fee + year.unit_load * cell.face_amount;
+        let av_for_db = value + premium - premium * year.premium_load - expense_charge;
+        var specified_amount = cell.face_amount;
+        if (cell.option == 1u) {
+            specified_amount = cell.face_amount + max(av_for_db, 0.0);
+        } else if (cell.option == 2u) {
+            specified_amount = cell.face_amount + premiums_paid;
+        }
+        let db = max(specified_amount, year.corridor_factor * av_for_db);
+        let naar = max(db * year.naar_discount - max(av_for_db, 0.0), 0.0);
+        let av_for_interest = av_for_db - naar * year.coi_rate;
+        var interest_rate = year.interest_rate;
+        if (cell.path != NO_PATH) {
+            interest_rate = paths[cell.path + i];
+        }
+        value = av_for_interest + max(av_for_interest * interest_rate, 0.0);
+    }
+    values[c] = value;
+}
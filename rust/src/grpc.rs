@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+use crate::{
+    age::IssueAge,
+    batch::PolicyRecord,
+    error::ValactError,
+    projection::{solve_for_premium_with, DeathBenefitOption, ProjectionConfig, SolveTarget},
+    reload::LiveRates,
+    repository::RateRepository,
+    runner,
+    solver::SolverOptions,
+};
+
+// grpc service from proto/valact.proto, sharing one rate repository across every call like the http api and
+// reloaded the same way when the server watches the data directory, Tables reports what is live
+// Solve is one policy, BatchProject streams each policy's premium back as soon as it is solved so a large
+// batch can be consumed while it runs, a policy that fails comes back with error set and the rest carry on
+
+pub mod proto {
+    tonic::include_proto!("valact");
+}
+
+use proto::{
+    valact_server::{Valact, ValactServer}, BatchRequest, Policy, PolicyResult, SolveRequest, SolveResponse, TableFile, TablesRequest, TablesResponse
+};
+
+// results waiting to be sent before the solving threads are held up by a slow client
+const STREAM_BUFFER: usize = 256;
+
+pub struct ValactService {
+    live: Arc<LiveRates>,
+}
+
+impl ValactService {
+    // a repository that is never reloaded
+    pub fn new(repository: impl Into<Arc<RateRepository>>) -> ValactService {
+        return ValactService::live(Arc::new(LiveRates::new(repository)));
+    }
+
+    pub fn live(live: Arc<LiveRates>) -> ValactService {
+        return ValactService { live };
+    }
+}
+
+fn to_record(policy: &Policy) -> Result<PolicyRecord, String> {
+    let death_benefit_option = match policy.death_benefit_option {
+        0 | 1 => DeathBenefitOption::Level,
+        2 => DeathBenefitOption::Increasing,
+        3 => DeathBenefitOption::ReturnOfPremium,
+        option => return Err(format!("death benefit option {} must be 1, 2 or 3", option)),
+    };
+    return Ok(PolicyRecord {
+        gender: policy.gender.clone(),
+        risk_class: policy.risk_class.clone(),
+        issue_age: IssueAge::try_from(i64::from(policy.issue_age)).map_err(|e| e.to_string())?,
+        face_amount: policy.face_amount,
+        death_benefit_option,
+        inforce: None,
+        issue_date: None,
+    });
+}
+
+fn solve(repository: &RateRepository, policy: &PolicyRecord) -> Result<SolveResponse, ValactError> {
+    let config = ProjectionConfig { death_benefit_option: policy.death_benefit_option, ..Default::default() };
+    let rates = repository.get_shared(&policy.gender, &policy.risk_class, policy.issue_age)?;
+    let solve = solve_for_premium_with(&rates, policy.issue_age, policy.face_amount, &config)?;
+    return Ok(SolveResponse { premium: solve.premium, iterations: solve.iterations as u32, converged: solve.converged });
+}
+
+#[tonic::async_trait]
+impl Valact for ValactService {
+    async fn solve(&self, request: Request<SolveRequest>) -> Result<Response<SolveResponse>, Status> {
+        let policy = request.into_inner().policy.ok_or_else(|| Status::invalid_argument("policy is missing"))?;
+        let policy = to_record(&policy).map_err(Status::invalid_argument)?;
+        let repository = self.live.current();
+        // cpu bound so off the async workers
+        let solved = tokio::task::spawn_blocking(move || solve(&repository, &policy).map_err(|e| e.to_string()))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        return solved.map(Response::new).map_err(|message| {
+            warn!("{}", message);
+            Status::failed_precondition(message)
+        });
+    }
+
+    type BatchProjectStream = ReceiverStream<Result<PolicyResult, Status>>;
+
+    async fn batch_project(&self, request: Request<BatchRequest>) -> Result<Response<Self::BatchProjectStream>, Status> {
+        let request = request.into_inner();
+        let policies = request.policies.iter().map(to_record).collect::<Result<Vec<PolicyRecord>, String>>().map_err(Status::invalid_argument)?;
+        let threads = if request.threads == 0 {None} else {Some(request.threads as usize)};
+        // the whole batch is solved on the repository live when it came in
+        let repository = self.live.current();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            let options = SolverOptions::new(SolveTarget::EndowAtMaturity.default_method(), 0.005);
+            let rates_for = |policy: &PolicyRecord| repository.get_shared(&policy.gender, &policy.risk_class, policy.issue_age);
+            let streamed = runner::solve_policies_streaming(&policies, threads, rates_for, &options, |index, result| {
+                let (premium, error) = match result {
+                    Ok(result) => (result.premium, String::new()),
+                    Err(e) => (0.0, e.to_string()),
+                };
+                let policy = Some(request.policies[index].clone());
+                // a closed channel means the client went away, the remaining solves are wasted but harmless
+                let _ = tx.blocking_send(Ok(PolicyResult { index: index as u32, policy, premium, error }));
+            });
+            if let Err(e) = streamed {
+                let _ = tx.blocking_send(Err(Status::internal(e.to_string())));
+            }
+        });
+        return Ok(Response::new(ReceiverStream::new(rx)));
+    }
+
+    async fn tables(&self, _request: Request<TablesRequest>) -> Result<Response<TablesResponse>, Status> {
+        let live = self.live.tables();
+        let tables = live.tables.into_iter().map(|table| TableFile {
+            file: table.file,
+            bytes: table.bytes,
+            modified: table.modified.map(|m| m.to_rfc3339()).unwrap_or_default(),
+            sha256: table.sha256,
+        }).collect();
+        return Ok(Response::new(TablesResponse {
+            data_dir: live.data_dir,
+            loaded_at: live.loaded_at.to_rfc3339(),
+            reloads: live.reloads as u32,
+            watching: live.watching,
+            versions: live.versions.iter().map(|date| date.to_string()).collect(),
+            tables,
+            last_error: live.last_error.unwrap_or_default(),
+        }));
+    }
+}
+
+// serves until the process is stopped
+pub fn serve(address: &str, live: Arc<LiveRates>) -> Result<(), ValactError> {
+    let address = address.parse().map_err(|e| format!("address {}: {}", address, e))?;
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    return runtime.block_on(async {
+        info!("grpc listening on {}", address);
+        tonic::transport::Server::builder().add_service(ValactServer::new(ValactService::live(live))).serve(address).await.map_err(|e| format!("grpc server: {}", e))?;
+        return Ok(());
+    });
+}
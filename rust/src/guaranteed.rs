@@ -0,0 +1,139 @@
+use std::{
+    fs::File, io::BufReader
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    age::IssueAge, decrements::read_cell_mortality, error::ValactError, projection::{at_issue_projection_detail_with, MonthResult, ProjectionConfig}, rates::{DataDir, RateSet}
+};
+
+// guaranteed basis projection for illustrations, the policy projected again on the maximum charges and minimum
+// interest the contract allows next to the current basis, with a ledger of the two side by side by policy year
+
+// guaranteed charges, loads and fees annual, unit loads per 1000 a year and interest annual effective
+// the guaranteed coi is the guaranteed_coi table in the data directory (or coi_table) laid out like the mortality tables,
+// else the current coi rates, times coi_multiple either way; premium_load and policy_fee replace the current
+// rates and unit loads are the current ones times unit_load_multiple
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuaranteedBasis {
+    pub coi_table: Option<String>,
+    pub coi_multiple: f64,
+    pub minimum_interest: f64,
+    pub premium_load: f64,
+    pub policy_fee: f64,
+    pub unit_load_multiple: f64,
+}
+
+impl Default for GuaranteedBasis {
+    fn default() -> Self {
+        return GuaranteedBasis { coi_table: None, coi_multiple: 1.0, minimum_interest: 0.02, premium_load: 0.08, policy_fee: 180.0, unit_load_multiple: 1.0 };
+    }
+}
+
+impl GuaranteedBasis {
+    pub fn from_json_file(path: &str) -> Result<GuaranteedBasis, ValactError> {
+        let file = File::open(path)?;
+        return Ok(serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))?);
+    }
+}
+
+// the cell's rates with the guaranteed charges and interest in their place, surrender charges as they are
+pub fn guaranteed_rates(data_dir: &DataDir, rates: &RateSet, gender: &str, risk_class: &str, issue_age: IssueAge, basis: &GuaranteedBasis) -> Result<RateSet, ValactError> {
+    let years = rates.years();
+    let monthly = (1.0 + basis.minimum_interest).powf(1.0 / 12.0) - 1.0;
+    return Ok(RateSet {
+        premium_loads: vec![basis.premium_load; years],
+        policy_fees: vec![basis.policy_fee; years],
+        unit_loads: rates.unit_loads.iter().map(|load| load * basis.unit_load_multiple).collect(),
+        coi_rates: read_cell_mortality(basis.coi_table.as_deref(), &data_dir.table_path("guaranteed_coi"), gender, risk_class, issue_age, &rates.coi_rates, basis.coi_multiple)?,
+        interest_rates: vec![monthly; years],
+        ..rates.clone()
+    });
+}
+
+// guaranteed projection at the same premium, any interest path or account split in config credits current
+// rates and does not apply
+pub fn guaranteed_projection(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig) -> Result<Vec<MonthResult>, ValactError> {
+    let config = ProjectionConfig { interest_path: Vec::new(), accounts: Vec::new(), ..config.clone() };
+    return at_issue_projection_detail_with(rates, issue_age, face_amount, annual_premium, &config);
+}
+
+// values at the end of a policy year on each basis, nothing once the basis has lapsed
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct LedgerYear {
+    #[serde(rename="Policy_Year")]
+    pub policy_year: i32,
+    #[serde(rename="Attained_Age")]
+    pub attained_age: i32,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Guaranteed_Account_Value")]
+    pub guaranteed_account_value: f64,
+    #[serde(rename="Guaranteed_Cash_Surrender_Value")]
+    pub guaranteed_cash_surrender_value: f64,
+    #[serde(rename="Guaranteed_Death_Benefit")]
+    pub guaranteed_death_benefit: f64,
+    #[serde(rename="Current_Account_Value")]
+    pub current_account_value: f64,
+    #[serde(rename="Current_Cash_Surrender_Value")]
+    pub current_cash_surrender_value: f64,
+    #[serde(rename="Current_Death_Benefit")]
+    pub current_death_benefit: f64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct GuaranteedSummary {
+    pub end_value: f64,
+    // policy year the account value goes negative on each basis
+    pub lapse_year: Option<i32>,
+    pub current_lapse_year: Option<i32>,
+}
+
+pub fn lapse_year(months: &[MonthResult]) -> Option<i32> {
+    return months.iter().find(|m| m.value_end < 0.0).map(|m| m.policy_year);
+}
+
+pub fn guaranteed_summary(current: &[MonthResult], guaranteed: &[MonthResult]) -> GuaranteedSummary {
+    return GuaranteedSummary {
+        end_value: guaranteed.last().map_or(0.0, |m| m.value_end),
+        lapse_year: lapse_year(guaranteed),
+        current_lapse_year: lapse_year(current),
+    };
+}
+
+pub fn illustration_ledger(current: &[MonthResult], guaranteed: &[MonthResult], issue_age: IssueAge) -> Vec<LedgerYear> {
+    let current_lapse = lapse_year(current);
+    let guaranteed_lapse = lapse_year(guaranteed);
+    let in_force = |lapse: Option<i32>, year: i32| lapse.is_none_or(|lapse| year < lapse);
+    let mut ledger: Vec<LedgerYear> = Vec::new();
+    for (current, guaranteed) in current.iter().zip(guaranteed) {
+        let year = current.policy_year;
+        if ledger.last().is_none_or(|last| last.policy_year != year) {
+            ledger.push(LedgerYear { policy_year: year, attained_age: i32::from(issue_age.get()) + year - 1, ..Default::default() });
+        }
+        let Some(entry) = ledger.last_mut() else { continue };
+        entry.premium += current.premium;
+        if in_force(guaranteed_lapse, year) {
+            entry.guaranteed_account_value = guaranteed.value_end;
+            entry.guaranteed_cash_surrender_value = guaranteed.cash_surrender_value;
+            entry.guaranteed_death_benefit = guaranteed.net_death_benefit;
+        }
+        if in_force(current_lapse, year) {
+            entry.current_account_value = current.value_end;
+            entry.current_cash_surrender_value = current.cash_surrender_value;
+            entry.current_death_benefit = current.net_death_benefit;
+        }
+    }
+    return ledger;
+}
+
+pub fn write_ledger_csv(path: &str, ledger: &[LedgerYear]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for year in ledger {
+        wtr.serialize(year)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
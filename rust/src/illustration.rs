@@ -0,0 +1,141 @@
+use serde::Serialize;
+
+use crate::{
+    age::IssueAge, assumptions::Assumptions, batch::PolicyRecord, decrements::{expected_cash_flows, ExpectedMonth}, error::ValactError, guaranteed::{guaranteed_projection, lapse_year},
+    projection::{at_issue_projection_detail_with, MonthResult, ProjectionConfig, Timestep}, rates::RateSet, sensitivity::PricingBasis
+};
+
+// illustration actuary tests of the NAIC Life Insurance Illustrations Model Regulation for a cell
+// the disciplined current scale is the current rates and the guaranteed scale those of guaranteed.rs
+// midpoint scale: interest and every charge halfway between the two, the premium projected on it
+// self-support: the company's expected cash flows per policy issued (premiums less expenses, commissions, taxes
+// and benefits) accumulated at the earned rate are at least the policy owner value, the expected cash surrender
+// value of the policies still in force, at the end of each policy year from the self-support year on
+// lapse-support: the same test with the lapse rates from the lapse-support year replaced, a cell that only
+// passes with the assumed lapses relies on the values lapsing policies leave behind and is lapse-supported
+
+// halfway between two rates by policy year
+fn midpoint(current: &[f64], guaranteed: &[f64]) -> Vec<f64> {
+    return current.iter().zip(guaranteed).map(|(c, g)| (c + g) / 2.0).collect();
+}
+
+// interest is averaged as annual effective rates, the rate set's being monthly
+pub fn midpoint_rates(current: &RateSet, guaranteed: &RateSet) -> RateSet {
+    let annual = |monthly: &f64| (1.0 + monthly).powi(12) - 1.0;
+    let interest_rates = current.interest_rates.iter().zip(&guaranteed.interest_rates)
+        .map(|(c, g)| (1.0 + (annual(c) + annual(g)) / 2.0).powf(1.0 / 12.0) - 1.0)
+        .collect();
+    return RateSet {
+        premium_loads: midpoint(&current.premium_loads, &guaranteed.premium_loads),
+        policy_fees: midpoint(&current.policy_fees, &guaranteed.policy_fees),
+        unit_loads: midpoint(&current.unit_loads, &guaranteed.unit_loads),
+        coi_rates: midpoint(&current.coi_rates, &guaranteed.coi_rates),
+        interest_rates,
+        ..current.clone()
+    };
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct SupportTest {
+    pub passed: bool,
+    // first tested policy year the accumulated cash flows fall short of the policy owner value
+    pub fail_year: Option<i32>,
+    // accumulated cash flows less the policy owner value at the tested year end where it is least
+    pub least_margin: f64,
+}
+
+// months are the single policy projection the expected cash flows were weighted from
+pub fn support_test(months: &[MonthResult], expected: &[ExpectedMonth], timestep: Timestep, earned_rate: f64, from_year: i32) -> SupportTest {
+    let earned = (1.0 + earned_rate).powf(1.0 / f64::from(timestep.steps_per_year())) - 1.0;
+    let mut accumulated = 0.0;
+    let mut fail_year = None;
+    let mut least_margin = f64::INFINITY;
+    for (i, (month, flows)) in months.iter().zip(expected).enumerate() {
+        let expenses = flows.acquisition_expense + flows.maintenance_expense + flows.premium_expense + flows.commission + flows.premium_tax;
+        let benefits = flows.death_benefits + flows.surrender_benefits + flows.maturity_benefits + flows.withdrawal;
+        accumulated = (accumulated + flows.premium - expenses) * (1.0 + earned) - benefits - flows.dac_tax;
+        let year_end = expected.get(i + 1).is_none_or(|next| next.policy_year != flows.policy_year);
+        if !year_end || flows.policy_year < from_year {
+            continue;
+        }
+        // paid out at maturity so nothing is left owing
+        let owner_value = if flows.maturity_benefits > 0.0 {0.0} else {flows.inforce_end * month.cash_surrender_value};
+        let margin = accumulated - owner_value;
+        if margin < 0.0 && fail_year.is_none() {
+            fail_year = Some(flows.policy_year);
+        }
+        least_margin = least_margin.min(margin);
+    }
+    return SupportTest { passed: fail_year.is_none(), fail_year, least_margin: if least_margin.is_finite() {least_margin} else {0.0} };
+}
+
+// results for a cell at an illustrated premium, columns for the cell test csv
+// the tests run on the expected experience of the pricing basis and the company assumptions
+#[derive(Clone, Debug, Serialize)]
+pub struct CellTest {
+    #[serde(rename="Gender")]
+    pub gender: String,
+    #[serde(rename="Risk_Class")]
+    pub risk_class: String,
+    #[serde(rename="Issue_Age")]
+    pub issue_age: IssueAge,
+    #[serde(rename="Face_Amount")]
+    pub face_amount: f64,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Midpoint_End_Value")]
+    pub midpoint_end_value: f64,
+    // policy year the account value goes negative on the midpoint scale
+    #[serde(rename="Midpoint_Lapse_Year")]
+    pub midpoint_lapse_year: Option<i32>,
+    #[serde(rename="Self_Supporting")]
+    pub self_supporting: bool,
+    #[serde(rename="Self_Support_Fail_Year")]
+    pub self_support_fail_year: Option<i32>,
+    #[serde(rename="Self_Support_Margin")]
+    pub self_support_margin: f64,
+    #[serde(rename="Lapse_Supported")]
+    pub lapse_supported: bool,
+    #[serde(rename="Lapse_Support_Fail_Year")]
+    pub lapse_support_fail_year: Option<i32>,
+}
+
+pub fn test_cell(current: &RateSet, guaranteed: &RateSet, policy: &PolicyRecord, premium: f64, config: &ProjectionConfig, basis: &PricingBasis, assumptions: &Assumptions) -> Result<CellTest, ValactError> {
+    let tests = &assumptions.illustration;
+    let earned_rate = assumptions.profit.earned_rate;
+    let months = at_issue_projection_detail_with(current, policy.issue_age, policy.face_amount, premium, config)?;
+    let expected = expected_cash_flows(&months, &basis.mortality, &basis.lapses, config.timestep, assumptions, basis.target_premium);
+    let self_support = support_test(&months, &expected, config.timestep, earned_rate, tests.self_support_from_year);
+
+    let modified_lapses: Vec<f64> = basis.lapses.iter().enumerate()
+        .map(|(year, rate)| if year as i32 + 1 >= tests.lapse_support_from_year {tests.lapse_support_rate} else {*rate})
+        .collect();
+    let expected_modified = expected_cash_flows(&months, &basis.mortality, &modified_lapses, config.timestep, assumptions, basis.target_premium);
+    let lapse_support = support_test(&months, &expected_modified, config.timestep, earned_rate, tests.self_support_from_year);
+
+    // projected like the guaranteed basis, the midpoint interest taking the place of any interest path
+    let midpoint = guaranteed_projection(&midpoint_rates(current, guaranteed), policy.issue_age, policy.face_amount, premium, config)?;
+    return Ok(CellTest {
+        gender: policy.gender.clone(),
+        risk_class: policy.risk_class.clone(),
+        issue_age: policy.issue_age,
+        face_amount: policy.face_amount,
+        premium,
+        midpoint_end_value: midpoint.last().map_or(0.0, |m| m.value_end),
+        midpoint_lapse_year: lapse_year(&midpoint),
+        self_supporting: self_support.passed,
+        self_support_fail_year: self_support.fail_year,
+        self_support_margin: self_support.least_margin,
+        lapse_supported: !lapse_support.passed,
+        lapse_support_fail_year: lapse_support.fail_year,
+    });
+}
+
+pub fn write_cell_tests_csv(path: &str, cells: &[CellTest]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for cell in cells {
+        wtr.serialize(cell)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
@@ -0,0 +1,115 @@
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::ValactError, rates::{read_py_carried, RateSet}, variable::Fund};
+
+// indexed universal life crediting, annual point-to-point
+// the index return over each policy year is multiplied by the participation rate and held between the floor
+// and the cap, and what comes out replaces the interest rate for that year
+// apply_indexed_crediting replaces the interest rate table for the whole account value, the credited rate then
+// being spread over the months of the year like the declared rate
+// the account value can instead be split into accounts, a fixed account on the interest rate table and index
+// accounts credited at the end of each policy year, with each net premium shared out by allocation
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PointToPoint {
+    pub cap: f64,
+    pub floor: f64,
+    pub participation: f64,
+}
+
+impl Default for PointToPoint {
+    fn default() -> Self {
+        return PointToPoint { cap: 0.10, floor: 0.0, participation: 1.0 };
+    }
+}
+
+impl PointToPoint {
+    pub fn credited_rate(&self, index_return: f64) -> f64 {
+        return (self.participation * index_return).min(self.cap).max(self.floor);
+    }
+}
+
+// index return by policy year, one Policy_Year and Rate row per year, years past the end stay at its last return
+pub fn read_index_returns(path: &str, years: usize) -> Result<Vec<f64>, ValactError> {
+    return read_py_carried(path, years);
+}
+
+pub fn indexed_interest_rates(index_returns: &[f64], strategy: &PointToPoint) -> Vec<f64> {
+    return index_returns.iter().map(|r| strategy.credited_rate(*r)).collect();
+}
+
+// in place of the interest rate table, which like the rest of the rate set holds monthly rates
+pub fn apply_indexed_crediting(rates: &mut RateSet, index_returns_path: &str, strategy: &PointToPoint) -> Result<(), ValactError> {
+    let returns = read_index_returns(index_returns_path, rates.interest_rates.len())?;
+    rates.interest_rates = indexed_interest_rates(&returns, strategy).iter().map(|rate| (1.0 + rate).powf(1.0 / 12.0) - 1.0).collect();
+    return Ok(());
+}
+
+// how an account in a split account value is credited
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum Crediting {
+    // the interest rate table, or the config's interest path, spread over the months of the year
+    #[default]
+    Fixed,
+    // point-to-point on the index return for the policy year, credited at the end of the year on what is in the
+    // account then; years past the end of index_returns stay at its last return
+    Indexed { strategy: PointToPoint, index_returns: Vec<f64> },
+    // a separate account fund of variable universal life, see variable.rs
+    Variable(Fund),
+}
+
+// one bucket of the account value, allocation is its share of each net premium
+// deductions come out of the accounts in proportion to what is in them
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Account {
+    pub name: String,
+    pub allocation: f64,
+    pub crediting: Crediting,
+}
+
+impl Account {
+    pub fn fixed(allocation: f64) -> Account {
+        return Account { name: "Fixed".to_string(), allocation, crediting: Crediting::Fixed };
+    }
+
+    // annual rate credited in a step, year_end is whether the step closes the policy year
+    // a fund is not credited a rate, it grows by its return path
+    pub fn credited_rate(&self, policy_year: usize, fixed_rate: f64, year_end: bool) -> f64 {
+        return match &self.crediting {
+            Crediting::Fixed => fixed_rate,
+            Crediting::Variable(_) => 0.0,
+            Crediting::Indexed { .. } if !year_end => 0.0,
+            Crediting::Indexed { strategy, index_returns } => match index_returns.get(policy_year - 1).or(index_returns.last()) {
+                Some(index_return) => strategy.credited_rate(*index_return),
+                None => 0.0,
+            },
+        };
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        return matches!(self.crediting, Crediting::Indexed { .. });
+    }
+
+    pub fn fund(&self) -> Option<&Fund> {
+        return match &self.crediting {
+            Crediting::Variable(fund) => Some(fund),
+            _ => None,
+        };
+    }
+}
+
+// the accounts a projection splits the account value across, a single fixed account when none are given
+pub fn account_buckets(accounts: &[Account]) -> Result<Vec<Account>, ValactError> {
+    if accounts.is_empty() {
+        return Ok(vec![Account::fixed(1.0)]);
+    }
+    if accounts.iter().any(|a| a.allocation < 0.0) {
+        return Err("account allocations cannot be negative".into());
+    }
+    let total = accounts.iter().fold(0.0, |total, a| total + a.allocation);
+    if (total - 1.0).abs() > 1e-9 {
+        return Err(format!("account allocations add up to {} rather than 1", total).into());
+    }
+    return Ok(accounts.to_vec());
+}
@@ -0,0 +1,238 @@
+use std::{collections::HashSet, fs::File};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    age::{insurance_age, AgeBasis, IssueAge}, batch::{PolicyRecord, PolicyResult}, calendar::duration_at, error::ValactError, projection::{DeathBenefitOption, InforceState}
+};
+
+// seriatim inforce extract, one csv row per policy as the admin system has it at a valuation date
+//
+// Policy_Number        - unique in the extract, not blank
+// Status               - In_Force, Paid_Up, Lapsed, Surrendered, Death_Claim or Matured (or IF, PU, LA, SU, DC, MA)
+// Issue_Date           - yyyy-mm-dd, on or before the valuation date
+// Plan_Code            - not blank, and one of the plan codes given if there are any
+// Gender, Risk_Class   - the cell the rates are looked up for
+// Issue_Age            - or Date_Of_Birth to work it out from the issue date on the age basis
+// Face_Amount          - more than 0
+// Account_Value        - at the monthiversary on or before the valuation date, can be negative in a grace period
+// Loan_Balance         - 0 or more, blank for no loan
+// Death_Benefit_Option - 1, 2 or 3, blank for 1
+// Surrender_Charge     - the charge on surrender now, blank to take it from the rate tables' schedule
+// Premiums_Paid        - premiums to date less withdrawals, blank for 0, only used by option 3
+//
+// only in force policies are projected, the rest are read and validated so the extract ties to the admin
+// system's counts but have nothing to solve
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum PolicyStatus {
+    #[serde(rename="In_Force")]
+    InForce,
+    #[serde(rename="Paid_Up")]
+    PaidUp,
+    Lapsed,
+    Surrendered,
+    #[serde(rename="Death_Claim")]
+    DeathClaim,
+    Matured,
+}
+
+// fields are read as strings where a bad value should be reported against its column rather than as a
+// deserialize error for the row
+#[derive(Deserialize)]
+struct ExtractRow {
+    #[serde(alias="Policy_Number")]
+    policy_number: String,
+    #[serde(alias="Status")]
+    status: String,
+    #[serde(alias="Issue_Date")]
+    issue_date: String,
+    #[serde(alias="Plan_Code")]
+    plan_code: String,
+    #[serde(alias="Gender")]
+    gender: String,
+    #[serde(alias="Risk_Class")]
+    risk_class: String,
+    #[serde(alias="Issue_Age", default)]
+    issue_age: Option<String>,
+    #[serde(alias="Date_Of_Birth", default)]
+    date_of_birth: Option<String>,
+    #[serde(alias="Face_Amount")]
+    face_amount: String,
+    #[serde(alias="Account_Value")]
+    account_value: String,
+    #[serde(alias="Loan_Balance", default)]
+    loan_balance: Option<String>,
+    #[serde(alias="Death_Benefit_Option", alias="DB_Option", default)]
+    death_benefit_option: Option<String>,
+    #[serde(alias="Surrender_Charge", default)]
+    surrender_charge: Option<String>,
+    #[serde(alias="Premiums_Paid", default)]
+    premiums_paid: Option<String>,
+}
+
+// a policy from the extract, policy has the inforce state at the valuation date for the batch runner
+#[derive(Clone, Debug)]
+pub struct InforcePolicy {
+    pub policy_number: String,
+    pub status: PolicyStatus,
+    pub issue_date: NaiveDate,
+    pub plan_code: String,
+    pub policy: PolicyRecord,
+}
+
+// a blank optional field is None
+fn present(value: &Option<String>) -> Option<&str> {
+    return value.as_deref().map(str::trim).filter(|v| !v.is_empty());
+}
+
+fn amount(value: &str) -> Result<f64, String> {
+    let amount: f64 = value.trim().parse().map_err(|_| format!("{:?} is not a number", value))?;
+    if !amount.is_finite() {
+        return Err(format!("{} is not a finite amount", value));
+    }
+    return Ok(amount);
+}
+
+fn not_negative(value: &str) -> Result<f64, String> {
+    let amount = amount(value)?;
+    if amount < 0.0 {
+        return Err(format!("{} is negative", value));
+    }
+    return Ok(amount);
+}
+
+fn date(value: &str) -> Result<NaiveDate, String> {
+    return value.trim().parse().map_err(|_| format!("{:?} is not a yyyy-mm-dd date", value));
+}
+
+fn status(value: &str) -> Result<PolicyStatus, String> {
+    return match value.trim() {
+        "In_Force" | "IF" => Ok(PolicyStatus::InForce),
+        "Paid_Up" | "PU" => Ok(PolicyStatus::PaidUp),
+        "Lapsed" | "LA" => Ok(PolicyStatus::Lapsed),
+        "Surrendered" | "SU" => Ok(PolicyStatus::Surrendered),
+        "Death_Claim" | "DC" => Ok(PolicyStatus::DeathClaim),
+        "Matured" | "MA" => Ok(PolicyStatus::Matured),
+        other => Err(format!("unknown status {:?}", other)),
+    };
+}
+
+fn death_benefit_option(value: Option<&str>) -> Result<DeathBenefitOption, String> {
+    return match value {
+        None | Some("1") => Ok(DeathBenefitOption::Level),
+        Some("2") => Ok(DeathBenefitOption::Increasing),
+        Some("3") => Ok(DeathBenefitOption::ReturnOfPremium),
+        Some(other) => Err(format!("death benefit option {} must be 1, 2 or 3", other)),
+    };
+}
+
+// plan_codes empty accepts any plan code
+pub fn read_inforce_extract(path: &str, valuation_date: NaiveDate, basis: AgeBasis, plan_codes: &[String]) -> Result<Vec<InforcePolicy>, ValactError> {
+    let file = File::open(path).map_err(|source| ValactError::File { path: path.to_string(), source })?;
+    let mut rdr = csv::Reader::from_reader(file);
+    let headers = rdr.headers().map_err(|e| ValactError::csv(path, None, e))?.clone();
+
+    let mut policies = Vec::new();
+    let mut policy_numbers: HashSet<String> = HashSet::new();
+    for result in rdr.records() {
+        let record = result.map_err(|e| ValactError::csv(path, None, e))?;
+        let line = record.position().map_or(0, |p| p.line());
+        let field_error = |column: &str, message: String| ValactError::Row { path: path.to_string(), line, column: Some(column.to_string()), message };
+        let row: ExtractRow = record.deserialize(Some(&headers)).map_err(|e| ValactError::csv(path, Some(&headers), e))?;
+
+        let policy_number = row.policy_number.trim().to_string();
+        if policy_number.is_empty() {
+            return Err(field_error("Policy_Number", "policy number is blank".to_string()));
+        }
+        if !policy_numbers.insert(policy_number.clone()) {
+            return Err(field_error("Policy_Number", format!("policy {} is in the extract more than once", policy_number)));
+        }
+        let status = status(&row.status).map_err(|e| field_error("Status", e))?;
+        let issue_date = date(&row.issue_date).map_err(|e| field_error("Issue_Date", e))?;
+        let duration = duration_at(issue_date, valuation_date).map_err(|e| field_error("Issue_Date", e.to_string()))?;
+        let plan_code = row.plan_code.trim().to_string();
+        if plan_code.is_empty() {
+            return Err(field_error("Plan_Code", "plan code is blank".to_string()));
+        }
+        if !plan_codes.is_empty() && !plan_codes.contains(&plan_code) {
+            return Err(field_error("Plan_Code", format!("plan code {} is not one of {}", plan_code, plan_codes.join(", "))));
+        }
+        let issue_age = match (present(&row.issue_age), present(&row.date_of_birth)) {
+            (Some(age), _) => {
+                let age: i64 = age.parse().map_err(|_| field_error("Issue_Age", format!("{:?} is not a whole number", age)))?;
+                IssueAge::try_from(age).map_err(|e| field_error("Issue_Age", e.to_string()))?
+            }
+            (None, Some(dob)) => {
+                let dob = date(dob).map_err(|e| field_error("Date_Of_Birth", e))?;
+                insurance_age(dob, issue_date, basis).map_err(|e| field_error("Date_Of_Birth", e.to_string()))?
+            }
+            (None, None) => return Err(field_error("Issue_Age", "needs Issue_Age or Date_Of_Birth".to_string())),
+        };
+        let face_amount = amount(&row.face_amount).map_err(|e| field_error("Face_Amount", e))?;
+        if face_amount <= 0.0 {
+            return Err(field_error("Face_Amount", format!("face amount {} must be more than 0", face_amount)));
+        }
+        let account_value = amount(&row.account_value).map_err(|e| field_error("Account_Value", e))?;
+        let loan_balance = present(&row.loan_balance).map_or(Ok(0.0), not_negative).map_err(|e| field_error("Loan_Balance", e))?;
+        let death_benefit_option = death_benefit_option(present(&row.death_benefit_option)).map_err(|e| field_error("Death_Benefit_Option", e))?;
+        let surrender_charge = present(&row.surrender_charge).map(not_negative).transpose().map_err(|e| field_error("Surrender_Charge", e))?;
+        let premiums_paid = present(&row.premiums_paid).map_or(Ok(0.0), not_negative).map_err(|e| field_error("Premiums_Paid", e))?;
+
+        policies.push(InforcePolicy {
+            policy_number,
+            status,
+            issue_date,
+            plan_code,
+            policy: PolicyRecord {
+                gender: row.gender.trim().to_string(),
+                risk_class: row.risk_class.trim().to_string(),
+                issue_age,
+                face_amount,
+                death_benefit_option,
+                inforce: Some(InforceState { duration, account_value, loan_balance, surrender_charge, premiums_paid }),
+                issue_date: Some(issue_date),
+            },
+        });
+    }
+    return Ok(policies);
+}
+
+// one row of the inforce results file, the extract's policy number and duration in front of the batch result
+#[derive(Clone, Debug, Serialize)]
+pub struct InforceResult {
+    #[serde(rename="Policy_Number")]
+    pub policy_number: String,
+    #[serde(rename="Plan_Code")]
+    pub plan_code: String,
+    #[serde(rename="Duration")]
+    pub duration: i32,
+    #[serde(rename="Account_Value")]
+    pub account_value: f64,
+    #[serde(skip)]
+    pub result: PolicyResult,
+}
+
+// results are in the order of the policies they were solved for, as the batch runner returns them
+pub fn inforce_results(policies: &[&InforcePolicy], results: Vec<PolicyResult>) -> Vec<InforceResult> {
+    return policies.iter().zip(results).map(|(policy, result)| {
+        let inforce = policy.policy.inforce.unwrap_or_default();
+        return InforceResult {
+            policy_number: policy.policy_number.clone(),
+            plan_code: policy.plan_code.clone(),
+            duration: inforce.duration,
+            account_value: inforce.account_value,
+            result,
+        };
+    }).collect();
+}
+
+pub fn write_inforce_results(path: &str, results: &[InforceResult]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for result in results {
+        wtr.serialize((result, &result.result))?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
@@ -0,0 +1,280 @@
+use crate::{
+    age::IssueAge, error::ValactError, projection::{DeathBenefitOption, PremiumPattern, ProjectionConfig, SolveResult}, rates::{projection_years, RateSet}, solver::{Method, SolverOptions, MAX_EXPANSIONS}
+};
+
+// lockstep projection of several policies at once for batch solves
+// policies of the same issue age are projected together, lane l of every array being policy l, so each step is
+// the same arithmetic over LANES values, which the compiler turns into simd instructions and which gives the cpu
+// independent work to overlap where a single policy's step waits on the one before it
+// only the plain at issue projection a census solve runs is covered, no schedules, riders, accounts, interest
+// path or inforce start (see supported); the operations are project()'s in the same order so the values come out
+// bit for bit the same as projecting each policy on its own
+// the rates are laid out by policy year with the lanes side by side and what project() works out from them every
+// step, the step's coi rate, discount, interest and expense charge, worked out once up front
+
+pub const LANES: usize = 8;
+
+pub type Lane = [f64; LANES];
+
+// what a lockstep solve can stand in for, anything else is solved a policy at a time
+pub fn supported(config: &ProjectionConfig, options: &SolverOptions) -> bool {
+    return config.withdrawals.is_empty()
+        && config.loans.is_empty()
+        && config.loan_repayments.is_empty()
+        && config.face_changes.is_empty()
+        && config.option_changes.is_empty()
+        && config.interest_path.is_empty()
+        && config.accounts.is_empty()
+        && config.riders.is_empty()
+        && config.inforce.is_none()
+        && options.method == Method::Bisection
+        && !options.log_iterations;
+}
+
+pub struct Lanes<'a> {
+    config: &'a ProjectionConfig,
+    issue_age: IssueAge,
+    // lanes with a policy in them, the rest repeat the last policy and are ignored
+    count: usize,
+    years: usize,
+    face_amounts: Lane,
+    // by policy year
+    premium_loads: Vec<Lane>,
+    expense_charges: Vec<Lane>,
+    corridor_factors: Vec<Lane>,
+    naar_discounts: Vec<Lane>,
+    coi_rates: Vec<Lane>,
+    interest_rates: Vec<Lane>,
+}
+
+impl<'a> Lanes<'a> {
+    // a policy per lane from its rates and face amount, up to LANES of them, all of the issue age
+    pub fn new(rates: &[&RateSet], face_amounts: &[f64], issue_age: IssueAge, config: &'a ProjectionConfig) -> Result<Lanes<'a>, ValactError> {
+        if rates.is_empty() || rates.len() > LANES || rates.len() != face_amounts.len() {
+            return Err(format!("{} rate sets and {} face amounts for {} lanes", rates.len(), face_amounts.len(), LANES).into());
+        }
+        let years = projection_years(issue_age, rates[0].maturity_age)?;
+        if let Some(short) = rates.iter().find(|r| r.maturity_age != rates[0].maturity_age || r.years() < years) {
+            return Err(format!("rates to maturity age {} covering {} policy years cannot be projected with the rest of the lanes", short.maturity_age, short.years()).into());
+        }
+        let steps_per_year = config.timestep.steps_per_year();
+        let months_per_step = 12 / steps_per_year;
+        let rounding = config.rounding;
+        let lane = |l: usize| l.min(rates.len() - 1);
+        // the level option takes withdrawals off the face amount, with none that leaves it floored at 0
+        let face_amounts: Lane = std::array::from_fn(|l| match config.death_benefit_option {
+            DeathBenefitOption::Level => face_amounts[lane(l)].max(0.0),
+            _ => face_amounts[lane(l)],
+        });
+        let by_year = |rate: &dyn Fn(&RateSet, usize, usize) -> f64| -> Vec<Lane> {
+            return (0..years).map(|y| std::array::from_fn(|l| rate(rates[lane(l)], y, l))).collect();
+        };
+        return Ok(Lanes {
+            config,
+            issue_age,
+            count: rates.len(),
+            years,
+            face_amounts,
+            premium_loads: by_year(&|r, y, _| r.premium_loads[y]),
+            expense_charges: by_year(&|r, y, l| rounding.amount((r.policy_fees[y] + r.unit_loads[y] * face_amounts[l] / 1000.0) / f64::from(steps_per_year))),
+            corridor_factors: by_year(&|r, y, _| r.corr_facts[y]),
+            naar_discounts: by_year(&|r, y, _| r.naar_discs[y].powi(months_per_step)),
+            coi_rates: by_year(&|r, y, _| r.coi_rates[y] / f64::from(steps_per_year)),
+            interest_rates: by_year(&|r, y, _| (1.0 + r.interest_rates[y]).powi(months_per_step) - 1.0),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        return self.count;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.count == 0;
+    }
+
+    // account values at maturity for each lane's annual premium, what at_issue_projection_with gives each policy
+    pub fn end_values(&self, annual_premiums: &Lane) -> Lane {
+        let config = self.config;
+        let steps_per_year = config.timestep.steps_per_year();
+        let months_per_step = 12 / steps_per_year;
+        let months_per_payment = 12 / config.premium_mode.payments_per_year();
+        let rounding = config.rounding;
+        let modal_premiums = annual_premiums.map(|premium| config.modal_premium(premium));
+        let face_amounts = &self.face_amounts;
+        let mut values: Lane = [0.0; LANES];
+        let mut premiums_paid: Lane = [0.0; LANES];
+        let mut policy_year = 0;
+
+        for i in 0..(steps_per_year * self.years as i32) {
+            policy_year += if (i % steps_per_year) == 0 {1} else {0};
+            let month_in_year = (i % steps_per_year) * months_per_step;
+            let payments = (month_in_year..month_in_year + months_per_step).filter(|m| m % months_per_payment == 0).count();
+            let premiums: Lane = match config.premium_pattern {
+                PremiumPattern::SinglePay if i == 0 => *annual_premiums,
+                PremiumPattern::SinglePay => [0.0; LANES],
+                pattern if pattern.pays_in_year(self.issue_age, policy_year) => modal_premiums.map(|premium| premium * payments as f64),
+                _ => [0.0; LANES],
+            };
+            let y = policy_year - 1;
+            let (premium_loads, expense_charges, corridor_factors) = (&self.premium_loads[y], &self.expense_charges[y], &self.corridor_factors[y]);
+            let (naar_discounts, coi_rates, interest_rates) = (&self.naar_discounts[y], &self.coi_rates[y], &self.interest_rates[y]);
+            for l in 0..LANES {
+                let premium = premiums[l];
+                let premium_load = rounding.amount(premium * premium_loads[l]);
+                premiums_paid[l] += premium;
+                let av_for_db = values[l] + premium - premium_load - expense_charges[l];
+                let specified_amount = match config.death_benefit_option {
+                    DeathBenefitOption::Level => face_amounts[l],
+                    DeathBenefitOption::Increasing => face_amounts[l] + av_for_db.max(0.0),
+                    DeathBenefitOption::ReturnOfPremium => face_amounts[l] + premiums_paid[l],
+                };
+                let db = specified_amount.max(corridor_factors[l] * av_for_db);
+                let naar = (db * naar_discounts[l] - av_for_db.max(0.0)).max(0.0);
+                let coi = rounding.amount((naar / 1000.0) * coi_rates[l]);
+                let av_for_interest = av_for_db - coi;
+                let interest = rounding.amount((av_for_interest * interest_rates[l]).max(0.0));
+                values[l] = rounding.amount(av_for_interest + interest);
+            }
+        }
+        return values;
+    }
+}
+
+// where a lane's solve has got to, the steps goal_seek_with takes for bisection and solve_for_premium_using's
+// check of the rounded premium
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Low,
+    High,
+    Bisect,
+    Check,
+    Done,
+}
+
+struct LaneSolve {
+    phase: Phase,
+    lo: f64,
+    hi: f64,
+    g_lo: f64,
+    lo_above: bool,
+    expansions: i32,
+    bisections: usize,
+    evaluations: usize,
+    premium: f64,
+    error: Option<String>,
+}
+
+impl LaneSolve {
+    fn new(face_amount: f64) -> LaneSolve {
+        return LaneSolve { phase: Phase::Low, lo: 0.0, hi: face_amount.max(0.0) / 100.0, g_lo: 0.0, lo_above: false, expansions: 0, bisections: 0, evaluations: 0, premium: 0.0, error: None };
+    }
+
+    // premium the lane wants projected next
+    fn next(&self) -> f64 {
+        return match self.phase {
+            Phase::Low => self.lo,
+            Phase::High => self.hi,
+            Phase::Bisect => (self.lo + self.hi) / 2.0,
+            Phase::Check => self.premium,
+            Phase::Done => 0.0,
+        };
+    }
+
+    fn bisect_or_finish(&mut self, config: &ProjectionConfig, options: &SolverOptions) {
+        if (self.hi - self.lo) > options.tolerance && self.bisections < options.max_iterations {
+            self.phase = Phase::Bisect;
+            return;
+        }
+        self.premium = config.rounding.round(if self.lo_above {self.lo} else {self.hi});
+        self.phase = Phase::Check;
+    }
+
+    // takes the end value at the premium next() asked for, the target being to endow so the value is g itself
+    fn take(&mut self, value: f64, config: &ProjectionConfig, options: &SolverOptions) {
+        match self.phase {
+            Phase::Low => {
+                self.evaluations += 1;
+                self.g_lo = value;
+                self.phase = Phase::High;
+            }
+            Phase::High => {
+                self.evaluations += 1;
+                if (value > 0.0) == (self.g_lo > 0.0) {
+                    if self.expansions == MAX_EXPANSIONS {
+                        self.error = Some(format!("target 0 not bracketed between 0 and {}", self.hi));
+                        self.phase = Phase::Done;
+                        return;
+                    }
+                    self.lo = self.hi;
+                    self.g_lo = value;
+                    self.hi = if self.hi == 0.0 {1.0} else {self.hi * 2.0};
+                    self.expansions += 1;
+                    return;
+                }
+                self.lo_above = self.g_lo > 0.0;
+                self.bisect_or_finish(config, options);
+            }
+            Phase::Bisect => {
+                self.evaluations += 1;
+                let md = (self.lo + self.hi) / 2.0;
+                if (value > 0.0) == self.lo_above {
+                    self.lo = md;
+                } else {
+                    self.hi = md;
+                }
+                self.bisections += 1;
+                self.bisect_or_finish(config, options);
+            }
+            Phase::Check => {
+                if value <= 0.0 {
+                    self.premium = config.rounding.round(self.premium + config.rounding.unit.size());
+                }
+                self.phase = Phase::Done;
+            }
+            Phase::Done => {}
+        }
+    }
+
+    fn result(&self, options: &SolverOptions) -> Result<SolveResult, String> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        return Ok(SolveResult {
+            premium: self.premium,
+            iterations: self.bisections,
+            evaluations: self.evaluations,
+            bracket: (self.lo, self.hi),
+            converged: (self.hi - self.lo) <= options.tolerance,
+        });
+    }
+}
+
+// the premium each lane's policy endows at maturity for, as solve_for_premium_using solves it by bisection
+// every lane takes the steps it would on its own and one that finishes first rides along until the rest do
+pub fn solve_for_premiums(lanes: &Lanes, options: &SolverOptions) -> Result<Vec<Result<SolveResult, String>>, ValactError> {
+    if !supported(lanes.config, options) {
+        return Err("only a bisection solve of a plain at issue projection can be run in lockstep".into());
+    }
+    let count = lanes.count;
+    return solve_in_lockstep(&lanes.face_amounts[..count], lanes.config, options, |premiums| {
+        let premiums: Lane = std::array::from_fn(|l| premiums[l.min(count - 1)]);
+        return Ok(lanes.end_values(&premiums)[..count].to_vec());
+    });
+}
+
+// the bisection of solve_for_premiums for any number of policies, end_values projecting every policy at once at
+// the premium each one asks for, so the gpu kernel (gpu.rs) solves a dispatch of policies the same way
+pub(crate) fn solve_in_lockstep<E>(face_amounts: &[f64], config: &ProjectionConfig, options: &SolverOptions, mut end_values: E) -> Result<Vec<Result<SolveResult, String>>, ValactError>
+where
+    E: FnMut(&[f64]) -> Result<Vec<f64>, ValactError>,
+{
+    let mut solves: Vec<LaneSolve> = face_amounts.iter().map(|face| LaneSolve::new(*face)).collect();
+    while solves.iter().any(|solve| solve.phase != Phase::Done) {
+        let premiums: Vec<f64> = solves.iter().map(|solve| solve.next()).collect();
+        let values = end_values(&premiums)?;
+        for (solve, value) in solves.iter_mut().zip(values) {
+            solve.take(value, config, options);
+        }
+    }
+    return Ok(solves.iter().map(|solve| solve.result(options)).collect());
+}
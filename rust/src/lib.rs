@@ -0,0 +1,152 @@
+/*
+Library portion of the Rust implementation of approach1.py
+
+The projection engine lives here so it can be embedded by other crates
+without shelling out to the binary. main.rs is a thin wrapper around it.
+
+age        - IssueAge, AttainedAge and PolicyYear, and insurance age from date of birth on an ANB or ALB basis
+annuity    - deferred fixed annuity accumulation with surrender charges, free withdrawals and annuitization value
+assumptions - company expense, commission and tax assumptions for pricing cash flows, read from json
+rates      - csv readers for the rate tables, their effective dated versions and assembly of rates for a cell
+projection - monthly at issue projection (terminal value or month by month detail) and premium solve
+batch      - census file reader, per policy solves, and results writer
+bands      - premium loads, policy fees and unit loads by face amount band, applied per policy
+checkpoint - completed results and cursor of a batch or scenario run saved as it goes, so an interrupted run can be resumed
+calendar   - monthiversary dates from an issue date, inforce duration at a valuation date and flows by calendar period
+config     - run configuration in toml, the data directory, product, command options and output paths of a run
+cvat       - CVAT net single premiums and corridor factors computed from a mortality table
+error      - ValactError, the library error type carrying the file, row and cell an error came from
+esg        - vasicek and lognormal short rate generator for monthly interest rate scenarios
+inforce    - seriatim inforce extract layout, reader with per-field validation, and results by policy number
+lanes      - lockstep projection and bisection premium solve of up to 8 policies of an issue age at once, for batch runs
+indexed    - indexed crediting by annual point-to-point with cap, floor and participation rate, and fixed and index accounts
+guaranteed - guaranteed basis projection at maximum charges and minimum interest and a side by side illustration ledger
+illustration - NAIC illustration midpoint scale and self-support and lapse-support tests per cell
+modelpoints - grouping of a census into weighted model points by cell and face band, with results expanded back
+nlg        - no-lapse guarantee shadow account and the guarantee duration a premium buys
+decrements - lapse rates and decrement weighted expected cash flows from a projection
+payout     - annuity certain, single life and joint life payout factors from a mortality table and interest rate
+product    - product specification in toml declaring where each charge comes from, a constant, table or formula
+profit     - book profit by year, pv of profits, profit margin and irr from expected cash flows
+reserve    - statutory crvm or net level reserves from a valuation mortality table and interest rate
+riders     - rider trait charged and reported in the projection loop, with adb, child term, waiver of deductions and chronic illness riders
+runner     - parallel execution of batch solves
+ratebook   - premiums for a grid of genders, classes, issue ages and face amounts, written as a rate book csv
+scenarios  - interest rate scenario paths read from csv or academy generator output, projected and solved per path
+repository - cache of assembled rates by cell
+summary    - totals, premium per 1000 and failure counts of a batch run by issue age band and risk class
+sensitivity - premium and profit changes under coi, interest, expense and lapse shocks
+schedule   - policy year schedules of withdrawals, loans, face changes and option switches layered onto a projection
+survivorship - frasier last survivor coi for two insureds and continuation on the survivor after a first death
+term       - level term with no account value, premium solved to a target profit margin or irr
+wholelife  - traditional whole life with net premium reserve cash values, premium solved to a target profit margin or irr
+tables     - rate tables loaded once and indexed in memory by cell
+tax7702    - IRC 7702 guideline premiums and limit, 7702A seven-pay test and MEC status
+select_ultimate - select and ultimate table structure and csv reader
+variable   - variable universal life separate account funds with return paths, fund expense ratios and a daily or monthly m&e charge
+validate   - checks on the rate tables for gaps, duplicates, negative rates and rows past maturity
+vm20       - VM-20 deterministic reserve from expected cash flows and a net asset earned rate path
+xtbml      - reader for SOA XTbML mortality tables
+rounding   - rounding policy for solved premiums and, optionally, every amount of the projection
+solver     - generic goal seek by bisection or brent's method, used by the premium and face solves
+report     - json reports of projection, solve, and batch results
+arrow_results - batch results as arrow record batches and arrow ipc files, for dataframes without a csv (arrow feature)
+decimal    - decimal projection and premium solve with money rounded to the cent, for tie-outs (decimal feature)
+ffi        - extern "C" api with an opaque rate repository handle and flat policy and result structs (ffi feature)
+gpu        - experimental single precision projection and premium solve of a batch or scenario paths on a wgpu compute shader (gpu feature)
+grpc       - grpc service from proto/valact.proto with a unary solve and streamed batch results (grpc feature)
+parquet_writer - parquet results writer for large batch runs (parquet feature)
+pdf_report - pdf illustration of the inputs and guaranteed, midpoint and current ledgers for a single policy (pdf feature)
+python     - python module with the approach1.py functions run on the engine (python feature)
+reload     - rate tables served live, swapped for the data directory's tables when they change, with their checksums (server or grpc feature)
+server     - http api with /solve and /project endpoints over the live rate repository and /admin/tables (server feature)
+sqlite_store - sqlite backed rate tables (sqlite feature)
+xlsx_report - formatted illustration workbook for a single policy (xlsx feature)
+wasm       - wasm-bindgen wrappers for browser illustrations, with the rate tables passed in as csv bytes (wasm feature)
+
+*/
+pub mod age;
+pub mod annuity;
+#[cfg(feature = "arrow")]
+pub mod arrow_results;
+pub mod assumptions;
+pub mod bands;
+pub mod batch;
+pub mod calendar;
+pub mod checkpoint;
+pub mod config;
+pub mod cvat;
+#[cfg(feature = "decimal")]
+pub mod decimal;
+pub mod decrements;
+pub mod error;
+pub mod esg;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod guaranteed;
+pub mod illustration;
+pub mod indexed;
+pub mod inforce;
+pub mod lanes;
+pub mod modelpoints;
+pub mod nlg;
+#[cfg(feature = "parquet")]
+pub mod parquet_writer;
+pub mod payout;
+#[cfg(feature = "pdf")]
+pub mod pdf_report;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod product;
+pub mod profit;
+pub mod projection;
+pub mod ratebook;
+pub mod rates;
+#[cfg(any(feature = "server", feature = "grpc"))]
+pub mod reload;
+pub mod report;
+pub mod reserve;
+pub mod riders;
+pub mod rounding;
+pub mod repository;
+pub mod runner;
+pub mod scenarios;
+pub mod schedule;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod select_ultimate;
+pub mod sensitivity;
+pub mod solver;
+pub mod summary;
+pub mod survivorship;
+pub mod tables;
+pub mod term;
+pub mod tax7702;
+pub mod validate;
+pub mod variable;
+pub mod vm20;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wholelife;
+pub mod xtbml;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+#[cfg(feature = "xlsx")]
+pub mod xlsx_report;
+
+pub use projection::{
+    at_issue_projection, at_issue_projection_detail, at_issue_projection_detail_with, at_issue_projection_with,
+    solve_for_face, solve_for_face_with, solve_for_modal_premium, solve_for_premium, solve_for_premium_to_target,
+    solve_for_premium_using, solve_for_premium_with, DeathBenefitOption, InforceState, MonthResult, PremiumMode, PremiumPattern, ProjectionConfig, SolveResult, SolveTarget,
+    Timestep,
+};
+pub use age::{AttainedAge, IssueAge, PolicyYear};
+pub use rounding::{RoundingMode, RoundingPolicy, RoundingUnit};
+pub use error::ValactError;
+pub use rates::{get_rates, get_rates_to_maturity, RateSet, DEFAULT_MATURITY_AGE};
+pub use repository::RateRepository;
+pub use tables::RateTables;
@@ -4,201 +4,2617 @@ Example Rust implementation of approach1.py
 Run from command line in parent directory (the one above src which contains this file)
 Build: cargo build .
 Build with optimizations: cargo build --release .
-Run and build if needed: cargo run .
-Run and build if needed with optimization: cargo run --release .
+Run and build if needed: cargo run -- solve --gender M --class NS --age 35 --face 100000
+Run and build if needed with optimization: cargo run --release -- solve ...
+
+Once built the binary can be called directly
+valact solve --gender F --class NS --age 45 --face 250000
+valact project --gender M --class NS --age 35 --face 100000 --premium 1255.03 --output-file detail.csv
+valact batch --input ./data/census.csv --output-file results.csv
+valact --rates-db rates.sqlite import-rates
+valact solve --gender M --class NS --dob 1990-03-15 --issue-date 2025-10-01 --face 100000
+valact --output json solve --gender M --class NS --age 35 --face 100000 --detail
+valact solve --gender M --class NS --age 35 --face 100000 --timestep annual
+valact solve --gender M --class NS --age 35 --face 100000 --mode monthly --modal-factor 0.0875
+valact solve --gender M --class NS --age 35 --face 100000 --pay-to-age 65
+valact solve-face --gender M --class NS --age 35 --premium 2400 --mode monthly
+valact solve --gender M --class NS --age 35 --face 100000 --target-value 100000 --target-age 65
+valact solve --gender M --class NS --age 35 --face 100000 --target-value 100000 --target-age 65 --method secant
+valact solve --gender M --class NS --age 35 --face 100000 --method brent --log-iterations
+valact project --gender M --class NS --age 35 --face 100000 --premium 2000 --withdrawal 20-30:5000 --output-file detail.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 3000 --loan 25-30:8000 --loan-repayment 31:10000 --output-file detail.csv
+valact solve --gender M --class NS --age 35 --face 100000 --db-option 2
+valact project --gender M --class NS --age 35 --face 100000 --premium 3000 --db-option 2 --db-option-change 20:1 --face-change 30:50000 --output-file detail.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 5000 --guideline-test refuse
+valact project --gender M --class NS --age 35 --face 100000 --premium 5000 --face-change 10:150000 --mec-test --output-file detail.csv
+valact solve --gender M --class NS --age 35 --face 100000 --cvat-corridor --cvat-interest 0.04
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --expected-file expected.csv --lapse-table tests/fixtures/lapse.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --expected-file expected.csv --mortality-table mortality_su.csv --mortality-multiple 0.9
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --expected-file expected.csv --assumptions pricing.json --state NY
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --profit-file profit.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --profit-file profit.csv --statutory --valuation-mortality cso2017_su.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --naer-file naer.csv --mortality-table vm20_mortality_su.csv
+valact scenarios --gender M --class NS --age 35 --face 100000 --premium 1500 --scenario-file rate_paths.csv --output-file scenarios.csv
+valact scenarios --gender M --class NS --age 35 --face 100000 --premium 1500 --generate 1000 --model lognormal --seed 42 --output-file scenarios.csv
+valact scenarios --gender M --class NS --age 35 --face 100000 --premium 1500 --scenario-file airg_10yr.csv --scenario-format airg
+valact scenarios --gender M --class NS --age 35 --face 100000 --premium 1500 --generate 50000 --backend gpu --output-file scenarios.csv
+valact generate-scenarios --count 100 --years 65 --model vasicek --mean-rate 0.045 --output-file rate_paths.csv
+valact sensitivity --gender M --class NS --age 35 --face 100000 --output-file sensitivities.csv
+valact sensitivity --gender M --class NS --age 35 --face 100000 --shocks shocks.csv --lapse-table tests/fixtures/lapse.csv --assumptions pricing.json
+valact term --gender M --class NS --age 35 --face 500000 --level-years 20 --target-margin 0.08
+valact term --gender F --class NS --age 45 --face 250000 --level-years 10 --target-irr 0.12 --lapse-table tests/fixtures/lapse.csv --profit-file term_profit.csv
+valact whole-life --gender M --class NS --age 35 --face 100000 --output-file cash_values.csv
+valact whole-life --gender F --class NS --age 40 --face 250000 --premium-years 20 --cash-value-interest 0.045 --target-margin 0.05
+valact annuity --age 55 --premium 1:100000 --withdrawal 8-15:6000 --output-file annuity.csv
+valact annuity --age 60 --premium 1-10:10000 --surrender-charges 0.08,0.07,0.06,0.05,0.04 --credited-rates declared.csv --annuitization-age 90
+valact payout --gender M --class NS --age 65 --amount 250000 --certain-years 10
+valact payout --gender M --class NS --age 67 --amount 400000 --joint-gender F --joint-class NS --joint-age 64 --survivor-fraction 0.5 --mortality-table annuity2012.csv
+valact --index-returns sp500_returns.csv --index-cap 0.095 --index-floor 0.0 --index-participation 1.0 solve --gender M --class NS --age 35 --face 100000
+valact project --gender M --class NS --age 35 --face 100000 --premium 2000 --index-allocation 0.6 --index-account-returns sp500_returns.csv --index-account-cap 0.09 --output-file detail.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 3000 --fund-returns fund_returns.csv --fund-allocations 0.6,0.4 --fund-expenses 0.0045,0.0080 --me-charge 0.009 --output-file detail.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 900 --nlg --nlg-basis shadow.json --output-file detail.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --adb 100000 --child-term 10 --output-file detail.csv
+valact solve --gender M --class NS --age 35 --face 100000 --wmd --wmd-table tests/fixtures/wmd.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --wmd --wmd-table tests/fixtures/wmd.csv --wmd-waived-from 10 --output-file detail.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --chronic-illness-year 30 --chronic-illness-share 0.5 --output-file detail.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --chronic-illness-year 30 --acceleration-method lien --lien-rate 0.06 --output-file detail.csv
+valact solve --gender M --class NS --age 55 --face 1000000 --second-gender F --second-class NS --second-age 52
+valact project --gender M --class NS --age 55 --face 1000000 --premium 9000 --second-gender F --second-class NS --second-age 52 --first-death-year 20 --survivor second
+valact --product products/ul2025.toml solve --gender M --class NS --age 35 --face 100000
+valact --config run.toml batch
+valact project --gender M --class NS --age 35 --face 100000 --premium 1255.03 --explain explain.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --guaranteed --ledger-file ledger.csv
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --guaranteed --pdf-file illustration.pdf
+valact project --gender M --class NS --age 35 --face 100000 --premium 1500 --inforce-duration 60 --inforce-account-value 5200 --inforce-loan-balance 1000
+valact project --gender M --class NS --age 35 --issue-date 2019-03-15 --face 100000 --premium 1500 --valuation-date 2025-12-31 --inforce-account-value 9800 --calendar-file calendar.csv --reporting-period quarter
+valact illustration-test --input census.csv --output-file cell_tests.csv
+valact batch --input census.csv --output-file results.csv --progress --progress-interval 10
+valact batch --input census.csv --output-file results.csv --keep-going --summary-file summary.json --age-band-width 5
+valact batch --input census.csv --output-file results.csv --model-points --model-point-bands 0,100000,250000,500000,1000000
+valact batch --input inforce_extract.csv --valuation-date 2025-12-31 --plan-code UL25 --output-file inforce_results.csv
+valact --log-level debug --log-format json batch --input census.csv --output-file results.csv 2> batch_log.jsonl
+valact serve --address 0.0.0.0:8080
+valact serve --address 0.0.0.0:8080 --watch --watch-interval 5
+valact serve --grpc --address 0.0.0.0:50051
+valact validate ./data
+valact --premium-load-table loads_by_year.csv --credited-rate-table graded_rates.csv solve --gender M --class NS --age 35 --face 100000
+valact --face-bands face_bands.csv batch --input census.csv --output-file results.csv
+valact --maturity-age 100 solve --gender M --class NS --age 35 --face 100000
+valact --rate-date 2024-06-30 batch --input census.csv --output-file results.csv
+valact batch --input census.csv --output-file results.csv --backend gpu
+valact batch --input census.csv --output-file results.csv --checkpoint --checkpoint-interval 300
+valact batch --input census.csv --output-file results.csv --resume 20260301-221500-4242
+valact batch --input census.csv --output-file results.parquet --stream --keep-going
+valact batch --input census.csv --output-file results.arrow
+valact ratebook --output-file ratebook.csv
+valact ratebook --output-file ratebook_ns.csv --classes NS --min-age 20 --max-age 70 --faces 50000,100000,250000 --db-option 2
+
+The projection engine itself lives in lib.rs (and the modules it declares),
+this file only drives it.
 
 */
 use std::{
-    error::Error, fs::File, process, collections::HashMap
+    borrow::Cow, io::IsTerminal, process, sync::Arc, time::Duration
 };
 
-// serde is for serialization and deserialization of data
-// using here to simplify reading csv files
-use serde::Deserialize;
-
-// need to prepare to deserialize data to structure
-#[derive(Deserialize)]
-struct IAPYRecord {
-    // unfortunately there is not a shortcut here given the nameing used in the csvs
-    // there is a quick way to indicate the fields are lowercase, UPPERCASE, PascalCase, 
-    // camelCase, snake_case, SCREAMING_SNAKE_CASE, kebab-case, and 
-    // SCREAMING-KEBAB-CASE
-    #[serde(alias="Issue_Age")]
-    issue_age: i8,
-    #[serde(alias="Policy_Year")]
-    policy_year: i8,
-    #[serde(alias="Rate")]
-    rate: f64,
-}
-
-#[derive(Deserialize)]
-struct GenRCIAPYRecord {
-    #[serde(alias="Gender")]
-    gender: String,
-    #[serde(alias="Risk_Class")]
-    risk_class: String,
-    #[serde(alias="Issue_Age")]
-    issue_age: i8,
-    #[serde(alias="Policy_Year")]
-    policy_year: i8,
-    #[serde(alias="Rate")]
-    rate: f64,
+use chrono::NaiveDate;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use tracing::warn;
+use tracing_subscriber::EnvFilter;
+
+use illus1::{
+    age::{insurance_age, AgeBasis, AttainedAge, IssueAge}, annuity::{self, AnnuityProduct}, assumptions::Assumptions, bands::FaceBands, calendar::{self, ReportingPeriod}, guaranteed::{self, GuaranteedBasis}, illustration, inforce, at_issue_projection_detail_with, schedule::{FaceChange, LoanTerms, OptionChange, ScheduledAmount, Withdrawal}, batch::{self, PolicyRecord}, checkpoint::{self, Checkpoint}, config::RunConfig, get_rates_to_maturity, cvat::{self, CVAT_INTEREST}, indexed::{self, Account, Crediting, PointToPoint}, nlg::{self, ShadowBasis}, product::ProductSpec, payout::{self, PaymentTiming, PayoutOptions}, survivorship::{self, FirstDeath, Insured}, riders::{AccelerationMethod, AccidentalDeathBenefit, ChildTerm, ChronicIllness, Riders, WaiverOfDeductions}, decrements::{self, ExpectedMonth}, profit::{self, ProfitTarget}, projection, ratebook, reserve, rates::{self, read_target_premium, DataDir}, report, runner, scenarios, summary, modelpoints, sensitivity::{self, PricingBasis}, esg::{self, GeneratorParams, ShortRateModel}, tax7702::{self, GuidelineTest}, term::{self, TermProduct}, validate, variable::{self, ChargeFrequency, MortalityExpense}, vm20, wholelife::{self, WholeLifeProduct},
+    error::ValactError,
+    solve_for_face_with, solve_for_modal_premium, solve_for_premium_using, solver::{Method, SolverOptions, DEFAULT_MAX_ITERATIONS},
+    DeathBenefitOption, InforceState, MonthResult, PremiumMode, PremiumPattern, ProjectionConfig, RateRepository, RateSet, RoundingMode, RoundingPolicy, RoundingUnit, SolveTarget, Timestep, DEFAULT_MATURITY_AGE
+};
+
+#[derive(Parser)]
+#[command(name = "valact", about = "Universal life illustration and premium solves")]
+struct Cli {
+    /// Run configuration (toml) giving global and per command options, anything on the command line takes precedence
+    #[arg(long, global = true)]
+    config: Option<String>,
+    /// Directory the rate tables and assumptions are read from
+    #[arg(long, global = true)]
+    data_dir: Option<String>,
+    /// Date (yyyy-mm-dd) that picks the version of versioned rate tables (coi_2024-06.csv ...) for every policy, in place of each policy's issue date
+    #[arg(long, global = true)]
+    rate_date: Option<NaiveDate>,
+    /// Format of what is printed to stdout
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// Most detailed log messages written to stderr, debug adds a line per policy solved
+    #[arg(long, value_enum, global = true, default_value_t = LogLevel::Info)]
+    log_level: LogLevel,
+    /// Format of the log messages, json gives one object per line for log collectors
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    log_format: OutputFormat,
+    /// Read rate tables from this sqlite database instead of ./data (needs the sqlite feature)
+    #[arg(long, global = true)]
+    rates_db: Option<String>,
+    /// Product specification (toml) declaring each charge as a constant, table or formula, in place of the built in product
+    #[arg(long, global = true, conflicts_with = "rates_db")]
+    product: Option<String>,
+    /// Age basis used when issue age is worked out from date of birth and issue date
+    #[arg(long, value_enum, global = true, default_value_t = AgeBasisArg::Anb)]
+    age_basis: AgeBasisArg,
+    /// Attained age at which policies mature and the projection ends
+    #[arg(long, global = true, default_value_t = DEFAULT_MATURITY_AGE)]
+    maturity_age: AttainedAge,
+    /// Premium loads by policy year (Issue_Age, Policy_Year, Rate) in place of the product's, years the table leaves out keep the product's load
+    #[arg(long, global = true)]
+    premium_load_table: Option<String>,
+    /// Annual credited rates by policy year (Issue_Age, Policy_Year, Rate) in place of the product's, years the table leaves out keep the product's rate
+    #[arg(long, global = true)]
+    credited_rate_table: Option<String>,
+    /// Premium loads, policy fees and unit loads by face amount band (Charge, Min_Face, Issue_Age, Policy_Year, Rate) in place of the product's
+    #[arg(long, global = true)]
+    face_bands: Option<String>,
+    /// Compute CVAT corridor factors in place of the corridor_factors table
+    #[arg(long, global = true)]
+    cvat_corridor: bool,
+    /// Mortality table for the CVAT net single premiums, XTbML or select and ultimate csv; defaults to the coi rates
+    #[arg(long, global = true, requires = "cvat_corridor")]
+    cvat_mortality: Option<String>,
+    /// Annual interest rate for the CVAT net single premiums
+    #[arg(long, global = true, default_value_t = CVAT_INTEREST)]
+    cvat_interest: f64,
+    /// Index returns by policy year (Policy_Year, Rate) credited by annual point-to-point in place of the interest rate table
+    #[arg(long, global = true)]
+    index_returns: Option<String>,
+    /// Cap on the indexed credited rate
+    #[arg(long, global = true, default_value_t = PointToPoint::default().cap)]
+    index_cap: f64,
+    /// Floor on the indexed credited rate
+    #[arg(long, global = true, default_value_t = PointToPoint::default().floor)]
+    index_floor: f64,
+    /// Share of the index return credited before the cap and floor
+    #[arg(long, global = true, default_value_t = PointToPoint::default().participation)]
+    index_participation: f64,
+    #[command(subcommand)]
+    command: Command,
+}
+
+impl Cli {
+    fn rate_source(&self) -> Result<RateSource, ValactError> {
+        return Ok(RateSource {
+            // a single policy command's issue date is the one date its tables are wanted for
+            data_dir: self.data_dir.as_deref().map_or_else(DataDir::default, DataDir::new).as_of(self.rate_date.or_else(|| self.command.issue_date())),
+            rates_db: self.rates_db.clone(),
+            product: self.product.as_deref().map(ProductSpec::from_file).transpose()?,
+            maturity_age: self.maturity_age,
+            premium_load_table: self.premium_load_table.clone(),
+            credited_rate_table: self.credited_rate_table.clone(),
+            face_bands: self.face_bands.as_deref().map(FaceBands::from_file).transpose()?.map(Arc::new),
+            cvat: if self.cvat_corridor {Some(CvatCorridor { mortality: self.cvat_mortality.clone(), interest: self.cvat_interest })} else {None},
+            indexed: self.index_returns.as_ref().map(|path| IndexedCrediting {
+                returns: path.clone(),
+                strategy: PointToPoint { cap: self.index_cap, floor: self.index_floor, participation: self.index_participation },
+            }),
+        });
+    }
+}
+
+// bands depend on the face amount so are applied per policy, after the cell's rates come out of any cache
+fn band_rates(face_bands: &Option<Arc<FaceBands>>, mut rates: RateSet, policy: &PolicyRecord) -> RateSet {
+    if let Some(bands) = face_bands {
+        bands.apply(&mut rates, policy.issue_age, policy.face_amount);
+    }
+    return rates;
+}
+
+// a policy's rates out of a run's repository, only a policy with face band charges needs its own copy of the cell's
+fn policy_rates(repository: &RateRepository, face_bands: &Option<Arc<FaceBands>>, policy: &PolicyRecord) -> Result<Arc<RateSet>, ValactError> {
+    let rates = repository.get_shared_as_of(&policy.gender, &policy.risk_class, policy.issue_age, policy.issue_date)?;
+    return match face_bands {
+        Some(_) => Ok(Arc::new(band_rates(face_bands, RateSet::clone(&rates), policy))),
+        None => Ok(rates),
+    };
 }
 
-#[derive(Deserialize)]
-struct AARecord {
-    #[serde(alias="Attained_Age")]
-    attained_age: i8,
-    #[serde(alias="Rate")]
-    rate: f64,
+#[derive(Clone)]
+struct CvatCorridor {
+    mortality: Option<String>,
+    interest: f64,
 }
 
-fn read_ia_py_csv(path: &str, default: f64, issue_age: i8, ) -> Result<[f64;121], Box<dyn Error>> {
-    // create default array
-    let mut rates: [f64; 121] = [default; 121];
+#[derive(Clone)]
+struct IndexedCrediting {
+    returns: String,
+    strategy: PointToPoint,
+}
+
+// where the rates come from and what replaces parts of them, cloned into the batch loader
+#[derive(Clone)]
+struct RateSource {
+    data_dir: DataDir,
+    rates_db: Option<String>,
+    product: Option<ProductSpec>,
+    maturity_age: AttainedAge,
+    premium_load_table: Option<String>,
+    credited_rate_table: Option<String>,
+    face_bands: Option<Arc<FaceBands>>,
+    cvat: Option<CvatCorridor>,
+    indexed: Option<IndexedCrediting>,
+}
+
+impl RateSource {
+    fn load(&self, gender: &str, class: &str, age: IssueAge) -> Result<RateSet, ValactError> {
+        return self.load_from(&self.data_dir, gender, class, age);
+    }
+
+    // with the tables in the versions data_dir is for
+    fn load_from(&self, data_dir: &DataDir, gender: &str, class: &str, age: IssueAge) -> Result<RateSet, ValactError> {
+        let rates = match &self.product {
+            Some(product) => product.rate_set(data_dir, gender, class, age, self.maturity_age)?,
+            None => load_rates(&self.rates_db, data_dir, self.maturity_age, gender, class, age)?,
+        };
+        return self.adjust(rates, age);
+    }
+
+    // a policy's rates, the cell's from the tables in effect on its issue date with its face amount band applied
+    fn load_policy(&self, policy: &PolicyRecord) -> Result<RateSet, ValactError> {
+        let rates = self.load_from(&self.data_dir.for_issue_date(policy.issue_date), &policy.gender, &policy.risk_class, policy.issue_age)?;
+        return Ok(band_rates(&self.face_bands, rates, policy));
+    }
+
+    fn adjust(&self, mut rates: RateSet, age: IssueAge) -> Result<RateSet, ValactError> {
+        if let Some(path) = &self.premium_load_table {
+            rates::apply_premium_load_table(&mut rates, path, age)?;
+        }
+        if let Some(path) = &self.credited_rate_table {
+            rates::apply_credited_rate_table(&mut rates, path, age)?;
+        }
+        if let Some(cvat) = &self.cvat {
+            cvat::apply_cvat_corridor(&mut rates, age, cvat.mortality.as_deref(), cvat.interest)?;
+        }
+        if let Some(indexed) = &self.indexed {
+            indexed::apply_indexed_crediting(&mut rates, &indexed.returns, &indexed.strategy)?;
+        }
+        return Ok(rates);
+    }
 
-    let file = File::open(path)?;
-    let mut rdr = csv::Reader::from_reader(file);
+    fn adjusts_rates(&self) -> bool {
+        return self.premium_load_table.is_some() || self.credited_rate_table.is_some() || self.cvat.is_some() || self.indexed.is_some();
+    }
 
-    for result in rdr.deserialize() {
-        let record: IAPYRecord = result?;
-        if record.issue_age == issue_age{
-            rates[(record.policy_year - 1) as usize] = record.rate
+    // with the csv tables everything is parsed once up front, a database or product spec is read per cell
+    // loaded once and shared by every worker thread of the run
+    fn repository(self) -> Result<Arc<RateRepository>, ValactError> {
+        let maturity_age = self.maturity_age;
+        let data_dir = self.data_dir.clone();
+        if self.rates_db.is_some() {
+            return Ok(Arc::new(RateRepository::with_loader(move |gender, class, age| self.load(gender, class, age)).with_data_dir(data_dir)));
+        }
+        if let Some(product) = &self.product {
+            let versions = data_dir.version_dates(&product.table_names());
+            let loader = move |gender: &str, class: &str, age: IssueAge, version: Option<NaiveDate>| self.load_from(&self.data_dir.as_of(version), gender, class, age);
+            return Ok(Arc::new(RateRepository::with_dated_loader(versions, loader).with_data_dir(data_dir)));
         }
+        if self.adjusts_rates() {
+            return Ok(Arc::new(RateRepository::in_memory_with(&data_dir, maturity_age, move |rates, age| self.adjust(rates, age))?));
+        }
+        return Ok(Arc::new(RateRepository::in_memory_to_maturity(&data_dir, maturity_age)?));
     }
-    return Ok(rates);
 }
 
-fn read_gen_rc_ia_py_csv(path: &str, default: f64, gender: &str, risk_class: &str, issue_age: i8) -> Result<[f64;121], Box<dyn Error>> {
-    let mut rates: [f64;121] = [default;121];
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
 
-    let file = File::open(path)?;
-    let mut rdr = csv::Reader::from_reader(file);
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
 
-    for result in rdr.deserialize() {
-       let record: GenRCIAPYRecord = result?;
-       if record.gender == gender && record.risk_class == risk_class && record.issue_age == issue_age {
-           rates[(record.policy_year - 1) as usize] = record.rate
-       }
+impl LogLevel {
+    fn filter(self) -> &'static str {
+        return match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        };
     }
-    return Ok(rates);
 }
 
-fn read_aa_csv(path: &str, default: f64, issue_age: i8) -> Result<[f64;121], Box<dyn Error>> {
-    let mut rates: [f64;121] = [default;121];
+// logs go to stderr so they never mix with the results on stdout
+// RUST_LOG, if set, takes the place of --log-level and can filter by module, e.g. RUST_LOG=illus1::batch=debug
+fn init_logging(level: LogLevel, format: OutputFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level.filter()));
+    let logger = tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr).with_ansi(std::io::stderr().is_terminal());
+    match format {
+        OutputFormat::Text => logger.init(),
+        OutputFormat::Json => logger.json().init(),
+    }
+}
 
-    let file = File::open(path)?;
-    let mut rdr = csv::Reader::from_reader(file);
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum AgeBasisArg {
+    /// Age nearest birthday
+    Anb,
+    /// Age last birthday
+    Alb,
+}
 
-    for result in rdr.deserialize() {
-       let record: AARecord = result?;
-       if record.attained_age >= issue_age {
-           rates[(record.attained_age - issue_age) as usize] = record.rate;
-       }
+impl AgeBasisArg {
+    fn basis(self) -> AgeBasis {
+        return match self {
+            AgeBasisArg::Anb => AgeBasis::NearestBirthday,
+            AgeBasisArg::Alb => AgeBasis::LastBirthday,
+        };
     }
-    return Ok(rates);
 }
 
-fn at_issue_projection(rates: HashMap<&'static str, [f64;121]>, issue_age: i8, face_amount: f64, annual_premium: f64) -> Result<f64, Box<dyn Error>> {
-    let maturity_age: i8 = 121;
-    let projection_years: i8 = maturity_age - issue_age;    
-    let mut end_value = 0.0;
-    let mut policy_year = 0;
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum TimestepArg {
+    /// Project month by month
+    Monthly,
+    /// Project a policy year at a time, for quick checks
+    Annual,
+}
+
+impl TimestepArg {
+    fn timestep(self) -> Timestep {
+        return match self {
+            TimestepArg::Monthly => Timestep::Monthly,
+            TimestepArg::Annual => Timestep::Annual,
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DbOptionArg {
+    /// Option 1, level face amount
+    #[value(name = "1")]
+    Level,
+    /// Option 2, face amount plus account value
+    #[value(name = "2")]
+    Increasing,
+    /// Option 3, face amount plus premiums paid
+    #[value(name = "3")]
+    ReturnOfPremium,
+}
+
+impl DbOptionArg {
+    fn option(self) -> DeathBenefitOption {
+        return match self {
+            DbOptionArg::Level => DeathBenefitOption::Level,
+            DbOptionArg::Increasing => DeathBenefitOption::Increasing,
+            DbOptionArg::ReturnOfPremium => DeathBenefitOption::ReturnOfPremium,
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum InsuredArg {
+    First,
+    Second,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum AccelerationMethodArg {
+    /// Pay the accelerated amount less a discount and reduce the face amount and account value
+    Discount,
+    /// Advance the accelerated amount as a lien against the death benefit and cash value
+    Lien,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ChargeFrequencyArg {
+    /// Out of the fund unit values every day
+    Daily,
+    /// Off the fund values at the start of each month
+    Monthly,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Backend {
+    /// Double precision on the cpu, across a thread per core
+    Cpu,
+    /// Experimental: single precision on a wgpu compute shader, premiums within a cent or two of the cpu's, falling back to the cpu without an adapter (needs the gpu feature)
+    Gpu,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ScenarioFormatArg {
+    /// A Month or Policy_Year column then one column of annual effective rates per scenario
+    Columns,
+    /// Academy interest rate generator output, a row of percent yields per scenario starting from month 0
+    Airg,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ShortRateModelArg {
+    /// Normal rate changes reverting to the mean rate, rates can go negative
+    Vasicek,
+    /// Normal changes in the log of the rate, rates stay positive
+    Lognormal,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum GuidelineTestArg {
+    /// Do not test premiums
+    Off,
+    /// Print a warning when premiums exceed the guideline limit
+    Warn,
+    /// Fail when premiums exceed the guideline limit
+    Refuse,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum RoundingArg {
+    /// Toward zero
+    Truncate,
+    /// Half away from zero
+    HalfUp,
+    /// Half to the even unit, banker's rounding
+    #[value(alias = "bankers")]
+    HalfEven,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum RoundToArg {
+    Cents,
+    Dollars,
+}
 
-    for i in 0..(12 * i32::from(projection_years)) {
-        policy_year += if (i % 12) == 0 {1} else {0};
-        let start_value = end_value;
-        let premium = if (i % 12) == 0 {annual_premium} else {0.0};
-        let premium_load = premium * rates["premium_loads"][policy_year-1];
-        let expense_charge = (rates["policy_fees"][policy_year-1] + rates["unit_loads"][policy_year-1] * face_amount / 1000.0) / 12.0;
-        let av_for_db = start_value + premium - premium_load - expense_charge;
-        let db = face_amount.max(rates["corr_facts"][policy_year-1] * av_for_db);
-        let naar = (db * rates["naar_discs"][policy_year-1] - av_for_db.max(0.0)).max(0.0);
-        let coi = (naar / 1000.0) * (rates["coi_rates"][policy_year-1] / 12.0);
-        let av_for_interest = av_for_db - coi;
-        let interest = (av_for_interest * rates["interest_rates"][policy_year - 1]).max(0.0);
-        end_value = av_for_interest + interest;
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum PeriodArg {
+    Month,
+    Quarter,
+    Year,
+}
+
+impl PeriodArg {
+    fn period(self) -> ReportingPeriod {
+        return match self {
+            PeriodArg::Month => ReportingPeriod::Month,
+            PeriodArg::Quarter => ReportingPeriod::Quarter,
+            PeriodArg::Year => ReportingPeriod::Year,
+        };
     }
-    
-    return Ok(end_value);
 }
 
-fn solve_for_premium(rates: HashMap<&'static str, [f64;121]>, issue_age: i8, face_amount: f64) -> Result<f64, Box<dyn Error>> {
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ModeArg {
+    Annual,
+    Semiannual,
+    Quarterly,
+    Monthly,
+}
 
-    let mut guess_lo = 0.0;
-    let mut guess_hi = face_amount / 100.0;
-    let mut guess_md = 0.0;
+// options for how the single policy subcommands run the projection
+#[derive(Args)]
+struct ProjectionArgs {
+    /// Length of each projection step
+    #[arg(long, value_enum, default_value_t = TimestepArg::Monthly)]
+    timestep: TimestepArg,
+    /// Death benefit option
+    #[arg(long, value_enum, default_value_t = DbOptionArg::Level)]
+    db_option: DbOptionArg,
+    /// How often premiums are paid
+    #[arg(long, value_enum, default_value_t = ModeArg::Annual)]
+    mode: ModeArg,
+    /// Modal payment as a fraction of the annual premium, e.g. 0.0875 monthly; defaults to an even split
+    #[arg(long)]
+    modal_factor: Option<f64>,
+    /// Pay a single premium at issue
+    #[arg(long, conflicts_with_all = ["pay_to_age", "pay_years"])]
+    single_pay: bool,
+    /// Stop paying premiums at this attained age
+    #[arg(long, conflicts_with = "pay_years")]
+    pay_to_age: Option<AttainedAge>,
+    /// Pay premiums for this many policy years
+    #[arg(long)]
+    pay_years: Option<u16>,
+    /// How solved premiums, and amounts with --round-amounts, are rounded
+    #[arg(long, value_enum, default_value_t = RoundingArg::HalfUp)]
+    rounding: RoundingArg,
+    /// Unit solved premiums, and amounts with --round-amounts, are rounded to
+    #[arg(long, value_enum, default_value_t = RoundToArg::Cents)]
+    round_to: RoundToArg,
+    /// Also round every charge, interest credit and account value of the projection as it is worked out
+    #[arg(long)]
+    round_amounts: bool,
+    /// Partial withdrawal as YEARS:AMOUNT, e.g. 10:5000 or 20-30:10% of account value, can be repeated
+    #[arg(long = "withdrawal")]
+    withdrawals: Vec<Withdrawal>,
+    /// New face amount from a policy year on as YEAR:FACE, e.g. 10:150000, can be repeated
+    #[arg(long = "face-change")]
+    face_changes: Vec<FaceChange>,
+    /// Death benefit option switch as YEAR:OPTION, e.g. 15:1, can be repeated
+    #[arg(long = "db-option-change")]
+    option_changes: Vec<OptionChange>,
+    /// Policy loan as YEARS:AMOUNT, e.g. 25-30:8000, can be repeated
+    #[arg(long = "loan")]
+    loans: Vec<ScheduledAmount>,
+    /// Loan repayment as YEARS:AMOUNT, can be repeated
+    #[arg(long = "loan-repayment")]
+    loan_repayments: Vec<ScheduledAmount>,
+    /// Annual rate charged on the loan balance
+    #[arg(long, default_value_t = LoanTerms::default().charged_rate)]
+    loan_rate: f64,
+    /// Annual rate credited on the account value backing the loan
+    #[arg(long, default_value_t = LoanTerms::default().credited_rate)]
+    loan_credited_rate: f64,
+    /// Test premiums paid against the IRC 7702 guideline premium limit
+    #[arg(long, value_enum, default_value_t = GuidelineTestArg::Off)]
+    guideline_test: GuidelineTestArg,
+    /// Accidental death benefit rider amount
+    #[arg(long)]
+    adb: Option<f64>,
+    /// Child term rider units of 1000
+    #[arg(long)]
+    child_term: Option<f64>,
+    /// Waiver of monthly deductions rider, covering the coi, expense and other rider charges
+    #[arg(long)]
+    wmd: bool,
+    /// Waiver charge per 1000 of deductions by attained age (Attained_Age, Rate); defaults to the wmd table in the data directory
+    #[arg(long, requires = "wmd")]
+    wmd_table: Option<String>,
+    /// Policy year from which the insured is taken as disabled and deductions are waived
+    #[arg(long, requires = "wmd")]
+    wmd_waived_from: Option<i32>,
+    /// Policy year the insured is taken as chronically ill and accelerates part of the face amount
+    #[arg(long)]
+    chronic_illness_year: Option<i32>,
+    /// Share of the face amount accelerated
+    #[arg(long, default_value_t = 0.5)]
+    chronic_illness_share: f64,
+    /// How the accelerated benefit is paid for
+    #[arg(long, value_enum, default_value_t = AccelerationMethodArg::Discount)]
+    acceleration_method: AccelerationMethodArg,
+    /// Share of the accelerated amount taken off for early payment, discount method
+    #[arg(long, default_value_t = 0.2)]
+    acceleration_discount: f64,
+    /// Annual interest accrued on the lien, lien method
+    #[arg(long, default_value_t = 0.05)]
+    lien_rate: f64,
+    /// Share of each net premium put in an index account, the rest stays in the fixed account
+    #[arg(long, requires = "index_account_returns")]
+    index_allocation: Option<f64>,
+    /// Index returns by policy year (Policy_Year, Rate) for the index account
+    #[arg(long, requires = "index_allocation")]
+    index_account_returns: Option<String>,
+    /// Cap on the index account's annual point-to-point credited rate
+    #[arg(long, default_value_t = PointToPoint::default().cap)]
+    index_account_cap: f64,
+    /// Floor on the index account's credited rate
+    #[arg(long, default_value_t = PointToPoint::default().floor)]
+    index_account_floor: f64,
+    /// Share of the index return credited to the index account before the cap and floor
+    #[arg(long, default_value_t = PointToPoint::default().participation)]
+    index_account_participation: f64,
+    /// Gross fund returns for variable universal life, a Month or Policy_Year column then a column of annual effective returns per fund
+    #[arg(long, requires = "fund_allocations")]
+    fund_returns: Option<String>,
+    /// Share of each net premium put in each fund, comma separated in the order of the fund columns, the rest stays in the fixed account
+    #[arg(long, value_delimiter = ',', requires = "fund_returns")]
+    fund_allocations: Vec<f64>,
+    /// Annual expense ratio of each fund charged daily, comma separated, or one for every fund
+    #[arg(long, value_delimiter = ',', requires = "fund_returns")]
+    fund_expenses: Vec<f64>,
+    /// Annual mortality and expense charge on the funds
+    #[arg(long, default_value_t = 0.0, requires = "fund_returns")]
+    me_charge: f64,
+    /// How often the mortality and expense charge is taken
+    #[arg(long, value_enum, default_value_t = ChargeFrequencyArg::Daily)]
+    me_frequency: ChargeFrequencyArg,
+    /// Project an existing policy from this many policy months after issue rather than from issue
+    #[arg(long)]
+    inforce_duration: Option<i32>,
+    /// Account value at the inforce duration or valuation date
+    #[arg(long)]
+    inforce_account_value: Option<f64>,
+    /// Loan balance at the inforce duration
+    #[arg(long)]
+    inforce_loan_balance: Option<f64>,
+    /// Surrender charge at the inforce duration, the rest of the schedule is scaled to it; defaults to the schedule's
+    #[arg(long)]
+    inforce_surrender_charge: Option<f64>,
+    /// Premiums paid to date less withdrawals, for the option 3 death benefit
+    #[arg(long)]
+    inforce_premiums_paid: Option<f64>,
+}
 
-    // get rates
-    loop {
-        let end_value = at_issue_projection(rates.clone(), issue_age, face_amount, guess_hi)?;
-        if end_value <= 0.0 {
-            guess_lo = guess_hi;
-            guess_hi *= 2.0;
-        } else {
-            break;
+impl ProjectionArgs {
+    fn to_config(&self, data_dir: &DataDir, maturity_age: AttainedAge) -> Result<ProjectionConfig, ValactError> {
+        return self.to_config_from(data_dir, maturity_age, self.inforce_duration);
+    }
+
+    // the inforce duration is --inforce-duration or worked out from a valuation date
+    fn to_config_from(&self, data_dir: &DataDir, maturity_age: AttainedAge, inforce_duration: Option<i32>) -> Result<ProjectionConfig, ValactError> {
+        let timestep = self.timestep.timestep();
+        let death_benefit_option = self.db_option.option();
+        let premium_mode = match self.mode {
+            ModeArg::Annual => PremiumMode::Annual,
+            ModeArg::Semiannual => PremiumMode::Semiannual,
+            ModeArg::Quarterly => PremiumMode::Quarterly,
+            ModeArg::Monthly => PremiumMode::Monthly,
+        };
+        let premium_pattern = match (self.single_pay, self.pay_to_age, self.pay_years) {
+            (true, _, _) => PremiumPattern::SinglePay,
+            (_, Some(age), _) => PremiumPattern::LevelToAge(age),
+            (_, _, Some(years)) => PremiumPattern::NPay(years),
+            _ => PremiumPattern::Level,
+        };
+        return Ok(ProjectionConfig {
+            timestep,
+            death_benefit_option,
+            premium_mode,
+            premium_pattern,
+            modal_factor: self.modal_factor,
+            withdrawals: self.withdrawals.clone(),
+            loans: self.loans.clone(),
+            loan_repayments: self.loan_repayments.clone(),
+            loan_terms: LoanTerms { charged_rate: self.loan_rate, credited_rate: self.loan_credited_rate },
+            face_changes: self.face_changes.clone(),
+            option_changes: self.option_changes.clone(),
+            interest_path: Vec::new(),
+            accounts: self.accounts(maturity_age)?,
+            mortality_expense: self.mortality_expense(),
+            riders: self.riders(data_dir)?,
+            rounding: self.rounding(),
+            inforce: self.inforce(inforce_duration)?,
+        });
+    }
+
+    fn inforce(&self, duration: Option<i32>) -> Result<Option<InforceState>, ValactError> {
+        let Some(duration) = duration else {
+            let given = [self.inforce_account_value, self.inforce_loan_balance, self.inforce_surrender_charge, self.inforce_premiums_paid];
+            if given.iter().any(Option::is_some) {
+                return Err("inforce values need --inforce-duration or --valuation-date".into());
+            }
+            return Ok(None);
+        };
+        return Ok(Some(InforceState {
+            duration,
+            account_value: self.inforce_account_value.unwrap_or(0.0),
+            loan_balance: self.inforce_loan_balance.unwrap_or(0.0),
+            surrender_charge: self.inforce_surrender_charge,
+            premiums_paid: self.inforce_premiums_paid.unwrap_or(0.0),
+        }));
+    }
+
+    fn rounding(&self) -> RoundingPolicy {
+        let mode = match self.rounding {
+            RoundingArg::Truncate => RoundingMode::Truncate,
+            RoundingArg::HalfUp => RoundingMode::HalfUp,
+            RoundingArg::HalfEven => RoundingMode::HalfEven,
+        };
+        let unit = match self.round_to {
+            RoundToArg::Cents => RoundingUnit::Cents,
+            RoundToArg::Dollars => RoundingUnit::Dollars,
+        };
+        return RoundingPolicy { mode, unit, amounts: self.round_amounts };
+    }
+
+    // the waiver goes last so it covers the other riders' charges
+    fn riders(&self, data_dir: &DataDir) -> Result<Riders, ValactError> {
+        let mut riders: Riders = Vec::new();
+        if let Some(amount) = self.adb {
+            riders.push(Arc::new(AccidentalDeathBenefit::new(amount)));
+        }
+        if let Some(units) = self.child_term {
+            riders.push(Arc::new(ChildTerm::new(units)));
+        }
+        if let Some(year) = self.chronic_illness_year {
+            let method = match self.acceleration_method {
+                AccelerationMethodArg::Discount => AccelerationMethod::Discount { discount: self.acceleration_discount },
+                AccelerationMethodArg::Lien => AccelerationMethod::Lien { interest: self.lien_rate },
+            };
+            riders.push(Arc::new(ChronicIllness::new(year, self.chronic_illness_share, method)));
         }
+        if self.wmd {
+            riders.push(Arc::new(WaiverOfDeductions::from_table(data_dir, self.wmd_table.as_deref(), self.wmd_waived_from)?));
+        }
+        return Ok(riders);
     }
 
-    while (guess_hi - guess_lo) > 0.005 {
-        guess_md = (guess_lo + guess_hi) / 2.0;
-        let end_value = at_issue_projection(rates.clone(), issue_age, face_amount, guess_md)?;
-        if end_value <= 0.0 {
-            guess_lo = guess_md;
-        } else {
-            guess_hi = guess_md;
+    // a fixed account with an index account when --index-allocation is given and a fund account per fund when
+    // --fund-returns is, otherwise none so the whole account value is in the fixed account
+    // index returns are read for every year to maturity_age, the years of the longest rate set it gives, a policy
+    // issued at age 0
+    fn accounts(&self, maturity_age: AttainedAge) -> Result<Vec<Account>, ValactError> {
+        let mut accounts = Vec::new();
+        if let (Some(allocation), Some(path)) = (self.index_allocation, &self.index_account_returns) {
+            if !(0.0..=1.0).contains(&allocation) {
+                return Err(format!("index allocation {} must be between 0 and 1", allocation).into());
+            }
+            let strategy = PointToPoint { cap: self.index_account_cap, floor: self.index_account_floor, participation: self.index_account_participation };
+            let index_returns = indexed::read_index_returns(path, usize::from(maturity_age.get()))?;
+            accounts.push(Account { name: "Index".to_string(), allocation, crediting: Crediting::Indexed { strategy, index_returns } });
+        }
+        if let Some(path) = &self.fund_returns {
+            if let Some(allocation) = self.fund_allocations.iter().find(|allocation| !(0.0..=1.0).contains(*allocation)) {
+                return Err(format!("fund allocation {} must be between 0 and 1", allocation).into());
+            }
+            accounts.extend(variable::read_fund_accounts(path, &self.fund_allocations, &self.fund_expenses)?);
+        }
+        if accounts.is_empty() {
+            return Ok(accounts);
         }
+        let allocated = accounts.iter().fold(0.0, |total, account| total + account.allocation);
+        if allocated > 1.0 + 1e-9 {
+            return Err(format!("index and fund allocations add up to {}, more than 1", allocated).into());
+        }
+        accounts.insert(0, Account::fixed((1.0 - allocated).max(0.0)));
+        return Ok(accounts);
+    }
+
+    fn mortality_expense(&self) -> MortalityExpense {
+        let frequency = match self.me_frequency {
+            ChargeFrequencyArg::Daily => ChargeFrequency::Daily,
+            ChargeFrequencyArg::Monthly => ChargeFrequency::Monthly,
+        };
+        return MortalityExpense { rate: self.me_charge, frequency };
+    }
+
+    fn guideline_test(&self) -> GuidelineTest {
+        return match self.guideline_test {
+            GuidelineTestArg::Off => GuidelineTest::Off,
+            GuidelineTestArg::Warn => GuidelineTest::Warn,
+            GuidelineTestArg::Refuse => GuidelineTest::Refuse,
+        };
+    }
+}
+
+// second insured of a survivorship policy, the joint coi replaces the first insured's
+#[derive(Args)]
+struct SecondInsuredArgs {
+    /// Gender of the second insured for a last survivor policy
+    #[arg(long, requires_all = ["second_class", "second_age"])]
+    second_gender: Option<String>,
+    /// Risk class of the second insured
+    #[arg(long, requires = "second_gender")]
+    second_class: Option<String>,
+    /// Issue age of the second insured
+    #[arg(long, requires = "second_gender")]
+    second_age: Option<IssueAge>,
+    /// Policy year at the start of which one insured dies, the policy continuing on the survivor
+    #[arg(long, requires_all = ["second_gender", "survivor"])]
+    first_death_year: Option<i32>,
+    /// Which insured survives the first death
+    #[arg(long, value_enum, requires = "first_death_year")]
+    survivor: Option<InsuredArg>,
+}
+
+impl SecondInsuredArgs {
+    // rates on the joint coi when there is a second insured, unchanged otherwise
+    fn adjust(&self, source: &RateSource, mut rates: RateSet) -> Result<RateSet, ValactError> {
+        let (Some(gender), Some(class), Some(age)) = (&self.second_gender, &self.second_class, self.second_age) else {
+            return Ok(rates);
+        };
+        let second = source.load(gender, class, age)?;
+        let first_death = match (self.first_death_year, self.survivor) {
+            (Some(policy_year), Some(survivor)) => Some(FirstDeath {
+                policy_year,
+                survivor: match survivor {
+                    InsuredArg::First => Insured::First,
+                    InsuredArg::Second => Insured::Second,
+                },
+            }),
+            _ => None,
+        };
+        survivorship::apply_survivorship(&mut rates, &second.coi_rates, first_death);
+        return Ok(rates);
     }
+}
 
-    let mut result = (guess_md * 100.0).round() / 100.0;
-    let end_value = at_issue_projection(rates.clone(), issue_age, face_amount, result)?;
-    if end_value <= 0.0 {result += 0.01}
+// short rate generator parameters for generated interest rate scenarios
+#[derive(Args)]
+struct GeneratorArgs {
+    /// Short rate model for generated scenarios
+    #[arg(long, value_enum, default_value_t = ShortRateModelArg::Vasicek)]
+    model: ShortRateModelArg,
+    /// Annual rate in the first month of every path
+    #[arg(long, default_value_t = 0.03)]
+    initial_rate: f64,
+    /// Long run annual rate paths revert to
+    #[arg(long, default_value_t = 0.04)]
+    mean_rate: f64,
+    /// Speed of mean reversion per year
+    #[arg(long, default_value_t = 0.1)]
+    reversion: f64,
+    /// Volatility per sqrt(year), absolute for vasicek and of the log rate for lognormal [default: 0.01 vasicek, 0.2 lognormal]
+    #[arg(long)]
+    volatility: Option<f64>,
+    /// Random seed, the same seed gives the same paths
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+}
 
-    return Ok(result);
+impl GeneratorArgs {
+    fn to_params(&self) -> GeneratorParams {
+        let model = match self.model {
+            ShortRateModelArg::Vasicek => ShortRateModel::Vasicek,
+            ShortRateModelArg::Lognormal => ShortRateModel::Lognormal,
+        };
+        return GeneratorParams {
+            model,
+            initial_rate: self.initial_rate,
+            mean_rate: self.mean_rate,
+            reversion: self.reversion,
+            volatility: self.volatility.unwrap_or(model.default_volatility()),
+            seed: self.seed,
+        };
+    }
 }
 
-fn get_rates(gender: &str, risk_class: &str, issue_age: i8) -> Result<HashMap<&'static str, [f64;121]>, Box<dyn Error>> {
-    let mut rates: HashMap<&'static str, [f64;121]> = HashMap::new();
-    rates.insert("premium_loads", [0.06; 121]);
-    rates.insert("policy_fees", [120.0;121]);
-    rates.insert("unit_loads", read_ia_py_csv("./data/unit_load.csv", 0.0, issue_age)?);
-    rates.insert("corr_facts", read_aa_csv("./data/corridor_factors.csv", 1.0, issue_age)?);
-    rates.insert("naar_discs", [f64::powf(1.01, -1.0/12.0);121]);
-    rates.insert("coi_rates", read_gen_rc_ia_py_csv("./data/coi.csv", 0.0, gender, risk_class, issue_age)?);
-    rates.insert("interest_rates", [f64::powf(1.03,1.0/12.0)-1.0;121]);
-    return Ok(rates);
+// pricing assumptions for the expected cash flows and profit test of a projection
+#[derive(Args)]
+struct PricingArgs {
+    /// Where to write expected cash flows per policy issued, weighting the projection by deaths and lapses
+    #[arg(long, group = "pricing")]
+    expected_file: Option<String>,
+    /// Where to write book profits by policy year from the expected cash flows
+    #[arg(long, group = "pricing")]
+    profit_file: Option<String>,
+    /// Net asset earned rates by policy year for a VM-20 deterministic reserve on the expected cash flows
+    #[arg(long, group = "pricing")]
+    naer_file: Option<String>,
+    /// Annual lapse rates by policy year for the expected cash flows; defaults to the lapse table in the data directory
+    #[arg(long, requires = "pricing")]
+    lapse_table: Option<String>,
+    /// Expected mortality per 1000 for the expected cash flows; defaults to the mortality table in ./data, then the coi rates
+    #[arg(long, requires = "pricing")]
+    mortality_table: Option<String>,
+    /// Multiple applied to the expected mortality, e.g. 0.85 for experience at 85% of the table
+    #[arg(long, default_value_t = 1.0)]
+    mortality_multiple: f64,
+    /// Json file of company expense, commission, tax, profit and mortality improvement assumptions; defaults to ./data/assumptions.json, then built in values
+    #[arg(long, requires = "pricing")]
+    assumptions: Option<String>,
+    /// State the policy is issued in, for the premium tax rate in the assumptions
+    #[arg(long, requires = "pricing")]
+    state: Option<String>,
+    /// Hold statutory reserves in the profit test instead of the account value
+    #[arg(long, requires = "pricing")]
+    statutory: bool,
+    /// Valuation mortality per 1000 for statutory reserves; defaults to the valuation_mortality table in ./data, then the coi rates
+    #[arg(long, requires = "statutory")]
+    valuation_mortality: Option<String>,
 }
 
-fn run() -> Result<(), Box<dyn Error>> {
-    use std::time::Instant;
-    let mut x = 0.0;
-    let now = Instant::now();
-    //let rates = get_rates("M", "NS", 35)?;
-    for _i in 0..1000 {
-        let rates = get_rates("M", "NS", 35)?;
-        //x = at_issue_projection(rates, 35, 100000.0, 1255.03)?;
-        x = solve_for_premium(rates.clone(), 35, 100000.0)?;
-        //println!("{}",_i)
+impl PricingArgs {
+    fn is_requested(&self) -> bool {
+        return self.expected_file.is_some() || self.profit_file.is_some() || self.naer_file.is_some();
+    }
+
+    fn assumptions(&self, data_dir: &DataDir) -> Result<Assumptions, ValactError> {
+        return load_assumptions(data_dir, self.assumptions.as_deref(), &self.state);
+    }
+
+    fn expected_cash_flows(&self, data_dir: &DataDir, rates: &RateSet, policy: &PolicyRecord, months: &[MonthResult], timestep: Timestep, assumptions: &Assumptions) -> Result<Vec<ExpectedMonth>, ValactError> {
+        let basis = pricing_basis(data_dir, self.lapse_table.as_deref(), self.mortality_table.as_deref(), self.mortality_multiple, rates, policy, assumptions)?;
+        return Ok(decrements::expected_cash_flows(months, &basis.mortality, &basis.lapses, timestep, assumptions, basis.target_premium));
+    }
+
+    // valuation mortality per 1000 for the statutory reserves, None unless --statutory
+    fn valuation_mortality(&self, data_dir: &DataDir, rates: &RateSet, policy: &PolicyRecord) -> Result<Option<Vec<f64>>, ValactError> {
+        if !self.statutory {
+            return Ok(None);
+        }
+        return Ok(Some(reserve::read_valuation_mortality(&data_dir.for_issue_date(policy.issue_date), self.valuation_mortality.as_deref(), &policy.gender, &policy.risk_class, policy.issue_age, &rates.coi_rates)?));
+    }
+}
+
+// statutory reserves held per policy issued at the end of each step on the valuation mortality, None without it
+fn statutory_reserves(mortality: Option<&[f64]>, months: &[MonthResult], expected: &[ExpectedMonth], timestep: Timestep, assumptions: &Assumptions) -> Option<Vec<f64>> {
+    let factors = reserve::reserve_factors(mortality?, &assumptions.valuation);
+    let reserves = reserve::statutory_reserves(months, &factors, timestep.steps_per_year());
+    return Some(reserve::reserves_held(expected, &reserves));
+}
+
+// inputs identifying the insured's rate cell
+#[derive(Args)]
+struct InsuredArgs {
+    /// Gender of insured, M or F expected
+    #[arg(long)]
+    gender: String,
+    /// Risk class of insured, NS or SM expected
+    #[arg(long)]
+    class: String,
+    /// Issue age of insured, or give --dob and --issue-date instead
+    #[arg(long, required_unless_present = "dob", conflicts_with = "dob")]
+    age: Option<IssueAge>,
+    /// Date of birth of insured (YYYY-MM-DD)
+    #[arg(long, requires = "issue_date")]
+    dob: Option<NaiveDate>,
+    /// Issue date of policy (YYYY-MM-DD), with --age it only dates the projection
+    #[arg(long)]
+    issue_date: Option<NaiveDate>,
+}
+
+impl InsuredArgs {
+    fn to_record(&self, basis: AgeBasis, face_amount: f64) -> Result<PolicyRecord, ValactError> {
+        let issue_age = match (self.age, self.dob, self.issue_date) {
+            (Some(age), _, _) => age,
+            (None, Some(dob), Some(issue_date)) => insurance_age(dob, issue_date, basis)?,
+            _ => return Err("need --age or both --dob and --issue-date".into()),
+        };
+        return Ok(PolicyRecord {
+            gender: self.gender.clone(),
+            risk_class: self.class.clone(),
+            issue_age,
+            face_amount,
+            death_benefit_option: DeathBenefitOption::default(),
+            inforce: None,
+            issue_date: self.issue_date,
+        });
+    }
+}
+
+// inputs identifying a single policy, shared by the single policy subcommands
+#[derive(Args)]
+struct PolicyArgs {
+    #[command(flatten)]
+    insured: InsuredArgs,
+    /// Face amount of policy
+    #[arg(long)]
+    face: f64,
+}
+
+impl PolicyArgs {
+    fn to_record(&self, basis: AgeBasis) -> Result<PolicyRecord, ValactError> {
+        return self.insured.to_record(basis, self.face);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum MethodArg {
+    Bisection,
+    Brent,
+    Secant,
+}
+
+// checkpoints of a long batch or scenario run, see checkpoint.rs
+#[derive(Args)]
+struct CheckpointArgs {
+    /// Save results as they finish so an interrupted run can be carried on with --resume, the run id is logged when it starts
+    #[arg(long)]
+    checkpoint: bool,
+    /// Carry on the checkpointed run with this id, solving only what it had not finished; the input and options have to be the ones it was started with
+    #[arg(long, conflicts_with = "checkpoint")]
+    resume: Option<String>,
+    /// Seconds between checkpoints
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    checkpoint_interval: u64,
+    /// Directory the checkpoints are kept in, one directory per run id, removed once the run finishes
+    #[arg(long, default_value = checkpoint::DEFAULT_DIR)]
+    checkpoint_dir: String,
+}
+
+impl CheckpointArgs {
+    // the run's checkpoint, None unless --checkpoint or --resume; inputs are everything the results depend on
+    fn open<T, I>(&self, command: &str, input: &str, total: usize, inputs: &I) -> Result<Option<Checkpoint<T>>, ValactError>
+    where
+        T: Clone + serde::Serialize + serde::de::DeserializeOwned,
+        I: serde::Serialize,
+    {
+        let interval = Duration::from_secs(self.checkpoint_interval);
+        return match &self.resume {
+            Some(run_id) => Ok(Some(Checkpoint::resume(&self.checkpoint_dir, run_id, command, &checkpoint::fingerprint(inputs)?, interval)?)),
+            None if self.checkpoint => Ok(Some(Checkpoint::start(&self.checkpoint_dir, command, input, total, checkpoint::fingerprint(inputs)?, interval)?)),
+            None => Ok(None),
+        };
+    }
+}
+
+// root finding options for the premium solve
+#[derive(Args)]
+struct SolverArgs {
+    /// Root finding method, defaults to bisection for targets at maturity and brent otherwise
+    #[arg(long, value_enum)]
+    method: Option<MethodArg>,
+    /// Width of the premium bracket at which the solve stops
+    #[arg(long, default_value_t = 0.005)]
+    tolerance: f64,
+    /// Cap on brent or secant steps before falling back to bisection, and on the bisection steps after
+    #[arg(long, default_value_t = DEFAULT_MAX_ITERATIONS)]
+    max_iterations: usize,
+    /// Log every evaluation the solve makes at info level, hidden by --log-level warn or error
+    #[arg(long)]
+    log_iterations: bool,
+}
+
+impl SolverArgs {
+    fn to_options(&self, target: SolveTarget) -> SolverOptions {
+        let method = match self.method {
+            Some(MethodArg::Bisection) => Method::Bisection,
+            Some(MethodArg::Brent) => Method::Brent,
+            Some(MethodArg::Secant) => Method::Secant,
+            None => target.default_method(),
+        };
+        return SolverOptions { method, tolerance: self.tolerance, max_iterations: self.max_iterations, log_iterations: self.log_iterations };
+    }
+}
+
+// only ever one of these, built once from the command line, so the size of the largest variant does not matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+enum Command {
+    /// Solve for the level annual premium that endows the policy at maturity, or reaches a target value
+    Solve {
+        #[command(flatten)]
+        policy: PolicyArgs,
+        #[command(flatten)]
+        second: SecondInsuredArgs,
+        #[command(flatten)]
+        projection: ProjectionArgs,
+        /// Account value to reach instead of just endowing, at maturity unless --target-age is given
+        #[arg(long)]
+        target_value: Option<f64>,
+        /// Attained age at which to reach --target-value
+        #[arg(long, requires = "target_value")]
+        target_age: Option<AttainedAge>,
+        #[command(flatten)]
+        solver: SolverArgs,
+        /// Include the monthly projection at the solved premium in json output
+        #[arg(long)]
+        detail: bool,
+        /// Write every rate and intermediate value of each step at the solved premium to this csv, for tying out by hand
+        #[arg(long, value_name = "FILE")]
+        explain: Option<String>,
+        /// Solve in decimal with money kept to the cent instead of floating point, for tying out to an admin system (needs the decimal feature)
+        #[arg(long, conflicts_with_all = ["target_value", "explain", "detail"])]
+        decimal: bool,
+    },
+    /// Solve for the largest face amount an annual premium endows
+    SolveFace {
+        #[command(flatten)]
+        insured: InsuredArgs,
+        #[command(flatten)]
+        projection: ProjectionArgs,
+        /// Annual premium available
+        #[arg(long)]
+        premium: f64,
+    },
+    /// Project a policy month by month
+    Project {
+        #[command(flatten)]
+        policy: PolicyArgs,
+        #[command(flatten)]
+        second: SecondInsuredArgs,
+        #[command(flatten)]
+        projection: ProjectionArgs,
+        /// Annual premium, paid at each policy anniversary or in installments given by --mode
+        #[arg(long)]
+        premium: f64,
+        /// Where to write the monthly detail csv, or a yearly illustration workbook if the file ends in .xlsx
+        #[arg(long)]
+        output_file: Option<String>,
+        /// Include the monthly projection in json output
+        #[arg(long)]
+        detail: bool,
+        /// Run the 7702A seven-pay test and flag the months the policy is a modified endowment
+        #[arg(long)]
+        mec_test: bool,
+        /// Project a no-lapse guarantee shadow account and report how long the premium keeps the guarantee
+        #[arg(long)]
+        nlg: bool,
+        /// Json file of shadow account premium_load, policy_fee, unit_load_per_1000, coi_multiple and interest; defaults to built in values
+        #[arg(long, requires = "nlg")]
+        nlg_basis: Option<String>,
+        /// Write every rate and intermediate value of each step to this csv, for tying out by hand
+        #[arg(long, value_name = "FILE")]
+        explain: Option<String>,
+        /// Also project on the guaranteed basis, maximum charges and minimum interest, alongside the current basis
+        #[arg(long)]
+        guaranteed: bool,
+        /// Json file of guaranteed coi_table, coi_multiple, minimum_interest, premium_load, policy_fee and unit_load_multiple; defaults to built in values
+        #[arg(long, requires = "guaranteed")]
+        guaranteed_basis: Option<String>,
+        /// Where to write the yearly illustration ledger with guaranteed and current values side by side
+        #[arg(long, value_name = "FILE", requires = "guaranteed")]
+        ledger_file: Option<String>,
+        /// Where to write a pdf illustration with the inputs and guaranteed, midpoint and current ledgers (needs the pdf feature)
+        #[arg(long, value_name = "FILE", requires = "guaranteed")]
+        pdf_file: Option<String>,
+        /// Project the policy in force on this date (YYYY-MM-DD) from the month in progress, taking the --inforce- values as at its monthiversary
+        #[arg(long, requires = "issue_date", conflicts_with = "inforce_duration")]
+        valuation_date: Option<NaiveDate>,
+        /// Where to write the flows and values by calendar period, from the monthiversaries on the issue date
+        #[arg(long, value_name = "FILE", requires = "issue_date")]
+        calendar_file: Option<String>,
+        /// Calendar period for --calendar-file
+        #[arg(long, value_enum, default_value_t = PeriodArg::Month, requires = "calendar_file")]
+        reporting_period: PeriodArg,
+        #[command(flatten)]
+        pricing: PricingArgs,
+        /// Project in decimal with money kept to the cent instead of floating point, for tying out to an admin system (needs the decimal feature)
+        #[arg(long, conflicts_with_all = ["detail", "mec_test", "nlg", "explain", "guaranteed"])]
+        decimal: bool,
+    },
+    /// Project a policy and solve its premium on each path of a set of interest rate scenarios
+    Scenarios {
+        #[command(flatten)]
+        policy: PolicyArgs,
+        #[command(flatten)]
+        projection: ProjectionArgs,
+        /// Annual premium the end values are projected at
+        #[arg(long)]
+        premium: f64,
+        /// Csv with a Month or Policy_Year column and one column of annual credited rates per scenario
+        #[arg(long, required_unless_present = "generate")]
+        scenario_file: Option<String>,
+        /// Layout of --scenario-file
+        #[arg(long, value_enum, default_value_t = ScenarioFormatArg::Columns, requires = "scenario_file")]
+        scenario_format: ScenarioFormatArg,
+        /// Generate this many scenarios from the short rate model instead of reading a file
+        #[arg(long, conflicts_with = "scenario_file")]
+        generate: Option<usize>,
+        #[command(flatten)]
+        generator: GeneratorArgs,
+        /// Where to write the end value and solved premium per scenario
+        #[arg(long)]
+        output_file: Option<String>,
+        /// Where the paths are projected and solved
+        #[arg(long, value_enum, default_value_t = Backend::Cpu)]
+        backend: Backend,
+        #[command(flatten)]
+        checkpoints: CheckpointArgs,
+    },
+    /// Generate monthly interest rate paths from the short rate model and write them in the scenario file layout
+    GenerateScenarios {
+        /// Number of paths
+        #[arg(long)]
+        count: usize,
+        /// Length of each path in years
+        #[arg(long)]
+        years: usize,
+        #[command(flatten)]
+        generator: GeneratorArgs,
+        #[arg(long)]
+        output_file: String,
+    },
+    /// Solve the premium and profit test again under coi, interest, expense and lapse shocks
+    Sensitivity {
+        #[command(flatten)]
+        policy: PolicyArgs,
+        #[command(flatten)]
+        projection: ProjectionArgs,
+        /// Csv of shocks with Name, COI_Multiple, Interest_Shift, Expense_Multiple and Lapse_Multiple columns; defaults to coi and expenses +-10%, interest +-50bp and lapses +-20%
+        #[arg(long)]
+        shocks: Option<String>,
+        /// Annual lapse rates by policy year; defaults to the lapse table in the data directory
+        #[arg(long)]
+        lapse_table: Option<String>,
+        /// Expected mortality per 1000; defaults to the mortality table in ./data, then the coi rates
+        #[arg(long)]
+        mortality_table: Option<String>,
+        /// Multiple applied to the expected mortality
+        #[arg(long, default_value_t = 1.0)]
+        mortality_multiple: f64,
+        /// Json file of company assumptions; defaults to ./data/assumptions.json, then built in values
+        #[arg(long)]
+        assumptions: Option<String>,
+        /// State the policy is issued in, for the premium tax rate
+        #[arg(long)]
+        state: Option<String>,
+        /// Where to write the table of results and changes from the base
+        #[arg(long)]
+        output_file: Option<String>,
+    },
+    /// Run the illustration actuary's midpoint scale, self-support and lapse-support tests on each cell of a census
+    IllustrationTest {
+        /// Census csv of the cells to test, laid out as for batch
+        #[arg(long)]
+        input: String,
+        #[command(flatten)]
+        projection: ProjectionArgs,
+        /// Illustrated annual premium for every cell; defaults to each cell's solved premium on the current basis
+        #[arg(long)]
+        premium: Option<f64>,
+        /// Json file of guaranteed coi_table, coi_multiple, minimum_interest, premium_load, policy_fee and unit_load_multiple; defaults to built in values
+        #[arg(long)]
+        guaranteed_basis: Option<String>,
+        /// Annual lapse rates by policy year; defaults to the lapse table in the data directory
+        #[arg(long)]
+        lapse_table: Option<String>,
+        /// Expected mortality per 1000; defaults to the mortality table in ./data, then the coi rates
+        #[arg(long)]
+        mortality_table: Option<String>,
+        /// Multiple applied to the expected mortality
+        #[arg(long, default_value_t = 1.0)]
+        mortality_multiple: f64,
+        /// Json file of company assumptions; defaults to ./data/assumptions.json, then built in values
+        #[arg(long)]
+        assumptions: Option<String>,
+        /// State the policies are issued in, for the premium tax rate
+        #[arg(long)]
+        state: Option<String>,
+        /// Where to write the test results per cell
+        #[arg(long)]
+        output_file: Option<String>,
+    },
+    /// Solve the level premium of a term policy to a target profit margin or irr
+    Term {
+        #[command(flatten)]
+        policy: PolicyArgs,
+        /// Years the premium is level and the cover lasts
+        #[arg(long, default_value_t = TermProduct::default().level_years)]
+        level_years: i32,
+        /// Policy fee per year on top of the rate per 1000
+        #[arg(long, default_value_t = TermProduct::default().policy_fee)]
+        policy_fee: f64,
+        /// Length of each step of the projection
+        #[arg(long, value_enum, default_value_t = TimestepArg::Monthly)]
+        timestep: TimestepArg,
+        /// Profit margin to solve to, PV of profits over PV of premiums at the hurdle rate
+        #[arg(long, conflicts_with = "target_irr")]
+        target_margin: Option<f64>,
+        /// IRR to solve to; defaults to the hurdle rate in the assumptions when no target is given
+        #[arg(long)]
+        target_irr: Option<f64>,
+        /// Annual lapse rates by policy year; defaults to the lapse table in the data directory
+        #[arg(long)]
+        lapse_table: Option<String>,
+        /// Expected mortality per 1000; defaults to the mortality table in ./data, then the coi rates
+        #[arg(long)]
+        mortality_table: Option<String>,
+        /// Multiple applied to the expected mortality
+        #[arg(long, default_value_t = 1.0)]
+        mortality_multiple: f64,
+        /// Json file of company assumptions; defaults to ./data/assumptions.json, then built in values
+        #[arg(long)]
+        assumptions: Option<String>,
+        /// State the policy is issued in, for the premium tax rate
+        #[arg(long)]
+        state: Option<String>,
+        /// Where to write book profits by policy year at the solved premium
+        #[arg(long)]
+        profit_file: Option<String>,
+    },
+    /// Solve the fixed premium of a whole life policy to a target profit margin or irr, with its cash values
+    WholeLife {
+        #[command(flatten)]
+        policy: PolicyArgs,
+        /// Years premiums are paid for; defaults to paying to maturity
+        #[arg(long)]
+        premium_years: Option<i32>,
+        /// Policy fee per year on top of the rate per 1000
+        #[arg(long, default_value_t = WholeLifeProduct::default().policy_fee)]
+        policy_fee: f64,
+        /// Annual interest rate the net premium cash values are calculated at
+        #[arg(long, default_value_t = WholeLifeProduct::default().cash_value_interest)]
+        cash_value_interest: f64,
+        /// Mortality per 1000 for cash values and reserves; defaults to the valuation_mortality table in ./data, then the coi rates
+        #[arg(long)]
+        valuation_mortality: Option<String>,
+        /// Length of each step of the projection
+        #[arg(long, value_enum, default_value_t = TimestepArg::Monthly)]
+        timestep: TimestepArg,
+        /// Profit margin to solve to, PV of profits over PV of premiums at the hurdle rate
+        #[arg(long, conflicts_with = "target_irr")]
+        target_margin: Option<f64>,
+        /// IRR to solve to; defaults to the hurdle rate in the assumptions when no target is given
+        #[arg(long)]
+        target_irr: Option<f64>,
+        /// Annual lapse rates by policy year; defaults to the lapse table in the data directory
+        #[arg(long)]
+        lapse_table: Option<String>,
+        /// Expected mortality per 1000; defaults to the mortality table in ./data, then the coi rates
+        #[arg(long)]
+        mortality_table: Option<String>,
+        /// Multiple applied to the expected mortality
+        #[arg(long, default_value_t = 1.0)]
+        mortality_multiple: f64,
+        /// Json file of company assumptions; defaults to ./data/assumptions.json, then built in values
+        #[arg(long)]
+        assumptions: Option<String>,
+        /// State the policy is issued in, for the premium tax rate
+        #[arg(long)]
+        state: Option<String>,
+        /// Where to write book profits by policy year at the solved premium
+        #[arg(long)]
+        profit_file: Option<String>,
+        /// Where to write the premium, cash value and death benefit by policy year
+        #[arg(long)]
+        output_file: Option<String>,
+    },
+    /// Project a deferred fixed annuity to its annuitization age
+    Annuity {
+        /// Issue age of the annuitant
+        #[arg(long)]
+        age: IssueAge,
+        /// Premium at the start of contract years as YEARS:AMOUNT, e.g. 1:100000 or 1-10:5000, can be repeated
+        #[arg(long = "premium", required = true)]
+        premiums: Vec<ScheduledAmount>,
+        /// Partial withdrawal as YEARS:AMOUNT, e.g. 10:5000 or 8-15:5% of the value, can be repeated
+        #[arg(long = "withdrawal")]
+        withdrawals: Vec<Withdrawal>,
+        /// Surrender charge rates by contract year, e.g. 0.07,0.06,0.05; defaults to 7% grading down to nil after year 7
+        #[arg(long, value_delimiter = ',', conflicts_with = "surrender_charge_table")]
+        surrender_charges: Option<Vec<f64>>,
+        /// Csv of surrender charge rates by Policy_Year
+        #[arg(long)]
+        surrender_charge_table: Option<String>,
+        /// Share of the value at the start of each contract year that can be withdrawn free of surrender charge
+        #[arg(long, default_value_t = AnnuityProduct::default().free_withdrawal_percent)]
+        free_withdrawal: f64,
+        /// Declared annual rate credited in every year
+        #[arg(long, default_value_t = 0.03, conflicts_with = "credited_rates")]
+        credited_rate: f64,
+        /// Csv of declared annual rates by Policy_Year, later years staying at the last rate
+        #[arg(long)]
+        credited_rates: Option<String>,
+        /// Minimum guaranteed annual rate
+        #[arg(long, default_value_t = AnnuityProduct::default().minimum_rate)]
+        minimum_rate: f64,
+        /// Attained age the value is projected to and annuitized at
+        #[arg(long, default_value_t = AnnuityProduct::default().annuitization_age)]
+        annuitization_age: i32,
+        /// Length of each step of the projection
+        #[arg(long, value_enum, default_value_t = TimestepArg::Monthly)]
+        timestep: TimestepArg,
+        /// Where to write the projection
+        #[arg(long)]
+        output_file: Option<String>,
+        /// Include the projection in json output
+        #[arg(long)]
+        detail: bool,
+    },
+    /// Payout annuity factor and the income an amount buys, single or joint life or certain only
+    Payout {
+        #[command(flatten)]
+        annuitant: InsuredArgs,
+        /// Amount applied to buy the income
+        #[arg(long)]
+        amount: Option<f64>,
+        /// Annual interest rate the factor is calculated at
+        #[arg(long, default_value_t = PayoutOptions::default().interest)]
+        interest: f64,
+        /// How often the income is paid
+        #[arg(long, value_enum, default_value_t = ModeArg::Monthly)]
+        frequency: ModeArg,
+        /// Pay the first installment at once rather than one installment later
+        #[arg(long)]
+        advance: bool,
+        /// Years the income is paid for whether or not the annuitant is alive
+        #[arg(long, default_value_t = 0)]
+        certain_years: i32,
+        /// Pay for the certain years only, with no life contingency
+        #[arg(long, requires = "certain_years", conflicts_with = "joint_gender")]
+        certain_only: bool,
+        /// Annuitant mortality per 1000; defaults to the payout_mortality table in ./data, then the coi rates
+        #[arg(long)]
+        mortality_table: Option<String>,
+        /// Gender of the joint annuitant
+        #[arg(long, requires_all = ["joint_class", "joint_age"])]
+        joint_gender: Option<String>,
+        /// Risk class of the joint annuitant
+        #[arg(long, requires = "joint_gender")]
+        joint_class: Option<String>,
+        /// Age of the joint annuitant
+        #[arg(long, requires = "joint_gender")]
+        joint_age: Option<IssueAge>,
+        /// Share of the income paid on after the first death, 1.0 for a last survivor annuity
+        #[arg(long, default_value_t = 1.0, requires = "joint_gender")]
+        survivor_fraction: f64,
+    },
+    /// Solve the premium for every policy in a census csv
+    Batch {
+        /// Census csv with Gender, Risk_Class, Issue_Age (or Date_Of_Birth and Issue_Date), Face_Amount columns, optionally Death_Benefit_Option (1, 2 or 3)
+        #[arg(long)]
+        input: String,
+        /// Where to write the results, csv unless the file ends in .parquet or .arrow (arrow ipc, for polars or pyarrow)
+        #[arg(long)]
+        output_file: String,
+        /// Cap on worker threads, defaults to one per core
+        #[arg(long)]
+        threads: Option<usize>,
+        #[command(flatten)]
+        solver: SolverArgs,
+        /// Print progress with counts, throughput and time left to stderr while the batch runs
+        #[arg(long)]
+        progress: bool,
+        /// Seconds between progress lines
+        #[arg(long, default_value_t = 1.0, requires = "progress")]
+        progress_interval: f64,
+        /// Read the input as a seriatim inforce extract at this date (yyyy-mm-dd) and solve the in force policies from it, see inforce.rs for the layout
+        #[arg(long)]
+        valuation_date: Option<NaiveDate>,
+        /// Plan code the extract's policies must have, repeat for more than one
+        #[arg(long, requires = "valuation_date")]
+        plan_code: Vec<String>,
+        /// Carry on past policies whose solve fails, leaving them out of the results and counting them in the summary
+        #[arg(long)]
+        keep_going: bool,
+        /// Write each policy's result as soon as it is solved, in the order they finish rather than census order, instead of holding every result until the batch is done
+        #[arg(long, conflicts_with_all = ["valuation_date", "summary_file", "model_points", "checkpoint", "resume"])]
+        stream: bool,
+        /// Where to write totals and premium by issue age band and risk class, json unless the file ends in .csv
+        #[arg(long)]
+        summary_file: Option<String>,
+        /// Years of issue age in each band of the summary
+        #[arg(long, default_value_t = 10, requires = "summary_file")]
+        age_band_width: u8,
+        /// Solve policies of the same gender, class, issue age, option and face band once as a weighted model point
+        #[arg(long)]
+        model_points: bool,
+        /// Smallest face amount of each model point band, comma separated, without them only equal face amounts are grouped; --face-bands bands are always split on
+        #[arg(long, value_delimiter = ',', requires = "model_points")]
+        model_point_bands: Vec<f64>,
+        /// Where the premiums are solved, policies the gpu kernel does not cover (inforce, schedules, riders, rounded amounts) are solved on the cpu either way
+        #[arg(long, value_enum, default_value_t = Backend::Cpu)]
+        backend: Backend,
+        #[command(flatten)]
+        checkpoints: CheckpointArgs,
+    },
+    /// Solve the premium of every gender, risk class and issue age at each of a list of face amounts and write them out as a rate book csv
+    Ratebook {
+        /// Where to write the rate book, a row per gender, class and issue age with a premium and premium per 1000 column per face amount
+        #[arg(long)]
+        output_file: String,
+        /// Genders in the book, comma separated
+        #[arg(long, value_delimiter = ',', default_value = "M,F")]
+        genders: Vec<String>,
+        /// Risk classes in the book, comma separated
+        #[arg(long, value_delimiter = ',', default_value = "NS,SM")]
+        classes: Vec<String>,
+        /// Youngest issue age in the book
+        #[arg(long, default_value_t = ratebook::DEFAULT_MIN_ISSUE_AGE)]
+        min_age: u8,
+        /// Oldest issue age in the book
+        #[arg(long, default_value_t = ratebook::DEFAULT_MAX_ISSUE_AGE)]
+        max_age: u8,
+        /// Face amounts in the book, comma separated
+        #[arg(long, value_delimiter = ',', default_value = "100000,250000,500000,1000000")]
+        faces: Vec<f64>,
+        /// Death benefit option of every cell
+        #[arg(long, value_enum, default_value_t = DbOptionArg::Level)]
+        db_option: DbOptionArg,
+        /// Cap on worker threads, defaults to one per core
+        #[arg(long)]
+        threads: Option<usize>,
+        #[command(flatten)]
+        solver: SolverArgs,
+    },
+    /// Check the rate tables in a data directory for missing issue ages, gaps in policy years, duplicate keys, negative rates and rows past maturity
+    Validate {
+        /// Directory to check, defaults to --data-dir or ./data
+        dir: Option<String>,
+    },
+    /// Load the rate tables in the data directory into the sqlite database given by --rates-db
+    ImportRates,
+    /// Serve /solve and /project over http, taking policies as json, with the rates loaded at startup and the live tables' checksums at /admin/tables (needs the server feature)
+    Serve {
+        /// Address and port to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        address: String,
+        /// Serve the grpc service in proto/valact.proto instead of the http api (needs the grpc feature)
+        #[arg(long)]
+        grpc: bool,
+        /// Watch the data directory and swap in the rate tables when they change, without a restart
+        #[arg(long)]
+        watch: bool,
+        /// Seconds between checks of the data directory with --watch, a change is loaded once it has stayed the same for one of these
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+        watch_interval: u64,
+    },
+}
+
+impl Command {
+    // issue date of the policy a single policy command is run for
+    fn issue_date(&self) -> Option<NaiveDate> {
+        return match self {
+            Command::Solve { policy, .. } | Command::Project { policy, .. } | Command::Scenarios { policy, .. }
+            | Command::Sensitivity { policy, .. } | Command::Term { policy, .. } | Command::WholeLife { policy, .. } => policy.insured.issue_date,
+            Command::SolveFace { insured, .. } => insured.issue_date,
+            _ => None,
+        };
+    }
+}
+
+// the command line with the options from a --config run file appended, those given on the command line are left out
+fn command_line() -> Result<Vec<String>, ValactError> {
+    let args: Vec<String> = std::env::args().collect();
+    let Ok(matches) = Cli::command().ignore_errors(true).try_get_matches_from(&args) else {
+        return Ok(args);
+    };
+    let Some(path) = matches.get_one::<String>("config") else {
+        return Ok(args);
+    };
+    let config = RunConfig::from_file(path)?;
+    let extra = config.args(matches.subcommand_name(), &args)?;
+    return Ok(args.into_iter().chain(extra).collect());
+}
+
+fn run() -> Result<(), ValactError> {
+    let cli = Cli::parse_from(command_line()?);
+    init_logging(cli.log_level, cli.log_format);
+    let source = cli.rate_source()?;
+    match cli.command {
+        Command::Solve { policy, second, projection, target_value, target_age, solver, detail, explain, decimal } => {
+            let config = projection.to_config(&source.data_dir, source.maturity_age)?;
+            let policy = PolicyRecord { death_benefit_option: config.death_benefit_option, ..policy.to_record(cli.age_basis.basis())? };
+            let target = match (target_value, target_age) {
+                (Some(amount), Some(age)) => SolveTarget::CashValueAtAge { age, amount },
+                (Some(amount), None) => SolveTarget::ValueAtMaturity { amount },
+                _ => SolveTarget::EndowAtMaturity,
+            };
+            let rates = second.adjust(&source, source.load_policy(&policy)?)?;
+            if decimal {
+                println!("Premium: {}", solve_decimal(&rates, policy.issue_age, policy.face_amount, &config)?);
+                return Ok(());
+            }
+            let solve = solve_for_premium_using(&rates, policy.issue_age, policy.face_amount, &config, target, &solver.to_options(target))?;
+            let premium = solve.premium;
+            check_guideline(projection.guideline_test(), &rates, policy.issue_age, policy.face_amount, premium, &config)?;
+            if let Some(path) = &explain {
+                projection::write_explain_csv(path, &projection::at_issue_projection_explain(&rates, policy.issue_age, policy.face_amount, premium, &config)?)?;
+            }
+            let modal_premium = match config.premium_mode {
+                PremiumMode::Annual => None,
+                mode => Some(report::ModalPremium { mode, premium: solve_for_modal_premium(&rates, policy.issue_age, policy.face_amount, &config)? }),
+            };
+            match cli.output {
+                OutputFormat::Text => {
+                    println!("Premium: {:.2}", premium);
+                    if !solve.converged {
+                        println!("Solve did not converge after {} iterations, premium bracket {:.4} to {:.4}", solve.iterations, solve.bracket.0, solve.bracket.1);
+                    }
+                    if let Some(modal) = &modal_premium {
+                        println!("Modal premium ({:?}): {:.2}", modal.mode, modal.premium);
+                    }
+                }
+                OutputFormat::Json => {
+                    let months = if detail {Some(at_issue_projection_detail_with(&rates, policy.issue_age, policy.face_amount, premium, &config)?)} else {None};
+                    let solved = report::SolveReport { inputs: policy, target, premium, solve, modal_premium, projection: months };
+                    println!("{}", report::to_json(&solved)?);
+                }
+            }
+        }
+        Command::SolveFace { insured, projection, premium } => {
+            // the band would change with the face amount being solved for
+            if source.face_bands.is_some() {
+                return Err("--face-bands is not supported by solve-face".into());
+            }
+            let policy = insured.to_record(cli.age_basis.basis(), 0.0)?;
+            let config = projection.to_config(&source.data_dir, source.maturity_age)?;
+            let rates = source.load(&policy.gender, &policy.risk_class, policy.issue_age)?;
+            let face_amount = solve_for_face_with(&rates, policy.issue_age, premium, &config)?;
+            check_guideline(projection.guideline_test(), &rates, policy.issue_age, face_amount, premium, &config)?;
+            match cli.output {
+                OutputFormat::Text => println!("Face amount: {:.0}", face_amount),
+                OutputFormat::Json => {
+                    let solved = report::FaceSolveReport {
+                        gender: policy.gender,
+                        risk_class: policy.risk_class,
+                        issue_age: policy.issue_age,
+                        annual_premium: premium,
+                        face_amount,
+                    };
+                    println!("{}", report::to_json(&solved)?);
+                }
+            }
+        }
+        Command::Project { policy, second, projection, premium, output_file, detail, mec_test, nlg, nlg_basis, explain, guaranteed, guaranteed_basis, ledger_file, pdf_file, valuation_date, calendar_file, reporting_period, pricing, decimal } => {
+            let issue_date = policy.insured.issue_date;
+            let inforce_duration = match (valuation_date, issue_date) {
+                (Some(valuation_date), Some(issue_date)) => Some(calendar::duration_at(issue_date, valuation_date)?),
+                _ => projection.inforce_duration,
+            };
+            let config = projection.to_config_from(&source.data_dir, source.maturity_age, inforce_duration)?;
+            let policy = PolicyRecord { death_benefit_option: config.death_benefit_option, ..policy.to_record(cli.age_basis.basis())? };
+            let rates = second.adjust(&source, source.load_policy(&policy)?)?;
+            check_guideline(projection.guideline_test(), &rates, policy.issue_age, policy.face_amount, premium, &config)?;
+            if decimal {
+                println!("End value: {}", project_decimal(&rates, policy.issue_age, policy.face_amount, premium, &config, output_file.as_deref())?);
+                if let Some(path) = &output_file {
+                    println!("Projection written to {}", path);
+                }
+                return Ok(());
+            }
+            let mut months = at_issue_projection_detail_with(&rates, policy.issue_age, policy.face_amount, premium, &config)?;
+            let seven_pay = if mec_test {Some(tax7702::seven_pay_test(&rates, policy.issue_age, policy.face_amount, &config, &months)?)} else {None};
+            if let Some(test) = &seven_pay {
+                tax7702::mark_mec(&mut months, test);
+            }
+            let no_lapse_guarantee = if nlg {
+                let basis = match &nlg_basis {
+                    Some(path) => ShadowBasis::from_json_file(path)?,
+                    None => ShadowBasis::default(),
+                };
+                let shadow = nlg::shadow_account(&rates, policy.issue_age, policy.face_amount, premium, &config, &basis)?;
+                nlg::mark_shadow_account(&mut months, &shadow);
+                Some(nlg::no_lapse_guarantee(&months, &shadow))
+            } else {
+                None
+            };
+            let guaranteed = if guaranteed {
+                let basis = match &guaranteed_basis {
+                    Some(path) => GuaranteedBasis::from_json_file(path)?,
+                    None => GuaranteedBasis::default(),
+                };
+                let guaranteed_rates = guaranteed::guaranteed_rates(&source.data_dir.for_issue_date(policy.issue_date), &rates, &policy.gender, &policy.risk_class, policy.issue_age, &basis)?;
+                let guaranteed_months = guaranteed::guaranteed_projection(&guaranteed_rates, policy.issue_age, policy.face_amount, premium, &config)?;
+                if let Some(path) = &ledger_file {
+                    guaranteed::write_ledger_csv(path, &guaranteed::illustration_ledger(&months, &guaranteed_months, policy.issue_age))?;
+                }
+                if let Some(path) = &pdf_file {
+                    let midpoint_months = guaranteed::guaranteed_projection(&illustration::midpoint_rates(&rates, &guaranteed_rates), policy.issue_age, policy.face_amount, premium, &config)?;
+                    write_illustration_pdf(path, &policy, premium, &guaranteed_months, &midpoint_months, &months)?;
+                }
+                Some(guaranteed::guaranteed_summary(&months, &guaranteed_months))
+            } else {
+                None
+            };
+            if let Some(path) = &output_file {
+                match issue_date {
+                    Some(issue_date) if !path.ends_with(".xlsx") => calendar::write_dated_projection_csv(path, &months, issue_date)?,
+                    _ => write_projection(path, &policy, premium, &months)?,
+                }
+            }
+            if let (Some(path), Some(issue_date)) = (&calendar_file, issue_date) {
+                calendar::write_calendar_csv(path, &calendar::calendar_summary(&months, issue_date, reporting_period.period())?)?;
+            }
+            if let Some(path) = &explain {
+                projection::write_explain_csv(path, &projection::at_issue_projection_explain(&rates, policy.issue_age, policy.face_amount, premium, &config)?)?;
+            }
+            let mut deterministic_reserve = None;
+            let profit = if pricing.is_requested() {
+                let assumptions = pricing.assumptions(&source.data_dir)?;
+                let expected = pricing.expected_cash_flows(&source.data_dir, &rates, &policy, &months, config.timestep, &assumptions)?;
+                if let Some(path) = &pricing.expected_file {
+                    decrements::write_expected_csv(path, &expected)?;
+                }
+                if let Some(path) = &pricing.naer_file {
+                    let naer = vm20::read_naer_path(path, rates.years())?;
+                    deterministic_reserve = Some(vm20::deterministic_reserve(&expected, &naer, config.timestep));
+                }
+                let valuation_mortality = pricing.valuation_mortality(&source.data_dir, &rates, &policy)?;
+                let reserves = statutory_reserves(valuation_mortality.as_deref(), &months, &expected, config.timestep, &assumptions);
+                let years = profit::profit_by_year(&expected, reserves.as_deref(), config.timestep, &assumptions.profit);
+                if let Some(path) = &pricing.profit_file {
+                    profit::write_profit_csv(path, &years)?;
+                }
+                Some(profit::profit_summary(&years, assumptions.profit.hurdle_rate))
+            } else {
+                None
+            };
+            let end_value = months.last().map_or(0.0, |m| m.value_end);
+            match cli.output {
+                OutputFormat::Text => {
+                    println!("End value: {:.2}", end_value);
+                    if let Some(test) = &seven_pay {
+                        match test.mec_year {
+                            Some(year) => println!("Modified endowment from policy year {} (seven-pay premium {:.2})", year, test.seven_pay_premium),
+                            None => println!("Not a modified endowment (seven-pay premium {:.2})", test.seven_pay_premium),
+                        }
+                    }
+                    if let Some(guarantee) = &no_lapse_guarantee {
+                        match guarantee.to_maturity {
+                            true => println!("No-lapse guarantee to maturity"),
+                            false => println!("No-lapse guarantee for {} policy years (to age {})", guarantee.guarantee_years, i32::from(policy.issue_age.get()) + guarantee.guarantee_years),
+                        }
+                        if let Some(year) = guarantee.lapse_year {
+                            println!("Lapses in policy year {}", year);
+                        } else if let Some(year) = guarantee.negative_value_year {
+                            println!("Account value negative from policy year {}, kept in force by the guarantee", year);
+                        }
+                    }
+                    if let Some(summary) = &guaranteed {
+                        println!("Guaranteed end value: {:.2}", summary.end_value);
+                        match summary.lapse_year {
+                            Some(year) => println!("Lapses on the guaranteed basis in policy year {} (age {})", year, i32::from(policy.issue_age.get()) + year - 1),
+                            None => println!("In force to maturity on the guaranteed basis"),
+                        }
+                    }
+                    if let Some(path) = &output_file {
+                        println!("Projection written to {}", path);
+                    }
+                    if let Some(path) = &ledger_file {
+                        println!("Illustration ledger written to {}", path);
+                    }
+                    if let Some(summary) = &profit {
+                        let irr = summary.irr.map_or("none".to_string(), |irr| format!("{:.2}%", irr * 100.0));
+                        println!(
+                            "PV of profits at {:.1}%: {:.2}, profit margin: {:.2}%, IRR: {}",
+                            summary.hurdle_rate * 100.0, summary.pv_profits, summary.profit_margin * 100.0, irr
+                        );
+                    }
+                    if let Some(dr) = &deterministic_reserve {
+                        println!(
+                            "Deterministic reserve: {:.2} (PV benefits {:.2}, PV expenses {:.2}, PV premiums {:.2})",
+                            dr.reserve, dr.pv_benefits, dr.pv_expenses, dr.pv_premiums
+                        );
+                    }
+                    if let Some(path) = &pricing.expected_file {
+                        println!("Expected cash flows written to {}", path);
+                    }
+                    if let Some(path) = &pricing.profit_file {
+                        println!("Profits by year written to {}", path);
+                    }
+                }
+                OutputFormat::Json => {
+                    let projected = report::ProjectionReport {
+                        inputs: policy,
+                        annual_premium: premium,
+                        end_value,
+                        seven_pay,
+                        no_lapse_guarantee,
+                        guaranteed,
+                        profit,
+                        deterministic_reserve,
+                        projection: if detail {Some(months)} else {None},
+                    };
+                    println!("{}", report::to_json(&projected)?);
+                }
+            }
+        }
+        Command::Scenarios { policy, projection, premium, scenario_file, scenario_format, generate, generator, output_file, backend, checkpoints } => {
+            let config = projection.to_config(&source.data_dir, source.maturity_age)?;
+            let policy = PolicyRecord { death_benefit_option: config.death_benefit_option, ..policy.to_record(cli.age_basis.basis())? };
+            let rates = source.load_policy(&policy)?;
+            let set = match (&scenario_file, generate) {
+                (Some(path), _) if scenario_format == ScenarioFormatArg::Airg => scenarios::read_airg_scenarios(path)?,
+                (Some(path), _) => scenarios::read_scenarios_csv(path)?,
+                (None, Some(count)) => esg::generate_scenarios(&generator.to_params(), count, rates.years() * 12)?,
+                (None, None) => return Err("scenarios needs --scenario-file or --generate".into()),
+            };
+            let input = scenario_file.clone().unwrap_or_else(|| format!("{} generated scenarios", set.paths.len()));
+            let checkpoint = checkpoints.open::<scenarios::ScenarioResult, _>("scenarios", &input, set.paths.len(), &(&policy, premium, &config, &set.names, &set.paths))?;
+            // a resumed run only runs the paths its checkpoint has not got
+            let positions: Vec<usize> = match &checkpoint {
+                Some(checkpoint) => checkpoint.remaining(),
+                None => (0..set.paths.len()).collect(),
+            };
+            let remaining: Cow<scenarios::ScenarioSet> = match positions.len() == set.paths.len() {
+                true => Cow::Borrowed(&set),
+                false => Cow::Owned(scenarios::ScenarioSet {
+                    names: positions.iter().map(|&i| set.names[i].clone()).collect(),
+                    paths: positions.iter().map(|&i| set.paths[i].clone()).collect(),
+                }),
+            };
+            let record = |index: usize, result: &scenarios::ScenarioResult| {
+                if let Some(checkpoint) = &checkpoint {
+                    checkpoint.record(positions[index], result);
+                }
+            };
+            let on_gpu = match backend {
+                Backend::Gpu => gpu_scenarios(&rates, policy.issue_age, policy.face_amount, premium, &config, &remaining)?,
+                Backend::Cpu => None,
+            };
+            let solved = match on_gpu {
+                Some(results) => {
+                    for (index, result) in results.iter().enumerate() {
+                        record(index, result);
+                    }
+                    Ok(results)
+                }
+                None => scenarios::run_scenarios_recording(&rates, policy.issue_age, policy.face_amount, premium, &config, &remaining, record),
+            };
+            // saved before a failure is returned so a resume carries on from the paths that did run
+            if let Some(checkpoint) = &checkpoint {
+                checkpoint.save()?;
+            }
+            let mut results: Vec<Option<scenarios::ScenarioResult>> = match &checkpoint {
+                Some(checkpoint) => checkpoint.results(),
+                None => vec![None; set.paths.len()],
+            };
+            for (index, result) in solved?.into_iter().enumerate() {
+                results[positions[index]] = Some(result);
+            }
+            let results: Vec<scenarios::ScenarioResult> = results.into_iter().flatten().collect();
+            if let Some(path) = &output_file {
+                scenarios::write_scenarios_csv(path, &results)?;
+            }
+            if let Some(checkpoint) = checkpoint {
+                checkpoint.finish()?;
+            }
+            match cli.output {
+                OutputFormat::Text => {
+                    match &output_file {
+                        Some(path) => println!("Ran {} scenarios, results written to {}", results.len(), path),
+                        None => {
+                            for result in &results {
+                                println!("{}: end value {:.2}, premium {:.2}", result.name, result.end_value, result.premium);
+                            }
+                        }
+                    }
+                }
+                OutputFormat::Json => {
+                    let run = report::ScenarioReport { inputs: policy, annual_premium: premium, scenario_file, generator: generate.map(|_| generator.to_params()), scenarios: results };
+                    println!("{}", report::to_json(&run)?);
+                }
+            }
+        }
+        Command::GenerateScenarios { count, years, generator, output_file } => {
+            let set = esg::generate_scenarios(&generator.to_params(), count, years * 12)?;
+            scenarios::write_scenario_set_csv(&output_file, &set)?;
+            println!("Generated {} scenarios of {} years, written to {}", count, years, output_file);
+        }
+        Command::Sensitivity { policy, projection, shocks, lapse_table, mortality_table, mortality_multiple, assumptions, state, output_file } => {
+            let config = projection.to_config(&source.data_dir, source.maturity_age)?;
+            let policy = PolicyRecord { death_benefit_option: config.death_benefit_option, ..policy.to_record(cli.age_basis.basis())? };
+            let rates = source.load_policy(&policy)?;
+            let assumptions = load_assumptions(&source.data_dir, assumptions.as_deref(), &state)?;
+            let basis = pricing_basis(&source.data_dir, lapse_table.as_deref(), mortality_table.as_deref(), mortality_multiple, &rates, &policy, &assumptions)?;
+            let shocks = match &shocks {
+                Some(path) => sensitivity::read_shocks_csv(path)?,
+                None => sensitivity::standard_shocks(),
+            };
+            let results = sensitivity::run_sensitivities(&rates, policy.issue_age, policy.face_amount, &config, &basis, &assumptions, &shocks)?;
+            if let Some(path) = &output_file {
+                sensitivity::write_sensitivities_csv(path, &results)?;
+            }
+            match cli.output {
+                OutputFormat::Text => {
+                    for result in &results {
+                        println!(
+                            "{:<16} premium {:>10.2} ({:+.2}), PV of profits {:>10.2} ({:+.2}), margin {:>6.2}% ({:+.2}%)",
+                            result.name, result.premium, result.premium_change, result.pv_profits, result.pv_profits_change,
+                            result.profit_margin * 100.0, result.profit_margin_change * 100.0
+                        );
+                    }
+                    if let Some(path) = &output_file {
+                        println!("Sensitivities written to {}", path);
+                    }
+                }
+                OutputFormat::Json => {
+                    let run = report::SensitivityReport { inputs: policy, sensitivities: results };
+                    println!("{}", report::to_json(&run)?);
+                }
+            }
+        }
+        Command::IllustrationTest { input, projection, premium, guaranteed_basis, lapse_table, mortality_table, mortality_multiple, assumptions, state, output_file } => {
+            let policies = batch::read_policies_with_basis(&input, cli.age_basis.basis())?;
+            let assumptions = load_assumptions(&source.data_dir, assumptions.as_deref(), &state)?;
+            let basis = match &guaranteed_basis {
+                Some(path) => GuaranteedBasis::from_json_file(path)?,
+                None => GuaranteedBasis::default(),
+            };
+            let mut cells = Vec::with_capacity(policies.len());
+            for policy in &policies {
+                // riders from the tables in effect on the policy's issue date
+                let data_dir = source.data_dir.for_issue_date(policy.issue_date);
+                let config = ProjectionConfig { death_benefit_option: policy.death_benefit_option, ..projection.to_config(&data_dir, source.maturity_age)? };
+                let rates = source.load_policy(policy)?;
+                let guaranteed_rates = guaranteed::guaranteed_rates(&data_dir, &rates, &policy.gender, &policy.risk_class, policy.issue_age, &basis)?;
+                let pricing = pricing_basis(&source.data_dir, lapse_table.as_deref(), mortality_table.as_deref(), mortality_multiple, &rates, policy, &assumptions)?;
+                let premium = match premium {
+                    Some(premium) => premium,
+                    None => projection::solve_for_premium_with(&rates, policy.issue_age, policy.face_amount, &config)?.premium,
+                };
+                cells.push(illustration::test_cell(&rates, &guaranteed_rates, policy, premium, &config, &pricing, &assumptions)?);
+            }
+            if let Some(path) = &output_file {
+                illustration::write_cell_tests_csv(path, &cells)?;
+            }
+            match cli.output {
+                OutputFormat::Text => {
+                    let show = |year: Option<i32>| year.map_or_else(|| "-".to_string(), |year| year.to_string());
+                    for cell in &cells {
+                        println!(
+                            "{} {} {:>3} {:>12.2} premium {:>10.2}: midpoint lapse year {:>3}, self-supporting {:<5} (fails {:>3}), lapse-supported {:<5} (fails {:>3})",
+                            cell.gender, cell.risk_class, cell.issue_age, cell.face_amount, cell.premium, show(cell.midpoint_lapse_year),
+                            cell.self_supporting, show(cell.self_support_fail_year), cell.lapse_supported, show(cell.lapse_support_fail_year)
+                        );
+                    }
+                    let self_supporting = cells.iter().filter(|cell| cell.self_supporting).count();
+                    let lapse_supported = cells.iter().filter(|cell| cell.lapse_supported).count();
+                    println!("{} cells, {} self-supporting, {} lapse-supported", cells.len(), self_supporting, lapse_supported);
+                    if let Some(path) = &output_file {
+                        println!("Cell tests written to {}", path);
+                    }
+                }
+                OutputFormat::Json => {
+                    let run = report::IllustrationTestReport { input, cells };
+                    println!("{}", report::to_json(&run)?);
+                }
+            }
+        }
+        Command::Term { policy, level_years, policy_fee, timestep, target_margin, target_irr, lapse_table, mortality_table, mortality_multiple, assumptions, state, profit_file } => {
+            let policy = policy.to_record(cli.age_basis.basis())?;
+            let rates = source.load_policy(&policy)?;
+            let assumptions = load_assumptions(&source.data_dir, assumptions.as_deref(), &state)?;
+            let basis = pricing_basis(&source.data_dir, lapse_table.as_deref(), mortality_table.as_deref(), mortality_multiple, &rates, &policy, &assumptions)?;
+            let product = TermProduct { level_years, policy_fee, timestep: timestep.timestep() };
+            let target = profit_target(target_margin, target_irr, &assumptions);
+            let pricing = term::solve_term_premium(policy.face_amount, &product, &basis, &assumptions, target)?;
+            if let Some(path) = &profit_file {
+                profit::write_profit_csv(path, &pricing.years)?;
+            }
+            match cli.output {
+                OutputFormat::Text => {
+                    let summary = &pricing.summary;
+                    println!("Premium: {:.2} ({:.4} per 1000 plus {:.2} policy fee)", pricing.annual_premium, pricing.rate_per_1000, policy_fee);
+                    let irr = summary.irr.map_or("none".to_string(), |irr| format!("{:.2}%", irr * 100.0));
+                    println!(
+                        "PV of profits at {:.1}%: {:.2}, profit margin: {:.2}%, IRR: {}",
+                        summary.hurdle_rate * 100.0, summary.pv_profits, summary.profit_margin * 100.0, irr
+                    );
+                    if let Some(path) = &profit_file {
+                        println!("Profits by year written to {}", path);
+                    }
+                }
+                OutputFormat::Json => {
+                    let run = report::TermReport { inputs: policy, pricing };
+                    println!("{}", report::to_json(&run)?);
+                }
+            }
+        }
+        Command::WholeLife {
+            policy, premium_years, policy_fee, cash_value_interest, valuation_mortality, timestep, target_margin, target_irr,
+            lapse_table, mortality_table, mortality_multiple, assumptions, state, profit_file, output_file
+        } => {
+            let policy = policy.to_record(cli.age_basis.basis())?;
+            let rates = source.load_policy(&policy)?;
+            let assumptions = load_assumptions(&source.data_dir, assumptions.as_deref(), &state)?;
+            let basis = pricing_basis(&source.data_dir, lapse_table.as_deref(), mortality_table.as_deref(), mortality_multiple, &rates, &policy, &assumptions)?;
+            let valuation = reserve::read_valuation_mortality(&source.data_dir.for_issue_date(policy.issue_date), valuation_mortality.as_deref(), &policy.gender, &policy.risk_class, policy.issue_age, &rates.coi_rates)?;
+            let product = WholeLifeProduct { premium_years, policy_fee, cash_value_interest, timestep: timestep.timestep() };
+            let target = profit_target(target_margin, target_irr, &assumptions);
+            let pricing = wholelife::solve_whole_life_premium(policy.face_amount, &product, &valuation, &basis, &assumptions, target)?;
+            if let Some(path) = &profit_file {
+                profit::write_profit_csv(path, &pricing.years)?;
+            }
+            if let Some(path) = &output_file {
+                wholelife::write_cash_values_csv(path, &pricing.cash_values)?;
+            }
+            match cli.output {
+                OutputFormat::Text => {
+                    let summary = &pricing.summary;
+                    println!("Premium: {:.2} ({:.4} per 1000 plus {:.2} policy fee)", pricing.annual_premium, pricing.rate_per_1000, policy_fee);
+                    let irr = summary.irr.map_or("none".to_string(), |irr| format!("{:.2}%", irr * 100.0));
+                    println!(
+                        "PV of profits at {:.1}%: {:.2}, profit margin: {:.2}%, IRR: {}",
+                        summary.hurdle_rate * 100.0, summary.pv_profits, summary.profit_margin * 100.0, irr
+                    );
+                    for year in [10, 20] {
+                        if let Some(value) = pricing.cash_values.get(year - 1) {
+                            println!("Cash value at the end of year {}: {:.2}", year, value.cash_value);
+                        }
+                    }
+                    if let Some(path) = &profit_file {
+                        println!("Profits by year written to {}", path);
+                    }
+                    if let Some(path) = &output_file {
+                        println!("Cash values written to {}", path);
+                    }
+                }
+                OutputFormat::Json => {
+                    let run = report::WholeLifeReport { inputs: policy, pricing };
+                    println!("{}", report::to_json(&run)?);
+                }
+            }
+        }
+        Command::Annuity {
+            age, premiums, withdrawals, surrender_charges, surrender_charge_table, free_withdrawal, credited_rate, credited_rates,
+            minimum_rate, annuitization_age, timestep, output_file, detail
+        } => {
+            let years = (annuitization_age - i32::from(age.get())).max(0) as usize;
+            let defaults = AnnuityProduct::default();
+            let surrender_charges = match (surrender_charges, &surrender_charge_table) {
+                (Some(rates), _) => rates,
+                (None, Some(path)) => annuity::read_surrender_schedule(path, years)?,
+                (None, None) => defaults.surrender_charges,
+            };
+            let credited_rates = match &credited_rates {
+                Some(path) => annuity::read_credited_rates(path, years)?,
+                None => vec![credited_rate],
+            };
+            let product = AnnuityProduct {
+                surrender_charges, free_withdrawal_percent: free_withdrawal, credited_rates, minimum_rate, annuitization_age, timestep: timestep.timestep()
+            };
+            let months = annuity::project_annuity(age, &product, &premiums, &withdrawals)?;
+            if let Some(path) = &output_file {
+                annuity::write_annuity_csv(path, &months)?;
+            }
+            let last = months.last().copied().unwrap_or_default();
+            match cli.output {
+                OutputFormat::Text => {
+                    println!("Annuitization value at age {}: {:.2}", annuitization_age, last.annuitization_value);
+                    for year in [5, 10] {
+                        if let Some(month) = months.iter().rev().find(|m| m.contract_year == year) {
+                            println!("Cash surrender value at the end of year {}: {:.2}", year, month.cash_surrender_value);
+                        }
+                    }
+                    if let Some(path) = &output_file {
+                        println!("Projection written to {}", path);
+                    }
+                }
+                OutputFormat::Json => {
+                    let run = report::AnnuityReport {
+                        issue_age: age,
+                        annuitization_age,
+                        annuitization_value: last.annuitization_value,
+                        cash_surrender_value: last.cash_surrender_value,
+                        projection: if detail {Some(months)} else {None},
+                    };
+                    println!("{}", report::to_json(&run)?);
+                }
+            }
+        }
+        Command::Payout {
+            annuitant, amount, interest, frequency, advance, certain_years, certain_only, mortality_table,
+            joint_gender, joint_class, joint_age, survivor_fraction
+        } => {
+            let annuitant = annuitant.to_record(cli.age_basis.basis(), 0.0)?;
+            let payments_per_year = match frequency {
+                ModeArg::Annual => PremiumMode::Annual,
+                ModeArg::Semiannual => PremiumMode::Semiannual,
+                ModeArg::Quarterly => PremiumMode::Quarterly,
+                ModeArg::Monthly => PremiumMode::Monthly,
+            }.payments_per_year();
+            let timing = if advance {PaymentTiming::Advance} else {PaymentTiming::Arrears};
+            let options = PayoutOptions { interest, payments_per_year, timing, certain_years };
+            let mortality = |gender: &str, risk_class: &str, age: IssueAge| -> Result<Vec<f64>, ValactError> {
+                let rates = source.load(gender, risk_class, age)?;
+                return decrements::read_cell_mortality(mortality_table.as_deref(), &source.data_dir.for_issue_date(annuitant.issue_date).table_path("payout_mortality"), gender, risk_class, age, &rates.coi_rates, 1.0);
+            };
+            let factor = match (&joint_gender, &joint_class, joint_age) {
+                _ if certain_only => payout::annuity_certain(certain_years, &options),
+                (Some(gender), Some(risk_class), Some(age)) => {
+                    let first = mortality(&annuitant.gender, &annuitant.risk_class, annuitant.issue_age)?;
+                    let second = mortality(gender, risk_class, age)?;
+                    payout::joint_annuity(&first, &second, survivor_fraction, &options)
+                }
+                _ => payout::life_annuity(&mortality(&annuitant.gender, &annuitant.risk_class, annuitant.issue_age)?, &options),
+            };
+            let annual_income = amount.map(|amount| payout::annual_income(amount, factor));
+            match cli.output {
+                OutputFormat::Text => {
+                    println!("Payout factor: {:.4}", factor);
+                    if let Some(income) = annual_income {
+                        println!("Annual income: {:.2} ({} installments of {:.2})", income, payments_per_year, income / f64::from(payments_per_year));
+                    }
+                }
+                OutputFormat::Json => {
+                    let run = report::PayoutReport { options, factor, amount, annual_income };
+                    println!("{}", report::to_json(&run)?);
+                }
+            }
+        }
+        Command::Batch { input, output_file, threads, solver, progress, progress_interval, valuation_date, plan_code, keep_going, stream, summary_file, age_band_width, model_points, model_point_bands, backend, checkpoints } => {
+            let extract = match valuation_date {
+                Some(valuation_date) => Some(inforce::read_inforce_extract(&input, valuation_date, cli.age_basis.basis(), &plan_code)?),
+                None => None,
+            };
+            let in_force: Vec<&inforce::InforcePolicy> = extract.iter().flatten().filter(|p| p.status == inforce::PolicyStatus::InForce).collect();
+            let policies = match &extract {
+                Some(_) => in_force.iter().map(|p| p.policy.clone()).collect(),
+                None => batch::read_policies_with_basis(&input, cli.age_basis.basis())?,
+            };
+            if extract.is_some() && (output_file.ends_with(".parquet") || output_file.ends_with(".arrow")) {
+                return Err("inforce results are written as csv".into());
+            }
+            let face_bands = source.face_bands.clone();
+            let repository = source.repository()?;
+            let options = solver.to_options(SolveTarget::EndowAtMaturity);
+            let rates_for = |policy: &PolicyRecord| policy_rates(&repository, &face_bands, policy);
+            let interval = match progress {
+                true => Duration::try_from_secs_f64(progress_interval).map_err(|_| format!("progress interval {} must be a positive number of seconds", progress_interval))?,
+                false => Duration::MAX,
+            };
+            let report_progress = |p: &runner::Progress| {
+                // straight to stderr rather than through the log so it shows whatever the log level
+                if progress {
+                    eprintln!("{}", progress_line(p));
+                }
+            };
+            let points = match model_points {
+                true => {
+                    let mut edges = model_point_bands;
+                    edges.extend(face_bands.iter().flat_map(|bands| bands.min_faces()));
+                    Some(modelpoints::compress(&policies, &edges)?)
+                }
+                false => None,
+            };
+            let solving: Cow<[PolicyRecord]> = match &points {
+                Some(points) => Cow::Owned(points.iter().map(|p| p.policy.clone()).collect()),
+                None => Cow::Borrowed(&policies),
+            };
+            let (solved, failed, resumed) = if stream {
+                if backend == Backend::Gpu {
+                    return Err("--stream solves on the cpu, leave out --backend gpu".into());
+                }
+                // a failure without --keep-going stops the run, what was solved before it stays in the file, which is
+                // finished either way so a parquet or arrow file gets its footer; rows carry their census row as
+                // they are in the order policies finish
+                let mut results = ResultsStream::create(&output_file)?;
+                let (mut solved, mut failed) = (0, 0);
+                let written = runner::solve_policies_writing(&policies, threads, rates_for, &options, interval, report_progress, |(index, result)| {
+                    match result {
+                        Ok(result) => {
+                            results.write(batch::PolicyResult { census_row: Some(index), ..result })?;
+                            solved += 1;
+                        }
+                        Err(_) if keep_going => failed += 1,
+                        Err(e) => return Err(e),
+                    }
+                    return Ok(());
+                });
+                let finished = results.finish();
+                written?;
+                finished?;
+                (solved, failed, 0)
+            } else {
+                let checkpoint = checkpoints.open::<batch::PolicyResult, _>("batch", &input, solving.len(), &(&*solving, &options))?;
+                // a resumed run only solves the policies, or model points, its checkpoint has not got
+                let positions: Vec<usize> = match &checkpoint {
+                    Some(checkpoint) => checkpoint.remaining(),
+                    None => (0..solving.len()).collect(),
+                };
+                let resumed = solving.len() - positions.len();
+                let remaining: Cow<[PolicyRecord]> = match resumed {
+                    0 => Cow::Borrowed(&solving),
+                    _ => Cow::Owned(positions.iter().map(|&i| solving[i].clone()).collect()),
+                };
+                let record = |index: usize, result: &Result<batch::PolicyResult, ValactError>| {
+                    if let (Some(checkpoint), Ok(result)) = (&checkpoint, result) {
+                        checkpoint.record(positions[index], result);
+                    }
+                };
+                let on_gpu = match backend {
+                    Backend::Gpu => gpu_batch(&remaining, &rates_for, &options)?,
+                    Backend::Cpu => None,
+                };
+                let solved: Vec<runner::Solved> = match on_gpu {
+                    Some(outcomes) => outcomes.into_iter().enumerate().map(|(index, result)| {
+                        record(index, &result);
+                        return (index, result);
+                    }).collect(),
+                    None => runner::solve_policies_recording(&remaining, threads, rates_for, &options, interval, report_progress, |index, result| {
+                        record(index, result);
+                        return keep_going || result.is_ok();
+                    })?,
+                };
+                if let Some(checkpoint) = &checkpoint {
+                    checkpoint.save()?;
+                }
+                let mut outcomes: Vec<Option<Result<batch::PolicyResult, ValactError>>> = match &checkpoint {
+                    Some(checkpoint) => checkpoint.results().into_iter().map(|result| result.map(Ok)).collect(),
+                    None => solving.iter().map(|_| None).collect(),
+                };
+                for (index, result) in solved {
+                    outcomes[positions[index]] = Some(result);
+                }
+                // without --keep-going the first failure fails the run, what was solved before it stays in the checkpoint
+                if !keep_going && let Some(failed) = outcomes.iter_mut().find(|result| matches!(result, Some(Err(_)))) && let Some(Err(e)) = failed.take() {
+                    return Err(e);
+                }
+                let outcomes: Vec<Result<batch::PolicyResult, ValactError>> = outcomes.into_iter().map(|result| result.unwrap_or_else(|| Err("not solved".into()))).collect();
+                let outcomes = match &points {
+                    Some(points) => modelpoints::expand(&policies, points, outcomes)?,
+                    None => outcomes,
+                };
+                if let Some(summary_file) = &summary_file {
+                    summary::write_summary(summary_file, &summary::summarize(&policies, &outcomes, age_band_width)?)?;
+                }
+                let failed = outcomes.iter().filter(|r| r.is_err()).count();
+                let solved_in_force: Vec<&inforce::InforcePolicy> = in_force.iter().zip(&outcomes).filter(|(_, r)| r.is_ok()).map(|(p, _)| *p).collect();
+                let results: Vec<batch::PolicyResult> = outcomes.into_iter().flatten().collect();
+                let solved = results.len();
+                if extract.is_some() {
+                    inforce::write_inforce_results(&output_file, &inforce::inforce_results(&solved_in_force, results))?;
+                } else {
+                    write_batch_results(&output_file, &results)?;
+                }
+                if let Some(checkpoint) = checkpoint {
+                    checkpoint.finish()?;
+                }
+                (solved, failed, resumed)
+            };
+            let skipped = extract.as_ref().map_or(0, |e| e.len() - in_force.len());
+            match cli.output {
+                OutputFormat::Text => {
+                    println!("Solved {} policies, results written to {}", solved, output_file);
+                    if let Some(run_id) = &checkpoints.resume {
+                        println!("Resumed run {}, {} of {} were solved before it stopped", run_id, resumed, solving.len());
+                    }
+                    if let Some(points) = &points {
+                        println!("Model points: {} for {} policies", points.len(), policies.len());
+                    }
+                    if failed > 0 {
+                        println!("Failed {} policies, left out of the results", failed);
+                    }
+                    if skipped > 0 {
+                        println!("Skipped {} policies not in force", skipped);
+                    }
+                    if let Some(summary_file) = &summary_file {
+                        println!("Summary written to {}", summary_file);
+                    }
+                    println!("Rate cache: {}", repository.stats());
+                }
+                OutputFormat::Json => {
+                    let summary = report::BatchReport { input_file: input, output_file, policies: solved, failed, summary_file, rate_cache: repository.stats() };
+                    println!("{}", report::to_json(&summary)?);
+                }
+            }
+        }
+        Command::Ratebook { output_file, genders, classes, min_age, max_age, faces, db_option, threads, solver } => {
+            let grid = ratebook::RateBookGrid {
+                genders,
+                risk_classes: classes,
+                min_issue_age: IssueAge::new(min_age)?,
+                max_issue_age: IssueAge::new(max_age)?,
+                face_amounts: faces,
+                death_benefit_option: db_option.option(),
+            };
+            let policies = grid.policies()?;
+            let face_bands = source.face_bands.clone();
+            let repository = source.repository()?;
+            let options = solver.to_options(SolveTarget::EndowAtMaturity);
+            let rates_for = |policy: &PolicyRecord| policy_rates(&repository, &face_bands, policy);
+            let results = runner::solve_policies_keep_going(&policies, threads, rates_for, &options, Duration::MAX, |_| {})?;
+            let book = ratebook::rate_book(&grid, &results)?;
+            ratebook::write_rate_book(&output_file, &book)?;
+            match cli.output {
+                OutputFormat::Text => {
+                    println!("Rate book of {} cells, {} rows by {} face amounts, written to {}", book.cells(), book.rows.len(), book.face_amounts.len(), output_file);
+                    if book.failed() > 0 {
+                        println!("Failed {} cells, left blank:", book.failed());
+                        for (message, count) in &book.failures {
+                            println!("  {} x {}", count, message);
+                        }
+                    }
+                    println!("Rate cache: {}", repository.stats());
+                }
+                OutputFormat::Json => {
+                    let run = report::RateBookReport { output_file, rows: book.rows.len(), cells: book.cells(), failed: book.failed(), failures: book.failures, rate_cache: repository.stats() };
+                    println!("{}", report::to_json(&run)?);
+                }
+            }
+        }
+        Command::Validate { dir } => {
+            let data_dir = dir.as_deref().map_or_else(|| source.data_dir.clone(), DataDir::new);
+            let validation = validate::validate_data_dir(&data_dir, cli.maturity_age)?;
+            match cli.output {
+                OutputFormat::Text => println!("{}", validation),
+                OutputFormat::Json => println!("{}", report::to_json(&validation)?),
+            }
+            // fails the command so a script can stop before running on bad tables
+            if validation.errors() > 0 {
+                return Err(format!("rate table validation failed with {} errors", validation.errors()).into());
+            }
+        }
+        Command::ImportRates => {
+            let db = cli.rates_db.ok_or("import-rates needs --rates-db")?;
+            let count = import_rates(&db, &source.data_dir)?;
+            println!("Loaded {} rate rows into {}", count, db);
+        }
+        Command::Serve { address, grpc, watch, watch_interval } => {
+            if source.face_bands.is_some() {
+                return Err("--face-bands is not supported by serve".into());
+            }
+            let watch = watch.then(|| Duration::from_secs(watch_interval));
+            if grpc {
+                serve_grpc(&address, source, watch)?;
+            } else {
+                serve(&address, source, watch)?;
+            }
+        }
     }
-    let elapsed = now.elapsed();
-    println!("Premium: {:.2?}", x);
-    println!("Elapsed: {:.2?}", elapsed);
-    pause();
     Ok(())
 }
 
-use std::io;
-use std::io::prelude::*;
+// e.g. 12000/100000 policies (12.0%), 0 failed, 850/s, 1m43s left
+fn progress_line(progress: &runner::Progress) -> String {
+    let done = progress.completed + progress.failed;
+    let percent = if progress.total > 0 {100.0 * done as f64 / progress.total as f64} else {100.0};
+    let left = match progress.eta() {
+        Some(eta) => format!("{} left", format_duration(eta)),
+        None => "time left unknown".to_string(),
+    };
+    return format!("{}/{} policies ({:.1}%), {} failed, {:.0}/s, {}", done, progress.total, percent, progress.failed, progress.throughput(), left);
+}
 
-fn pause() {
-    let mut stdin = io::stdin();
-    let mut stdout = io::stdout();
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    return match seconds {
+        s if s >= 3600 => format!("{}h{:02}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m{:02}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    };
+}
 
-    write!(stdout, "Press any key to continue...").unwrap();
-    stdout.flush().unwrap();
+// the given assumptions file or the defaults, with the state overridden if one was given
+fn load_assumptions(data_dir: &DataDir, path: Option<&str>, state: &Option<String>) -> Result<Assumptions, ValactError> {
+    let mut assumptions = Assumptions::load(data_dir, path)?;
+    if state.is_some() {
+        assumptions.taxes.state = state.clone();
+    }
+    return Ok(assumptions);
+}
 
-    let _ = stdin.read(&mut [0u8]).unwrap();
+// the profit margin or irr a premium is solved to, the hurdle rate as an irr when neither is given
+fn profit_target(margin: Option<f64>, irr: Option<f64>, assumptions: &Assumptions) -> ProfitTarget {
+    return match (margin, irr) {
+        (Some(margin), _) => ProfitTarget::ProfitMargin(margin),
+        (None, Some(irr)) => ProfitTarget::Irr(irr),
+        (None, None) => ProfitTarget::Irr(assumptions.profit.hurdle_rate),
+    };
 }
+
+// expected mortality with the assumptions' improvement, lapses and commission target premium for the policy, from
+// the tables in effect on its issue date
+fn pricing_basis(data_dir: &DataDir, lapse_table: Option<&str>, mortality_table: Option<&str>, mortality_multiple: f64, rates: &RateSet, policy: &PolicyRecord, assumptions: &Assumptions) -> Result<PricingBasis, ValactError> {
+    let data_dir = &data_dir.for_issue_date(policy.issue_date);
+    let lapses = decrements::read_lapse_rates(data_dir, lapse_table, rates.years())?;
+    let mut mortality = decrements::read_mortality_rates(
+        data_dir, mortality_table, &policy.gender, &policy.risk_class, policy.issue_age, &rates.coi_rates, mortality_multiple
+    )?;
+    assumptions.mortality_improvement.apply(&mut mortality);
+    let target_per_1000 = match assumptions.commissions.target_premium_per_1000 {
+        Some(rate) => rate,
+        None => read_target_premium(data_dir, policy.issue_age)?,
+    };
+    return Ok(PricingBasis { mortality, lapses, target_premium: target_per_1000 * policy.face_amount / 1000.0 });
+}
+
+// a warning goes to stderr so json on stdout is still parseable
+fn check_guideline(test: GuidelineTest, rates: &RateSet, issue_age: IssueAge, face_amount: f64, premium: f64, config: &ProjectionConfig) -> Result<(), ValactError> {
+    if test == GuidelineTest::Off {
+        return Ok(());
+    }
+    let premiums = tax7702::guideline_premiums(rates, issue_age, face_amount, config)?;
+    let months = at_issue_projection_detail_with(rates, issue_age, face_amount, premium, config)?;
+    if let Some(violation) = tax7702::first_violation(&months, &premiums) {
+        let message = format!(
+            "premiums paid of {:.2} by policy year {} exceed the guideline limit of {:.2} (GSP {:.2}, GLP {:.2})",
+            violation.premiums_paid, violation.policy_year, violation.limit, premiums.single, premiums.level
+        );
+        if test == GuidelineTest::Refuse {
+            return Err(message.into());
+        }
+        warn!("{}", message);
+    }
+    return Ok(());
+}
+
+// rates from the data directory unless a sqlite database was given
+#[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+fn load_rates(rates_db: &Option<String>, data_dir: &DataDir, maturity_age: AttainedAge, gender: &str, class: &str, age: IssueAge) -> Result<RateSet, ValactError> {
+    if let Some(db) = rates_db {
+        #[cfg(feature = "sqlite")]
+        return illus1::sqlite_store::get_rates_to_maturity(db, gender, class, age, maturity_age);
+        #[cfg(not(feature = "sqlite"))]
+        return Err("--rates-db requires building with --features sqlite".into());
+    }
+    return get_rates_to_maturity(data_dir, gender, class, age, maturity_age);
+}
+
+#[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+fn import_rates(db: &str, data_dir: &DataDir) -> Result<usize, ValactError> {
+    #[cfg(feature = "sqlite")]
+    return illus1::sqlite_store::SqliteRateStore::open(db)?.import_data_dir(data_dir);
+    #[cfg(not(feature = "sqlite"))]
+    return Err("import-rates requires building with --features sqlite".into());
+}
+
+// xlsx is only available when built with the xlsx feature
+#[cfg_attr(not(feature = "xlsx"), allow(unused_variables))]
+fn write_projection(path: &str, inputs: &PolicyRecord, premium: f64, months: &[MonthResult]) -> Result<(), ValactError> {
+    if path.ends_with(".xlsx") {
+        #[cfg(feature = "xlsx")]
+        return illus1::xlsx_report::write_illustration_xlsx(path, inputs, premium, months);
+        #[cfg(not(feature = "xlsx"))]
+        return Err("xlsx output requires building with --features xlsx".into());
+    }
+    return projection::write_projection_csv(path, months);
+}
+
+// the pdf illustration is only available when built with the pdf feature
+#[cfg_attr(not(feature = "pdf"), allow(unused_variables))]
+fn write_illustration_pdf(path: &str, inputs: &PolicyRecord, premium: f64, guaranteed: &[MonthResult], midpoint: &[MonthResult], current: &[MonthResult]) -> Result<(), ValactError> {
+    #[cfg(feature = "pdf")]
+    return illus1::pdf_report::write_illustration_pdf(path, inputs, premium, guaranteed, midpoint, current);
+    #[cfg(not(feature = "pdf"))]
+    return Err("pdf output requires building with --features pdf".into());
+}
+
+// the rates a server solves against, rebuilt from the same source whenever the data directory changes with --watch
+#[cfg(any(feature = "server", feature = "grpc"))]
+fn live_rates(source: RateSource, watch: Option<Duration>) -> Result<Arc<illus1::reload::LiveRates>, ValactError> {
+    let data_dir = source.data_dir.clone();
+    let build = move || source.clone().repository();
+    let live = Arc::new(illus1::reload::LiveRates::load(data_dir, &build)?);
+    if let Some(interval) = watch {
+        live.watch(interval, build)?;
+    }
+    return Ok(live);
+}
+
+// the http api is only available when built with the server feature
+#[cfg_attr(not(feature = "server"), allow(unused_variables))]
+fn serve(address: &str, source: RateSource, watch: Option<Duration>) -> Result<(), ValactError> {
+    #[cfg(feature = "server")]
+    return illus1::server::serve(address, live_rates(source, watch)?);
+    #[cfg(not(feature = "server"))]
+    return Err("serve requires building with --features server".into());
+}
+
+// and the grpc service only with the grpc feature
+#[cfg_attr(not(feature = "grpc"), allow(unused_variables))]
+fn serve_grpc(address: &str, source: RateSource, watch: Option<Duration>) -> Result<(), ValactError> {
+    #[cfg(feature = "grpc")]
+    return illus1::grpc::serve(address, live_rates(source, watch)?);
+    #[cfg(not(feature = "grpc"))]
+    return Err("serve --grpc requires building with --features grpc".into());
+}
+
+// the gpu backend only with the gpu feature, without an adapter the run goes to the cpu
+#[cfg(feature = "gpu")]
+fn gpu_device() -> Option<illus1::gpu::Gpu> {
+    return match illus1::gpu::Gpu::new() {
+        Ok(gpu) => Some(gpu),
+        Err(e) => {
+            warn!("{}, running on the cpu", e);
+            None
+        }
+    };
+}
+
+// scenarios on the gpu, None to run them on the cpu
+#[cfg_attr(not(feature = "gpu"), allow(unused_variables))]
+fn gpu_scenarios(rates: &RateSet, issue_age: IssueAge, face_amount: f64, premium: f64, config: &ProjectionConfig, set: &scenarios::ScenarioSet) -> Result<Option<Vec<scenarios::ScenarioResult>>, ValactError> {
+    #[cfg(feature = "gpu")]
+    {
+        use illus1::gpu;
+        if !gpu::supported(config, &SolverOptions::new(Method::Bisection, 0.005)) {
+            warn!("the gpu kernel does not cover schedules, riders, accounts or rounded amounts, running on the cpu");
+            return Ok(None);
+        }
+        return match gpu_device() {
+            Some(device) => Ok(Some(gpu::run_scenarios(&device, rates, issue_age, face_amount, premium, config, set)?)),
+            None => Ok(None),
+        };
+    }
+    #[cfg(not(feature = "gpu"))]
+    return Err("--backend gpu requires building with --features gpu".into());
+}
+
+// batch solves on the gpu, None to solve them on the cpu
+#[cfg_attr(not(feature = "gpu"), allow(unused_variables))]
+fn gpu_batch<F>(policies: &[PolicyRecord], rates_for: &F, options: &SolverOptions) -> Result<Option<Vec<Result<batch::PolicyResult, ValactError>>>, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<Arc<RateSet>, ValactError>,
+{
+    #[cfg(feature = "gpu")]
+    {
+        use illus1::gpu;
+        if !gpu::supported(&ProjectionConfig::default(), options) {
+            warn!("the gpu kernel only solves by bisection, running on the cpu");
+            return Ok(None);
+        }
+        return match gpu_device() {
+            Some(device) => Ok(Some(gpu::solve_policies(&device, policies, rates_for, options)?)),
+            None => Ok(None),
+        };
+    }
+    #[cfg(not(feature = "gpu"))]
+    return Err("--backend gpu requires building with --features gpu".into());
+}
+
+// decimal solves and projections only with the decimal feature, amounts come back formatted to the cent
+#[cfg_attr(not(feature = "decimal"), allow(unused_variables))]
+fn solve_decimal(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig) -> Result<String, ValactError> {
+    #[cfg(feature = "decimal")]
+    {
+        use illus1::decimal;
+        let face_amount = decimal::to_decimal(face_amount, "face amount")?;
+        return Ok(decimal::solve_for_premium_decimal(rates, issue_age, face_amount, config)?.to_string());
+    }
+    #[cfg(not(feature = "decimal"))]
+    return Err("--decimal requires building with --features decimal".into());
+}
+
+#[cfg_attr(not(feature = "decimal"), allow(unused_variables))]
+fn project_decimal(rates: &RateSet, issue_age: IssueAge, face_amount: f64, premium: f64, config: &ProjectionConfig, output_file: Option<&str>) -> Result<String, ValactError> {
+    #[cfg(feature = "decimal")]
+    {
+        use illus1::decimal;
+        let face_amount = decimal::to_decimal(face_amount, "face amount")?;
+        let premium = decimal::to_decimal(premium, "premium")?;
+        let months = decimal::decimal_projection_detail(rates, issue_age, face_amount, premium, config)?;
+        if let Some(path) = output_file {
+            decimal::write_decimal_projection_csv(path, &months)?;
+        }
+        return Ok(months.last().map_or(rust_decimal::Decimal::ZERO, |m| m.value_end).to_string());
+    }
+    #[cfg(not(feature = "decimal"))]
+    return Err("--decimal requires building with --features decimal".into());
+}
+
+// parquet and arrow are only available when built with their features
+fn write_batch_results(path: &str, results: &[batch::PolicyResult]) -> Result<(), ValactError> {
+    if path.ends_with(".parquet") {
+        #[cfg(feature = "parquet")]
+        return illus1::parquet_writer::write_results_parquet(path, results, illus1::parquet_writer::DEFAULT_BATCH_SIZE);
+        #[cfg(not(feature = "parquet"))]
+        return Err("parquet output requires building with --features parquet".into());
+    }
+    if path.ends_with(".arrow") {
+        #[cfg(feature = "arrow")]
+        return illus1::arrow_results::write_results_ipc(path, results, illus1::arrow_results::DEFAULT_BATCH_SIZE);
+        #[cfg(not(feature = "arrow"))]
+        return Err("arrow output requires building with --features arrow".into());
+    }
+    return batch::write_results(path, results);
+}
+
+// results file of a streamed batch, parquet and arrow are written a record batch of rows at a time
+enum ResultsStream {
+    Csv(batch::ResultsWriter),
+    #[cfg(feature = "parquet")]
+    Parquet(illus1::parquet_writer::ParquetResultsWriter, Vec<batch::PolicyResult>),
+    #[cfg(feature = "arrow")]
+    Arrow(illus1::arrow_results::IpcResultsWriter, Vec<batch::PolicyResult>),
+}
+
+impl ResultsStream {
+    fn create(path: &str) -> Result<ResultsStream, ValactError> {
+        if path.ends_with(".parquet") {
+            #[cfg(feature = "parquet")]
+            return Ok(ResultsStream::Parquet(illus1::parquet_writer::ParquetResultsWriter::create(path)?, Vec::new()));
+            #[cfg(not(feature = "parquet"))]
+            return Err("parquet output requires building with --features parquet".into());
+        }
+        if path.ends_with(".arrow") {
+            #[cfg(feature = "arrow")]
+            return Ok(ResultsStream::Arrow(illus1::arrow_results::IpcResultsWriter::create(path)?, Vec::new()));
+            #[cfg(not(feature = "arrow"))]
+            return Err("arrow output requires building with --features arrow".into());
+        }
+        return Ok(ResultsStream::Csv(batch::ResultsWriter::create(path)?));
+    }
+
+    fn write(&mut self, result: batch::PolicyResult) -> Result<(), ValactError> {
+        match self {
+            ResultsStream::Csv(writer) => writer.write(&result)?,
+            #[cfg(feature = "parquet")]
+            ResultsStream::Parquet(writer, rows) => {
+                rows.push(result);
+                if rows.len() >= illus1::parquet_writer::DEFAULT_BATCH_SIZE {
+                    writer.write(rows)?;
+                    rows.clear();
+                }
+            }
+            #[cfg(feature = "arrow")]
+            ResultsStream::Arrow(writer, rows) => {
+                rows.push(result);
+                if rows.len() >= illus1::arrow_results::DEFAULT_BATCH_SIZE {
+                    writer.write(rows)?;
+                    rows.clear();
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn finish(self) -> Result<(), ValactError> {
+        return match self {
+            ResultsStream::Csv(writer) => writer.finish(),
+            #[cfg(feature = "parquet")]
+            ResultsStream::Parquet(mut writer, rows) => {
+                if !rows.is_empty() {
+                    writer.write(&rows)?;
+                }
+                writer.finish()
+            }
+            #[cfg(feature = "arrow")]
+            ResultsStream::Arrow(mut writer, rows) => {
+                if !rows.is_empty() {
+                    writer.write(&rows)?;
+                }
+                writer.finish()
+            }
+        };
+    }
+}
+
 fn main() {
     if let Err(err) = run() {
         println!("{}", err);
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use crate::{
+    batch::{PolicyRecord, PolicyResult}, error::ValactError, projection::DeathBenefitOption, rounding::RoundingPolicy
+};
+
+// model point compression for large censuses, policies with the same gender, risk class, issue age, death benefit
+// option and face band are solved once as a model point at the group's average face and the premium is scaled
+// back out to each policy by its face amount
+// bands are given by their smallest face amount as in bands.rs, with no bands only policies of exactly the same
+// face amount are grouped and the results are the ones a seriatim run gives; within a band the per policy fee
+// does not scale with face so the bands have to be narrow enough for that not to matter
+// policies with an inforce state are never grouped, no two are likely to be at the same account value
+
+#[derive(Clone, Debug)]
+pub struct ModelPoint {
+    pub policy: PolicyRecord,
+    // positions in the census of the policies the point stands for, how many is the point's weight
+    pub members: Vec<usize>,
+}
+
+impl ModelPoint {
+    pub fn weight(&self) -> usize {
+        return self.members.len();
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CellKey {
+    gender: String,
+    risk_class: String,
+    issue_age: u8,
+    death_benefit_option: DeathBenefitOption,
+    // the band's position in the edges, or the face amount's bits when there are no bands
+    face: u64,
+}
+
+// edges are the smallest face of each band, a face below all of them is a band of its own
+fn face_key(face_amount: f64, edges: &[f64]) -> u64 {
+    if edges.is_empty() {
+        return face_amount.to_bits();
+    }
+    return edges.iter().filter(|edge| **edge <= face_amount).count() as u64;
+}
+
+// points are in the order of their first policy in the census
+pub fn compress(policies: &[PolicyRecord], edges: &[f64]) -> Result<Vec<ModelPoint>, ValactError> {
+    if let Some(edge) = edges.iter().find(|edge| !edge.is_finite() || **edge < 0.0) {
+        return Err(format!("model point band minimum face {} must be 0 or more", edge).into());
+    }
+    let mut points: Vec<ModelPoint> = Vec::new();
+    let mut index: HashMap<CellKey, usize> = HashMap::new();
+    for (i, policy) in policies.iter().enumerate() {
+        if policy.inforce.is_some() {
+            points.push(ModelPoint { policy: policy.clone(), members: vec![i] });
+            continue;
+        }
+        let key = CellKey {
+            gender: policy.gender.clone(),
+            risk_class: policy.risk_class.clone(),
+            issue_age: policy.issue_age.get(),
+            death_benefit_option: policy.death_benefit_option,
+            face: face_key(policy.face_amount, edges),
+        };
+        match index.get(&key) {
+            Some(&point) => points[point].members.push(i),
+            None => {
+                index.insert(key, points.len());
+                points.push(ModelPoint { policy: policy.clone(), members: vec![i] });
+            }
+        }
+    }
+    for point in points.iter_mut().filter(|p| p.weight() > 1) {
+        point.policy.face_amount = point.members.iter().map(|i| policies[*i].face_amount).sum::<f64>() / point.weight() as f64;
+    }
+    return Ok(points);
+}
+
+// a point's premium for a policy, rounded as the batch solve rounds it
+fn scaled(premium: f64, face_amount: f64, point_face_amount: f64) -> f64 {
+    if face_amount == point_face_amount {
+        return premium;
+    }
+    return RoundingPolicy::default().round(premium * face_amount / point_face_amount);
+}
+
+// a result per census policy from the results of the points, in the order of policies, a failed point fails
+// every policy it stands for, its first member with the point's error and the others with its message
+pub fn expand(policies: &[PolicyRecord], points: &[ModelPoint], results: Vec<Result<PolicyResult, ValactError>>) -> Result<Vec<Result<PolicyResult, ValactError>>, ValactError> {
+    if points.len() != results.len() {
+        return Err(format!("{} results for {} model points", results.len(), points.len()).into());
+    }
+    let mut expanded: Vec<Result<PolicyResult, ValactError>> = policies.iter().map(|_| Err("not in any model point".into())).collect();
+    for (point, result) in points.iter().zip(results) {
+        let message = result.as_ref().err().map(ToString::to_string).unwrap_or_default();
+        let (result, mut error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(e) => (None, Some(e)),
+        };
+        for &i in &point.members {
+            let policy = policies.get(i).ok_or_else(|| format!("model point member {} is not in the census", i))?;
+            let Some(result) = &result else {
+                expanded[i] = Err(error.take().unwrap_or_else(|| message.as_str().into()));
+                continue;
+            };
+            expanded[i] = Ok(PolicyResult {
+                gender: policy.gender.clone(),
+                risk_class: policy.risk_class.clone(),
+                issue_age: policy.issue_age,
+                face_amount: policy.face_amount,
+                death_benefit_option: policy.death_benefit_option,
+                premium: scaled(result.premium, policy.face_amount, point.policy.face_amount),
+                census_row: None,
+            });
+        }
+    }
+    return Ok(expanded);
+}
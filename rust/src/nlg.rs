@@ -0,0 +1,94 @@
+use std::{
+    fs::File, io::BufReader
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    age::IssueAge, error::ValactError, projection::{at_issue_projection_detail_with, MonthResult, ProjectionConfig}, rates::RateSet
+};
+
+// no-lapse guarantee by shadow account
+// the shadow account is the policy projected again on the guarantee's own loads, charges and interest, and the
+// policy stays in force while the shadow account is not negative even once the real account value is
+// the guarantee lasts as long as the shadow account stays at or above zero, so the premium paid sets how long
+// it runs; withdrawals and loans come out of the shadow account as they do the real one
+
+// shadow account charges, rates annual and interest annual effective
+// the shadow coi is a multiple of the policy's coi rates
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShadowBasis {
+    pub premium_load: f64,
+    pub policy_fee: f64,
+    pub unit_load_per_1000: f64,
+    pub coi_multiple: f64,
+    pub interest: f64,
+}
+
+impl Default for ShadowBasis {
+    fn default() -> Self {
+        return ShadowBasis { premium_load: 0.06, policy_fee: 60.0, unit_load_per_1000: 0.5, coi_multiple: 1.0, interest: 0.045 };
+    }
+}
+
+impl ShadowBasis {
+    pub fn from_json_file(path: &str) -> Result<ShadowBasis, ValactError> {
+        let file = File::open(path)?;
+        return Ok(serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))?);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct NoLapseGuarantee {
+    // complete policy years the shadow account stays at or above zero
+    pub guarantee_years: i32,
+    pub to_maturity: bool,
+    // first policy year the real account value is negative, the guarantee is what keeps the policy in force
+    // from then until it runs out
+    pub negative_value_year: Option<i32>,
+    // policy year the policy lapses, with both the account value and the shadow account negative
+    pub lapse_year: Option<i32>,
+}
+
+// the policy's rates with the shadow charges and interest in their place, no surrender charges
+fn shadow_rates(rates: &RateSet, basis: &ShadowBasis) -> RateSet {
+    let years = rates.years();
+    let monthly = (1.0 + basis.interest).powf(1.0 / 12.0) - 1.0;
+    return RateSet {
+        premium_loads: vec![basis.premium_load; years],
+        policy_fees: vec![basis.policy_fee; years],
+        unit_loads: vec![basis.unit_load_per_1000; years],
+        coi_rates: rates.coi_rates.iter().map(|rate| rate * basis.coi_multiple).collect(),
+        interest_rates: vec![monthly; years],
+        surrender_charges: vec![0.0; years],
+        ..rates.clone()
+    };
+}
+
+// shadow account projection at the same premium, any interest path or account split in config is the real
+// account's and does not apply
+pub fn shadow_account(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig, basis: &ShadowBasis) -> Result<Vec<MonthResult>, ValactError> {
+    let config = ProjectionConfig { interest_path: Vec::new(), accounts: Vec::new(), ..config.clone() };
+    return at_issue_projection_detail_with(&shadow_rates(rates, basis), issue_age, face_amount, annual_premium, &config);
+}
+
+pub fn no_lapse_guarantee(months: &[MonthResult], shadow: &[MonthResult]) -> NoLapseGuarantee {
+    let ends = shadow.iter().find(|m| m.value_end < 0.0).map(|m| m.policy_year);
+    let last_year = months.last().map_or(0, |m| m.policy_year);
+    let lapse_year = months.iter().zip(shadow)
+        .find(|(month, shadow)| month.value_end < 0.0 && shadow.value_end < 0.0)
+        .map(|(month, _)| month.policy_year);
+    return NoLapseGuarantee {
+        guarantee_years: ends.map_or(last_year, |year| year - 1),
+        to_maturity: ends.is_none(),
+        negative_value_year: months.iter().find(|m| m.value_end < 0.0).map(|m| m.policy_year),
+        lapse_year,
+    };
+}
+
+pub fn mark_shadow_account(months: &mut [MonthResult], shadow: &[MonthResult]) {
+    for (month, shadow) in months.iter_mut().zip(shadow) {
+        month.shadow_account_value = shadow.value_end;
+    }
+}
@@ -0,0 +1,48 @@
+use std::fs::File;
+
+use arrow_schema::SchemaRef;
+use parquet::{
+    arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties
+};
+
+pub use crate::arrow_results::{results_schema, to_record_batch, DEFAULT_BATCH_SIZE};
+use crate::{batch::PolicyResult, error::ValactError};
+
+// writes batch results to a parquet file one record batch at a time
+pub struct ParquetResultsWriter {
+    schema: SchemaRef,
+    writer: ArrowWriter<File>,
+}
+
+impl ParquetResultsWriter {
+    pub fn create(path: &str) -> Result<ParquetResultsWriter, ValactError> {
+        let schema = results_schema();
+        let file = File::create(path)?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(props))?;
+        return Ok(ParquetResultsWriter { schema, writer });
+    }
+
+    pub fn write(&mut self, results: &[PolicyResult]) -> Result<(), ValactError> {
+        let batch = to_record_batch(self.schema.clone(), results)?;
+        self.writer.write(&batch)?;
+        return Ok(());
+    }
+
+    // writes the parquet footer, the file is not readable until this is called
+    pub fn finish(self) -> Result<(), ValactError> {
+        self.writer.close()?;
+        return Ok(());
+    }
+}
+
+pub fn write_results_parquet(path: &str, results: &[PolicyResult], batch_size: usize) -> Result<(), ValactError> {
+    let mut writer = ParquetResultsWriter::create(path)?;
+    for chunk in results.chunks(batch_size.max(1)) {
+        writer.write(chunk)?;
+    }
+    writer.finish()?;
+    return Ok(());
+}
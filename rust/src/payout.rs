@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+// payout annuity factors, the present value of an income of 1 a year paid in equal installments
+// annuity certain pays for a fixed number of years, a life annuity while the annuitant is alive, and a joint
+// annuity the full income while both are alive and survivor_fraction of it after the first death
+// mortality is per 1000 by year from the annuitant's age at the start of payments, with deaths spread uniformly
+// through each year and nobody surviving past the end of the table, and the two lives of a joint annuity are
+// taken as independent
+// payments in advance start at once, in arrears one installment later
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PaymentTiming {
+    Advance,
+    #[default]
+    Arrears,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PayoutOptions {
+    // annual effective
+    pub interest: f64,
+    pub payments_per_year: i32,
+    pub timing: PaymentTiming,
+    // installments in the first certain_years are paid whether or not anyone is alive
+    pub certain_years: i32,
+}
+
+impl Default for PayoutOptions {
+    fn default() -> Self {
+        return PayoutOptions { interest: 0.04, payments_per_year: 12, timing: PaymentTiming::Arrears, certain_years: 0 };
+    }
+}
+
+impl PayoutOptions {
+    // number and time in years of each installment in the first years years
+    fn payment_times(&self, years: usize) -> impl Iterator<Item = (i32, f64)> + '_ {
+        let m = self.payments_per_year.max(1);
+        let offset = match self.timing {
+            PaymentTiming::Advance => 0,
+            PaymentTiming::Arrears => 1,
+        };
+        return (0..years as i32 * m).map(move |k| (k, f64::from(k + offset) / f64::from(m)));
+    }
+
+    fn discount(&self, t: f64) -> f64 {
+        return (1.0 + self.interest).powf(-t);
+    }
+
+    fn installment(&self) -> f64 {
+        return 1.0 / f64::from(self.payments_per_year.max(1));
+    }
+}
+
+// probability of surviving t years, uniform deaths within each year
+pub fn survival(mortality: &[f64], t: f64) -> f64 {
+    let whole = t.floor() as usize;
+    if whole > mortality.len() || (whole == mortality.len() && t > t.floor()) {
+        return 0.0;
+    }
+    let survived = mortality[..whole].iter().fold(1.0, |p, q| p * (1.0 - (q / 1000.0).clamp(0.0, 1.0)));
+    let fraction = t - t.floor();
+    if fraction == 0.0 {
+        return survived;
+    }
+    return survived * (1.0 - fraction * (mortality[whole] / 1000.0).clamp(0.0, 1.0));
+}
+
+// income of 1 a year for years years
+pub fn annuity_certain(years: i32, options: &PayoutOptions) -> f64 {
+    return options.payment_times(years.max(0) as usize).fold(0.0, |pv, (_, t)| pv + options.installment() * options.discount(t));
+}
+
+// factor for any pattern of payments given the probability income is paid at t, guaranteed for the certain years
+fn contingent_factor<F>(years: usize, options: &PayoutOptions, paid: F) -> f64
+where
+    F: Fn(f64) -> f64,
+{
+    let certain = options.certain_years.max(0);
+    let guaranteed = certain * options.payments_per_year.max(1);
+    return options.payment_times(years.max(certain as usize)).fold(0.0, |pv, (k, t)| {
+        let probability = if k < guaranteed {1.0} else {paid(t)};
+        pv + options.installment() * options.discount(t) * probability
+    });
+}
+
+pub fn life_annuity(mortality: &[f64], options: &PayoutOptions) -> f64 {
+    return contingent_factor(mortality.len(), options, |t| survival(mortality, t));
+}
+
+// survivor_fraction of 1.0 is a last survivor annuity, 0.0 pays only while both are alive
+pub fn joint_annuity(first: &[f64], second: &[f64], survivor_fraction: f64, options: &PayoutOptions) -> f64 {
+    return contingent_factor(first.len().max(second.len()), options, |t| {
+        let (p1, p2) = (survival(first, t), survival(second, t));
+        let both = p1 * p2;
+        both + survivor_fraction * (p1 + p2 - 2.0 * both)
+    });
+}
+
+// yearly income the value buys at a factor
+pub fn annual_income(value: f64, factor: f64) -> f64 {
+    return if factor > 0.0 {value / factor} else {0.0};
+}
@@ -0,0 +1,230 @@
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+
+use crate::{
+    batch::PolicyRecord, error::ValactError, guaranteed::lapse_year, projection::{DeathBenefitOption, MonthResult}
+};
+
+// basic policy illustration for a quoting tool, a summary of the inputs and premium and then a ledger by
+// policy year of the account value, cash surrender value and death benefit on the guaranteed, midpoint and
+// current bases, over as many landscape letter pages as it takes
+// the standard helvetica faces are used so no font is embedded, text is the ascii the cli prints
+
+const PAGE_WIDTH: f32 = 792.0;
+const PAGE_HEIGHT: f32 = 612.0;
+const MARGIN: f32 = 36.0;
+const ROW_HEIGHT: f32 = 10.0;
+const LEDGER_SIZE: f32 = 7.5;
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+// helvetica bold advance widths per 1000 of the font size for ascii 32 to 126, the digits and punctuation of
+// amounts are as wide in the regular face so the one table lines up both
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+fn text_width(text: &str, size: f32) -> f32 {
+    let units: u32 = text.bytes()
+        .map(|b| HELVETICA_BOLD.get(usize::from(b.wrapping_sub(32))).map_or(556, |w| u32::from(*w)))
+        .sum();
+    return units as f32 * size / 1000.0;
+}
+
+fn text(content: &mut Content, font: Name, size: f32, x: f32, y: f32, line: &str) {
+    content.begin_text();
+    content.set_font(font, size);
+    content.next_line(x, y);
+    content.show(Str(line.as_bytes()));
+    content.end_text();
+}
+
+// ending at x
+fn right(content: &mut Content, font: Name, size: f32, x: f32, y: f32, line: &str) {
+    text(content, font, size, x - text_width(line, size), y, line);
+}
+
+fn rule(content: &mut Content, y: f32) {
+    content.set_line_width(0.5);
+    content.move_to(MARGIN, y);
+    content.line_to(PAGE_WIDTH - MARGIN, y);
+    content.stroke();
+}
+
+// grouped by thousands to the places given
+fn amount(value: f64, places: usize) -> String {
+    let formatted = format!("{:.*}", places, value.abs());
+    let (whole, fraction) = formatted.split_at(formatted.find('.').unwrap_or(formatted.len()));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    let negative = value < 0.0 && formatted.bytes().any(|b| b.is_ascii_digit() && b != b'0');
+    return format!("{}{}{}", if negative {"-"} else {""}, grouped, fraction);
+}
+
+#[derive(Clone, Copy)]
+struct BasisValues {
+    account_value: f64,
+    cash_surrender_value: f64,
+    death_benefit: f64,
+}
+
+// values at the end of a policy year on the guaranteed, midpoint and current bases, None once a basis has lapsed
+struct LedgerRow {
+    policy_year: i32,
+    attained_age: i32,
+    premium: f64,
+    values: [Option<BasisValues>; 3],
+}
+
+fn ledger_rows(inputs: &PolicyRecord, bases: [&[MonthResult]; 3]) -> Vec<LedgerRow> {
+    let lapse_years = bases.map(lapse_year);
+    let mut rows: Vec<LedgerRow> = Vec::new();
+    for (i, month) in bases[2].iter().enumerate() {
+        let year = month.policy_year;
+        if rows.last().is_none_or(|last| last.policy_year != year) {
+            rows.push(LedgerRow { policy_year: year, attained_age: i32::from(inputs.issue_age.get()) + year - 1, premium: 0.0, values: [None; 3] });
+        }
+        let Some(row) = rows.last_mut() else { continue };
+        row.premium += month.premium;
+        for (basis, values) in row.values.iter_mut().enumerate() {
+            let in_force = lapse_years[basis].is_none_or(|lapse| year < lapse);
+            *values = match bases[basis].get(i) {
+                Some(m) if in_force => Some(BasisValues { account_value: m.value_end, cash_surrender_value: m.cash_surrender_value, death_benefit: m.net_death_benefit }),
+                _ => None,
+            };
+        }
+    }
+    return rows;
+}
+
+// right edges of the year, age and premium columns and of the first column of each basis
+const YEAR_RIGHT: f32 = 62.0;
+const AGE_RIGHT: f32 = 100.0;
+const PREMIUM_RIGHT: f32 = 152.0;
+const BASIS_LEFT: [f32; 3] = [164.0, 364.0, 564.0];
+const BASIS_COLUMN: f32 = 64.0;
+const BASES: [&str; 3] = ["Guaranteed", "Midpoint", "Current"];
+
+fn ledger_header(content: &mut Content, mut y: f32) -> f32 {
+    for (basis, left) in BASES.iter().zip(BASIS_LEFT) {
+        let centre = left + 1.5 * BASIS_COLUMN;
+        text(content, BOLD, LEDGER_SIZE, centre - text_width(basis, LEDGER_SIZE) / 2.0, y, basis);
+    }
+    y -= ROW_HEIGHT;
+    let columns = [("Policy", "Year", YEAR_RIGHT), ("Attained", "Age", AGE_RIGHT), ("Annual", "Premium", PREMIUM_RIGHT)];
+    let basis_columns = BASIS_LEFT.iter().flat_map(|left| {
+        return [("Account", "Value"), ("Surrender", "Value"), ("Death", "Benefit")].into_iter().enumerate()
+            .map(move |(i, (top, bottom))| (top, bottom, left + (i + 1) as f32 * BASIS_COLUMN));
+    });
+    for (top, bottom, x) in columns.into_iter().chain(basis_columns) {
+        right(content, BOLD, LEDGER_SIZE, x, y, top);
+        right(content, BOLD, LEDGER_SIZE, x, y - ROW_HEIGHT, bottom);
+    }
+    y -= ROW_HEIGHT + 4.0;
+    rule(content, y);
+    return y - ROW_HEIGHT;
+}
+
+fn ledger_row(content: &mut Content, y: f32, row: &LedgerRow) {
+    right(content, REGULAR, LEDGER_SIZE, YEAR_RIGHT, y, &row.policy_year.to_string());
+    right(content, REGULAR, LEDGER_SIZE, AGE_RIGHT, y, &row.attained_age.to_string());
+    right(content, REGULAR, LEDGER_SIZE, PREMIUM_RIGHT, y, &amount(row.premium, 0));
+    for (values, left) in row.values.iter().zip(BASIS_LEFT) {
+        let Some(values) = values else {
+            right(content, REGULAR, LEDGER_SIZE, left + 3.0 * BASIS_COLUMN, y, "lapsed");
+            continue;
+        };
+        let columns = [values.account_value, values.cash_surrender_value, values.death_benefit];
+        for (i, value) in columns.iter().enumerate() {
+            right(content, REGULAR, LEDGER_SIZE, left + (i + 1) as f32 * BASIS_COLUMN, y, &amount(*value, 0));
+        }
+    }
+}
+
+fn outcome(name: &str, lapse: Option<i32>, inputs: &PolicyRecord) -> String {
+    return match lapse {
+        Some(year) => format!("{} basis: lapses in policy year {} (age {})", name, year, i32::from(inputs.issue_age.get()) + year - 1),
+        None => format!("{} basis: in force to maturity", name),
+    };
+}
+
+fn summary(content: &mut Content, mut y: f32, inputs: &PolicyRecord, annual_premium: f64, bases: [&[MonthResult]; 3]) -> f32 {
+    text(content, BOLD, 16.0, MARGIN, y - 16.0, "Policy Illustration");
+    y -= 40.0;
+    let option = match inputs.death_benefit_option {
+        DeathBenefitOption::Level => "level (option 1)",
+        DeathBenefitOption::Increasing => "increasing (option 2)",
+        DeathBenefitOption::ReturnOfPremium => "return of premium (option 3)",
+    };
+    let mut lines = vec![
+        format!("Insured: gender {}, risk class {}, issue age {}", inputs.gender, inputs.risk_class, inputs.issue_age),
+        format!("Face amount: {}, death benefit {}", amount(inputs.face_amount, 0), option),
+        format!("Annual premium: {}", amount(annual_premium, 2)),
+    ];
+    lines.extend(BASES.iter().zip(bases).map(|(name, months)| outcome(name, lapse_year(months), inputs)));
+    for line in &lines {
+        text(content, REGULAR, 10.0, MARGIN, y, line);
+        y -= 14.0;
+    }
+    return y - 14.0;
+}
+
+fn footer(content: &mut Content, page: usize, pages: usize) {
+    let note = "Guaranteed values assume the maximum charges and minimum interest of the contract. Midpoint and current values are not guaranteed.";
+    text(content, REGULAR, 7.0, MARGIN, MARGIN - 12.0, note);
+    right(content, REGULAR, 7.0, PAGE_WIDTH - MARGIN, MARGIN - 12.0, &format!("Page {} of {}", page, pages));
+}
+
+// the three projections are of the same policy and premium, current as projected and the others from
+// guaranteed.rs and illustration.rs
+pub fn write_illustration_pdf(path: &str, inputs: &PolicyRecord, annual_premium: f64, guaranteed: &[MonthResult], midpoint: &[MonthResult], current: &[MonthResult]) -> Result<(), ValactError> {
+    let bases = [guaranteed, midpoint, current];
+    let rows = ledger_rows(inputs, bases);
+    let mut rows = rows.iter().peekable();
+    let mut pages: Vec<Content> = Vec::new();
+    while pages.is_empty() || rows.peek().is_some() {
+        let mut content = Content::new();
+        let mut y = PAGE_HEIGHT - MARGIN;
+        if pages.is_empty() {
+            y = summary(&mut content, y, inputs, annual_premium, bases);
+        }
+        y = ledger_header(&mut content, y);
+        while y >= MARGIN && let Some(row) = rows.next() {
+            ledger_row(&mut content, y, row);
+            y -= ROW_HEIGHT;
+        }
+        pages.push(content);
+    }
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let regular_id = Ref::new(3);
+    let bold_id = Ref::new(4);
+    let page_ids: Vec<Ref> = (0..pages.len() as i32).map(|i| Ref::new(5 + 2 * i)).collect();
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).kids(page_ids.iter().copied()).count(pages.len() as i32);
+    pdf.type1_font(regular_id).base_font(Name(b"Helvetica")).encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_id).base_font(Name(b"Helvetica-Bold")).encoding_predefined(Name(b"WinAnsiEncoding"));
+    let count = pages.len();
+    for (i, (mut content, page_id)) in pages.into_iter().zip(page_ids).enumerate() {
+        footer(&mut content, i + 1, count);
+        let content_id = Ref::new(page_id.get() + 1);
+        let mut page = pdf.page(page_id);
+        page.parent(page_tree_id).media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT)).contents(content_id);
+        page.resources().fonts().pair(REGULAR, regular_id).pair(BOLD, bold_id);
+        page.finish();
+        pdf.stream(content_id, &content.finish());
+    }
+    std::fs::write(path, pdf.finish())?;
+    return Ok(());
+}
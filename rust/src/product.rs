@@ -0,0 +1,372 @@
+use std::{
+    fs, path::Path
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::rates::{
+    projection_years, read_aa, read_cell_coi_table, read_gen_rc_ia_py, read_ia, read_ia_py, read_py, DataDir, RateSet
+};
+
+use crate::{age::{AttainedAge, IssueAge}, error::ValactError};
+
+// product specification, where every charge in the rate set comes from, read from toml (or json)
+// each charge is a constant, a table or a formula, e.g. the built in product written out in full:
+//
+//   premium_load = { constant = 0.06 }
+//   policy_fee = { constant = 120 }
+//   unit_load = { table = "unit_load", layout = "issue_age_policy_year" }
+//   corridor_factor = { table = "corridor_factors", layout = "attained_age", default = 1 }
+//   naar_discount = { formula = "1.01 ^ (-1/12)" }
+//   coi = { table = "coi", layout = "cell" }
+//   interest = { formula = "1.03 ^ (1/12) - 1" }
+//   surrender_charge = { table = "surrender_charge", layout = "issue_age_policy_year", optional = true }
+//
+// charges left out of the file are as above and maturity_age can be given to override --maturity-age
+// units are those of the rate set, premium loads a fraction, policy fees per year, unit loads, coi and surrender
+// charges per 1000, and interest and the naar discount monthly
+// a table is a name in the data directory (csv or json as for the other tables) or a path, default fills policy years
+// the table has no rate for, and an optional table that does not exist gives default for every year
+// annual = true takes the constant, table or formula as annual effective rates and converts them to monthly, so
+// graded credited rates can be given as declared, e.g. interest = { table = "credited_rates", layout =
+// "policy_year", annual = true }
+// formulas are evaluated each policy year with + - * / ^, parentheses, min(a, b), max(a, b) and the variables
+// policy_year, issue_age and attained_age
+// a coi table in the cell layout still gives way to a per cell coi file in the data directory, see
+// DataDir::cell_coi_path
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TableLayout {
+    // Issue_Age, Policy_Year, Rate
+    IssueAgePolicyYear,
+    // Gender, Risk_Class, Issue_Age, Policy_Year, Rate
+    Cell,
+    // Attained_Age, Rate
+    AttainedAge,
+    // Policy_Year, Rate
+    PolicyYear,
+    // Issue_Age, Rate, the same rate every policy year
+    IssueAge,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChargeSpec {
+    pub constant: Option<f64>,
+    pub table: Option<String>,
+    pub layout: Option<TableLayout>,
+    pub formula: Option<String>,
+    pub default: f64,
+    pub optional: bool,
+    pub annual: bool,
+}
+
+impl ChargeSpec {
+    pub fn constant(rate: f64) -> Self {
+        return ChargeSpec { constant: Some(rate), ..Default::default() };
+    }
+
+    pub fn table(table: &str, layout: TableLayout) -> Self {
+        return ChargeSpec { table: Some(table.to_string()), layout: Some(layout), ..Default::default() };
+    }
+
+    pub fn formula(formula: &str) -> Self {
+        return ChargeSpec { formula: Some(formula.to_string()), ..Default::default() };
+    }
+
+    fn table_path(&self, data_dir: &DataDir) -> Option<String> {
+        let table = self.table.as_deref()?;
+        return Some(if Path::new(table).extension().is_some() {table.to_string()} else {data_dir.table_path(table)});
+    }
+
+    // rates by policy year for the cell
+    pub fn rates(&self, data_dir: &DataDir, name: &str, gender: &str, risk_class: &str, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+        let rates = self.stated_rates(data_dir, name, gender, risk_class, issue_age, years)?;
+        if self.annual {
+            return Ok(rates.into_iter().map(|rate| (1.0 + rate).powf(1.0 / 12.0) - 1.0).collect());
+        }
+        return Ok(rates);
+    }
+
+    fn stated_rates(&self, data_dir: &DataDir, name: &str, gender: &str, risk_class: &str, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+        match (self.constant, self.table_path(data_dir), &self.formula) {
+            (Some(rate), None, None) => return Ok(vec![rate; years]),
+            (None, Some(path), None) => {
+                if self.optional && !Path::new(&path).exists() {
+                    return Ok(vec![self.default; years]);
+                }
+                let layout = self.layout.ok_or_else(|| format!("{}: table {} needs a layout", name, path))?;
+                return match layout {
+                    TableLayout::IssueAgePolicyYear => read_ia_py(&path, self.default, issue_age, years),
+                    TableLayout::Cell => read_gen_rc_ia_py(&path, self.default, gender, risk_class, issue_age, years),
+                    TableLayout::AttainedAge => read_aa(&path, self.default, issue_age, years),
+                    TableLayout::PolicyYear => read_py(&path, self.default, years),
+                    TableLayout::IssueAge => Ok(vec![read_ia(&path, self.default, issue_age)?; years]),
+                };
+            }
+            (None, None, Some(formula)) => {
+                let expr = Parser::parse(formula).map_err(|e| format!("{}: formula '{}': {}", name, formula, e))?;
+                let mut rates = Vec::with_capacity(years);
+                for year in 0..years {
+                    let policy_year = year as f64 + 1.0;
+                    let vars = Vars { policy_year, issue_age: f64::from(issue_age.get()), attained_age: f64::from(issue_age.get()) + policy_year - 1.0 };
+                    rates.push(expr.eval(&vars));
+                }
+                return Ok(rates);
+            }
+            _ => return Err(format!("{}: give exactly one of constant, table or formula", name).into()),
+        }
+    }
+}
+
+// charges already looked up from tables for a cell, e.g. by tables::RateTables, each running to maturity
+pub struct TableRates {
+    pub unit_loads: Vec<f64>,
+    pub corr_facts: Vec<f64>,
+    pub coi_rates: Vec<f64>,
+    pub surrender_charges: Vec<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProductSpec {
+    pub name: Option<String>,
+    pub maturity_age: Option<AttainedAge>,
+    pub premium_load: ChargeSpec,
+    pub policy_fee: ChargeSpec,
+    pub unit_load: ChargeSpec,
+    pub corridor_factor: ChargeSpec,
+    pub naar_discount: ChargeSpec,
+    pub coi: ChargeSpec,
+    pub interest: ChargeSpec,
+    pub surrender_charge: ChargeSpec,
+}
+
+impl Default for ProductSpec {
+    fn default() -> Self {
+        return ProductSpec {
+            name: None,
+            maturity_age: None,
+            premium_load: ChargeSpec::constant(0.06),
+            policy_fee: ChargeSpec::constant(120.0),
+            unit_load: ChargeSpec::table("unit_load", TableLayout::IssueAgePolicyYear),
+            corridor_factor: ChargeSpec { default: 1.0, ..ChargeSpec::table("corridor_factors", TableLayout::AttainedAge) },
+            naar_discount: ChargeSpec::formula("1.01 ^ (-1/12)"),
+            coi: ChargeSpec::table("coi", TableLayout::Cell),
+            interest: ChargeSpec::formula("1.03 ^ (1/12) - 1"),
+            surrender_charge: ChargeSpec { optional: true, ..ChargeSpec::table("surrender_charge", TableLayout::IssueAgePolicyYear) },
+        };
+    }
+}
+
+impl ProductSpec {
+    // the tables in the data directory the spec reads, by name, whose versions split policies by issue date
+    pub fn table_names(&self) -> Vec<&str> {
+        let charges = [
+            &self.premium_load, &self.policy_fee, &self.unit_load, &self.corridor_factor, &self.naar_discount, &self.coi, &self.interest, &self.surrender_charge,
+        ];
+        return charges.into_iter().filter_map(|charge| charge.table.as_deref()).filter(|table| Path::new(table).extension().is_none()).collect();
+    }
+
+    // toml unless the file ends in .json
+    pub fn from_file(path: &str) -> Result<ProductSpec, ValactError> {
+        let text = fs::read_to_string(path)?;
+        if path.ends_with(".json") {
+            return Ok(serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?);
+        }
+        return Ok(toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?);
+    }
+
+    // the cell's rates with any tables read from data_dir
+    pub fn rate_set(&self, data_dir: &DataDir, gender: &str, risk_class: &str, issue_age: IssueAge, maturity_age: AttainedAge) -> Result<RateSet, ValactError> {
+        let maturity_age = self.maturity_age.unwrap_or(maturity_age);
+        let years = projection_years(issue_age, maturity_age)?;
+        let coi_rates = match data_dir.cell_coi_path(gender, risk_class) {
+            Some(path) if self.coi.layout == Some(TableLayout::Cell) && self.coi.table.is_some() => {
+                read_cell_coi_table(&path)?.rates_for_issue_age(issue_age, self.coi.default, years)
+            }
+            _ => self.coi.rates(data_dir, "coi", gender, risk_class, issue_age, years)?,
+        };
+        let tables = TableRates {
+            unit_loads: self.unit_load.rates(data_dir, "unit_load", gender, risk_class, issue_age, years)?,
+            corr_facts: self.corridor_factor.rates(data_dir, "corridor_factor", gender, risk_class, issue_age, years)?,
+            coi_rates,
+            surrender_charges: self.surrender_charge.rates(data_dir, "surrender_charge", gender, risk_class, issue_age, years)?,
+        };
+        return self.build_rate_set(data_dir, gender, risk_class, issue_age, maturity_age, tables);
+    }
+
+    // the premium load, policy fee, naar discount and interest from the spec combined with the rest
+    pub fn build_rate_set(&self, data_dir: &DataDir, gender: &str, risk_class: &str, issue_age: IssueAge, maturity_age: AttainedAge, tables: TableRates) -> Result<RateSet, ValactError> {
+        let years = tables.unit_loads.len().min(tables.corr_facts.len()).min(tables.coi_rates.len()).min(tables.surrender_charges.len());
+        return Ok(RateSet {
+            maturity_age,
+            premium_loads: self.premium_load.rates(data_dir, "premium_load", gender, risk_class, issue_age, years)?,
+            policy_fees: self.policy_fee.rates(data_dir, "policy_fee", gender, risk_class, issue_age, years)?,
+            unit_loads: tables.unit_loads,
+            corr_facts: tables.corr_facts,
+            naar_discs: self.naar_discount.rates(data_dir, "naar_discount", gender, risk_class, issue_age, years)?,
+            coi_rates: tables.coi_rates,
+            interest_rates: self.interest.rates(data_dir, "interest", gender, risk_class, issue_age, years)?,
+            surrender_charges: tables.surrender_charges,
+        });
+    }
+}
+
+struct Vars {
+    policy_year: f64,
+    issue_age: f64,
+    attained_age: f64,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(f64),
+    PolicyYear,
+    IssueAge,
+    AttainedAge,
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Min(Box<Expr>, Box<Expr>),
+    Max(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, vars: &Vars) -> f64 {
+        return match self {
+            Expr::Number(x) => *x,
+            Expr::PolicyYear => vars.policy_year,
+            Expr::IssueAge => vars.issue_age,
+            Expr::AttainedAge => vars.attained_age,
+            Expr::Neg(a) => -a.eval(vars),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(vars), b.eval(vars));
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    _ => a.powf(b),
+                }
+            }
+            Expr::Min(a, b) => a.eval(vars).min(b.eval(vars)),
+            Expr::Max(a, b) => a.eval(vars).max(b.eval(vars)),
+        };
+    }
+}
+
+// recursive descent, ^ binds tightest and to the right, then unary minus, then * and /, then + and -
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    text: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(text: &'a str) -> Result<Expr, String> {
+        let mut parser = Parser { chars: text.chars().collect(), pos: 0, text };
+        let expr = parser.sum()?;
+        parser.skip_space();
+        if parser.pos < parser.chars.len() {
+            return Err(format!("unexpected '{}' at {}", parser.chars[parser.pos], parser.pos + 1));
+        }
+        return Ok(expr);
+    }
+
+    fn skip_space(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_space();
+        return self.chars.get(self.pos).copied();
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() != Some(c) {
+            return Err(format!("expected '{}' at {} in {}", c, self.pos + 1, self.text));
+        }
+        self.pos += 1;
+        return Ok(());
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.pos += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        return Ok(expr);
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.pos += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        return Ok(expr);
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some('-') {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        return self.power();
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.peek() == Some('^') {
+            self.pos += 1;
+            return Ok(Expr::Binary('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        return Ok(base);
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.sum()?;
+                self.expect(')')?;
+                return Ok(expr);
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit() || *c == '.' || *c == 'e' || *c == 'E') {
+                    self.pos += 1;
+                }
+                let number: String = self.chars[start..self.pos].iter().collect();
+                return number.parse().map(Expr::Number).map_err(|_| format!("bad number '{}'", number));
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                return match name.as_str() {
+                    "policy_year" => Ok(Expr::PolicyYear),
+                    "issue_age" => Ok(Expr::IssueAge),
+                    "attained_age" => Ok(Expr::AttainedAge),
+                    "min" | "max" => {
+                        self.expect('(')?;
+                        let a = self.sum()?;
+                        self.expect(',')?;
+                        let b = self.sum()?;
+                        self.expect(')')?;
+                        Ok(if name == "min" {Expr::Min(Box::new(a), Box::new(b))} else {Expr::Max(Box::new(a), Box::new(b))})
+                    }
+                    _ => Err(format!("unknown name '{}'", name)),
+                };
+            }
+            Some(c) => return Err(format!("unexpected '{}' at {}", c, self.pos + 1)),
+            None => return Err("unexpected end".to_string()),
+        }
+    }
+}
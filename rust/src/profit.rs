@@ -0,0 +1,157 @@
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assumptions::ProfitAssumptions, decrements::ExpectedMonth, error::ValactError, projection::Timestep, solver::{goal_seek_with, Method, SolverOptions}
+};
+
+// book profit from the expected cash flows per policy issued
+// the reserve is the account value held for the policies in force unless statutory reserves are given, the policy
+// charges are the part of the premium and interest the account value does not keep so they come through in the
+// increase in reserve
+// investment income is at the earned rate on the reserve at the start of each step plus the premium less
+// expenses paid at the start of it
+// profit = premium + investment income - benefits - expenses - commission - taxes - increase in reserve
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct YearProfit {
+    #[serde(rename="Policy_Year")]
+    pub policy_year: i32,
+    #[serde(rename="Inforce_End")]
+    pub inforce_end: f64,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Investment_Income")]
+    pub investment_income: f64,
+    // death, surrender and maturity benefits and withdrawals
+    #[serde(rename="Benefits")]
+    pub benefits: f64,
+    // acquisition, maintenance and percent of premium expenses
+    #[serde(rename="Expenses")]
+    pub expenses: f64,
+    #[serde(rename="Commission")]
+    pub commission: f64,
+    // premium tax and dac tax
+    #[serde(rename="Taxes")]
+    pub taxes: f64,
+    #[serde(rename="Reserve_End")]
+    pub reserve_end: f64,
+    #[serde(rename="Increase_In_Reserve")]
+    pub increase_in_reserve: f64,
+    #[serde(rename="Book_Profit")]
+    pub book_profit: f64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ProfitSummary {
+    pub hurdle_rate: f64,
+    // profits discounted from the end of each policy year, premiums from the start
+    pub pv_profits: f64,
+    pub pv_premiums: f64,
+    // pv of profits as a fraction of pv of premiums
+    pub profit_margin: f64,
+    // None when profits do not change sign, e.g. a product that makes money from the first year
+    pub irr: Option<f64>,
+}
+
+// what a premium is solved to, margin is pv of profits over pv of premiums and irr the rate the pv of profits is
+// zero at, which is solved as a zero pv at that rate so it works even where profits never change sign
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ProfitTarget {
+    ProfitMargin(f64),
+    Irr(f64),
+}
+
+// reserves are held per policy issued at the end of each step, see reserve::reserves_held
+pub fn profit_by_year(expected: &[ExpectedMonth], reserves: Option<&[f64]>, timestep: Timestep, assumptions: &ProfitAssumptions) -> Vec<YearProfit> {
+    let earned = (1.0 + assumptions.earned_rate).powf(1.0 / f64::from(timestep.steps_per_year())) - 1.0;
+    let mut years: Vec<YearProfit> = Vec::new();
+    let mut reserve = 0.0;
+
+    for (i, month) in expected.iter().enumerate() {
+        if years.last().is_none_or(|year| year.policy_year != month.policy_year) {
+            years.push(YearProfit { policy_year: month.policy_year, ..Default::default() });
+        }
+        let year = years.last_mut().unwrap();
+        let expenses = month.acquisition_expense + month.maintenance_expense + month.premium_expense;
+        let taxes = month.premium_tax + month.dac_tax;
+        let benefits = month.death_benefits + month.surrender_benefits + month.maturity_benefits + month.withdrawal;
+        let investment_income = earned * (reserve + month.premium - expenses - month.commission - month.premium_tax);
+        // the reserve is released at maturity with the maturity benefit paid out of it
+        let held = reserves.map_or(month.account_value, |reserves| reserves[i]);
+        let reserve_end = if month.maturity_benefits > 0.0 {0.0} else {held};
+        let increase_in_reserve = reserve_end - reserve;
+
+        year.inforce_end = month.inforce_end;
+        year.premium += month.premium;
+        year.investment_income += investment_income;
+        year.benefits += benefits;
+        year.expenses += expenses;
+        year.commission += month.commission;
+        year.taxes += taxes;
+        year.reserve_end = reserve_end;
+        year.increase_in_reserve += increase_in_reserve;
+        year.book_profit += month.premium + investment_income - benefits - expenses - month.commission - taxes - increase_in_reserve;
+        reserve = reserve_end;
+    }
+    return years;
+}
+
+// value at issue of profits at the end of each year
+fn present_value(profits: &[f64], rate: f64) -> f64 {
+    return profits.iter().enumerate().fold(0.0, |pv, (t, profit)| pv + profit / (1.0 + rate).powi(t as i32 + 1));
+}
+
+// rate at which the pv of profits is zero, found between -99% and wherever bracketing gets to
+pub fn irr(profits: &[f64]) -> Option<f64> {
+    let positive = profits.iter().any(|p| *p > 0.0);
+    let negative = profits.iter().any(|p| *p < 0.0);
+    if !positive || !negative {
+        return None;
+    }
+    // pv falls as the rate rises for profits that start negative, so the metric solved for is its negative
+    let metric = |rate: f64| -> Result<f64, ValactError> { return Ok(-present_value(profits, rate)); };
+    let root = goal_seek_with(metric, 0.0, (-0.99, 1.0), &SolverOptions::new(Method::Brent, 1e-8)).ok()?;
+    return if root.converged {Some(root.x)} else {None};
+}
+
+pub fn profit_summary(years: &[YearProfit], hurdle_rate: f64) -> ProfitSummary {
+    let profits: Vec<f64> = years.iter().map(|y| y.book_profit).collect();
+    let pv_profits = present_value(&profits, hurdle_rate);
+    let pv_premiums = years.iter().enumerate().fold(0.0, |pv, (t, year)| pv + year.premium / (1.0 + hurdle_rate).powi(t as i32));
+    return ProfitSummary {
+        hurdle_rate,
+        pv_profits,
+        pv_premiums,
+        profit_margin: if pv_premiums > 0.0 {pv_profits / pv_premiums} else {0.0},
+        irr: irr(&profits),
+    };
+}
+
+// the premium rate at which the profits by year from profits_at hit target, searched from bounds upward
+pub fn solve_for_profit_target<F>(mut profits_at: F, target: ProfitTarget, hurdle_rate: f64, bounds: (f64, f64)) -> Result<f64, ValactError>
+where
+    F: FnMut(f64) -> Result<Vec<YearProfit>, ValactError>,
+{
+    let (rate, goal) = match target {
+        ProfitTarget::ProfitMargin(margin) => (hurdle_rate, margin),
+        ProfitTarget::Irr(irr) => (irr, 0.0),
+    };
+    let metric = |x: f64| -> Result<f64, ValactError> {
+        let summary = profit_summary(&profits_at(x)?, rate);
+        return Ok(match target {
+            ProfitTarget::ProfitMargin(_) => summary.profit_margin,
+            ProfitTarget::Irr(_) => summary.pv_profits,
+        });
+    };
+    return Ok(goal_seek_with(metric, goal, bounds, &SolverOptions::new(Method::Brent, 1e-6))?.x);
+}
+
+pub fn write_profit_csv(path: &str, years: &[YearProfit]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for year in years {
+        wtr.serialize(year)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
@@ -0,0 +1,877 @@
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    age::{AttainedAge, IssueAge},
+    error::ValactError,
+    indexed::{account_buckets, Account},
+    rates::{projection_years, RateSet},
+    riders::{RiderContext, Riders},
+    rounding::RoundingPolicy,
+    schedule::{
+        face_change_in_year, option_change_in_year, scheduled_in_year, withdrawals_in_year, FaceChange, LoanTerms, OptionChange, ScheduledAmount, Withdrawal
+    },
+    solver::{goal_seek, goal_seek_with, Method, SolverOptions},
+    variable::MortalityExpense,
+};
+
+// length of each step of the projection loop
+// annual is for quick pricing checks, it is close to but will not match the monthly values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Timestep {
+    #[default]
+    Monthly,
+    Annual,
+}
+
+impl Timestep {
+    pub fn steps_per_year(self) -> i32 {
+        return match self {
+            Timestep::Monthly => 12,
+            Timestep::Annual => 1,
+        };
+    }
+}
+
+// how often premiums are paid within a policy year
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PremiumMode {
+    #[default]
+    Annual,
+    Semiannual,
+    Quarterly,
+    Monthly,
+}
+
+impl PremiumMode {
+    pub fn payments_per_year(self) -> i32 {
+        return match self {
+            PremiumMode::Annual => 1,
+            PremiumMode::Semiannual => 2,
+            PremiumMode::Quarterly => 4,
+            PremiumMode::Monthly => 12,
+        };
+    }
+}
+
+// which policy years premiums are paid in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PremiumPattern {
+    // every year to maturity
+    #[default]
+    Level,
+    // one premium at issue, the premium mode is ignored
+    SinglePay,
+    // up to but not including this attained age
+    LevelToAge(AttainedAge),
+    // for this many policy years
+    NPay(u16),
+}
+
+impl PremiumPattern {
+    pub fn pays_in_year(self, issue_age: IssueAge, policy_year: usize) -> bool {
+        return match self {
+            PremiumPattern::Level => true,
+            PremiumPattern::SinglePay => policy_year == 1,
+            PremiumPattern::LevelToAge(age) => (i32::from(issue_age.get()) + policy_year as i32 - 1) < i32::from(age.get()),
+            PremiumPattern::NPay(years) => policy_year <= usize::from(years),
+        };
+    }
+}
+
+// death benefit before the corridor, A/B/C on some admin systems
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum DeathBenefitOption {
+    // option 1, the face amount
+    #[default]
+    #[serde(rename = "1", alias = "A", alias = "Level")]
+    Level,
+    // option 2, face amount plus account value
+    #[serde(rename = "2", alias = "B", alias = "Increasing")]
+    Increasing,
+    // option 3, face amount plus premiums paid less withdrawals
+    #[serde(rename = "3", alias = "C", alias = "ReturnOfPremium")]
+    ReturnOfPremium,
+}
+
+impl DeathBenefitOption {
+    pub fn number(self) -> i8 {
+        return match self {
+            DeathBenefitOption::Level => 1,
+            DeathBenefitOption::Increasing => 2,
+            DeathBenefitOption::ReturnOfPremium => 3,
+        };
+    }
+}
+
+// options for how a projection is run, the default matches the original monthly projection
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ProjectionConfig {
+    pub timestep: Timestep,
+    #[serde(default)]
+    pub death_benefit_option: DeathBenefitOption,
+    pub premium_mode: PremiumMode,
+    #[serde(default)]
+    pub premium_pattern: PremiumPattern,
+    // each modal payment is the annual premium times this, e.g. 0.0875 for monthly
+    // None means no modal loading, the annual premium is split evenly
+    #[serde(default)]
+    pub modal_factor: Option<f64>,
+    // taken at the start of the policy year and capped at the account value
+    // a withdrawal reduces the face amount under option 1 and the premiums added back under option 3
+    #[serde(default)]
+    pub withdrawals: Vec<Withdrawal>,
+    // loans are taken and repaid in cash at the start of the policy year, a loan is limited to the
+    // cash surrender value not already borrowed and a repayment to the balance
+    #[serde(default)]
+    pub loans: Vec<ScheduledAmount>,
+    #[serde(default)]
+    pub loan_repayments: Vec<ScheduledAmount>,
+    #[serde(default)]
+    pub loan_terms: LoanTerms,
+    // applied at the start of the policy year, before any withdrawal that year
+    // unit loads and surrender charges follow the face amount in force
+    #[serde(default)]
+    pub face_changes: Vec<FaceChange>,
+    // a switch resets the face amount so the death benefit is unchanged at the switch, e.g. option 2 to 1
+    // adds the account value to the face amount
+    #[serde(default)]
+    pub option_changes: Vec<OptionChange>,
+    // annual effective rate credited in each month from the start of the projection (issue, or the inforce
+    // duration) in place of the interest rate table, e.g. one path of an interest rate scenario; months past the
+    // end of the path stay at its last rate, empty uses the table
+    #[serde(default)]
+    pub interest_path: Vec<f64>,
+    // fixed, index and fund accounts the account value is split across, see indexed::Account
+    // empty keeps the whole account value in one fixed account
+    #[serde(default)]
+    pub accounts: Vec<Account>,
+    // m&e charge on the fund accounts of variable universal life, see variable.rs
+    #[serde(default)]
+    pub mortality_expense: MortalityExpense,
+    // charged and reported every step, see riders::Rider; riders are code rather than data so are not serialized
+    #[serde(skip)]
+    pub riders: Riders,
+    // applied to the solved premium, and to each amount the projection works out if rounding.amounts is set
+    #[serde(default)]
+    pub rounding: RoundingPolicy,
+    // project an existing policy forward from where it stands rather than from issue
+    #[serde(default)]
+    pub inforce: Option<InforceState>,
+}
+
+// an inforce policy at its current duration, the policy months completed since issue, the projection starting
+// with the next month; withdrawals, loans and face changes in the config are still by policy year from issue
+// surrender_charge is what the policy would be charged on surrender now, the rest of the rate set's schedule is
+// scaled to it, or if the schedule has already run off it is charged to the end of the current policy year
+// premiums_paid is premiums to date less withdrawals, what the option 3 death benefit adds to the face amount
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InforceState {
+    pub duration: i32,
+    pub account_value: f64,
+    pub loan_balance: f64,
+    pub surrender_charge: Option<f64>,
+    pub premiums_paid: f64,
+}
+
+impl InforceState {
+    // projection step the policy picks up at, a duration has to fall on a step boundary
+    fn start_step(&self, projection_years: usize, months_per_step: i32) -> Result<i32, ValactError> {
+        if self.duration < 0 || self.duration >= 12 * projection_years as i32 {
+            return Err(format!("inforce duration of {} months is outside the {} policy years to maturity", self.duration, projection_years).into());
+        }
+        if self.duration % months_per_step != 0 {
+            return Err(format!("inforce duration of {} months is not a whole number of {} month steps", self.duration, months_per_step).into());
+        }
+        if self.loan_balance < 0.0 {
+            return Err(format!("inforce loan balance {} is negative", self.loan_balance).into());
+        }
+        return Ok(self.duration / months_per_step);
+    }
+
+    // surrender charges per 1000 by policy year with the current charge in place, policy_year is the current one
+    fn surrender_charges(&self, rates: &RateSet, policy_year: usize, face_amount: f64) -> Vec<f64> {
+        let mut charges = rates.surrender_charges.clone();
+        let Some(charge) = self.surrender_charge else {
+            return charges;
+        };
+        if face_amount <= 0.0 {
+            return charges;
+        }
+        let per_1000 = charge * 1000.0 / face_amount;
+        let scheduled = charges[policy_year - 1];
+        if scheduled > 0.0 {
+            for rate in charges.iter_mut().skip(policy_year - 1) {
+                *rate *= per_1000 / scheduled;
+            }
+        } else {
+            charges[policy_year - 1] = per_1000;
+        }
+        return charges;
+    }
+}
+
+impl ProjectionConfig {
+    pub fn modal_factor(&self) -> f64 {
+        return self.modal_factor.unwrap_or(1.0 / f64::from(self.premium_mode.payments_per_year()));
+    }
+
+    // premium paid at each modal date for a given annual premium
+    pub fn modal_premium(&self, annual_premium: f64) -> f64 {
+        return annual_premium * self.modal_factor();
+    }
+}
+
+// values for one month of the projection, columns match the output of illustrate() in approach1.py
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct MonthResult {
+    #[serde(rename="Policy_Month")]
+    pub policy_month: i32,
+    #[serde(rename="Policy_Year")]
+    pub policy_year: i32,
+    #[serde(rename="Month_In_Policy_Year")]
+    pub month_in_policy_year: i32,
+    #[serde(rename="Value_Start")]
+    pub value_start: f64,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Premium_Load")]
+    pub premium_load: f64,
+    #[serde(rename="Withdrawal")]
+    pub withdrawal: f64,
+    #[serde(rename="Face_Amount")]
+    pub face_amount: f64,
+    #[serde(rename="Death_Benefit_Option")]
+    pub death_benefit_option: DeathBenefitOption,
+    #[serde(rename="Expense_Charge")]
+    pub expense_charge: f64,
+    #[serde(rename="Death_Benefit")]
+    pub death_benefit: f64,
+    #[serde(rename="NAAR")]
+    pub naar: f64,
+    #[serde(rename="COI_Charge")]
+    pub coi_charge: f64,
+    // all riders' charges, benefits and credits for the step
+    #[serde(rename="Rider_Charge")]
+    pub rider_charge: f64,
+    #[serde(rename="Rider_Benefit")]
+    pub rider_benefit: f64,
+    #[serde(rename="Rider_Credit")]
+    pub rider_credit: f64,
+    #[serde(rename="Interest")]
+    pub interest: f64,
+    #[serde(rename="Value_End")]
+    pub value_end: f64,
+    // split of value_end between the fixed account, the index accounts and the variable funds
+    #[serde(rename="Fixed_Account_Value")]
+    pub fixed_account_value: f64,
+    #[serde(rename="Index_Account_Value")]
+    pub index_account_value: f64,
+    #[serde(rename="Variable_Account_Value")]
+    pub variable_account_value: f64,
+    // on the variable funds, interest above includes their returns net of fund expenses
+    #[serde(rename="ME_Charge")]
+    pub mortality_expense_charge: f64,
+    #[serde(rename="Surrender_Charge")]
+    pub surrender_charge: f64,
+    #[serde(rename="Loan")]
+    pub loan: f64,
+    #[serde(rename="Loan_Repayment")]
+    pub loan_repayment: f64,
+    #[serde(rename="Loan_Interest")]
+    pub loan_interest: f64,
+    #[serde(rename="Loan_Balance")]
+    pub loan_balance: f64,
+    // account value released and amount paid on an accelerated benefit, and the lien against the policy
+    #[serde(rename="Accelerated_Value")]
+    pub accelerated_value: f64,
+    #[serde(rename="Accelerated_Benefit")]
+    pub accelerated_benefit: f64,
+    #[serde(rename="Lien_Balance")]
+    pub lien_balance: f64,
+    // net of surrender charge, loan balance and lien
+    #[serde(rename="Cash_Surrender_Value")]
+    pub cash_surrender_value: f64,
+    #[serde(rename="Net_Death_Benefit")]
+    pub net_death_benefit: f64,
+    // set by tax7702::mark_mec, the projection itself does not run the seven-pay test
+    #[serde(rename="MEC")]
+    pub mec: bool,
+    // set by nlg::mark_shadow_account, zero when there is no no-lapse guarantee
+    #[serde(rename="Shadow_Account_Value")]
+    pub shadow_account_value: f64,
+}
+
+// every rate and intermediate amount behind a step of the projection, in the order the loop works them out, so
+// a step can be tied out by hand or against a spreadsheet
+// rates are as held in the rate set (policy fee and unit load annual, coi annual per 1000) and the naar discount
+// and interest rate are for the step
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ExplainMonth {
+    #[serde(rename="Policy_Month")]
+    pub policy_month: i32,
+    #[serde(rename="Policy_Year")]
+    pub policy_year: i32,
+    #[serde(rename="Attained_Age")]
+    pub attained_age: i32,
+    #[serde(rename="Value_Start")]
+    pub value_start: f64,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Premium_Load_Rate")]
+    pub premium_load_rate: f64,
+    #[serde(rename="Premium_Load")]
+    pub premium_load: f64,
+    #[serde(rename="Withdrawal")]
+    pub withdrawal: f64,
+    #[serde(rename="Accelerated_Value")]
+    pub accelerated_value: f64,
+    #[serde(rename="Face_Amount")]
+    pub face_amount: f64,
+    #[serde(rename="Policy_Fee")]
+    pub policy_fee: f64,
+    #[serde(rename="Unit_Load_Rate")]
+    pub unit_load_rate: f64,
+    #[serde(rename="Expense_Charge")]
+    pub expense_charge: f64,
+    #[serde(rename="AV_For_DB")]
+    pub av_for_db: f64,
+    // the benefit before the corridor, face plus the account value or premiums under options 2 and 3
+    #[serde(rename="Specified_Amount")]
+    pub specified_amount: f64,
+    #[serde(rename="Corridor_Factor")]
+    pub corridor_factor: f64,
+    #[serde(rename="Corridor_Death_Benefit")]
+    pub corridor_death_benefit: f64,
+    #[serde(rename="Death_Benefit")]
+    pub death_benefit: f64,
+    #[serde(rename="NAAR_Discount")]
+    pub naar_discount: f64,
+    #[serde(rename="Discounted_Death_Benefit")]
+    pub discounted_death_benefit: f64,
+    #[serde(rename="NAAR")]
+    pub naar: f64,
+    #[serde(rename="COI_Rate")]
+    pub coi_rate: f64,
+    #[serde(rename="COI_Charge")]
+    pub coi_charge: f64,
+    #[serde(rename="Rider_Charge")]
+    pub rider_charge: f64,
+    #[serde(rename="Rider_Credit")]
+    pub rider_credit: f64,
+    #[serde(rename="AV_For_Interest")]
+    pub av_for_interest: f64,
+    #[serde(rename="Interest_Rate")]
+    pub interest_rate: f64,
+    #[serde(rename="Loaned_Value")]
+    pub loaned_value: f64,
+    // the variable funds' m&e charge, their returns are in interest
+    #[serde(rename="ME_Charge")]
+    pub mortality_expense_charge: f64,
+    #[serde(rename="Interest")]
+    pub interest: f64,
+    #[serde(rename="Value_End")]
+    pub value_end: f64,
+}
+
+// monthly results rolled up to a policy year, flows are summed and values taken at the ends of the year
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct YearResult {
+    #[serde(rename="Policy_Year")]
+    pub policy_year: i32,
+    #[serde(rename="Attained_Age")]
+    pub attained_age: i32,
+    #[serde(rename="Value_Start")]
+    pub value_start: f64,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Premium_Load")]
+    pub premium_load: f64,
+    #[serde(rename="Withdrawal")]
+    pub withdrawal: f64,
+    #[serde(rename="Expense_Charge")]
+    pub expense_charge: f64,
+    #[serde(rename="COI_Charge")]
+    pub coi_charge: f64,
+    #[serde(rename="Interest")]
+    pub interest: f64,
+    #[serde(rename="Death_Benefit")]
+    pub death_benefit: f64,
+    #[serde(rename="Value_End")]
+    pub value_end: f64,
+    #[serde(rename="Loan_Balance")]
+    pub loan_balance: f64,
+    #[serde(rename="Cash_Surrender_Value")]
+    pub cash_surrender_value: f64,
+    #[serde(rename="Net_Death_Benefit")]
+    pub net_death_benefit: f64,
+}
+
+// loop shared by the terminal value and detail projections
+// on_month is called with every step's values, for the terminal value it does nothing and gets optimized away
+// runs from issue to the maturity age the rates were assembled for
+// the rate set holds annual charges and monthly naar discount and interest, these are scaled to the step length
+fn project<F: FnMut(&MonthResult, &ExplainMonth)>(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig, mut on_month: F) -> Result<f64, ValactError> {
+    let projection_years = projection_years(issue_age, rates.maturity_age)?;
+    if rates.years() < projection_years {
+        return Err(format!("rates cover {} policy years but issue age {} to maturity age {} needs {}", rates.years(), issue_age, rates.maturity_age, projection_years).into());
+    }
+    let steps_per_year = config.timestep.steps_per_year();
+    let months_per_step = 12 / steps_per_year;
+    let months_per_payment = 12 / config.premium_mode.payments_per_year();
+    let modal_premium = config.modal_premium(annual_premium);
+    let start = match &config.inforce {
+        Some(inforce) => inforce.start_step(projection_years, months_per_step)?,
+        None => 0,
+    };
+    let mut end_value = config.inforce.map_or(0.0, |inforce| inforce.account_value);
+    // the year is counted up on its first step so starting part way through one starts in it
+    let mut policy_year = (start / steps_per_year) as usize + if start % steps_per_year == 0 {0} else {1};
+    let rounding = config.rounding;
+    let mut face_amount = face_amount;
+    let mut loan_balance = config.inforce.map_or(0.0, |inforce| inforce.loan_balance);
+    let mut premiums_paid = config.inforce.map_or(0.0, |inforce| inforce.premiums_paid);
+    let surrender_charges = match &config.inforce {
+        Some(inforce) => Cow::Owned(inforce.surrender_charges(rates, (start / steps_per_year) as usize + 1, face_amount)),
+        None => Cow::Borrowed(rates.surrender_charges.as_slice()),
+    };
+    let mut db_option = config.death_benefit_option;
+    let loan_charge_rate = (1.0 + config.loan_terms.charged_rate).powf(f64::from(months_per_step) / 12.0) - 1.0;
+    let loan_credit_rate = (1.0 + config.loan_terms.credited_rate).powf(f64::from(months_per_step) / 12.0) - 1.0;
+    let accounts = account_buckets(&config.accounts)?;
+    // an inforce account value starts out split by allocation
+    let mut account_values: Vec<f64> = accounts.iter().map(|account| end_value * account.allocation).collect();
+    let mut lien_balance = 0.0;
+    let mut lien_rate = 0.0;
+
+    for i in start..(steps_per_year * projection_years as i32) {
+        policy_year += if (i % steps_per_year) == 0 {1} else {0};
+        let start_value = end_value;
+        // payments falling due within the step, an annual step takes the whole year's payments at its start
+        let month_in_year = (i % steps_per_year) * months_per_step;
+        let payments = (month_in_year..month_in_year + months_per_step).filter(|m| m % months_per_payment == 0).count();
+        let premium = match config.premium_pattern {
+            PremiumPattern::SinglePay if i == 0 => annual_premium,
+            PremiumPattern::SinglePay => 0.0,
+            pattern if pattern.pays_in_year(issue_age, policy_year) => modal_premium * payments as f64,
+            _ => 0.0,
+        };
+        let premium_load = rounding.amount(premium * rates.premium_loads[policy_year-1]);
+        let withdrawal = if month_in_year == 0 {
+            withdrawals_in_year(&config.withdrawals, policy_year as i32, start_value).min(start_value.max(0.0))
+        } else {
+            0.0
+        };
+        if month_in_year == 0 {
+            if let Some(face) = face_change_in_year(&config.face_changes, policy_year as i32) {
+                face_amount = face;
+            }
+            if let Some(option) = option_change_in_year(&config.option_changes, policy_year as i32) {
+                let added = |option: DeathBenefitOption| -> f64 {
+                    return match option {
+                        DeathBenefitOption::Level => 0.0,
+                        DeathBenefitOption::Increasing => start_value.max(0.0),
+                        DeathBenefitOption::ReturnOfPremium => premiums_paid,
+                    };
+                };
+                face_amount = (face_amount + added(db_option) - added(option)).max(0.0);
+                db_option = option;
+            }
+        }
+        premiums_paid += premium;
+        match db_option {
+            DeathBenefitOption::Level => face_amount = (face_amount - withdrawal).max(0.0),
+            DeathBenefitOption::Increasing => {}
+            DeathBenefitOption::ReturnOfPremium => premiums_paid = (premiums_paid - withdrawal).max(0.0),
+        }
+        let mut accelerated_value = 0.0;
+        let mut accelerated_benefit = 0.0;
+        for rider in &config.riders {
+            let context = RiderContext {
+                policy_year: policy_year as i32,
+                policy_month: i * months_per_step + 1,
+                attained_age: i32::from(issue_age.get()) + policy_year as i32 - 1,
+                steps_per_year,
+                face_amount,
+                premium,
+                expense_charge: 0.0,
+                coi_charge: 0.0,
+                account_value: start_value - withdrawal - accelerated_value,
+                rider_charges: 0.0,
+            };
+            let adjustment = rider.adjust(&context);
+            face_amount = (face_amount - adjustment.face_reduction).max(0.0);
+            accelerated_value += adjustment.value_reduction;
+            accelerated_benefit += adjustment.payment;
+            if adjustment.lien > 0.0 {
+                lien_balance += adjustment.lien;
+                lien_rate = (1.0 + adjustment.lien_interest).powf(f64::from(months_per_step) / 12.0) - 1.0;
+            }
+        }
+        let (loan, loan_repayment) = if month_in_year == 0 {
+            let loan_value = (start_value - withdrawal - accelerated_value - surrender_charges[policy_year-1] * face_amount / 1000.0 - loan_balance - lien_balance).max(0.0);
+            let loan = scheduled_in_year(&config.loans, policy_year as i32).min(loan_value);
+            (loan, scheduled_in_year(&config.loan_repayments, policy_year as i32).min(loan_balance + loan))
+        } else {
+            (0.0, 0.0)
+        };
+        loan_balance += loan - loan_repayment;
+        let expense_charge = rounding.amount((rates.policy_fees[policy_year-1] + rates.unit_loads[policy_year-1] * face_amount / 1000.0) / f64::from(steps_per_year));
+        let av_for_db = start_value + premium - premium_load - withdrawal - accelerated_value - expense_charge;
+        // the naar below is the discounted benefit less the account value whichever option gives the benefit
+        let specified_amount = match db_option {
+            DeathBenefitOption::Level => face_amount,
+            DeathBenefitOption::Increasing => face_amount + av_for_db.max(0.0),
+            DeathBenefitOption::ReturnOfPremium => face_amount + premiums_paid,
+        };
+        let db = specified_amount.max(rates.corr_facts[policy_year-1] * av_for_db);
+        let naar_disc = rates.naar_discs[policy_year-1].powi(months_per_step);
+        let naar = (db * naar_disc - av_for_db.max(0.0)).max(0.0);
+        let coi = rounding.amount((naar / 1000.0) * (rates.coi_rates[policy_year-1] / f64::from(steps_per_year)));
+        let mut rider_context = RiderContext {
+            policy_year: policy_year as i32,
+            policy_month: i * months_per_step + 1,
+            attained_age: i32::from(issue_age.get()) + policy_year as i32 - 1,
+            steps_per_year,
+            face_amount,
+            premium,
+            expense_charge,
+            coi_charge: coi,
+            account_value: av_for_db,
+            rider_charges: 0.0,
+        };
+        let mut rider_benefit = 0.0;
+        let mut rider_credit = 0.0;
+        for rider in &config.riders {
+            rider_benefit += rider.benefit(&rider_context);
+            rider_credit += rider.credit(&rider_context);
+            rider_context.rider_charges += rider.charge(&rider_context);
+        }
+        let rider_charge = rider_context.rider_charges;
+        let av_for_interest = av_for_db - coi - rider_charge + rider_credit;
+        let interest_rate = if config.interest_path.is_empty() {
+            (1.0 + rates.interest_rates[policy_year - 1]).powi(months_per_step) - 1.0
+        } else {
+            path_rate(&config.interest_path, ((i - start) * months_per_step) as usize, months_per_step as usize)
+        };
+        // the part of the account value backing the loan is credited the loaned rate instead
+        let loaned_value = loan_balance.min(av_for_interest.max(0.0));
+        // net premium goes to the accounts by allocation and everything else comes out in proportion to what
+        // is in them, or by allocation once there is nothing left
+        for (value, account) in account_values.iter_mut().zip(&accounts) {
+            *value += (premium - premium_load) * account.allocation;
+        }
+        let before_deductions = account_values.iter().fold(0.0, |total, value| total + value);
+        let year_end = (i % steps_per_year) == steps_per_year - 1;
+        let mut interest = 0.0;
+        let mut mortality_expense_charge = 0.0;
+        let mut index_account_value = 0.0;
+        let mut variable_account_value = 0.0;
+        for (value, account) in account_values.iter_mut().zip(&accounts) {
+            let share = if before_deductions > 0.0 {*value / before_deductions} else {account.allocation};
+            *value = av_for_interest * share;
+            let loan_interest = loaned_value * share * loan_credit_rate;
+            // a fund's return can be negative so unlike a credited rate is not floored
+            let account_interest = match account.fund() {
+                Some(fund) => {
+                    let growth = fund.growth(((i - start) * months_per_step) as usize, months_per_step as usize);
+                    let step = config.mortality_expense.step((*value - loaned_value * share).max(0.0), growth, months_per_step as usize);
+                    let charge = rounding.amount(step.mortality_expense);
+                    *value -= charge;
+                    mortality_expense_charge += charge;
+                    rounding.amount(step.fund_return + loan_interest)
+                }
+                None => rounding.amount(((*value - loaned_value * share) * account.credited_rate(policy_year, interest_rate, year_end)).max(0.0) + loan_interest),
+            };
+            *value += account_interest;
+            interest += account_interest;
+            if account.is_indexed() {
+                index_account_value += *value;
+            }
+            if account.fund().is_some() {
+                variable_account_value += *value;
+            }
+        }
+        end_value = rounding.amount(av_for_interest - mortality_expense_charge + interest);
+        let loan_interest = loan_balance * loan_charge_rate;
+        loan_balance += loan_interest;
+        lien_balance += lien_balance * lien_rate;
+        let surrender_charge = rounding.amount(surrender_charges[policy_year-1] * face_amount / 1000.0);
+
+        let explain = ExplainMonth {
+            policy_month: i * months_per_step + 1,
+            policy_year: policy_year as i32,
+            attained_age: i32::from(issue_age.get()) + policy_year as i32 - 1,
+            value_start: start_value,
+            premium,
+            premium_load_rate: rates.premium_loads[policy_year-1],
+            premium_load,
+            withdrawal,
+            accelerated_value,
+            face_amount,
+            policy_fee: rates.policy_fees[policy_year-1],
+            unit_load_rate: rates.unit_loads[policy_year-1],
+            expense_charge,
+            av_for_db,
+            specified_amount,
+            corridor_factor: rates.corr_facts[policy_year-1],
+            corridor_death_benefit: rates.corr_facts[policy_year-1] * av_for_db,
+            death_benefit: db,
+            naar_discount: naar_disc,
+            discounted_death_benefit: db * naar_disc,
+            naar,
+            coi_rate: rates.coi_rates[policy_year-1],
+            coi_charge: coi,
+            rider_charge,
+            rider_credit,
+            av_for_interest,
+            interest_rate,
+            loaned_value,
+            mortality_expense_charge,
+            interest,
+            value_end: end_value,
+        };
+        // with an annual step each row stands for the whole policy year and is labelled with its first month
+        on_month(&MonthResult {
+            policy_month: i * months_per_step + 1,
+            policy_year: policy_year as i32,
+            month_in_policy_year: (i % steps_per_year) * months_per_step + 1,
+            value_start: start_value,
+            premium,
+            premium_load,
+            withdrawal,
+            face_amount,
+            death_benefit_option: db_option,
+            expense_charge,
+            death_benefit: db,
+            naar,
+            coi_charge: coi,
+            rider_charge,
+            rider_benefit,
+            rider_credit,
+            interest,
+            value_end: end_value,
+            fixed_account_value: end_value - index_account_value - variable_account_value,
+            index_account_value,
+            variable_account_value,
+            mortality_expense_charge,
+            surrender_charge,
+            loan,
+            loan_repayment,
+            loan_interest,
+            loan_balance,
+            accelerated_value,
+            accelerated_benefit,
+            lien_balance,
+            cash_surrender_value: (end_value - surrender_charge - loan_balance - lien_balance).max(0.0),
+            net_death_benefit: (db - loan_balance - lien_balance).max(0.0),
+            mec: false,
+            shadow_account_value: 0.0,
+        }, &explain);
+    }
+
+    return Ok(end_value);
+}
+
+// rate for months first_month..first_month + months of an annual effective rate path
+pub(crate) fn path_rate(path: &[f64], first_month: usize, months: usize) -> f64 {
+    let growth = (first_month..first_month + months).fold(1.0, |growth, m| growth * (1.0 + path[m.min(path.len() - 1)]).powf(1.0 / 12.0));
+    return growth - 1.0;
+}
+
+pub fn at_issue_projection(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64) -> Result<f64, ValactError> {
+    return at_issue_projection_with(rates, issue_age, face_amount, annual_premium, &ProjectionConfig::default());
+}
+
+pub fn at_issue_projection_with(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig) -> Result<f64, ValactError> {
+    let end_value = project(rates, issue_age, face_amount, annual_premium, config, |_, _| {})?;
+    return Ok(end_value);
+}
+
+// same projection as at_issue_projection but keeping every month for auditing the mechanics
+pub fn at_issue_projection_detail(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64) -> Result<Vec<MonthResult>, ValactError> {
+    return at_issue_projection_detail_with(rates, issue_age, face_amount, annual_premium, &ProjectionConfig::default());
+}
+
+pub fn at_issue_projection_detail_with(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig) -> Result<Vec<MonthResult>, ValactError> {
+    let mut months = Vec::with_capacity(config.timestep.steps_per_year() as usize * rates.years());
+    project(rates, issue_age, face_amount, annual_premium, config, |month, _| months.push(*month))?;
+    return Ok(months);
+}
+
+// the projection with every intermediate value of each step, see ExplainMonth
+pub fn at_issue_projection_explain(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig) -> Result<Vec<ExplainMonth>, ValactError> {
+    let mut months = Vec::with_capacity(config.timestep.steps_per_year() as usize * rates.years());
+    project(rates, issue_age, face_amount, annual_premium, config, |_, explain| months.push(*explain))?;
+    return Ok(months);
+}
+
+pub fn annual_summary(months: &[MonthResult], issue_age: IssueAge) -> Vec<YearResult> {
+    let mut years: Vec<YearResult> = Vec::new();
+    for month in months {
+        if month.month_in_policy_year == 1 || years.is_empty() {
+            years.push(YearResult {
+                policy_year: month.policy_year,
+                attained_age: i32::from(issue_age.get()) + month.policy_year - 1,
+                value_start: month.value_start,
+                ..Default::default()
+            });
+        }
+        let year = years.last_mut().unwrap();
+        year.premium += month.premium;
+        year.premium_load += month.premium_load;
+        year.withdrawal += month.withdrawal;
+        year.expense_charge += month.expense_charge;
+        year.coi_charge += month.coi_charge;
+        year.interest += month.interest;
+        year.death_benefit = month.death_benefit;
+        year.value_end = month.value_end;
+        year.loan_balance = month.loan_balance;
+        year.cash_surrender_value = month.cash_surrender_value;
+        year.net_death_benefit = month.net_death_benefit;
+    }
+    return years;
+}
+
+pub fn write_projection_csv(path: &str, months: &[MonthResult]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for month in months {
+        wtr.serialize(month)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
+
+pub fn write_explain_csv(path: &str, months: &[ExplainMonth]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for month in months {
+        wtr.serialize(month)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
+
+// solved premium with the root finding diagnostics, bracket and converged are as the root finder left them
+// before the premium was rounded to cents
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct SolveResult {
+    pub premium: f64,
+    pub iterations: usize,
+    pub evaluations: usize,
+    pub bracket: (f64, f64),
+    pub converged: bool,
+}
+
+pub fn solve_for_premium(rates: &RateSet, issue_age: IssueAge, face_amount: f64) -> Result<SolveResult, ValactError> {
+    return solve_for_premium_with(rates, issue_age, face_amount, &ProjectionConfig::default());
+}
+
+// what a solve is aiming for, the solve finds the smallest premium where the account value exceeds amount
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum SolveTarget {
+    // account value above zero at maturity
+    #[default]
+    EndowAtMaturity,
+    // account value above amount at maturity
+    ValueAtMaturity { amount: f64 },
+    // account value above amount when the insured reaches attained age, i.e. at the end of the prior policy year
+    CashValueAtAge { age: AttainedAge, amount: f64 },
+}
+
+impl SolveTarget {
+    // a policy that only just fails to endow collapses to a large negative value by maturity, so the end value
+    // jumps across the solution and interpolation does worse than bisection, values at earlier ages are smooth
+    pub fn default_method(self) -> Method {
+        return match self {
+            SolveTarget::CashValueAtAge { .. } => Method::Brent,
+            _ => Method::Bisection,
+        };
+    }
+
+    fn amount(self) -> f64 {
+        return match self {
+            SolveTarget::EndowAtMaturity => 0.0,
+            SolveTarget::ValueAtMaturity { amount } => amount,
+            SolveTarget::CashValueAtAge { amount, .. } => amount,
+        };
+    }
+}
+
+// account value the target is measured against
+pub fn value_at_target(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig, target: SolveTarget) -> Result<f64, ValactError> {
+    let SolveTarget::CashValueAtAge { age, .. } = target else {
+        return at_issue_projection_with(rates, issue_age, face_amount, annual_premium, config);
+    };
+    let target_year = match age.years_since(issue_age) {
+        Some(years) if years > 0 && age <= rates.maturity_age => years as i32,
+        _ => return Err(format!("target age {} must be after issue age {} and no later than maturity age {}", age, issue_age, rates.maturity_age).into()),
+    };
+    let mut value = 0.0;
+    project(rates, issue_age, face_amount, annual_premium, config, |month, _| {
+        if month.policy_year == target_year {
+            value = month.value_end;
+        }
+    })?;
+    return Ok(value);
+}
+
+// the premium is solved on an annual basis, each modal payment being config.modal_premium of it
+// and paid only in the years config.premium_pattern allows
+pub fn solve_for_premium_with(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig) -> Result<SolveResult, ValactError> {
+    return solve_for_premium_to_target(rates, issue_age, face_amount, config, SolveTarget::EndowAtMaturity);
+}
+
+pub fn solve_for_premium_to_target(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig, target: SolveTarget) -> Result<SolveResult, ValactError> {
+    let options = SolverOptions::new(target.default_method(), 0.005);
+    return solve_for_premium_using(rates, issue_age, face_amount, config, target, &options);
+}
+
+// the projection for the rounding check is not counted in evaluations
+pub fn solve_for_premium_using(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig, target: SolveTarget, options: &SolverOptions) -> Result<SolveResult, ValactError> {
+    let amount = target.amount();
+    let value = |premium| value_at_target(rates, issue_age, face_amount, premium, config, target);
+    let root = goal_seek_with(value, amount, (0.0, face_amount.max(amount) / 100.0), options)?;
+
+    let mut result = config.rounding.round(root.x);
+    let end_value = value_at_target(rates, issue_age, face_amount, result, config, target)?;
+    if end_value <= amount {result = config.rounding.round(result + config.rounding.unit.size())}
+
+    return Ok(SolveResult {
+        premium: result,
+        iterations: root.iterations,
+        evaluations: root.evaluations,
+        bracket: root.bracket,
+        converged: root.converged,
+    });
+}
+
+// premium paid at each modal date, rounded by config.rounding and bumped a unit if rounding leaves the policy
+// short of endowing
+pub fn solve_for_modal_premium(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig) -> Result<f64, ValactError> {
+    let annual_premium = solve_for_premium_with(rates, issue_age, face_amount, config)?.premium;
+    let mut modal_premium = config.rounding.round(config.modal_premium(annual_premium));
+    let end_value = at_issue_projection_with(rates, issue_age, face_amount, modal_premium / config.modal_factor(), config)?;
+    if end_value <= 0.0 {modal_premium = config.rounding.round(modal_premium + config.rounding.unit.size())}
+
+    return Ok(modal_premium);
+}
+
+pub fn solve_for_face(rates: &RateSet, issue_age: IssueAge, annual_premium: f64) -> Result<f64, ValactError> {
+    return solve_for_face_with(rates, issue_age, annual_premium, &ProjectionConfig::default());
+}
+
+// largest whole dollar face amount the premium endows, charges grow with face so end value falls as it rises
+pub fn solve_for_face_with(rates: &RateSet, issue_age: IssueAge, annual_premium: f64, config: &ProjectionConfig) -> Result<f64, ValactError> {
+
+    if at_issue_projection_with(rates, issue_age, 0.0, annual_premium, config)? <= 0.0 {
+        return Err(format!("annual premium {:.2} does not cover the policy charges at any face amount", annual_premium).into());
+    }
+
+    let value = |face_amount| at_issue_projection_with(rates, issue_age, face_amount, annual_premium, config);
+    let face_amount = goal_seek(value, 0.0, (0.0, annual_premium * 100.0), 0.5)?;
+
+    return Ok(face_amount.floor());
+}
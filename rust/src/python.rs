@@ -0,0 +1,197 @@
+
+use pyo3::{
+    exceptions::{PyKeyError, PyRuntimeError, PyValueError}, prelude::*, types::{PyBytes, PyDict}
+};
+
+use crate::{
+    age::{AttainedAge, IssueAge}, arrow_results, batch::{self, PolicyRecord}, error::ValactError, projection::{self, at_issue_projection_detail, DeathBenefitOption, MonthResult}, rates::{self, RateSet}, repository::RateRepository, runner
+};
+
+// python module, the same functions as approach1.py so scripts using it can import this instead
+//   import valact as approach1
+//   premium, illustration = approach1.solve_for_premium("M", "NS", 35, 100000)
+// rates are the dict approach1.get_rates returns, lists by policy year keyed premium_load, policy_fee, per_unit,
+// corridor_factor, naar_disc, coi and interest, optionally surrender_charge, with naar_disc and interest monthly
+// unlike approach1.illustrate the projection carries on past a negative value rather than stopping there
+// built with maturin from the directory above src: maturin develop --release
+
+fn runtime_error(e: ValactError) -> PyErr {
+    return PyRuntimeError::new_err(e.to_string());
+}
+
+// ages come in as plain ints, out of range is a ValueError
+fn to_issue_age(age: u8) -> PyResult<IssueAge> {
+    return IssueAge::new(age).map_err(|e| PyValueError::new_err(e.to_string()));
+}
+
+// csv tables to the default maturity age, same as approach1.get_rates
+#[pyfunction]
+fn get_rates<'py>(py: Python<'py>, gender: &str, risk_class: &str, issue_age: u8) -> PyResult<Bound<'py, PyDict>> {
+    let issue_age = to_issue_age(issue_age)?;
+    let rates = rates::get_rates(gender, risk_class, issue_age).map_err(runtime_error)?;
+    return rates_to_dict(py, &rates);
+}
+
+fn rates_to_dict<'py>(py: Python<'py>, rates: &RateSet) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("premium_load", &rates.premium_loads)?;
+    dict.set_item("policy_fee", &rates.policy_fees)?;
+    dict.set_item("per_unit", &rates.unit_loads)?;
+    dict.set_item("corridor_factor", &rates.corr_facts)?;
+    dict.set_item("naar_disc", &rates.naar_discs)?;
+    dict.set_item("coi", &rates.coi_rates)?;
+    dict.set_item("interest", &rates.interest_rates)?;
+    dict.set_item("surrender_charge", &rates.surrender_charges)?;
+    return Ok(dict);
+}
+
+// the rates run from issue to maturity so maturity is the issue age plus the shortest list
+fn rates_from_dict(rates: &Bound<'_, PyDict>, issue_age: IssueAge) -> PyResult<RateSet> {
+    let get = |key: &str| -> PyResult<Vec<f64>> {
+        return match rates.get_item(key)? {
+            Some(value) => value.extract(),
+            None => Err(PyKeyError::new_err(format!("rates have no {}", key))),
+        };
+    };
+    let mut rate_set = RateSet {
+        maturity_age: issue_age.at_issue(),
+        premium_loads: get("premium_load")?,
+        policy_fees: get("policy_fee")?,
+        unit_loads: get("per_unit")?,
+        corr_facts: get("corridor_factor")?,
+        naar_discs: get("naar_disc")?,
+        coi_rates: get("coi")?,
+        interest_rates: get("interest")?,
+        surrender_charges: Vec::new(),
+    };
+    rate_set.surrender_charges = match rates.get_item("surrender_charge")? {
+        Some(value) => value.extract()?,
+        None => vec![0.0; rate_set.coi_rates.len()],
+    };
+    let years = u8::try_from(rate_set.years()).map_err(|_| PyRuntimeError::new_err("rates cover too many policy years"))?;
+    rate_set.maturity_age = AttainedAge::new(issue_age.get().saturating_add(years)).map_err(runtime_error)?;
+    return Ok(rate_set);
+}
+
+// the columns approach1.illustrate gives, lists by month
+fn illustration<'py>(py: Python<'py>, rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64) -> PyResult<Bound<'py, PyDict>> {
+    let months = at_issue_projection_detail(rates, issue_age, face_amount, annual_premium).map_err(runtime_error)?;
+    let dict = PyDict::new(py);
+    dict.set_item("Policy_Month", months.iter().map(|m| m.policy_month).collect::<Vec<i32>>())?;
+    dict.set_item("Policy_Year", months.iter().map(|m| m.policy_year).collect::<Vec<i32>>())?;
+    dict.set_item("Month_In_Policy_Year", months.iter().map(|m| m.month_in_policy_year).collect::<Vec<i32>>())?;
+    let column = |value: fn(&MonthResult) -> f64| months.iter().map(value).collect::<Vec<f64>>();
+    dict.set_item("Value_Start", column(|m| m.value_start))?;
+    dict.set_item("Premium", column(|m| m.premium))?;
+    dict.set_item("Premium_Load", column(|m| m.premium_load))?;
+    dict.set_item("Expense_Charge", column(|m| m.expense_charge))?;
+    dict.set_item("Death_Benefit", column(|m| m.death_benefit))?;
+    dict.set_item("NAAR", column(|m| m.naar))?;
+    dict.set_item("COI_Charge", column(|m| m.coi_charge))?;
+    dict.set_item("Interest", column(|m| m.interest))?;
+    dict.set_item("Value_End", column(|m| m.value_end))?;
+    return Ok(dict);
+}
+
+#[pyfunction]
+fn illustrate<'py>(py: Python<'py>, rates: &Bound<'py, PyDict>, issue_age: u8, face_amount: f64, annual_premium: f64) -> PyResult<Bound<'py, PyDict>> {
+    let issue_age = to_issue_age(issue_age)?;
+    let rates = rates_from_dict(rates, issue_age)?;
+    return illustration(py, &rates, issue_age, face_amount, annual_premium);
+}
+
+// value at maturity for a cell at a premium
+#[pyfunction]
+fn at_issue_projection(gender: &str, risk_class: &str, issue_age: u8, face_amount: f64, annual_premium: f64) -> PyResult<f64> {
+    let issue_age = to_issue_age(issue_age)?;
+    let rates = rates::get_rates(gender, risk_class, issue_age).map_err(runtime_error)?;
+    return projection::at_issue_projection(&rates, issue_age, face_amount, annual_premium).map_err(runtime_error);
+}
+
+// (premium, illustration at the premium) as approach1.solve_for_premium returns
+#[pyfunction]
+fn solve_for_premium<'py>(py: Python<'py>, gender: &str, risk_class: &str, issue_age: u8, face_amount: f64) -> PyResult<(f64, Bound<'py, PyDict>)> {
+    let issue_age = to_issue_age(issue_age)?;
+    let rates = rates::get_rates(gender, risk_class, issue_age).map_err(runtime_error)?;
+    let premium = projection::solve_for_premium(&rates, issue_age, face_amount).map_err(runtime_error)?.premium;
+    return Ok((premium, illustration(py, &rates, issue_age, face_amount, premium)?));
+}
+
+// premiums for a list of (gender, risk_class, issue_age, face_amount) across threads, the tables parsed once
+// the gil is released while the solves run
+#[pyfunction]
+#[pyo3(signature = (policies, threads=None))]
+fn solve_policies(py: Python<'_>, policies: Vec<(String, String, u8, f64)>, threads: Option<usize>) -> PyResult<Vec<f64>> {
+    let policies = policies.into_iter().map(|(gender, risk_class, issue_age, face_amount)| -> PyResult<PolicyRecord> {
+        return Ok(PolicyRecord {
+            gender,
+            risk_class,
+            issue_age: to_issue_age(issue_age)?,
+            face_amount,
+            death_benefit_option: DeathBenefitOption::Level,
+            inforce: None,
+            issue_date: None,
+        });
+    }).collect::<PyResult<Vec<PolicyRecord>>>()?;
+    let results = py.detach(|| -> Result<Vec<f64>, String> {
+        let repository = RateRepository::in_memory().map_err(|e| e.to_string())?;
+        let results = runner::solve_policies_parallel_with(&policies, threads, |policy| repository.get_shared(&policy.gender, &policy.risk_class, policy.issue_age)).map_err(|e| e.to_string())?;
+        return Ok(results.iter().map(|r| r.premium).collect());
+    });
+    return results.map_err(PyRuntimeError::new_err);
+}
+
+// the results of a census csv solved across threads
+fn solve_census(input: &str, threads: Option<usize>) -> Result<Vec<batch::PolicyResult>, String> {
+    let policies = batch::read_policies(input).map_err(|e| e.to_string())?;
+    let repository = RateRepository::in_memory().map_err(|e| e.to_string())?;
+    return runner::solve_policies_parallel_with(&policies, threads, |policy| repository.get_shared(&policy.gender, &policy.risk_class, policy.issue_age)).map_err(|e| e.to_string());
+}
+
+// valact batch, a census csv in and results csv out, returns the number of policies solved
+#[pyfunction]
+#[pyo3(signature = (input, output_file, threads=None))]
+fn run_batch(py: Python<'_>, input: &str, output_file: &str, threads: Option<usize>) -> PyResult<usize> {
+    let solved = py.detach(|| -> Result<usize, String> {
+        let results = solve_census(input, threads)?;
+        batch::write_results(output_file, &results).map_err(|e| e.to_string())?;
+        return Ok(results.len());
+    });
+    return solved.map_err(PyRuntimeError::new_err);
+}
+
+// valact batch with the results returned as the bytes of an arrow ipc file rather than written to csv, for
+// pyarrow.ipc.open_file or polars.read_ipc
+#[pyfunction]
+#[pyo3(signature = (input, threads=None))]
+fn batch_arrow<'py>(py: Python<'py>, input: &str, threads: Option<usize>) -> PyResult<Bound<'py, PyBytes>> {
+    let bytes = py.detach(|| -> Result<Vec<u8>, String> {
+        let results = solve_census(input, threads)?;
+        return arrow_results::to_ipc_bytes(&results, arrow_results::DEFAULT_BATCH_SIZE).map_err(|e| e.to_string());
+    });
+    return Ok(PyBytes::new(py, &bytes.map_err(PyRuntimeError::new_err)?));
+}
+
+// batch_arrow read into a polars DataFrame with the results file's columns, polars is imported here so the module
+// works without it
+//   results = valact.batch_dataframe("census.csv")
+//   results.group_by("Issue_Age").agg(pl.col("Premium").mean())
+#[pyfunction]
+#[pyo3(signature = (input, threads=None))]
+fn batch_dataframe<'py>(py: Python<'py>, input: &str, threads: Option<usize>) -> PyResult<Bound<'py, PyAny>> {
+    let bytes = batch_arrow(py, input, threads)?;
+    return py.import("polars")?.call_method1("read_ipc", (bytes,));
+}
+
+#[pymodule]
+fn valact(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(get_rates, m)?)?;
+    m.add_function(wrap_pyfunction!(illustrate, m)?)?;
+    m.add_function(wrap_pyfunction!(at_issue_projection, m)?)?;
+    m.add_function(wrap_pyfunction!(solve_for_premium, m)?)?;
+    m.add_function(wrap_pyfunction!(solve_policies, m)?)?;
+    m.add_function(wrap_pyfunction!(run_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_arrow, m)?)?;
+    m.add_function(wrap_pyfunction!(batch_dataframe, m)?)?;
+    return Ok(());
+}
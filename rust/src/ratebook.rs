@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    age::IssueAge, batch::{PolicyRecord, PolicyResult}, error::ValactError, projection::DeathBenefitOption
+};
+
+// a rate book, the premium of every cell of a grid of genders, risk classes and issue ages at each of a list of
+// face amounts, laid out as the table a premium is quoted from
+// the grid is solved as one batch (runner.rs) so the cells spread over the threads and policies of an issue age
+// go through in lockstep; a row per gender, class and issue age in the order given, then a premium and premium per
+// 1000 column per face amount
+// a cell whose solve fails is left blank rather than failing the whole book, with its error counted
+
+pub const DEFAULT_MIN_ISSUE_AGE: u8 = 18;
+pub const DEFAULT_MAX_ISSUE_AGE: u8 = 85;
+
+#[derive(Clone, Debug)]
+pub struct RateBookGrid {
+    pub genders: Vec<String>,
+    pub risk_classes: Vec<String>,
+    pub min_issue_age: IssueAge,
+    pub max_issue_age: IssueAge,
+    pub face_amounts: Vec<f64>,
+    pub death_benefit_option: DeathBenefitOption,
+}
+
+impl RateBookGrid {
+    fn check(&self) -> Result<(), ValactError> {
+        if self.genders.is_empty() || self.risk_classes.is_empty() || self.face_amounts.is_empty() {
+            return Err("a rate book needs at least one gender, risk class and face amount".into());
+        }
+        if self.min_issue_age > self.max_issue_age {
+            return Err(format!("youngest issue age {} is past the oldest {}", self.min_issue_age, self.max_issue_age).into());
+        }
+        if let Some(face_amount) = self.face_amounts.iter().find(|face| !(**face > 0.0 && face.is_finite())) {
+            return Err(format!("rate book face amount {} must be positive", face_amount).into());
+        }
+        return Ok(());
+    }
+
+    pub fn issue_ages(&self) -> impl Iterator<Item = IssueAge> + '_ {
+        return (self.min_issue_age.get()..=self.max_issue_age.get()).flat_map(IssueAge::new);
+    }
+
+    // gender, class and issue age of each row of the book
+    fn rows(&self) -> impl Iterator<Item = (&String, &String, IssueAge)> + '_ {
+        return self.genders.iter().flat_map(move |gender| self.risk_classes.iter().flat_map(move |class| self.issue_ages().map(move |age| (gender, class, age))));
+    }
+
+    // a policy for every cell and face amount, a row's face amounts together in the order of the rows
+    pub fn policies(&self) -> Result<Vec<PolicyRecord>, ValactError> {
+        self.check()?;
+        return Ok(self.rows().flat_map(|(gender, class, issue_age)| self.face_amounts.iter().map(move |&face_amount| PolicyRecord {
+            gender: gender.clone(),
+            risk_class: class.clone(),
+            issue_age,
+            face_amount,
+            death_benefit_option: self.death_benefit_option,
+            inforce: None,
+            issue_date: None,
+        })).collect());
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RateBookRow {
+    pub gender: String,
+    pub risk_class: String,
+    pub issue_age: IssueAge,
+    // by face amount, None where the solve failed
+    pub premiums: Vec<Option<f64>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RateBook {
+    pub face_amounts: Vec<f64>,
+    pub rows: Vec<RateBookRow>,
+    // a failure's error message and how many cells failed with it
+    pub failures: BTreeMap<String, usize>,
+}
+
+impl RateBook {
+    pub fn cells(&self) -> usize {
+        return self.rows.len() * self.face_amounts.len();
+    }
+
+    pub fn failed(&self) -> usize {
+        return self.failures.values().sum();
+    }
+}
+
+// results are in the order of grid.policies(), a failed solve with its error
+pub fn rate_book(grid: &RateBookGrid, results: &[Result<PolicyResult, ValactError>]) -> Result<RateBook, ValactError> {
+    let faces = grid.face_amounts.len();
+    let rows: Vec<(&String, &String, IssueAge)> = grid.rows().collect();
+    if results.len() != rows.len() * faces {
+        return Err(format!("{} results for a rate book of {} cells", results.len(), rows.len() * faces).into());
+    }
+    let mut failures: BTreeMap<String, usize> = BTreeMap::new();
+    let rows = rows.into_iter().zip(results.chunks(faces)).map(|((gender, class, issue_age), results)| {
+        let premiums = results.iter().map(|result| match result {
+            Ok(result) => Some(result.premium),
+            Err(e) => {
+                *failures.entry(e.to_string()).or_insert(0) += 1;
+                None
+            }
+        }).collect();
+        return RateBookRow { gender: gender.clone(), risk_class: class.clone(), issue_age, premiums };
+    }).collect();
+    return Ok(RateBook { face_amounts: grid.face_amounts.clone(), rows, failures });
+}
+
+// 100000 rather than 100000.0 in a column header
+fn face_label(face_amount: f64) -> String {
+    return if face_amount.fract() == 0.0 {format!("{:.0}", face_amount)} else {face_amount.to_string()};
+}
+
+// Gender, Risk_Class, Issue_Age then Premium_<face> and Per_1000_<face> for each face amount, both to the cent,
+// blank for a cell that failed
+pub fn write_rate_book(path: &str, book: &RateBook) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    let mut header = vec!["Gender".to_string(), "Risk_Class".to_string(), "Issue_Age".to_string()];
+    for &face_amount in &book.face_amounts {
+        header.push(format!("Premium_{}", face_label(face_amount)));
+        header.push(format!("Per_1000_{}", face_label(face_amount)));
+    }
+    wtr.write_record(&header)?;
+    for row in &book.rows {
+        let mut record = vec![row.gender.clone(), row.risk_class.clone(), row.issue_age.to_string()];
+        for (premium, face_amount) in row.premiums.iter().zip(&book.face_amounts) {
+            match premium {
+                Some(premium) => {
+                    record.push(format!("{:.2}", premium));
+                    record.push(format!("{:.2}", 1000.0 * premium / face_amount));
+                }
+                None => record.extend([String::new(), String::new()]),
+            }
+        }
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
@@ -0,0 +1,473 @@
+use std::{
+    fmt, fs::{self, File}, io::BufReader, path::Path
+};
+
+use chrono::NaiveDate;
+
+// serde is for serialization and deserialization of data
+// using here to simplify reading csv files
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    age::{AttainedAge, IssueAge, PolicyYear}, error::ValactError, product::ProductSpec, select_ultimate::{read_select_ultimate_csv, SelectUltimateTable}, xtbml::read_xtbml
+};
+
+// need to prepare to deserialize data to structure
+#[derive(Deserialize)]
+pub struct IAPYRecord {
+    // unfortunately there is not a shortcut here given the nameing used in the csvs
+    // there is a quick way to indicate the fields are lowercase, UPPERCASE, PascalCase, 
+    // camelCase, snake_case, SCREAMING_SNAKE_CASE, kebab-case, and 
+    // SCREAMING-KEBAB-CASE
+    #[serde(alias="Issue_Age")]
+    pub issue_age: IssueAge,
+    #[serde(alias="Policy_Year")]
+    pub policy_year: PolicyYear,
+    #[serde(alias="Rate")]
+    pub rate: f64,
+}
+
+#[derive(Deserialize)]
+pub struct GenRCIAPYRecord {
+    #[serde(alias="Gender")]
+    pub gender: String,
+    #[serde(alias="Risk_Class")]
+    pub risk_class: String,
+    #[serde(alias="Issue_Age")]
+    pub issue_age: IssueAge,
+    #[serde(alias="Policy_Year")]
+    pub policy_year: PolicyYear,
+    #[serde(alias="Rate")]
+    pub rate: f64,
+}
+
+#[derive(Deserialize)]
+pub struct IARecord {
+    #[serde(alias="Issue_Age")]
+    pub issue_age: IssueAge,
+    #[serde(alias="Rate")]
+    pub rate: f64,
+}
+
+#[derive(Deserialize)]
+pub struct PYRecord {
+    #[serde(alias="Policy_Year")]
+    pub policy_year: PolicyYear,
+    #[serde(alias="Rate")]
+    pub rate: f64,
+}
+
+#[derive(Deserialize)]
+pub struct AARecord {
+    #[serde(alias="Attained_Age")]
+    pub attained_age: AttainedAge,
+    #[serde(alias="Rate")]
+    pub rate: f64,
+}
+
+// csv and json tables share the same record shape (json being an array of objects
+// with the csv column names as keys) so both feed the same fill functions
+
+// errors name the file, line and column of the bad field
+fn deserialize_records<T: DeserializeOwned, R: std::io::Read>(mut rdr: csv::Reader<R>, path: &str) -> impl Iterator<Item = Result<T, ValactError>> + use<T, R> {
+    let path = path.to_string();
+    let headers = rdr.headers().ok().cloned();
+    return rdr.into_deserialize().map(move |result| result.map_err(|e| ValactError::csv(&path, headers.as_ref(), e)));
+}
+
+fn csv_records<T: DeserializeOwned>(path: &str) -> Result<impl Iterator<Item = Result<T, ValactError>> + use<T>, ValactError> {
+    let file = File::open(path).map_err(|source| ValactError::File { path: path.to_string(), source })?;
+    return Ok(deserialize_records(csv::Reader::from_reader(file), path));
+}
+
+// csv text already in memory, as for tables passed in rather than read from data_dir, name is used in errors
+pub fn csv_text_records<T: DeserializeOwned + 'static>(name: &str, text: &str) -> RecordIter<T> {
+    return Box::new(deserialize_records(csv::Reader::from_reader(std::io::Cursor::new(text.to_string())), name));
+}
+
+fn json_records<T: DeserializeOwned>(path: &str) -> Result<impl Iterator<Item = Result<T, ValactError>> + use<T>, ValactError> {
+    let file = File::open(path).map_err(|source| ValactError::File { path: path.to_string(), source })?;
+    let records: Vec<T> = serde_json::from_reader(BufReader::new(file)).map_err(|e| ValactError::from(e).in_file(path))?;
+    return Ok(records.into_iter().map(Ok));
+}
+
+pub fn is_json(path: &str) -> bool {
+    return Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+}
+
+pub type RecordIter<T> = Box<dyn Iterator<Item = Result<T, ValactError>>>;
+
+// every record of a csv or json table, for when the whole table is wanted rather than one cell
+pub fn table_records<T: DeserializeOwned + 'static>(path: &str) -> Result<RecordIter<T>, ValactError> {
+    if is_json(path) {
+        return Ok(Box::new(json_records(path)?));
+    }
+    return Ok(Box::new(csv_records(path)?));
+}
+
+// rates come back with one entry per policy year for `years` years, entries in the table past that are ignored
+
+fn fill_ia_py(records: impl Iterator<Item = Result<IAPYRecord, ValactError>>, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    // create default vector
+    let mut rates: Vec<f64> = vec![default; years];
+
+    for result in records {
+        let record = result?;
+        let idx = record.policy_year.index();
+        if record.issue_age == issue_age && idx < years {
+            rates[idx] = record.rate
+        }
+    }
+    return Ok(rates);
+}
+
+fn fill_gen_rc_ia_py(records: impl Iterator<Item = Result<GenRCIAPYRecord, ValactError>>, default: f64, gender: &str, risk_class: &str, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    let mut rates: Vec<f64> = vec![default; years];
+
+    for result in records {
+       let record = result?;
+       let idx = record.policy_year.index();
+       if record.gender == gender && record.risk_class == risk_class && record.issue_age == issue_age && idx < years {
+           rates[idx] = record.rate
+       }
+    }
+    return Ok(rates);
+}
+
+fn fill_py(records: impl Iterator<Item = Result<PYRecord, ValactError>>, default: f64, years: usize) -> Result<Vec<f64>, ValactError> {
+    let mut rates: Vec<f64> = vec![default; years];
+
+    for result in records {
+       let record = result?;
+       if record.policy_year.index() < years {
+           rates[record.policy_year.index()] = record.rate;
+       }
+    }
+    return Ok(rates);
+}
+
+fn fill_aa(records: impl Iterator<Item = Result<AARecord, ValactError>>, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    let mut rates: Vec<f64> = vec![default; years];
+
+    for result in records {
+       let record = result?;
+       if let Some(idx) = record.attained_age.years_since(issue_age) && idx < years {
+           rates[idx] = record.rate;
+       }
+    }
+    return Ok(rates);
+}
+
+pub fn read_ia_py_csv(path: &str, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    return fill_ia_py(csv_records(path)?, default, issue_age, years);
+}
+
+pub fn read_ia_py_json(path: &str, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    return fill_ia_py(json_records(path)?, default, issue_age, years);
+}
+
+pub fn read_gen_rc_ia_py_csv(path: &str, default: f64, gender: &str, risk_class: &str, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    return fill_gen_rc_ia_py(csv_records(path)?, default, gender, risk_class, issue_age, years);
+}
+
+pub fn read_gen_rc_ia_py_json(path: &str, default: f64, gender: &str, risk_class: &str, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    return fill_gen_rc_ia_py(json_records(path)?, default, gender, risk_class, issue_age, years);
+}
+
+pub fn read_py_csv(path: &str, default: f64, years: usize) -> Result<Vec<f64>, ValactError> {
+    return fill_py(csv_records(path)?, default, years);
+}
+
+pub fn read_py_json(path: &str, default: f64, years: usize) -> Result<Vec<f64>, ValactError> {
+    return fill_py(json_records(path)?, default, years);
+}
+
+pub fn read_aa_csv(path: &str, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    return fill_aa(csv_records(path)?, default, issue_age, years);
+}
+
+pub fn read_aa_json(path: &str, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    return fill_aa(json_records(path)?, default, issue_age, years);
+}
+
+// a single rate for the issue age rather than one per policy year
+pub fn read_ia(path: &str, default: f64, issue_age: IssueAge) -> Result<f64, ValactError> {
+    let records: RecordIter<IARecord> = table_records(path)?;
+    for result in records {
+        let record = result?;
+        if record.issue_age == issue_age {
+            return Ok(record.rate);
+        }
+    }
+    return Ok(default);
+}
+
+// format agnostic versions, json if the file ends in .json otherwise csv
+pub fn read_ia_py(path: &str, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    if is_json(path) {read_ia_py_json(path, default, issue_age, years)} else {read_ia_py_csv(path, default, issue_age, years)}
+}
+
+pub fn read_gen_rc_ia_py(path: &str, default: f64, gender: &str, risk_class: &str, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    if is_json(path) {read_gen_rc_ia_py_json(path, default, gender, risk_class, issue_age, years)} else {read_gen_rc_ia_py_csv(path, default, gender, risk_class, issue_age, years)}
+}
+
+pub fn read_py(path: &str, default: f64, years: usize) -> Result<Vec<f64>, ValactError> {
+    if is_json(path) {read_py_json(path, default, years)} else {read_py_csv(path, default, years)}
+}
+
+// policy year rates where years past the end of the table stay at its last rate, e.g. lapse rates or a rate path
+pub fn read_py_carried(path: &str, years: usize) -> Result<Vec<f64>, ValactError> {
+    let mut rates = read_py(path, f64::NAN, years)?;
+    let mut last = 0.0;
+    for rate in rates.iter_mut() {
+        if rate.is_nan() {
+            *rate = last;
+        }
+        last = *rate;
+    }
+    return Ok(rates);
+}
+
+pub fn read_aa(path: &str, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    if is_json(path) {read_aa_json(path, default, issue_age, years)} else {read_aa_csv(path, default, issue_age, years)}
+}
+
+// an Issue_Age, Policy_Year, Rate table laid over rates already assembled, years the table has no rate for keep
+// the rate they had
+fn overlay_ia_py(path: &str, rates: &mut [f64], issue_age: IssueAge, convert: impl Fn(f64) -> f64) -> Result<(), ValactError> {
+    let table = read_ia_py(path, f64::NAN, issue_age, rates.len())?;
+    for (rate, over) in rates.iter_mut().zip(table) {
+        if !over.is_nan() {
+            *rate = convert(over);
+        }
+    }
+    return Ok(());
+}
+
+// premium loads by policy year in place of the product's, e.g. a load that steps down after the first ten years
+pub fn apply_premium_load_table(rates: &mut RateSet, path: &str, issue_age: IssueAge) -> Result<(), ValactError> {
+    return overlay_ia_py(path, &mut rates.premium_loads, issue_age, |load| load);
+}
+
+// credited rates by policy year in place of the product's, e.g. a rate graded up by duration
+// the table gives annual effective rates and the rate set holds monthly ones
+pub fn apply_credited_rate_table(rates: &mut RateSet, path: &str, issue_age: IssueAge) -> Result<(), ValactError> {
+    return overlay_ia_py(path, &mut rates.interest_rates, issue_age, |rate| (1.0 + rate).powf(1.0 / 12.0) - 1.0);
+}
+
+// a directory of rate tables, ./data unless --data-dir or a run config gives another, and the date the versions
+// of its tables are chosen for, the run's --rate-date or a policy's issue date
+// a value rather than a setting for the process so a run, a repository or an ffi handle each read from their own
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DataDir {
+    dir: String,
+    as_of: Option<NaiveDate>,
+}
+
+impl Default for DataDir {
+    fn default() -> Self {
+        return DataDir::new("./data");
+    }
+}
+
+impl fmt::Display for DataDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.dir)
+    }
+}
+
+impl DataDir {
+    pub fn new(dir: &str) -> DataDir {
+        return DataDir { dir: dir.trim_end_matches('/').to_string(), as_of: None };
+    }
+
+    // the same directory with its tables in the versions in effect on a date, None for the latest
+    pub fn as_of(&self, as_of: Option<NaiveDate>) -> DataDir {
+        return DataDir { dir: self.dir.clone(), as_of };
+    }
+
+    // for a policy issued on a date, a date already set for the run takes its place
+    pub fn for_issue_date(&self, issue_date: Option<NaiveDate>) -> DataDir {
+        return self.as_of(self.as_of.or(issue_date));
+    }
+
+    pub fn date(&self) -> Option<NaiveDate> {
+        return self.as_of;
+    }
+
+    pub fn as_str(&self) -> &str {
+        return &self.dir;
+    }
+
+    // a file in the directory
+    pub fn path(&self, file: &str) -> String {
+        return format!("{}/{}", self.dir, file);
+    }
+
+    // a table named e.g. "coi" is read from coi.json when present, otherwise coi.csv, or if the table is
+    // versioned, from the version in effect on the directory's date
+    pub fn table_path(&self, name: &str) -> String {
+        return self.table_path_as_of(name, self.as_of);
+    }
+
+    // tables can come in versions by effective date, coi_2023-01.csv, coi_2024-06-15.json and so on, the day of
+    // the month defaulting to the 1st, alongside or in place of the unversioned coi.csv
+    // a date takes the latest version effective on or before it, a date before every version the unversioned
+    // table, and no date at all the latest version; with no unversioned table the earliest version stands in for it
+    pub fn table_path_as_of(&self, name: &str, as_of: Option<NaiveDate>) -> String {
+        let versions = self.table_versions(name);
+        let unversioned = || {
+            let json = self.path(&format!("{}.json", name));
+            return if Path::new(&json).exists() {json} else {self.path(&format!("{}.csv", name))};
+        };
+        let chosen = match as_of {
+            Some(date) => versions.iter().rfind(|(effective, _)| *effective <= date),
+            None => versions.last(),
+        };
+        return match chosen {
+            Some((_, path)) => path.clone(),
+            None if Path::new(&unversioned()).exists() => unversioned(),
+            None => versions.first().map_or_else(unversioned, |(_, path)| path.clone()),
+        };
+    }
+
+    // the versions of a table by effective date, earliest first, a json version taking the place of a csv one of
+    // the same date
+    pub fn table_versions(&self, name: &str) -> Vec<(NaiveDate, String)> {
+        let mut versions: Vec<(NaiveDate, String)> = Vec::new();
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return versions;
+        };
+        let prefix = format!("{}_", name);
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some((stem, extension)) = file_name.rsplit_once('.') else { continue };
+            if extension != "csv" && extension != "json" {
+                continue;
+            }
+            if let Some(date) = stem.strip_prefix(&prefix).and_then(effective_date) {
+                versions.push((date, self.path(&file_name)));
+            }
+        }
+        versions.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| is_json(&b.1).cmp(&is_json(&a.1))));
+        versions.dedup_by_key(|(date, _)| *date);
+        return versions;
+    }
+
+    // every effective date any table has a version for, earliest first, so policies issued between two of them
+    // use the same tables
+    pub fn version_dates(&self, names: &[&str]) -> Vec<NaiveDate> {
+        let mut dates: Vec<NaiveDate> = names.iter().flat_map(|name| self.table_versions(name)).map(|(date, _)| date).collect();
+        dates.sort();
+        dates.dedup();
+        return dates;
+    }
+
+    // a per cell coi table can be dropped into the directory and is used in place of the coi table for that cell
+    // coi_{gender}_{risk_class}.xml     XTbML table downloaded from mort.soa.org
+    // coi_{gender}_{risk_class}_su.csv  select and ultimate table, see select_ultimate.rs
+    pub fn cell_coi_path(&self, gender: &str, risk_class: &str) -> Option<String> {
+        for suffix in [".xml", "_su.csv"] {
+            let path = self.path(&format!("coi_{}_{}{}", gender, risk_class, suffix));
+            if Path::new(&path).exists() {
+                return Some(path);
+            }
+        }
+        return None;
+    }
+}
+
+// yyyy-mm or yyyy-mm-dd
+fn effective_date(text: &str) -> Option<NaiveDate> {
+    return NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()
+        .or_else(|| NaiveDate::parse_from_str(&format!("{}-01", text), "%Y-%m-%d").ok());
+}
+
+// all rates needed for a projection, indexed by policy year - 1
+// named fields rather than a map so a typo is a compile error instead of a panic
+// the vectors run from issue to maturity_age, one entry per policy year
+#[derive(Clone, Debug)]
+pub struct RateSet {
+    pub maturity_age: AttainedAge,
+    pub premium_loads: Vec<f64>,
+    pub policy_fees: Vec<f64>,
+    pub unit_loads: Vec<f64>,
+    pub corr_facts: Vec<f64>,
+    pub naar_discs: Vec<f64>,
+    pub coi_rates: Vec<f64>,
+    pub interest_rates: Vec<f64>,
+    // per 1000 of face, taken off the account value on surrender
+    pub surrender_charges: Vec<f64>,
+}
+
+impl RateSet {
+    // number of policy years every rate vector covers
+    pub fn years(&self) -> usize {
+        return [
+            self.premium_loads.len(), self.policy_fees.len(), self.unit_loads.len(), self.corr_facts.len(),
+            self.naar_discs.len(), self.coi_rates.len(), self.interest_rates.len(), self.surrender_charges.len(),
+        ].into_iter().min().unwrap_or(0);
+    }
+}
+
+// the python and go samples, and the original version of this, all run to 121
+pub const DEFAULT_MATURITY_AGE: AttainedAge = AttainedAge::of(121);
+
+// policy years from issue to maturity
+pub fn projection_years(issue_age: IssueAge, maturity_age: AttainedAge) -> Result<usize, ValactError> {
+    return match maturity_age.years_since(issue_age) {
+        Some(years) if years > 0 => Ok(years),
+        _ => Err(format!("maturity age {} must be after issue age {}", maturity_age, issue_age).into()),
+    };
+}
+
+// the (gender, risk class) a cell coi file name is for, None if the name is not one
+pub fn cell_coi_key(file_name: &str) -> Option<(String, String)> {
+    let cell = file_name.strip_prefix("coi_")?;
+    let cell = cell.strip_suffix(".xml").or_else(|| cell.strip_suffix("_su.csv"))?;
+    let (gender, risk_class) = cell.split_once('_')?;
+    return Some((gender.to_string(), risk_class.to_string()));
+}
+
+// rates per 1000 either way, xtbml rates are per unit so are scaled up
+pub fn read_cell_coi_table(path: &str) -> Result<SelectUltimateTable, ValactError> {
+    if path.ends_with(".xml") {
+        return Ok(read_xtbml(path)?.to_select_ultimate().scaled(1000.0));
+    }
+    return read_select_ultimate_csv(path);
+}
+
+pub fn read_coi(data_dir: &DataDir, gender: &str, risk_class: &str, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    if let Some(path) = data_dir.cell_coi_path(gender, risk_class) {
+        return Ok(read_cell_coi_table(&path)?.rates_for_issue_age(issue_age, 0.0, years));
+    }
+    return read_gen_rc_ia_py(&data_dir.table_path("coi"), 0.0, gender, risk_class, issue_age, years);
+}
+
+// surrender charges are optional, a product without a surrender_charge table has none
+pub fn read_surrender_charges(data_dir: &DataDir, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+    let path = data_dir.table_path("surrender_charge");
+    if !Path::new(&path).exists() {
+        return Ok(vec![0.0; years]);
+    }
+    return read_ia_py(&path, 0.0, issue_age, years);
+}
+
+// commission target premium per 1000 of face, none without a target_premium table
+pub fn read_target_premium(data_dir: &DataDir, issue_age: IssueAge) -> Result<f64, ValactError> {
+    let path = data_dir.table_path("target_premium");
+    if !Path::new(&path).exists() {
+        return Ok(0.0);
+    }
+    return read_ia(&path, 0.0, issue_age);
+}
+
+// from the tables in ./data
+pub fn get_rates(gender: &str, risk_class: &str, issue_age: IssueAge) -> Result<RateSet, ValactError> {
+    return get_rates_to_maturity(&DataDir::default(), gender, risk_class, issue_age, DEFAULT_MATURITY_AGE);
+}
+
+// the built in product, see product.rs for what its charges are
+pub fn get_rates_to_maturity(data_dir: &DataDir, gender: &str, risk_class: &str, issue_age: IssueAge, maturity_age: AttainedAge) -> Result<RateSet, ValactError> {
+    return ProductSpec::default().rate_set(data_dir, gender, risk_class, issue_age, maturity_age).map_err(|e| e.for_cell(gender, risk_class, issue_age));
+}
@@ -0,0 +1,208 @@
+use std::{
+    fs, path::Path, sync::{Arc, RwLock}, thread, time::{Duration, SystemTime}
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::{
+    error::ValactError, rates::DataDir, repository::RateRepository, tables::TABLE_NAMES
+};
+
+// the rate repository a server solves against, swapped whole for one built from the data directory's tables
+// when they change so the tables can be updated without a restart
+// the watcher polls the sizes and modified times of the csv and json files in the data directory rather than
+// relying on file system events, and only reloads once they have stayed the same for a whole interval so a
+// table still being copied in is not read half written
+// the new repository is built to the side and the old one keeps serving until it is swapped in, requests
+// already running finish on the repository they started with, and a build that fails leaves the old one live
+// checksums are taken before and after a build and have to agree, tables that changed while they were being
+// read are loaded again on the next poll rather than reported with checksums of files that were not what was read
+
+// a table file in the data directory
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TableFile {
+    pub file: String,
+    pub bytes: u64,
+    pub modified: Option<DateTime<Utc>>,
+    // hex sha256 of the file, as sha256sum prints it
+    pub sha256: String,
+}
+
+// the tables being served, as the admin endpoint reports them
+#[derive(Clone, Debug, Serialize)]
+pub struct LiveTables {
+    pub data_dir: String,
+    pub loaded_at: DateTime<Utc>,
+    // times the tables have been swapped since the server started
+    pub reloads: usize,
+    pub watching: bool,
+    // effective dates of the versioned rate tables
+    pub versions: Vec<NaiveDate>,
+    pub tables: Vec<TableFile>,
+    // why the last reload failed, the tables above are still the ones served
+    pub last_error: Option<String>,
+}
+
+// a file's name, size and modified time, what the watcher compares between polls
+type Stamp = (String, u64, Option<SystemTime>);
+
+fn stamps(dir: &str) -> Result<Vec<Stamp>, ValactError> {
+    let entries = fs::read_dir(dir).map_err(|source| ValactError::File { path: dir.to_string(), source })?;
+    let mut stamps = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let table = path.extension().is_some_and(|ext| ext == "csv" || ext == "json");
+        let metadata = entry.metadata()?;
+        if !table || !metadata.is_file() {
+            continue;
+        }
+        stamps.push((entry.file_name().to_string_lossy().into_owned(), metadata.len(), metadata.modified().ok()));
+    }
+    stamps.sort();
+    return Ok(stamps);
+}
+
+fn sha256(path: &Path) -> Result<String, ValactError> {
+    let bytes = fs::read(path).map_err(|source| ValactError::File { path: path.display().to_string(), source })?;
+    return Ok(Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect());
+}
+
+pub fn table_files(dir: &str) -> Result<Vec<TableFile>, ValactError> {
+    return stamps(dir)?.into_iter().map(|(file, bytes, modified)| {
+        let sha256 = sha256(&Path::new(dir).join(&file))?;
+        return Ok(TableFile { file, bytes, modified: modified.map(DateTime::<Utc>::from), sha256 });
+    }).collect();
+}
+
+// a repository from the tables as they are and the checksums of what it was built from
+fn build_checked<B>(data_dir: &DataDir, build: &B) -> Result<(Arc<RateRepository>, Vec<TableFile>), ValactError>
+where
+    B: Fn() -> Result<Arc<RateRepository>, ValactError>,
+{
+    let before = table_files(data_dir.as_str())?;
+    let repository = build()?;
+    if table_files(data_dir.as_str())? != before {
+        return Err("the rate tables changed while they were being loaded".into());
+    }
+    return Ok((repository, before));
+}
+
+pub struct LiveRates {
+    repository: RwLock<Arc<RateRepository>>,
+    tables: RwLock<LiveTables>,
+    data_dir: DataDir,
+}
+
+impl LiveRates {
+    // a repository built elsewhere, reported against its data directory's tables as they are now
+    pub fn new(repository: impl Into<Arc<RateRepository>>) -> LiveRates {
+        let repository = repository.into();
+        let data_dir = repository.data_dir().clone();
+        let (tables, last_error) = match table_files(data_dir.as_str()) {
+            Ok(tables) => (tables, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        return LiveRates::with_tables(data_dir, repository, tables, last_error);
+    }
+
+    // build is what is called again on every reload, with the tables in data_dir
+    pub fn load<B>(data_dir: DataDir, build: &B) -> Result<LiveRates, ValactError>
+    where
+        B: Fn() -> Result<Arc<RateRepository>, ValactError>,
+    {
+        let (repository, tables) = build_checked(&data_dir, build)?;
+        return Ok(LiveRates::with_tables(data_dir, repository, tables, None));
+    }
+
+    fn with_tables(data_dir: DataDir, repository: Arc<RateRepository>, tables: Vec<TableFile>, last_error: Option<String>) -> LiveRates {
+        let tables = LiveTables {
+            data_dir: data_dir.to_string(),
+            loaded_at: DateTime::<Utc>::from(SystemTime::now()),
+            reloads: 0,
+            watching: false,
+            versions: data_dir.version_dates(&TABLE_NAMES),
+            tables,
+            last_error,
+        };
+        return LiveRates { repository: RwLock::new(repository), tables: RwLock::new(tables), data_dir };
+    }
+
+    // the repository to solve a request against, held for the whole request
+    pub fn current(&self) -> Arc<RateRepository> {
+        return Arc::clone(&self.repository.read().unwrap());
+    }
+
+    pub fn tables(&self) -> LiveTables {
+        return self.tables.read().unwrap().clone();
+    }
+
+    fn live_stamps(&self) -> Vec<Stamp> {
+        return self.tables.read().unwrap().tables.iter()
+            .map(|table| (table.file.clone(), table.bytes, table.modified.map(SystemTime::from)))
+            .collect();
+    }
+
+    // swaps in a repository built from the tables as they are now, the old one stays live if the build fails
+    pub fn reload<B>(&self, build: &B) -> Result<(), ValactError>
+    where
+        B: Fn() -> Result<Arc<RateRepository>, ValactError>,
+    {
+        let built = build_checked(&self.data_dir, build);
+        let mut live = self.tables.write().unwrap();
+        let (repository, tables) = match built {
+            Ok(built) => built,
+            Err(e) => {
+                live.last_error = Some(e.to_string());
+                return Err(e);
+            }
+        };
+        *self.repository.write().unwrap() = repository;
+        live.loaded_at = DateTime::<Utc>::from(SystemTime::now());
+        live.reloads += 1;
+        live.versions = self.data_dir.version_dates(&TABLE_NAMES);
+        live.tables = tables;
+        live.last_error = None;
+        info!(reloads = live.reloads, tables = live.tables.len(), "rate tables reloaded from {}", live.data_dir);
+        return Ok(());
+    }
+
+    // polls the data directory on a thread of its own for as long as the process runs
+    pub fn watch<B>(self: &Arc<Self>, interval: Duration, build: B) -> Result<(), ValactError>
+    where
+        B: Fn() -> Result<Arc<RateRepository>, ValactError> + Send + 'static,
+    {
+        self.tables.write().unwrap().watching = true;
+        let live = Arc::clone(self);
+        info!("watching {} for rate table changes every {:?}", self.data_dir, interval);
+        thread::Builder::new().name("rate-table-watch".to_string()).spawn(move || {
+            let mut previous = live.live_stamps();
+            // the stamps of a reload that failed, not tried again until the tables change
+            let mut failed: Option<Vec<Stamp>> = None;
+            loop {
+                thread::sleep(interval);
+                let stamps = match stamps(live.data_dir.as_str()) {
+                    Ok(stamps) => stamps,
+                    Err(e) => {
+                        warn!("watching rate tables: {}", e);
+                        continue;
+                    }
+                };
+                let settled = stamps == previous;
+                previous = stamps;
+                if !settled || previous == live.live_stamps() || failed.as_ref() == Some(&previous) {
+                    continue;
+                }
+                failed = None;
+                if let Err(e) = live.reload(&build) {
+                    warn!("rate tables not reloaded, the old ones are still live: {}", e);
+                    failed = Some(previous.clone());
+                }
+            }
+        })?;
+        return Ok(());
+    }
+}
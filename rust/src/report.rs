@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    age::IssueAge, annuity::AnnuityMonth, batch::PolicyRecord, error::ValactError, esg::GeneratorParams, guaranteed::GuaranteedSummary, illustration::CellTest, nlg::NoLapseGuarantee, payout::PayoutOptions, profit::ProfitSummary, projection::{MonthResult, PremiumMode, SolveResult, SolveTarget}, repository::CacheStats, scenarios::ScenarioResult, sensitivity::SensitivityResult, tax7702::SevenPayTest, term::TermPricing, vm20::DeterministicReserve, wholelife::WholeLifePricing
+};
+
+// structured versions of what the cli prints, for downstream tooling
+
+#[derive(Debug, Serialize)]
+pub struct SolveReport {
+    pub inputs: PolicyRecord,
+    pub target: SolveTarget,
+    pub premium: f64,
+    // how the root finder got there
+    pub solve: SolveResult,
+    // payment at each modal date, only when premiums are not paid annually
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modal_premium: Option<ModalPremium>,
+    // monthly projection at the solved premium, only when asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projection: Option<Vec<MonthResult>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModalPremium {
+    pub mode: PremiumMode,
+    pub premium: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectionReport {
+    pub inputs: PolicyRecord,
+    pub annual_premium: f64,
+    pub end_value: f64,
+    // only when the seven-pay test was asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seven_pay: Option<SevenPayTest>,
+    // only when the no-lapse guarantee was asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_lapse_guarantee: Option<NoLapseGuarantee>,
+    // only when the guaranteed basis was asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guaranteed: Option<GuaranteedSummary>,
+    // only when expected cash flows or profits were asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profit: Option<ProfitSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deterministic_reserve: Option<DeterministicReserve>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projection: Option<Vec<MonthResult>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FaceSolveReport {
+    pub gender: String,
+    pub risk_class: String,
+    pub issue_age: IssueAge,
+    pub annual_premium: f64,
+    pub face_amount: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScenarioReport {
+    pub inputs: PolicyRecord,
+    pub annual_premium: f64,
+    // one or the other, the file read or the parameters the scenarios were generated from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scenario_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generator: Option<GeneratorParams>,
+    pub scenarios: Vec<ScenarioResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IllustrationTestReport {
+    // census the cells came from
+    pub input: String,
+    pub cells: Vec<CellTest>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SensitivityReport {
+    pub inputs: PolicyRecord,
+    // unshocked base first
+    pub sensitivities: Vec<SensitivityResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TermReport {
+    pub inputs: PolicyRecord,
+    pub pricing: TermPricing,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WholeLifeReport {
+    pub inputs: PolicyRecord,
+    pub pricing: WholeLifePricing,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnnuityReport {
+    pub issue_age: IssueAge,
+    pub annuitization_age: i32,
+    pub annuitization_value: f64,
+    pub cash_surrender_value: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projection: Option<Vec<AnnuityMonth>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PayoutReport {
+    pub options: PayoutOptions,
+    // value of 1 a year of income
+    pub factor: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annual_income: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    pub input_file: String,
+    pub output_file: String,
+    pub policies: usize,
+    pub failed: usize,
+    pub summary_file: Option<String>,
+    pub rate_cache: CacheStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RateBookReport {
+    pub output_file: String,
+    pub rows: usize,
+    pub cells: usize,
+    pub failed: usize,
+    // a failure's error message and how many cells failed with it
+    pub failures: BTreeMap<String, usize>,
+    pub rate_cache: CacheStats,
+}
+
+pub fn to_json<T: Serialize>(report: &T) -> Result<String, ValactError> {
+    return Ok(serde_json::to_string_pretty(report)?);
+}
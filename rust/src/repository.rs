@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap, fmt, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex}
+};
+
+use chrono::NaiveDate;
+
+use crate::{
+    age::{AttainedAge, IssueAge}, error::ValactError, rates::{get_rates, DataDir, RateSet, DEFAULT_MATURITY_AGE}, tables::{RateTables, TABLE_NAMES}
+};
+
+// the version is the effective date of the rate tables to load the cell from, None for the tables as they are
+type Loader = Box<dyn Fn(&str, &str, IssueAge, Option<NaiveDate>) -> Result<RateSet, ValactError> + Send + Sync>;
+
+type CellKey = (String, String, IssueAge, Option<NaiveDate>);
+
+#[derive(Clone, Copy, Debug, Default, serde::Serialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub entries: usize,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lookups = self.hits + self.misses;
+        let hit_rate = if lookups > 0 {100.0 * self.hits as f64 / lookups as f64} else {0.0};
+        write!(f, "{} lookups, {} hits, {} misses ({:.1}% hit rate), {} cells cached", lookups, self.hits, self.misses, hit_rate, self.entries)
+    }
+}
+
+// rates by (gender, risk class, issue age) so policies in the same cell only read the tables once
+// safe to share across the batch runner's worker threads, or behind an Arc between threads that outlive the
+// run, and each cell's rates are held once behind an Arc of their own that lookups hand out rather than a copy
+// with versioned tables a cell is cached once per version, policies issued while the same versions were in
+// effect sharing it
+pub struct RateRepository {
+    loader: Loader,
+    cache: Mutex<HashMap<CellKey, Arc<RateSet>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    // effective dates of the table versions, earliest first, empty when the tables are not versioned
+    versions: Vec<NaiveDate>,
+    // where the tables are read from, what a server reports and watches, and the run's rate date if it has one
+    data_dir: DataDir,
+}
+
+impl RateRepository {
+    // rate tables from ./data
+    pub fn new() -> RateRepository {
+        return RateRepository::with_loader(get_rates);
+    }
+
+    // tables from ./data parsed up front so a miss is an in memory lookup rather than a read of every file
+    pub fn in_memory() -> Result<RateRepository, ValactError> {
+        return RateRepository::in_memory_to_maturity(&DataDir::default(), DEFAULT_MATURITY_AGE);
+    }
+
+    // the tables in data_dir, with every cell's rates sized to run to maturity_age
+    pub fn in_memory_to_maturity(data_dir: &DataDir, maturity_age: AttainedAge) -> Result<RateRepository, ValactError> {
+        return RateRepository::in_memory_with(data_dir, maturity_age, |rates, _| Ok(rates));
+    }
+
+    // same, with adjust applied to each cell's rates as it is loaded
+    // the tables as they are load up front, each other version the first time a policy needs it
+    pub fn in_memory_with<A>(data_dir: &DataDir, maturity_age: AttainedAge, adjust: A) -> Result<RateRepository, ValactError>
+    where
+        A: Fn(RateSet, IssueAge) -> Result<RateSet, ValactError> + Send + Sync + 'static,
+    {
+        let loaded: Mutex<HashMap<Option<NaiveDate>, Arc<RateTables>>> = Mutex::new(HashMap::from([(None, Arc::new(RateTables::load(data_dir)?))]));
+        let versions = data_dir.version_dates(&TABLE_NAMES);
+        let tables_dir = data_dir.clone();
+        let loader = move |gender: &str, risk_class: &str, issue_age: IssueAge, version: Option<NaiveDate>| {
+            let tables = {
+                let mut loaded = loaded.lock().unwrap();
+                match loaded.get(&version) {
+                    Some(tables) => Arc::clone(tables),
+                    None => Arc::clone(loaded.entry(version).or_insert(Arc::new(RateTables::load(&tables_dir.as_of(version))?))),
+                }
+            };
+            return adjust(tables.get_rates_to_maturity(gender, risk_class, issue_age, maturity_age)?, issue_age);
+        };
+        return Ok(RateRepository::with_dated_loader(versions, loader).with_data_dir(data_dir.clone()));
+    }
+
+    // loader is used for cells not yet in the cache, e.g. to read from a database instead
+    pub fn with_loader<F>(loader: F) -> RateRepository
+    where
+        F: Fn(&str, &str, IssueAge) -> Result<RateSet, ValactError> + Send + Sync + 'static,
+    {
+        return RateRepository::with_dated_loader(Vec::new(), move |gender, risk_class, issue_age, _| loader(gender, risk_class, issue_age));
+    }
+
+    // loader is given the version of the tables in effect at the policy's date, one of versions
+    pub fn with_dated_loader<F>(versions: Vec<NaiveDate>, loader: F) -> RateRepository
+    where
+        F: Fn(&str, &str, IssueAge, Option<NaiveDate>) -> Result<RateSet, ValactError> + Send + Sync + 'static,
+    {
+        return RateRepository {
+            loader: Box::new(loader),
+            cache: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            versions,
+            data_dir: DataDir::default(),
+        };
+    }
+
+    // the directory a loader reads its tables from, when not ./data
+    pub fn with_data_dir(self, data_dir: DataDir) -> RateRepository {
+        return RateRepository { data_dir, ..self };
+    }
+
+    pub fn data_dir(&self) -> &DataDir {
+        return &self.data_dir;
+    }
+
+    // the run's rate date, if set, takes the place of the policy's; a date before every version is NaiveDate::MIN,
+    // for the unversioned tables
+    fn version(&self, as_of: Option<NaiveDate>) -> Option<NaiveDate> {
+        if self.versions.is_empty() {
+            return None;
+        }
+        let as_of = self.data_dir.date().or(as_of)?;
+        return Some(self.versions.iter().rfind(|effective| **effective <= as_of).copied().unwrap_or(NaiveDate::MIN));
+    }
+
+    // a copy of the cell's rates to change, e.g. with face amount band charges
+    pub fn get_rates(&self, gender: &str, risk_class: &str, issue_age: IssueAge) -> Result<RateSet, ValactError> {
+        let rates = self.get_shared(gender, risk_class, issue_age)?;
+        return Ok(RateSet::clone(&rates));
+    }
+
+    pub fn get_shared(&self, gender: &str, risk_class: &str, issue_age: IssueAge) -> Result<Arc<RateSet>, ValactError> {
+        return self.get_shared_as_of(gender, risk_class, issue_age, None);
+    }
+
+    // rates from the table versions in effect on a date, a policy's issue date
+    pub fn get_shared_as_of(&self, gender: &str, risk_class: &str, issue_age: IssueAge, as_of: Option<NaiveDate>) -> Result<Arc<RateSet>, ValactError> {
+        let version = self.version(as_of);
+        let key = (gender.to_string(), risk_class.to_string(), issue_age, version);
+        if let Some(rates) = self.cache.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Arc::clone(rates));
+        }
+
+        // load outside the lock so other cells are not held up by the file reads, two threads missing on the same
+        // cell both load it and the first one in is kept
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let rates = Arc::new((self.loader)(gender, risk_class, issue_age, version).map_err(|e| e.for_cell(gender, risk_class, issue_age))?);
+        return Ok(Arc::clone(self.cache.lock().unwrap().entry(key).or_insert(rates)));
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        return CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.cache.lock().unwrap().len(),
+        };
+    }
+}
+
+impl Default for RateRepository {
+    fn default() -> RateRepository {
+        return RateRepository::new();
+    }
+}
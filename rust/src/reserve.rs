@@ -0,0 +1,67 @@
+
+use crate::{
+    age::IssueAge, assumptions::{ReserveMethod, ValuationAssumptions}, decrements::{read_cell_mortality, ExpectedMonth}, error::ValactError, projection::MonthResult, rates::DataDir
+};
+
+// statutory reserves on a simple CRVM or net level basis
+// the benefit is a level $1 of death benefit to the end of the valuation mortality with $1 paid as an endowment
+// there, valued with annual deaths at the end of the year and premiums at the start, and the reserve for a
+// policy is the larger of that reserve for its face amount and its cash surrender value
+// crvm is taken as full preliminary term, the first year net premium is the one year term cost and the renewal
+// net premium is the net level premium for the same policy issued a year older
+
+// valuation mortality per 1000 by policy year, the valuation_mortality table in the data directory when no table
+// is given and the coi rates when there is no valuation table either
+pub fn read_valuation_mortality(data_dir: &DataDir, path: Option<&str>, gender: &str, risk_class: &str, issue_age: IssueAge, coi_rates: &[f64]) -> Result<Vec<f64>, ValactError> {
+    return read_cell_mortality(path, &data_dir.table_path("valuation_mortality"), gender, risk_class, issue_age, coi_rates, 1.0);
+}
+
+// (benefit nsp, annuity due) at the start of each policy year, with one more entry for maturity
+fn commutation(mortality: &[f64], interest: f64) -> (Vec<f64>, Vec<f64>) {
+    let v = 1.0 / (1.0 + interest);
+    let years = mortality.len();
+    let mut benefit = vec![1.0; years + 1];
+    let mut annuity = vec![0.0; years + 1];
+    for t in (0..years).rev() {
+        let q = (mortality[t] / 1000.0).clamp(0.0, 1.0);
+        benefit[t] = v * (q + (1.0 - q) * benefit[t + 1]);
+        annuity[t] = 1.0 + v * (1.0 - q) * annuity[t + 1];
+    }
+    return (benefit, annuity);
+}
+
+// terminal reserve per $1 of face at the end of each policy year
+pub fn reserve_factors(mortality: &[f64], valuation: &ValuationAssumptions) -> Vec<f64> {
+    let (benefit, annuity) = commutation(mortality, valuation.interest);
+    let years = mortality.len();
+    if years == 0 {
+        return Vec::new();
+    }
+    // the full preliminary term first year net premium only pays for the first year's deaths, so the reserve at
+    // the end of the first year is nil and the terminal reserves only need the renewal net premium
+    let renewal = match valuation.method {
+        ReserveMethod::NetLevel => benefit[0] / annuity[0],
+        ReserveMethod::Crvm if annuity[1] > 0.0 => benefit[1] / annuity[1],
+        ReserveMethod::Crvm => 0.0,
+    };
+    return (1..=years).map(|t| (benefit[t] - renewal * annuity[t]).max(0.0)).collect();
+}
+
+// reserve per policy in force at the end of each step, terminal reserves interpolated through the policy year
+pub fn statutory_reserves(months: &[MonthResult], factors: &[f64], steps_per_year: i32) -> Vec<f64> {
+    return months.iter().map(|month| {
+        let year = (month.policy_year - 1) as usize;
+        let start = if year == 0 {0.0} else {factors[year - 1]};
+        let end = factors[year];
+        // from the month rather than the position so an inforce projection starting part way through a year lines up
+        let step_in_year = (month.month_in_policy_year - 1) / (12 / steps_per_year) + 1;
+        let fraction = f64::from(step_in_year) / f64::from(steps_per_year);
+        let reserve = month.face_amount * (start + (end - start) * fraction);
+        reserve.max(month.cash_surrender_value)
+    }).collect();
+}
+
+// reserves held per policy issued, the per policy reserve times the expected number in force
+pub fn reserves_held(expected: &[ExpectedMonth], reserves: &[f64]) -> Vec<f64> {
+    return expected.iter().zip(reserves).map(|(month, reserve)| month.inforce_end * reserve).collect();
+}
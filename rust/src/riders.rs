@@ -0,0 +1,246 @@
+use std::{
+    fmt::Debug, path::Path, sync::Arc
+};
+
+use crate::{age::IssueAge, error::ValactError, rates::{read_aa, DataDir, DEFAULT_MATURITY_AGE}};
+
+// riders attached to a policy, each is asked for its charge and benefit every step of the projection
+// charges come off the account value after the coi charge and before interest, in the order the riders are
+// attached so a rider that covers other riders' charges goes last, and credits go back on at the same point
+// benefits are reported alongside
+// the death benefit rather than added to it as what sets them off (an accident, a child's death) is not the
+// death of the insured
+// riders are shared between configs and the threads a batch or scenario set runs on, so they are immutable and
+// anything that changes through the projection is worked out from the context
+
+// what a rider can see of the policy in a step, amounts are for the step before the rider charges
+#[derive(Clone, Copy, Debug)]
+pub struct RiderContext {
+    pub policy_year: i32,
+    pub policy_month: i32,
+    pub attained_age: i32,
+    pub steps_per_year: i32,
+    pub face_amount: f64,
+    pub premium: f64,
+    pub expense_charge: f64,
+    pub coi_charge: f64,
+    // after premium, load, withdrawal and expense charge, what the death benefit is based on
+    pub account_value: f64,
+    // charges of the riders attached before this one
+    pub rider_charges: f64,
+}
+
+// what a rider does to the policy at the start of a step, after withdrawals and before any charges
+// a lien is held against the death benefit and cash value and accrues at lien_interest, annual effective
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RiderAdjustment {
+    pub face_reduction: f64,
+    pub value_reduction: f64,
+    pub lien: f64,
+    pub lien_interest: f64,
+    // paid out to the policyholder
+    pub payment: f64,
+}
+
+pub trait Rider: Debug + Send + Sync {
+    fn name(&self) -> &str;
+
+    // context has the face amount and account value at the start of the step and nothing charged yet
+    fn adjust(&self, _context: &RiderContext) -> RiderAdjustment {
+        return RiderAdjustment::default();
+    }
+
+    // taken from the account value in the step
+    fn charge(&self, _context: &RiderContext) -> f64 {
+        return 0.0;
+    }
+
+    // paid if the rider's benefit is triggered in the step
+    fn benefit(&self, _context: &RiderContext) -> f64 {
+        return 0.0;
+    }
+
+    // added to the account value in the step, e.g. deductions waived
+    fn credit(&self, _context: &RiderContext) -> f64 {
+        return 0.0;
+    }
+}
+
+pub type Riders = Vec<Arc<dyn Rider>>;
+
+// rates in these are annual per 1000 of benefit and charged monthly, i.e. a twelfth a month
+fn step_charge(annual_per_1000: f64, amount: f64, steps_per_year: i32) -> f64 {
+    return annual_per_1000 * amount / 1000.0 / f64::from(steps_per_year);
+}
+
+// accidental death benefit, an extra amount on accidental death up to expiry_age
+#[derive(Clone, Copy, Debug)]
+pub struct AccidentalDeathBenefit {
+    pub amount: f64,
+    pub annual_rate_per_1000: f64,
+    pub expiry_age: i32,
+}
+
+impl AccidentalDeathBenefit {
+    pub fn new(amount: f64) -> AccidentalDeathBenefit {
+        return AccidentalDeathBenefit { amount, annual_rate_per_1000: 0.96, expiry_age: 70 };
+    }
+}
+
+impl Rider for AccidentalDeathBenefit {
+    fn name(&self) -> &str {
+        return "ADB";
+    }
+
+    fn charge(&self, context: &RiderContext) -> f64 {
+        if context.attained_age >= self.expiry_age {
+            return 0.0;
+        }
+        return step_charge(self.annual_rate_per_1000, self.amount, context.steps_per_year);
+    }
+
+    fn benefit(&self, context: &RiderContext) -> f64 {
+        return if context.attained_age < self.expiry_age {self.amount} else {0.0};
+    }
+}
+
+// level term on the insured's children, units of 1000 each to expiry_age of the insured
+#[derive(Clone, Copy, Debug)]
+pub struct ChildTerm {
+    pub units: f64,
+    pub annual_rate_per_unit: f64,
+    pub expiry_age: i32,
+}
+
+impl ChildTerm {
+    pub fn new(units: f64) -> ChildTerm {
+        return ChildTerm { units, annual_rate_per_unit: 6.0, expiry_age: 65 };
+    }
+}
+
+impl Rider for ChildTerm {
+    fn name(&self) -> &str {
+        return "Child_Term";
+    }
+
+    fn charge(&self, context: &RiderContext) -> f64 {
+        if context.attained_age >= self.expiry_age {
+            return 0.0;
+        }
+        return self.units * self.annual_rate_per_unit / f64::from(context.steps_per_year);
+    }
+
+    fn benefit(&self, context: &RiderContext) -> f64 {
+        return if context.attained_age < self.expiry_age {self.units * 1000.0} else {0.0};
+    }
+}
+
+// waiver of monthly deductions, while the insured is disabled the coi, expense and earlier riders' charges are
+// credited back so the account value is not run down, charged per 1000 of those deductions at a rate by attained
+// age from the wmd table in the data directory until expiry_age
+// waived_from_year is a what-if disability from the start of that policy year to expiry_age
+#[derive(Clone, Debug)]
+pub struct WaiverOfDeductions {
+    // per 1000 of deductions by attained age
+    pub rates_per_1000: Vec<f64>,
+    pub expiry_age: i32,
+    pub waived_from_year: Option<i32>,
+}
+
+impl WaiverOfDeductions {
+    // table has Attained_Age and Rate columns, ages not in it are not charged
+    pub fn from_table(data_dir: &DataDir, path: Option<&str>, waived_from_year: Option<i32>) -> Result<WaiverOfDeductions, ValactError> {
+        let path = path.map_or_else(|| data_dir.table_path("wmd"), str::to_string);
+        if !Path::new(&path).exists() {
+            return Err(format!("no waiver of monthly deductions rate table at {}", path).into());
+        }
+        let rates_per_1000 = read_aa(&path, 0.0, IssueAge::new(0)?, usize::from(DEFAULT_MATURITY_AGE.get()))?;
+        return Ok(WaiverOfDeductions { rates_per_1000, expiry_age: 65, waived_from_year });
+    }
+
+    fn in_force(&self, context: &RiderContext) -> bool {
+        return context.attained_age < self.expiry_age;
+    }
+
+    fn waived(&self, context: &RiderContext) -> bool {
+        return self.in_force(context) && self.waived_from_year.is_some_and(|year| context.policy_year >= year);
+    }
+
+    fn deductions(context: &RiderContext) -> f64 {
+        return context.coi_charge + context.expense_charge + context.rider_charges;
+    }
+}
+
+impl Rider for WaiverOfDeductions {
+    fn name(&self) -> &str {
+        return "WMD";
+    }
+
+    // nothing is charged while deductions are being waived
+    fn charge(&self, context: &RiderContext) -> f64 {
+        if !self.in_force(context) || self.waived(context) {
+            return 0.0;
+        }
+        let rate = self.rates_per_1000.get(context.attained_age as usize).copied().unwrap_or(0.0);
+        return rate * WaiverOfDeductions::deductions(context) / 1000.0;
+    }
+
+    fn credit(&self, context: &RiderContext) -> f64 {
+        return if self.waived(context) {WaiverOfDeductions::deductions(context)} else {0.0};
+    }
+}
+
+// how an accelerated death benefit is paid for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccelerationMethod {
+    // the amount is paid less a discount for the early payment and the face amount and account value are
+    // reduced in proportion to the share of face accelerated
+    Discount { discount: f64 },
+    // the amount is advanced as a lien against the death benefit and cash value, which accrues interest and
+    // leaves the face amount and account value as they were
+    Lien { interest: f64 },
+}
+
+// chronic illness accelerated benefit, a what-if where the insured is chronically ill and accelerates a share of
+// the face amount at the start of policy_year, less a fixed processing fee
+#[derive(Clone, Copy, Debug)]
+pub struct ChronicIllness {
+    pub policy_year: i32,
+    pub share_of_face: f64,
+    pub method: AccelerationMethod,
+    pub fee: f64,
+}
+
+impl ChronicIllness {
+    pub fn new(policy_year: i32, share_of_face: f64, method: AccelerationMethod) -> ChronicIllness {
+        return ChronicIllness { policy_year, share_of_face: share_of_face.clamp(0.0, 1.0), method, fee: 150.0 };
+    }
+}
+
+impl Rider for ChronicIllness {
+    fn name(&self) -> &str {
+        return "Chronic_Illness";
+    }
+
+    fn adjust(&self, context: &RiderContext) -> RiderAdjustment {
+        let year_start = (context.policy_month - 1) % 12 == 0;
+        if context.policy_year != self.policy_year || !year_start {
+            return RiderAdjustment::default();
+        }
+        let accelerated = self.share_of_face * context.face_amount;
+        return match self.method {
+            AccelerationMethod::Discount { discount } => RiderAdjustment {
+                face_reduction: accelerated,
+                value_reduction: self.share_of_face * context.account_value.max(0.0),
+                payment: (accelerated * (1.0 - discount) - self.fee).max(0.0),
+                ..Default::default()
+            },
+            AccelerationMethod::Lien { interest } => RiderAdjustment {
+                lien: accelerated,
+                lien_interest: interest,
+                payment: (accelerated - self.fee).max(0.0),
+                ..Default::default()
+            },
+        };
+    }
+}
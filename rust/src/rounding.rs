@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+// how solved premiums, and optionally every charge and value of the projection, are rounded
+// the default matches the original solve, premiums to the cent half up and the projection left unrounded as in
+// approach1.py; rounding amounts as they are worked out is the way an admin system keeps its books
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RoundingMode {
+    // toward zero
+    Truncate,
+    // half away from zero
+    #[default]
+    HalfUp,
+    // half to the even unit, banker's rounding
+    #[serde(alias = "Bankers")]
+    HalfEven,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RoundingUnit {
+    #[default]
+    Cents,
+    Dollars,
+}
+
+impl RoundingUnit {
+    pub fn decimal_places(self) -> u32 {
+        return match self {
+            RoundingUnit::Cents => 2,
+            RoundingUnit::Dollars => 0,
+        };
+    }
+
+    // smallest step between two rounded amounts, what a solve bumps by when rounding leaves it short
+    pub fn size(self) -> f64 {
+        return match self {
+            RoundingUnit::Cents => 0.01,
+            RoundingUnit::Dollars => 1.0,
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RoundingPolicy {
+    #[serde(default)]
+    pub mode: RoundingMode,
+    #[serde(default)]
+    pub unit: RoundingUnit,
+    // round each premium load, expense, coi and interest credit, the surrender charge and the account value to
+    // the unit as the projection works them out, off leaves them at full precision
+    #[serde(default)]
+    pub amounts: bool,
+}
+
+// how far a scaled amount can be from a whole unit and still be taken as on it, so truncating 1255.03 does not
+// give 1255.02 because 1255.03 * 100 is 125502.99999999999
+const ON_UNIT: f64 = 1e-6;
+
+impl RoundingPolicy {
+    pub fn round(&self, amount: f64) -> f64 {
+        let scale = 10f64.powi(self.unit.decimal_places() as i32);
+        let scaled = amount * scale;
+        let nearest = scaled.round();
+        let rounded = if (scaled - nearest).abs() < ON_UNIT {
+            nearest
+        } else {
+            match self.mode {
+                RoundingMode::Truncate => scaled.trunc(),
+                RoundingMode::HalfUp => nearest,
+                RoundingMode::HalfEven => scaled.round_ties_even(),
+            }
+        };
+        return rounded / scale;
+    }
+
+    // an amount worked out in the projection, rounded only if the policy rounds amounts
+    pub fn amount(&self, amount: f64) -> f64 {
+        return if self.amounts {self.round(amount)} else {amount};
+    }
+}
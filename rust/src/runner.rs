@@ -0,0 +1,298 @@
+use std::{
+    borrow::Borrow, collections::HashMap, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, mpsc, Mutex}, thread, time::{Duration, Instant}
+};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    age::IssueAge, batch::{solve_policies_lockstep, PolicyRecord, PolicyResult}, error::ValactError, lanes::LANES, projection::{DeathBenefitOption, SolveTarget}, rates::RateSet, repository::RateRepository, solver::SolverOptions
+};
+
+// each policy solve is independent so the census is split across a rayon pool, policies of the same issue age
+// being solved a few at a time in lockstep (lanes.rs)
+// threads = None uses rayon's default of one thread per core
+// the tables are read into memory once and every thread looks its cells up in the one repository
+pub fn solve_policies_parallel(policies: &[PolicyRecord], threads: Option<usize>) -> Result<Vec<PolicyResult>, ValactError> {
+    let repository = RateRepository::in_memory()?;
+    return solve_policies_parallel_with(policies, threads, |policy| repository.get_shared(&policy.gender, &policy.risk_class, policy.issue_age));
+}
+
+// rates_for is called from the worker threads so has to be Sync
+pub fn solve_policies_parallel_with<F, S>(policies: &[PolicyRecord], threads: Option<usize>, rates_for: F) -> Result<Vec<PolicyResult>, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+{
+    let options = SolverOptions::new(SolveTarget::EndowAtMaturity.default_method(), 0.005);
+    return solve_policies_parallel_using(policies, threads, rates_for, &options);
+}
+
+pub fn solve_policies_parallel_using<F, S>(policies: &[PolicyRecord], threads: Option<usize>, rates_for: F, options: &SolverOptions) -> Result<Vec<PolicyResult>, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+{
+    return solve_policies_reporting(policies, threads, rates_for, options, Duration::MAX, |_| {});
+}
+
+// where a batch run has got to, passed to the progress callback
+// a failed solve stops the run, but policies already being solved on other threads still finish and are counted
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Progress {
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    // policies finished per second so far
+    pub fn throughput(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        return if seconds > 0.0 {(self.completed + self.failed) as f64 / seconds} else {0.0};
+    }
+
+    // time left at the throughput so far, None until something has finished
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.throughput();
+        if rate <= 0.0 {
+            return None;
+        }
+        let left = self.total.saturating_sub(self.completed + self.failed);
+        return Some(Duration::from_secs_f64(left as f64 / rate));
+    }
+}
+
+// as solve_policies_parallel_using, calling progress from the worker threads at most once every interval and once
+// more when the run ends
+pub fn solve_policies_reporting<F, S, P>(policies: &[PolicyRecord], threads: Option<usize>, rates_for: F, options: &SolverOptions, interval: Duration, progress: P) -> Result<Vec<PolicyResult>, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+    P: Fn(&Progress) + Sync,
+{
+    let results = solve_tracked(policies, threads, rates_for, options, interval, progress, |_, result| result.is_ok())?;
+    // a failure stops the run so not every policy has a result, the first failure is the one reported
+    return results.into_iter().map(|(_, result)| result).collect();
+}
+
+// as solve_policies_reporting but every policy is solved even when some fail, each with its result or error in the
+// order of policies
+pub fn solve_policies_keep_going<F, S, P>(policies: &[PolicyRecord], threads: Option<usize>, rates_for: F, options: &SolverOptions, interval: Duration, progress: P) -> Result<Vec<Result<PolicyResult, ValactError>>, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+    P: Fn(&Progress) + Sync,
+{
+    let results = solve_tracked(policies, threads, rates_for, options, interval, progress, |_, _| true)?;
+    return Ok(results.into_iter().map(|(_, result)| result).collect());
+}
+
+// as solve_policies_keep_going with on_result called from the worker threads as each policy finishes, along with
+// its position in policies, e.g. to checkpoint it (checkpoint.rs); on_result returns false to stop the run as a
+// failure does without keep going, and the results are of the policies that were solved before it stopped
+pub fn solve_policies_recording<F, S, P, R>(policies: &[PolicyRecord], threads: Option<usize>, rates_for: F, options: &SolverOptions, interval: Duration, progress: P, on_result: R) -> Result<Vec<Solved>, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+    P: Fn(&Progress) + Sync,
+    R: Fn(usize, &Result<PolicyResult, ValactError>) -> bool + Sync,
+{
+    return solve_tracked(policies, threads, rates_for, options, interval, progress, on_result);
+}
+
+// a policy's position in policies and its result
+pub type Solved = (usize, Result<PolicyResult, ValactError>);
+
+// results with their positions in policies, in that order, counting and reporting progress as each policy finishes
+// the run stops once on_result returns false for a policy
+fn solve_tracked<F, S, P, R>(policies: &[PolicyRecord], threads: Option<usize>, rates_for: F, options: &SolverOptions, interval: Duration, progress: P, on_result: R) -> Result<Vec<Solved>, ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+    P: Fn(&Progress) + Sync,
+    R: Fn(usize, &Result<PolicyResult, ValactError>) -> bool + Sync,
+{
+    let pool = pool(threads)?;
+    let tracker = Tracker::new(policies.len(), interval);
+    let mut results = pool.install(|| solve_groups(policies, &rates_for, options, |index, result| {
+        tracker.finished(result.is_ok(), &progress);
+        return on_result(index, result);
+    }));
+    progress(&tracker.snapshot());
+    results.sort_unstable_by_key(|(index, _)| *index);
+    return Ok(results);
+}
+
+// results waiting between the workers and the writer of solve_policies_writing, a worker that finds this many
+// waits for the writer to catch up
+pub const STREAM_CAPACITY: usize = 1024;
+
+// as solve_policies_recording with each result handed to write as it finishes rather than kept, so a batch of any
+// size is solved in the memory of the census and STREAM_CAPACITY results
+// write is called on this thread, the single writer, while the workers run on the pool; results come in the order
+// the policies finish, not census order
+// write returning an error stops the run, e.g. on a failed solve without keep going, and the error is returned once
+// the groups already started have finished
+pub fn solve_policies_writing<F, S, P, W>(policies: &[PolicyRecord], threads: Option<usize>, rates_for: F, options: &SolverOptions, interval: Duration, progress: P, mut write: W) -> Result<(), ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+    P: Fn(&Progress) + Sync,
+    W: FnMut(Solved) -> Result<(), ValactError>,
+{
+    let pool = pool(threads)?;
+    let tracker = Tracker::new(policies.len(), interval);
+    let (sender, receiver) = mpsc::sync_channel::<Solved>(STREAM_CAPACITY);
+    let written = thread::scope(|scope| {
+        let (rates_for, progress, tracker) = (&rates_for, &progress, &tracker);
+        scope.spawn(move || pool.install(|| stream_groups(policies, rates_for, options, |index, result| {
+            tracker.finished(result.is_ok(), progress);
+            // the writer has stopped once the receiver is gone
+            return sender.send((index, result)).is_ok();
+        })));
+        let written = receiver.iter().try_for_each(&mut write);
+        // dropped before the workers are joined so none is left waiting on a full channel
+        drop(receiver);
+        return written;
+    });
+    progress(&tracker.snapshot());
+    return written;
+}
+
+// counts of a run's finished policies, reported to its progress callback at most once every interval
+struct Tracker {
+    total: usize,
+    interval: Duration,
+    start: Instant,
+    completed: AtomicUsize,
+    failed: AtomicUsize,
+    last_report: Mutex<Instant>,
+}
+
+impl Tracker {
+    fn new(total: usize, interval: Duration) -> Tracker {
+        let start = Instant::now();
+        return Tracker { total, interval, start, completed: AtomicUsize::new(0), failed: AtomicUsize::new(0), last_report: Mutex::new(start) };
+    }
+
+    fn snapshot(&self) -> Progress {
+        return Progress {
+            total: self.total,
+            completed: self.completed.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            elapsed: self.start.elapsed(),
+        };
+    }
+
+    // whichever thread finds the interval has passed reports, the others carry on rather than wait for the lock
+    fn finished<P: Fn(&Progress)>(&self, solved: bool, progress: &P) {
+        let counter = if solved {&self.completed} else {&self.failed};
+        counter.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut last) = self.last_report.try_lock() && last.elapsed() >= self.interval {
+            *last = Instant::now();
+            progress(&self.snapshot());
+        }
+    }
+}
+
+fn pool(threads: Option<usize>) -> Result<rayon::ThreadPool, ValactError> {
+    let mut builder = rayon::ThreadPoolBuilder::new();
+    if let Some(n) = threads {
+        builder = builder.num_threads(n);
+    }
+    return Ok(builder.build()?);
+}
+
+// positions of the policies solved together in lockstep, policies of an issue age and death benefit option LANES
+// at a time in census order, with inforce policies on their own
+fn lockstep_groups(policies: &[PolicyRecord]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut filling: HashMap<(IssueAge, DeathBenefitOption), usize> = HashMap::new();
+    for (i, policy) in policies.iter().enumerate() {
+        if policy.inforce.is_some() {
+            groups.push(vec![i]);
+            continue;
+        }
+        let key = (policy.issue_age, policy.death_benefit_option);
+        match filling.get(&key) {
+            Some(&group) if groups[group].len() < LANES => groups[group].push(i),
+            _ => {
+                filling.insert(key, groups.len());
+                groups.push(vec![i]);
+            }
+        }
+    }
+    return groups;
+}
+
+// every policy solved a lockstep group at a time across the pool this is run in, each result with its position in
+// policies; on_result is called from the worker threads as each policy finishes and returns false to stop the run,
+// groups already started still finish
+fn solve_groups<F, S, R>(policies: &[PolicyRecord], rates_for: &F, options: &SolverOptions, on_result: R) -> Vec<Solved>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+    R: Fn(usize, &Result<PolicyResult, ValactError>) -> bool + Sync,
+{
+    let stopped = AtomicBool::new(false);
+    return lockstep_groups(policies).par_iter().flat_map_iter(|group| {
+        if stopped.load(Ordering::Relaxed) {
+            return Vec::new();
+        }
+        return solve_group(policies, group, rates_for, options).inspect(|(index, result)| {
+            if !on_result(*index, result) {
+                stopped.store(true, Ordering::Relaxed);
+            }
+        }).collect();
+    }).collect();
+}
+
+// as solve_groups with each result handed to on_result rather than kept
+fn stream_groups<F, S, R>(policies: &[PolicyRecord], rates_for: &F, options: &SolverOptions, on_result: R)
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+    R: Fn(usize, Result<PolicyResult, ValactError>) -> bool + Sync,
+{
+    let stopped = AtomicBool::new(false);
+    lockstep_groups(policies).par_iter().for_each(|group| {
+        if stopped.load(Ordering::Relaxed) {
+            return;
+        }
+        for (index, result) in solve_group(policies, group, rates_for, options) {
+            if !on_result(index, result) {
+                stopped.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+}
+
+// the policies at the positions in group solved together, each result with its position
+fn solve_group<'a, F, S>(policies: &[PolicyRecord], group: &'a [usize], rates_for: &F, options: &SolverOptions) -> impl Iterator<Item = Solved> + 'a
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+{
+    let members: Vec<&PolicyRecord> = group.iter().map(|&i| &policies[i]).collect();
+    let results = solve_policies_lockstep(&members, rates_for, options);
+    return group.iter().zip(results).map(|(&index, result)| (index, result));
+}
+
+// every policy is solved even when some fail, with on_result called from the worker threads as each one finishes
+// along with its position in policies, for callers streaming results out rather than waiting for the whole batch
+pub fn solve_policies_streaming<F, S, R>(policies: &[PolicyRecord], threads: Option<usize>, rates_for: F, options: &SolverOptions, on_result: R) -> Result<(), ValactError>
+where
+    F: Fn(&PolicyRecord) -> Result<S, ValactError> + Sync,
+    S: Borrow<RateSet>,
+    R: Fn(usize, Result<PolicyResult, ValactError>) + Sync,
+{
+    pool(threads)?.install(|| {
+        stream_groups(policies, &rates_for, options, |index, result| {
+            on_result(index, result);
+            return true;
+        });
+    });
+    return Ok(());
+}
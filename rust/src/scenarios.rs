@@ -0,0 +1,139 @@
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    age::IssueAge, error::ValactError, projection::{at_issue_projection_with, solve_for_premium_with, ProjectionConfig}, rates::RateSet
+};
+
+// interest rate scenarios, each path is credited in place of the interest rate table and the policy is
+// projected at the given premium and the premium solved again for every path
+// the scenario file has a Month or Policy_Year column followed by one column of annual effective rates per
+// scenario with the scenario name as its header, a Policy_Year file applies each rate to the 12 months of the year
+// prescribed scenario sets can be read straight from the academy generator's output instead
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScenarioSet {
+    pub names: Vec<String>,
+    // annual effective rate for each policy month, one path per name
+    pub paths: Vec<Vec<f64>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScenarioResult {
+    #[serde(rename="Scenario")]
+    pub name: String,
+    // account value at maturity at the given premium
+    #[serde(rename="End_Value")]
+    pub end_value: f64,
+    // annual premium that endows the policy on the path
+    #[serde(rename="Premium")]
+    pub premium: f64,
+}
+
+pub fn read_scenarios_csv(path: &str) -> Result<ScenarioSet, ValactError> {
+    let mut rdr = csv::Reader::from_path(path)?;
+    let headers = rdr.headers()?.clone();
+    let months_per_row = match headers.get(0) {
+        Some("Month") => 1,
+        Some("Policy_Year") => 12,
+        _ => return Err(format!("{}: first column must be Month or Policy_Year", path).into()),
+    };
+    let names: Vec<String> = headers.iter().skip(1).map(str::to_string).collect();
+    if names.is_empty() {
+        return Err(format!("{}: no scenario columns", path).into());
+    }
+
+    let mut paths: Vec<Vec<f64>> = vec![Vec::new(); names.len()];
+    for (row, record) in rdr.records().enumerate() {
+        let record = record?;
+        for (scenario, path_rates) in paths.iter_mut().enumerate() {
+            let field = record.get(scenario + 1).unwrap_or("");
+            let rate: f64 = field.trim().parse().map_err(|_| format!("{}: row {} scenario {}: bad rate '{}'", path, row + 1, names[scenario], field))?;
+            path_rates.extend(std::iter::repeat_n(rate, months_per_row));
+        }
+    }
+    if paths[0].is_empty() {
+        return Err(format!("{}: no rates", path).into());
+    }
+    return Ok(ScenarioSet { names, paths });
+}
+
+// output of the academy interest rate generator for one maturity, a row per scenario with the scenario number
+// followed by the yield at each month as a percent, bond equivalent, and the first yield is the starting curve
+// at month 0 so the path credits from the second; rows that do not start with a scenario number are headers
+// yields are converted to annual effective rates for the projection
+pub fn read_airg_scenarios(path: &str) -> Result<ScenarioSet, ValactError> {
+    let mut rdr = csv::ReaderBuilder::new().has_headers(false).flexible(true).trim(csv::Trim::All).from_path(path)?;
+    let mut names: Vec<String> = Vec::new();
+    let mut paths: Vec<Vec<f64>> = Vec::new();
+    for (row, record) in rdr.records().enumerate() {
+        let record = record?;
+        let Some(name) = record.get(0).filter(|field| field.parse::<u32>().is_ok()) else {
+            continue;
+        };
+        let mut path_rates = Vec::with_capacity(record.len());
+        for field in record.iter().skip(2).filter(|field| !field.is_empty()) {
+            let bond_yield: f64 = field.parse().map_err(|_| format!("{}: row {}: bad yield '{}'", path, row + 1, field))?;
+            path_rates.push((1.0 + bond_yield / 200.0).powi(2) - 1.0);
+        }
+        if path_rates.is_empty() {
+            return Err(format!("{}: scenario {} has no yields after month 0", path, name).into());
+        }
+        names.push(name.to_string());
+        paths.push(path_rates);
+    }
+    if names.is_empty() {
+        return Err(format!("{}: no scenarios", path).into());
+    }
+    return Ok(ScenarioSet { names, paths });
+}
+
+// every path is independent so they are run across a rayon pool
+pub fn run_scenarios(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig, scenarios: &ScenarioSet) -> Result<Vec<ScenarioResult>, ValactError> {
+    return run_scenarios_recording(rates, issue_age, face_amount, annual_premium, config, scenarios, |_, _| {});
+}
+
+// as run_scenarios with on_result called from the worker threads as each path finishes, along with its position
+// in scenarios, e.g. to checkpoint it (checkpoint.rs)
+pub fn run_scenarios_recording<R>(rates: &RateSet, issue_age: IssueAge, face_amount: f64, annual_premium: f64, config: &ProjectionConfig, scenarios: &ScenarioSet, on_result: R) -> Result<Vec<ScenarioResult>, ValactError>
+where
+    R: Fn(usize, &ScenarioResult) + Sync,
+{
+    return scenarios.names.par_iter().zip(scenarios.paths.par_iter()).enumerate()
+        .map(|(index, (name, path))| {
+            let config = ProjectionConfig { interest_path: path.clone(), ..config.clone() };
+            let end_value = at_issue_projection_with(rates, issue_age, face_amount, annual_premium, &config)?;
+            let premium = solve_for_premium_with(rates, issue_age, face_amount, &config)?.premium;
+            let result = ScenarioResult { name: name.clone(), end_value, premium };
+            on_result(index, &result);
+            Ok(result)
+        })
+        .collect();
+}
+
+// in the Month layout read_scenarios_csv reads, paths of different lengths are written out to the longest
+// with the shorter ones carried at their last rate
+pub fn write_scenario_set_csv(path: &str, scenarios: &ScenarioSet) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    let mut header = vec!["Month".to_string()];
+    header.extend(scenarios.names.iter().cloned());
+    wtr.write_record(&header)?;
+    let months = scenarios.paths.iter().map(Vec::len).max().unwrap_or(0);
+    for month in 0..months {
+        let mut row = vec![(month + 1).to_string()];
+        row.extend(scenarios.paths.iter().map(|p| p[month.min(p.len() - 1)].to_string()));
+        wtr.write_record(&row)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
+
+pub fn write_scenarios_csv(path: &str, results: &[ScenarioResult]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for result in results {
+        wtr.serialize(result)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
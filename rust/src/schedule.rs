@@ -0,0 +1,173 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::projection::DeathBenefitOption;
+
+// policy year by policy year transactions layered onto a projection, withdrawals, policy loans,
+// face amount changes and death benefit option switches
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum WithdrawalAmount {
+    Amount(f64),
+    // fraction of the account value at the start of the policy year, 0.1 for 10%
+    PercentOfValue(f64),
+}
+
+// withdrawal taken at the start of each policy year from from_year to to_year inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Withdrawal {
+    pub from_year: i32,
+    pub to_year: i32,
+    pub amount: WithdrawalAmount,
+}
+
+impl Withdrawal {
+    // amount wanted in policy_year given the account value at the start of it, before capping at what is available
+    pub fn requested(&self, policy_year: i32, start_value: f64) -> f64 {
+        if policy_year < self.from_year || policy_year > self.to_year {
+            return 0.0;
+        }
+        return match self.amount {
+            WithdrawalAmount::Amount(amount) => amount,
+            WithdrawalAmount::PercentOfValue(pct) => pct * start_value.max(0.0),
+        };
+    }
+}
+
+// total withdrawals requested in a policy year, every entry covering the year adds to it
+pub fn withdrawals_in_year(withdrawals: &[Withdrawal], policy_year: i32, start_value: f64) -> f64 {
+    // folded from 0.0 rather than summed, an empty f64 sum is -0.0 which shows up in the output
+    return withdrawals.iter().fold(0.0, |total, w| total + w.requested(policy_year, start_value));
+}
+
+// a flat amount at the start of each policy year from from_year to to_year inclusive, used for loans and repayments
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ScheduledAmount {
+    pub from_year: i32,
+    pub to_year: i32,
+    pub amount: f64,
+}
+
+pub fn scheduled_in_year(amounts: &[ScheduledAmount], policy_year: i32) -> f64 {
+    return amounts.iter().filter(|a| (a.from_year..=a.to_year).contains(&policy_year)).fold(0.0, |total, a| total + a.amount);
+}
+
+// interest on policy loans, both annual effective
+// the loan balance accrues at charged_rate and the account value backing it is credited at credited_rate
+// in place of the unloaned rate
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LoanTerms {
+    pub charged_rate: f64,
+    pub credited_rate: f64,
+}
+
+impl Default for LoanTerms {
+    fn default() -> Self {
+        return LoanTerms { charged_rate: 0.05, credited_rate: 0.04 };
+    }
+}
+
+// face amount in force from the start of policy_year on, an increase or a decrease
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct FaceChange {
+    pub policy_year: i32,
+    pub face_amount: f64,
+}
+
+// new face amount taking effect in policy_year if there is one, the last entry for the year wins
+pub fn face_change_in_year(changes: &[FaceChange], policy_year: i32) -> Option<f64> {
+    return changes.iter().rev().find(|c| c.policy_year == policy_year).map(|c| c.face_amount);
+}
+
+// death benefit option in force from the start of policy_year on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct OptionChange {
+    pub policy_year: i32,
+    pub option: DeathBenefitOption,
+}
+
+pub fn option_change_in_year(changes: &[OptionChange], policy_year: i32) -> Option<DeathBenefitOption> {
+    return changes.iter().rev().find(|c| c.policy_year == policy_year).map(|c| c.option);
+}
+
+// years are a single year "10" or an inclusive range "10-20"
+// errors are strings so clap can use these as value parsers
+fn parse_years(years: &str) -> Result<(i32, i32), String> {
+    let (from, to) = years.split_once('-').unwrap_or((years, years));
+    let from: i32 = from.trim().parse().map_err(|e| format!("policy year {}: {}", from, e))?;
+    let to: i32 = to.trim().parse().map_err(|e| format!("policy year {}: {}", to, e))?;
+    if from < 1 || to < from {
+        return Err(format!("policy years {} are not a valid range", years));
+    }
+    return Ok((from, to));
+}
+
+fn parse_amount(amount: &str) -> Result<f64, String> {
+    return amount.trim().parse().map_err(|e| format!("amount {}: {}", amount, e));
+}
+
+// cli form is YEARS:AMOUNT, e.g. "10:5000" or "20-30:10%"
+impl FromStr for Withdrawal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (years, amount) = s.split_once(':').ok_or_else(|| format!("withdrawal {} should be YEARS:AMOUNT", s))?;
+        let (from_year, to_year) = parse_years(years)?;
+        let amount = amount.trim();
+        let amount = match amount.strip_suffix('%') {
+            Some(pct) => WithdrawalAmount::PercentOfValue(parse_amount(pct)? / 100.0),
+            None => WithdrawalAmount::Amount(parse_amount(amount)?),
+        };
+        return Ok(Withdrawal { from_year, to_year, amount });
+    }
+}
+
+// cli form is YEARS:AMOUNT, e.g. "10-15:5000"
+impl FromStr for ScheduledAmount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (years, amount) = s.split_once(':').ok_or_else(|| format!("{} should be YEARS:AMOUNT", s))?;
+        let (from_year, to_year) = parse_years(years)?;
+        return Ok(ScheduledAmount { from_year, to_year, amount: parse_amount(amount)? });
+    }
+}
+
+fn parse_year(year: &str) -> Result<i32, String> {
+    let (from_year, to_year) = parse_years(year)?;
+    if from_year != to_year {
+        return Err(format!("policy year {} should be a single year", year));
+    }
+    return Ok(from_year);
+}
+
+// cli form is YEAR:FACE, e.g. "10:150000"
+impl FromStr for FaceChange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (year, face) = s.split_once(':').ok_or_else(|| format!("face change {} should be YEAR:FACE", s))?;
+        let face_amount = parse_amount(face)?;
+        if face_amount < 0.0 {
+            return Err(format!("face amount {} is negative", face));
+        }
+        return Ok(FaceChange { policy_year: parse_year(year)?, face_amount });
+    }
+}
+
+// cli form is YEAR:OPTION, e.g. "15:1" or "15:A"
+impl FromStr for OptionChange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (year, option) = s.split_once(':').ok_or_else(|| format!("option change {} should be YEAR:OPTION", s))?;
+        let option = match option.trim() {
+            "1" | "A" => DeathBenefitOption::Level,
+            "2" | "B" => DeathBenefitOption::Increasing,
+            "3" | "C" => DeathBenefitOption::ReturnOfPremium,
+            other => return Err(format!("death benefit option {} should be 1, 2 or 3", other)),
+        };
+        return Ok(OptionChange { policy_year: parse_year(year)?, option });
+    }
+}
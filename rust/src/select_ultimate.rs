@@ -0,0 +1,87 @@
+/*
+Select and ultimate rate tables
+
+Select rates vary by issue age and duration for the first select_period policy
+years, after which rates come from the ultimate table by attained age.
+
+The csv layout is the usual printed one, one row per issue age:
+Issue_Age,1,2,...,N,Ultimate
+where 1..N are the select durations and Ultimate is the rate at attained age
+Issue_Age + N. Rows with the select columns left blank only add an ultimate
+rate, for attained ages past the last issue age.
+
+*/
+use std::collections::BTreeMap;
+
+use crate::{age::IssueAge, error::ValactError};
+
+#[derive(Clone, Debug, Default)]
+pub struct SelectUltimateTable {
+    pub select_period: i32,
+    // issue age -> rates for durations 1..=select_period
+    pub select: BTreeMap<i32, Vec<f64>>,
+    // attained age -> rate
+    pub ultimate: BTreeMap<i32, f64>,
+}
+
+impl SelectUltimateTable {
+    pub fn rate(&self, issue_age: i32, policy_year: i32) -> Option<f64> {
+        if policy_year <= self.select_period
+            && let Some(rates) = self.select.get(&issue_age)
+        {
+            return rates.get((policy_year - 1) as usize).copied();
+        }
+        // past the select period, or an issue age without select rates
+        return self.ultimate.get(&(issue_age + policy_year - 1)).copied();
+    }
+
+    // rates by policy year for the projection, default where the table has no rate
+    pub fn rates_for_issue_age(&self, issue_age: IssueAge, default: f64, years: usize) -> Vec<f64> {
+        let mut rates: Vec<f64> = vec![default; years];
+        for (i, rate) in rates.iter_mut().enumerate() {
+            if let Some(table_rate) = self.rate(i32::from(issue_age.get()), i as i32 + 1) {
+                *rate = table_rate;
+            }
+        }
+        return rates;
+    }
+
+    pub fn scaled(mut self, factor: f64) -> SelectUltimateTable {
+        for rates in self.select.values_mut() {
+            rates.iter_mut().for_each(|rate| *rate *= factor);
+        }
+        self.ultimate.values_mut().for_each(|rate| *rate *= factor);
+        return self;
+    }
+}
+
+pub fn read_select_ultimate_csv(path: &str) -> Result<SelectUltimateTable, ValactError> {
+    let mut rdr = csv::Reader::from_path(path).map_err(|e| ValactError::csv(path, None, e))?;
+    let headers = rdr.headers().map_err(|e| ValactError::csv(path, None, e))?.clone();
+    if headers.len() < 3 || !headers[headers.len() - 1].eq_ignore_ascii_case("ultimate") {
+        return Err(ValactError::Table { path: path.to_string(), message: "expected Issue_Age, select duration columns, then Ultimate".to_string() });
+    }
+    let select_period = (headers.len() - 2) as i32;
+
+    let mut table = SelectUltimateTable { select_period, ..Default::default() };
+    for result in rdr.records() {
+        let record = result.map_err(|e| ValactError::csv(path, Some(&headers), e))?;
+        let line = record.position().map_or(0, |p| p.line());
+        let bad = |i: usize, message: String| ValactError::Row { path: path.to_string(), line, column: Some(headers[i].to_string()), message };
+        let issue_age: i32 = record[0].trim().parse().map_err(|e: std::num::ParseIntError| bad(0, e.to_string()))?;
+
+        let cells: Vec<&str> = (1..=select_period as usize).map(|i| record[i].trim()).collect();
+        if cells.iter().all(|c| !c.is_empty()) {
+            let rates = cells.iter().enumerate().map(|(i, c)| c.parse::<f64>().map_err(|e| bad(i + 1, e.to_string()))).collect::<Result<Vec<f64>, _>>()?;
+            table.select.insert(issue_age, rates);
+        } else if cells.iter().any(|c| !c.is_empty()) {
+            return Err(ValactError::Row { path: path.to_string(), line, column: None, message: format!("issue age {} has a partial row of select rates", issue_age) });
+        }
+
+        let ultimate = record[headers.len() - 1].trim();
+        if !ultimate.is_empty() {
+            table.ultimate.insert(issue_age + select_period, ultimate.parse().map_err(|e: std::num::ParseFloatError| bad(headers.len() - 1, e.to_string()))?);
+        }
+    }
+    return Ok(table);
+}
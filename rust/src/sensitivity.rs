@@ -0,0 +1,160 @@
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    age::IssueAge, assumptions::Assumptions, decrements::expected_cash_flows, error::ValactError, profit::{profit_by_year, profit_summary}, projection::{at_issue_projection_detail_with, solve_for_premium_with, ProjectionConfig}, rates::RateSet
+};
+
+// pricing sensitivities, the premium is solved again under each shock and the profit test rerun at the new
+// premium, with everything reported as a change from the unshocked base
+// an interest shock moves the credited rates and the earned rate together, a coi shock is to the charge only
+// so expected mortality stays where it was, and the profit test holds the account value as the reserve
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Shock {
+    #[serde(rename="Name")]
+    pub name: String,
+    #[serde(rename="COI_Multiple")]
+    pub coi_multiple: f64,
+    // added to every annual credited and earned rate
+    #[serde(rename="Interest_Shift")]
+    pub interest_shift: f64,
+    // company expenses in the assumptions, not the expense charges the policy makes
+    #[serde(rename="Expense_Multiple")]
+    pub expense_multiple: f64,
+    #[serde(rename="Lapse_Multiple")]
+    pub lapse_multiple: f64,
+}
+
+impl Default for Shock {
+    fn default() -> Self {
+        return Shock { name: "Base".to_string(), coi_multiple: 1.0, interest_shift: 0.0, expense_multiple: 1.0, lapse_multiple: 1.0 };
+    }
+}
+
+impl Shock {
+    fn apply_to_rates(&self, rates: &RateSet) -> RateSet {
+        return RateSet {
+            coi_rates: rates.coi_rates.iter().map(|rate| rate * self.coi_multiple).collect(),
+            // the rate set holds monthly rates and the shift is to the annual rate
+            interest_rates: rates.interest_rates.iter().map(|rate| ((1.0 + rate).powi(12) + self.interest_shift).powf(1.0 / 12.0) - 1.0).collect(),
+            ..rates.clone()
+        };
+    }
+
+    fn apply_to_assumptions(&self, assumptions: &Assumptions) -> Assumptions {
+        let mut shocked = assumptions.clone();
+        let expenses = &mut shocked.expenses;
+        expenses.acquisition_per_policy *= self.expense_multiple;
+        expenses.acquisition_per_1000 *= self.expense_multiple;
+        expenses.maintenance_per_policy *= self.expense_multiple;
+        expenses.first_year_percent_of_premium *= self.expense_multiple;
+        expenses.renewal_percent_of_premium *= self.expense_multiple;
+        shocked.profit.earned_rate += self.interest_shift;
+        return shocked;
+    }
+}
+
+// coi and expenses +-10%, interest +-50bp, lapses +-20%
+pub fn standard_shocks() -> Vec<Shock> {
+    let shock = |name: &str| Shock { name: name.to_string(), ..Default::default() };
+    return vec![
+        Shock { coi_multiple: 1.1, ..shock("COI +10%") },
+        Shock { coi_multiple: 0.9, ..shock("COI -10%") },
+        Shock { interest_shift: 0.005, ..shock("Interest +50bp") },
+        Shock { interest_shift: -0.005, ..shock("Interest -50bp") },
+        Shock { expense_multiple: 1.1, ..shock("Expenses +10%") },
+        Shock { expense_multiple: 0.9, ..shock("Expenses -10%") },
+        Shock { lapse_multiple: 1.2, ..shock("Lapse +20%") },
+        Shock { lapse_multiple: 0.8, ..shock("Lapse -20%") },
+    ];
+}
+
+// one shock per row, columns as in Shock and any left out unshocked
+pub fn read_shocks_csv(path: &str) -> Result<Vec<Shock>, ValactError> {
+    let mut rdr = csv::Reader::from_path(path)?;
+    let mut shocks: Vec<Shock> = Vec::new();
+    for result in rdr.deserialize() {
+        shocks.push(result?);
+    }
+    return Ok(shocks);
+}
+
+// expected experience the profit test is run on, mortality per 1000 and lapses by policy year and the
+// commission target premium per year
+#[derive(Clone, Debug, Default)]
+pub struct PricingBasis {
+    pub mortality: Vec<f64>,
+    pub lapses: Vec<f64>,
+    pub target_premium: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SensitivityResult {
+    #[serde(rename="Shock")]
+    pub name: String,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Premium_Change")]
+    pub premium_change: f64,
+    #[serde(rename="PV_Profits")]
+    pub pv_profits: f64,
+    #[serde(rename="PV_Profits_Change")]
+    pub pv_profits_change: f64,
+    #[serde(rename="Profit_Margin")]
+    pub profit_margin: f64,
+    #[serde(rename="Profit_Margin_Change")]
+    pub profit_margin_change: f64,
+}
+
+// solved premium, pv of profits and profit margin under one shock
+fn shocked_values(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig, basis: &PricingBasis, assumptions: &Assumptions, shock: &Shock) -> Result<(f64, f64, f64), ValactError> {
+    let rates = shock.apply_to_rates(rates);
+    let assumptions = shock.apply_to_assumptions(assumptions);
+    let lapses: Vec<f64> = basis.lapses.iter().map(|rate| rate * shock.lapse_multiple).collect();
+    let premium = solve_for_premium_with(&rates, issue_age, face_amount, config)?.premium;
+    let months = at_issue_projection_detail_with(&rates, issue_age, face_amount, premium, config)?;
+    let expected = expected_cash_flows(&months, &basis.mortality, &lapses, config.timestep, &assumptions, basis.target_premium);
+    let years = profit_by_year(&expected, None, config.timestep, &assumptions.profit);
+    let summary = profit_summary(&years, assumptions.profit.hurdle_rate);
+    return Ok((premium, summary.pv_profits, summary.profit_margin));
+}
+
+// the unshocked base comes first, then a row per shock
+pub fn run_sensitivities(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig, basis: &PricingBasis, assumptions: &Assumptions, shocks: &[Shock]) -> Result<Vec<SensitivityResult>, ValactError> {
+    let base = Shock::default();
+    let (base_premium, base_pv, base_margin) = shocked_values(rates, issue_age, face_amount, config, basis, assumptions, &base)?;
+    let mut results: Vec<SensitivityResult> = Vec::with_capacity(shocks.len() + 1);
+    results.push(SensitivityResult {
+        name: base.name,
+        premium: base_premium,
+        premium_change: 0.0,
+        pv_profits: base_pv,
+        pv_profits_change: 0.0,
+        profit_margin: base_margin,
+        profit_margin_change: 0.0,
+    });
+    for shock in shocks {
+        let (premium, pv_profits, profit_margin) = shocked_values(rates, issue_age, face_amount, config, basis, assumptions, shock)?;
+        results.push(SensitivityResult {
+            name: shock.name.clone(),
+            premium,
+            premium_change: premium - base_premium,
+            pv_profits,
+            pv_profits_change: pv_profits - base_pv,
+            profit_margin,
+            profit_margin_change: profit_margin - base_margin,
+        });
+    }
+    return Ok(results);
+}
+
+pub fn write_sensitivities_csv(path: &str, results: &[SensitivityResult]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for result in results {
+        wtr.serialize(result)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
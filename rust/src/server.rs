@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State, http::StatusCode, response::{IntoResponse, Response}, routing::{get, post}, Json, Router
+};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    batch::{self, PolicyRecord},
+    error::ValactError,
+    projection::{at_issue_projection_detail_with, solve_for_premium_with, SolveTarget},
+    reload::{LiveRates, LiveTables},
+    report::{ProjectionReport, SolveReport},
+    repository::RateRepository,
+};
+
+// http api over the engine, the rate repository is built when the server starts and shared by every request,
+// and swapped for a new one when the tables change if the server is watching the data directory (reload.rs)
+//   POST /solve         {"gender": "M", "risk_class": "NS", "issue_age": 35, "face_amount": 100000}
+//   POST /project       {"gender": "M", "risk_class": "NS", "issue_age": 35, "face_amount": 100000, "annual_premium": 1255.03}
+//   GET  /admin/tables  the table files being served with their sizes and sha256 checksums, the versions and
+//                       when they were loaded
+// policies take the same fields as a census row (death_benefit_option and inforce optional) and are set up as
+// a batch run sets them up, an issue_date picking the version of versioned rate tables; the responses are the
+// json reports the cli prints with --output json
+// engine errors come back as 422 with {"error": message}
+
+#[derive(Deserialize)]
+pub struct SolveRequest {
+    #[serde(flatten)]
+    pub policy: PolicyRecord,
+    // include the monthly projection at the solved premium
+    #[serde(default)]
+    pub detail: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ProjectRequest {
+    #[serde(flatten)]
+    pub policy: PolicyRecord,
+    pub annual_premium: f64,
+}
+
+struct ApiError(String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": self.0 }))).into_response();
+    }
+}
+
+type Repository = Arc<RateRepository>;
+type Live = Arc<LiveRates>;
+
+pub fn solve(repository: &RateRepository, request: SolveRequest) -> Result<SolveReport, ValactError> {
+    let policy = request.policy;
+    let config = batch::policy_config(&policy);
+    let rates = repository.get_shared_as_of(&policy.gender, &policy.risk_class, policy.issue_age, policy.issue_date)?;
+    let solve = solve_for_premium_with(&rates, policy.issue_age, policy.face_amount, &config)?;
+    let projection = if request.detail {Some(at_issue_projection_detail_with(&rates, policy.issue_age, policy.face_amount, solve.premium, &config)?)} else {None};
+    return Ok(SolveReport { inputs: policy, target: SolveTarget::EndowAtMaturity, premium: solve.premium, solve, modal_premium: None, projection });
+}
+
+pub fn project(repository: &RateRepository, request: ProjectRequest) -> Result<ProjectionReport, ValactError> {
+    let policy = request.policy;
+    let config = batch::policy_config(&policy);
+    let rates = repository.get_shared_as_of(&policy.gender, &policy.risk_class, policy.issue_age, policy.issue_date)?;
+    let months = at_issue_projection_detail_with(&rates, policy.issue_age, policy.face_amount, request.annual_premium, &config)?;
+    return Ok(ProjectionReport {
+        inputs: policy,
+        annual_premium: request.annual_premium,
+        end_value: months.last().map_or(0.0, |m| m.value_end),
+        seven_pay: None,
+        no_lapse_guarantee: None,
+        guaranteed: None,
+        profit: None,
+        deterministic_reserve: None,
+        projection: Some(months),
+    });
+}
+
+// solves and projections are cpu bound so they run on the blocking pool rather than holding up the listener
+// a request keeps the repository that was live when it came in through a reload
+async fn run_blocking<T, F>(live: Live, f: F) -> Result<Json<T>, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&RateRepository) -> Result<T, ValactError> + Send + 'static,
+{
+    let repository = live.current();
+    let result = tokio::task::spawn_blocking(move || f(&repository).map_err(|e| e.to_string())).await;
+    return match result {
+        Ok(Ok(value)) => Ok(Json(value)),
+        Ok(Err(message)) => {
+            warn!("{}", message);
+            Err(ApiError(message))
+        }
+        Err(e) => Err(ApiError(e.to_string())),
+    };
+}
+
+async fn solve_handler(State(live): State<Live>, Json(request): Json<SolveRequest>) -> Result<Json<SolveReport>, ApiError> {
+    return run_blocking(live, move |repository| solve(repository, request)).await;
+}
+
+async fn project_handler(State(live): State<Live>, Json(request): Json<ProjectRequest>) -> Result<Json<ProjectionReport>, ApiError> {
+    return run_blocking(live, move |repository| project(repository, request)).await;
+}
+
+async fn tables_handler(State(live): State<Live>) -> Json<LiveTables> {
+    return Json(live.tables());
+}
+
+// a repository already behind an Arc can be shared with other threads of the embedding process, it is never
+// reloaded
+pub fn router(repository: impl Into<Repository>) -> Router {
+    return live_router(Arc::new(LiveRates::new(repository)));
+}
+
+pub fn live_router(live: Live) -> Router {
+    return Router::new()
+        .route("/solve", post(solve_handler))
+        .route("/project", post(project_handler))
+        .route("/admin/tables", get(tables_handler))
+        .with_state(live);
+}
+
+// serves until the process is stopped
+pub fn serve(address: &str, live: Live) -> Result<(), ValactError> {
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    return runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        info!("listening on http://{}", listener.local_addr()?);
+        axum::serve(listener, live_router(live)).await?;
+        return Ok(());
+    });
+}
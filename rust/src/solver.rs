@@ -0,0 +1,246 @@
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::ValactError;
+
+// bracket doubling gives up after this many tries, upper bounds past 2^64 times the guess are not useful
+pub(crate) const MAX_EXPANSIONS: i32 = 64;
+// brent or secant steps before giving up on them and bisecting what is left of the bracket
+pub const DEFAULT_MAX_ITERATIONS: usize = 100;
+
+// root finding method used by goal_seek_with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Method {
+    #[default]
+    Bisection,
+    // inverse quadratic interpolation and secant steps, taking bisection steps whenever those would leave the bracket
+    Brent,
+    // secant steps from the ends of the bracket, fastest on smooth metrics, bisects if a step leaves the bracket
+    Secant,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct SolverOptions {
+    pub method: Method,
+    // the solve stops once the bracket is narrower than this
+    pub tolerance: f64,
+    // cap on brent or secant steps, and separately on the bisection steps that follow if they do not converge
+    pub max_iterations: usize,
+    // log every evaluation of the metric as an info event, so it only shows at info logging or below
+    #[serde(default)]
+    pub log_iterations: bool,
+}
+
+impl SolverOptions {
+    pub fn new(method: Method, tolerance: f64) -> Self {
+        return SolverOptions { method, tolerance, max_iterations: DEFAULT_MAX_ITERATIONS, log_iterations: false };
+    }
+}
+
+// where the target was found and how much work it took
+// iterations counts the steps narrowing the bracket, evaluations every call to the metric including bracketing
+// bracket is the final (low, high) interval, converged is false if max_iterations ran out before it was within tolerance
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Root {
+    pub x: f64,
+    pub iterations: usize,
+    pub evaluations: usize,
+    pub bracket: (f64, f64),
+    pub converged: bool,
+}
+
+// finds where metric(x) crosses target by bisection between bounds
+// works for metrics that rise or fall with x, returning the end of the final bracket where metric is above target
+// if bounds do not bracket the target the upper bound is doubled until they do
+// e.g. the premium that gives $50,000 of account value at the end of year 20:
+//   goal_seek(|premium| Ok(at_issue_projection_detail(&rates, 35, 100000.0, premium)?[239].value_end), 50000.0, (0.0, 1000.0), 0.005)
+pub fn goal_seek<F>(metric: F, target: f64, bounds: (f64, f64), tol: f64) -> Result<f64, ValactError>
+where
+    F: FnMut(f64) -> Result<f64, ValactError>,
+{
+    return Ok(goal_seek_with(metric, target, bounds, &SolverOptions::new(Method::Bisection, tol))?.x);
+}
+
+// same as goal_seek with a choice of method, all stop once the bracket is narrower than options.tolerance
+pub fn goal_seek_with<F>(mut metric: F, target: f64, bounds: (f64, f64), options: &SolverOptions) -> Result<Root, ValactError>
+where
+    F: FnMut(f64) -> Result<f64, ValactError>,
+{
+    let mut evaluations = 0;
+    let mut g = |x: f64| -> Result<f64, ValactError> {
+        evaluations += 1;
+        let value = metric(x)?;
+        if options.log_iterations {
+            info!(method = ?options.method, evaluation = evaluations, x, metric = value, target, "solver evaluation");
+        }
+        return Ok(value - target);
+    };
+
+    let (mut lo, mut hi) = bounds;
+    let mut g_lo = g(lo)?;
+    let mut g_hi = g(hi)?;
+    let mut expansions = 0;
+    while (g_hi > 0.0) == (g_lo > 0.0) {
+        if expansions == MAX_EXPANSIONS {
+            return Err(format!("target {} not bracketed between {} and {}", target, bounds.0, hi).into());
+        }
+        lo = hi;
+        g_lo = g_hi;
+        hi = if hi == 0.0 {1.0} else {hi * 2.0};
+        g_hi = g(hi)?;
+        expansions += 1;
+    }
+
+    let tol = options.tolerance;
+    let mut iterations = 0;
+    let end = match options.method {
+        Method::Bisection => None,
+        Method::Brent => Some(brent(&mut g, (lo, g_lo), (hi, g_hi), tol, options.max_iterations, &mut iterations)?),
+        Method::Secant => Some(secant(&mut g, (lo, g_lo), (hi, g_hi), tol, options.max_iterations, &mut iterations)?),
+    };
+    if let Some(end) = end {
+        if end.converged {
+            let x = if end.fb > 0.0 || end.fc <= 0.0 {end.b} else {end.c};
+            return Ok(Root { x, iterations, evaluations, bracket: (end.b.min(end.c), end.b.max(end.c)), converged: true });
+        }
+        // interpolation stalled or diverged, bisect the bracket it got to
+        (lo, g_lo, hi) = if end.b < end.c {(end.b, end.fb, end.c)} else {(end.c, end.fc, end.b)};
+    }
+
+    let lo_above = g_lo > 0.0;
+    let mut bisections = 0;
+    while (hi - lo) > tol && bisections < options.max_iterations {
+        let md = (lo + hi) / 2.0;
+        if (g(md)? > 0.0) == lo_above {
+            lo = md;
+        } else {
+            hi = md;
+        }
+        bisections += 1;
+    }
+    iterations += bisections;
+
+    let x = if lo_above {lo} else {hi};
+    return Ok(Root { x, iterations, evaluations, bracket: (lo, hi), converged: (hi - lo) <= tol });
+}
+
+// best estimate b and the other end of the bracket c with g at each, and whether the bracket got within tol
+struct BracketEnd {
+    b: f64,
+    fb: f64,
+    c: f64,
+    fc: f64,
+    converged: bool,
+}
+
+// brent's method on a bracket where g changes sign, as laid out in numerical recipes' zbrent
+fn brent<G>(g: &mut G, lo: (f64, f64), hi: (f64, f64), tol: f64, max_iterations: usize, iterations: &mut usize) -> Result<BracketEnd, ValactError>
+where
+    G: FnMut(f64) -> Result<f64, ValactError>,
+{
+    let (mut a, mut fa) = lo;
+    let (mut b, mut fb) = hi;
+    let (mut c, mut fc) = hi;
+    let mut d = b - a;
+    let mut e = d;
+
+    while *iterations < max_iterations {
+        if (fb > 0.0) == (fc > 0.0) {
+            c = a;
+            fc = fa;
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            a = b;
+            b = c;
+            c = a;
+            fa = fb;
+            fb = fc;
+            fc = fa;
+        }
+        // bracket [b, c] is 2 * xm wide, so stopping at tol1 leaves it no wider than tol
+        let tol1 = 2.0 * f64::EPSILON * b.abs() + 0.5 * tol;
+        let xm = 0.5 * (c - b);
+        if xm.abs() <= tol1 || fb == 0.0 {
+            return Ok(BracketEnd { b, fb, c, fc, converged: true });
+        }
+        if e.abs() >= tol1 && fa.abs() > fb.abs() {
+            let s = fb / fa;
+            let (mut p, mut q);
+            if a == c {
+                p = 2.0 * xm * s;
+                q = 1.0 - s;
+            } else {
+                let r = fb / fc;
+                q = fa / fc;
+                p = s * (2.0 * xm * q * (q - r) - (b - a) * (r - 1.0));
+                q = (q - 1.0) * (r - 1.0) * (s - 1.0);
+            }
+            if p > 0.0 {q = -q}
+            p = p.abs();
+            let min1 = 3.0 * xm * q - (tol1 * q).abs();
+            let min2 = (e * q).abs();
+            if 2.0 * p < min1.min(min2) {
+                e = d;
+                d = p / q;
+            } else {
+                d = xm;
+                e = d;
+            }
+        } else {
+            d = xm;
+            e = d;
+        }
+        a = b;
+        fa = fb;
+        b += if d.abs() > tol1 {d} else {tol1.copysign(xm)};
+        fb = g(b)?;
+        *iterations += 1;
+    }
+
+    return Ok(BracketEnd { b, fb, c, fc, converged: false });
+}
+
+// secant steps starting from the ends of the bracket, every point evaluated narrows the bracket
+// once a step is within tol the points either side of the estimate are checked so the bracket is within tol too
+// a step landing outside the bracket or a flat secant counts as diverged and what is left is bisected
+fn secant<G>(g: &mut G, lo: (f64, f64), hi: (f64, f64), tol: f64, max_iterations: usize, iterations: &mut usize) -> Result<BracketEnd, ValactError>
+where
+    G: FnMut(f64) -> Result<f64, ValactError>,
+{
+    let (mut a, mut fa) = lo;
+    let (mut b, mut fb) = hi;
+    // bracket kept as (point, g) with g at or below zero and above zero
+    let (mut below, mut above) = if fa > 0.0 {(hi, lo)} else {(lo, hi)};
+
+    while *iterations < max_iterations {
+        if fb == fa {
+            break;
+        }
+        let x = b - fb * (b - a) / (fb - fa);
+        let (bracket_lo, bracket_hi) = (below.0.min(above.0), below.0.max(above.0));
+        if !(bracket_lo..=bracket_hi).contains(&x) {
+            break;
+        }
+        let fx = g(x)?;
+        *iterations += 1;
+        if fx > 0.0 {above = (x, fx)} else {below = (x, fx)}
+
+        if (x - b).abs() <= 0.5 * tol {
+            // step a hair either side of the estimate, on the side that has not been pinned down yet
+            let toward = if fx > 0.0 {below.0} else {above.0};
+            let y = x + (0.5 * tol).copysign(toward - x);
+            let fy = g(y)?;
+            if fy > 0.0 {above = (y, fy)} else {below = (y, fy)}
+            if (above.0 - below.0).abs() <= tol {
+                return Ok(BracketEnd { b: above.0, fb: above.1, c: below.0, fc: below.1, converged: true });
+            }
+        }
+        (a, fa) = (b, fb);
+        (b, fb) = (x, fx);
+    }
+
+    return Ok(BracketEnd { b: above.0, fb: above.1, c: below.0, fc: below.1, converged: (above.0 - below.0).abs() <= tol });
+}
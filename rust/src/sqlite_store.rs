@@ -0,0 +1,150 @@
+/*
+SQLite backed rate store
+
+All rate tables live in a single table keyed by table name plus whichever of
+gender, risk class, issue age, policy year, and attained age the table varies by.
+Columns a table does not vary by are left null. Table names match the csv file
+names in ./data (unit_load, corridor_factors, coi, surrender_charge).
+
+*/
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::{
+    age::{AttainedAge, IssueAge, PolicyYear}, error::ValactError, product::{ProductSpec, TableRates}, rates::{projection_years, table_records, AARecord, DataDir, GenRCIAPYRecord, IAPYRecord, RateSet, DEFAULT_MATURITY_AGE}
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS rates (
+    table_name   TEXT NOT NULL,
+    gender       TEXT,
+    risk_class   TEXT,
+    issue_age    INTEGER,
+    policy_year  INTEGER,
+    attained_age INTEGER,
+    rate         REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS rates_cell ON rates (table_name, gender, risk_class, issue_age);
+";
+
+pub struct SqliteRateStore {
+    conn: Connection,
+}
+
+impl SqliteRateStore {
+    pub fn open(path: &str) -> Result<SqliteRateStore, ValactError> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        return Ok(SqliteRateStore { conn });
+    }
+
+    pub fn read_ia_py(&self, table: &str, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+        let mut rates: Vec<f64> = vec![default; years];
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT policy_year, rate FROM rates WHERE table_name = ?1 AND issue_age = ?2"
+        )?;
+        let rows = stmt.query_map(params![table, issue_age.get()], |row| Ok((row.get::<_, u16>(0)?, row.get::<_, f64>(1)?)))?;
+        for row in rows {
+            let (policy_year, rate) = row?;
+            if let Some(entry) = rates.get_mut(PolicyYear::new(policy_year)?.index()) {
+                *entry = rate;
+            }
+        }
+        return Ok(rates);
+    }
+
+    pub fn read_gen_rc_ia_py(&self, table: &str, default: f64, gender: &str, risk_class: &str, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+        let mut rates: Vec<f64> = vec![default; years];
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT policy_year, rate FROM rates WHERE table_name = ?1 AND gender = ?2 AND risk_class = ?3 AND issue_age = ?4"
+        )?;
+        let rows = stmt.query_map(params![table, gender, risk_class, issue_age.get()], |row| Ok((row.get::<_, u16>(0)?, row.get::<_, f64>(1)?)))?;
+        for row in rows {
+            let (policy_year, rate) = row?;
+            if let Some(entry) = rates.get_mut(PolicyYear::new(policy_year)?.index()) {
+                *entry = rate;
+            }
+        }
+        return Ok(rates);
+    }
+
+    pub fn read_aa(&self, table: &str, default: f64, issue_age: IssueAge, years: usize) -> Result<Vec<f64>, ValactError> {
+        let mut rates: Vec<f64> = vec![default; years];
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT attained_age, rate FROM rates WHERE table_name = ?1 AND attained_age >= ?2"
+        )?;
+        let rows = stmt.query_map(params![table, issue_age.get()], |row| Ok((row.get::<_, u8>(0)?, row.get::<_, f64>(1)?)))?;
+        for row in rows {
+            let (attained_age, rate) = row?;
+            if let Some(years) = AttainedAge::new(attained_age)?.years_since(issue_age) && let Some(entry) = rates.get_mut(years) {
+                *entry = rate;
+            }
+        }
+        return Ok(rates);
+    }
+
+    // same as rates::get_rates but sourcing the tables from the database
+    pub fn get_rates(&self, gender: &str, risk_class: &str, issue_age: IssueAge) -> Result<RateSet, ValactError> {
+        return self.get_rates_to_maturity(gender, risk_class, issue_age, DEFAULT_MATURITY_AGE);
+    }
+
+    pub fn get_rates_to_maturity(&self, gender: &str, risk_class: &str, issue_age: IssueAge, maturity_age: AttainedAge) -> Result<RateSet, ValactError> {
+        let years = projection_years(issue_age, maturity_age)?;
+        let tables = TableRates {
+            unit_loads: self.read_ia_py("unit_load", 0.0, issue_age, years)?,
+            corr_facts: self.read_aa("corridor_factors", 1.0, issue_age, years)?,
+            coi_rates: self.read_gen_rc_ia_py("coi", 0.0, gender, risk_class, issue_age, years)?,
+            surrender_charges: self.read_ia_py("surrender_charge", 0.0, issue_age, years)?,
+        };
+        return ProductSpec::default().build_rate_set(&DataDir::default(), gender, risk_class, issue_age, maturity_age, tables);
+    }
+
+    // replace the database contents with the tables currently in data_dir, returns rows loaded
+    pub fn import_data_dir(&mut self, data_dir: &DataDir) -> Result<usize, ValactError> {
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        tx.execute("DELETE FROM rates WHERE table_name IN ('unit_load', 'corridor_factors', 'coi', 'surrender_charge')", [])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO rates (table_name, gender, risk_class, issue_age, policy_year, attained_age, rate)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+            )?;
+            for result in table_records::<IAPYRecord>(&data_dir.table_path("unit_load"))? {
+                let r = result?;
+                insert.execute(params!["unit_load", None::<String>, None::<String>, r.issue_age.get(), r.policy_year.get(), None::<u8>, r.rate])?;
+                count += 1;
+            }
+            for result in table_records::<AARecord>(&data_dir.table_path("corridor_factors"))? {
+                let r = result?;
+                insert.execute(params!["corridor_factors", None::<String>, None::<String>, None::<u8>, None::<u16>, r.attained_age.get(), r.rate])?;
+                count += 1;
+            }
+            for result in table_records::<GenRCIAPYRecord>(&data_dir.table_path("coi"))? {
+                let r = result?;
+                insert.execute(params!["coi", r.gender, r.risk_class, r.issue_age.get(), r.policy_year.get(), None::<u8>, r.rate])?;
+                count += 1;
+            }
+            // optional, see rates::read_surrender_charges
+            let surrender_charge_path = data_dir.table_path("surrender_charge");
+            if Path::new(&surrender_charge_path).exists() {
+                for result in table_records::<IAPYRecord>(&surrender_charge_path)? {
+                    let r = result?;
+                    insert.execute(params!["surrender_charge", None::<String>, None::<String>, r.issue_age.get(), r.policy_year.get(), None::<u8>, r.rate])?;
+                    count += 1;
+                }
+            }
+        }
+        tx.commit()?;
+        return Ok(count);
+    }
+}
+
+// opens the database for every call, convenient for the batch runner where each worker needs its own connection
+pub fn get_rates(db_path: &str, gender: &str, risk_class: &str, issue_age: IssueAge) -> Result<RateSet, ValactError> {
+    return get_rates_to_maturity(db_path, gender, risk_class, issue_age, DEFAULT_MATURITY_AGE);
+}
+
+pub fn get_rates_to_maturity(db_path: &str, gender: &str, risk_class: &str, issue_age: IssueAge, maturity_age: AttainedAge) -> Result<RateSet, ValactError> {
+    return SqliteRateStore::open(db_path)?.get_rates_to_maturity(gender, risk_class, issue_age, maturity_age);
+}
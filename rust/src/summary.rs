@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    batch::{PolicyRecord, PolicyResult}, error::ValactError
+};
+
+// aggregates of a batch run written alongside the seriatim results, totals over the run and the same by issue
+// age band and risk class so a census can be checked against the last run at a glance
+// premium per 1000 is face weighted, total premium over total face, so big policies count for what they are
+
+// one issue age band and risk class, or the whole run when the band and class are "All"
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SummaryRow {
+    #[serde(rename="Issue_Age_Band")]
+    pub issue_age_band: String,
+    #[serde(rename="Risk_Class")]
+    pub risk_class: String,
+    #[serde(rename="Policies")]
+    pub policies: usize,
+    #[serde(rename="Solved")]
+    pub solved: usize,
+    #[serde(rename="Failed")]
+    pub failed: usize,
+    #[serde(rename="Face_Amount")]
+    pub face_amount: f64,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Average_Premium")]
+    pub average_premium: f64,
+    #[serde(rename="Premium_Per_1000")]
+    pub premium_per_1000: f64,
+}
+
+impl SummaryRow {
+    fn add(&mut self, policy: &PolicyRecord, result: &Result<PolicyResult, ValactError>) {
+        self.policies += 1;
+        match result {
+            Ok(result) => {
+                self.solved += 1;
+                self.face_amount += policy.face_amount;
+                self.premium += result.premium;
+            }
+            Err(_) => self.failed += 1,
+        }
+    }
+
+    // face and premium are of the solved policies only
+    fn finish(mut self) -> SummaryRow {
+        if self.solved > 0 {
+            self.average_premium = self.premium / self.solved as f64;
+        }
+        if self.face_amount > 0.0 {
+            self.premium_per_1000 = 1000.0 * self.premium / self.face_amount;
+        }
+        return self;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchSummary {
+    pub total: SummaryRow,
+    pub bands: Vec<SummaryRow>,
+    // a failure's error message and how many policies failed with it
+    pub failures: BTreeMap<String, usize>,
+}
+
+// e.g. 30-39 for a band width of 10, the bands start at multiples of the width
+fn band_label(issue_age: u8, width: u8) -> String {
+    let start = issue_age / width * width;
+    return if width == 1 {start.to_string()} else {format!("{}-{}", start, start.saturating_add(width - 1))};
+}
+
+// results are in the order of policies, a failed solve with its error
+pub fn summarize(policies: &[PolicyRecord], results: &[Result<PolicyResult, ValactError>], band_width: u8) -> Result<BatchSummary, ValactError> {
+    if band_width == 0 {
+        return Err("issue age band width must be at least 1 year".into());
+    }
+    if policies.len() != results.len() {
+        return Err(format!("{} results for {} policies", results.len(), policies.len()).into());
+    }
+    let mut total = SummaryRow { issue_age_band: "All".to_string(), risk_class: "All".to_string(), ..Default::default() };
+    let mut bands: BTreeMap<(u8, String), SummaryRow> = BTreeMap::new();
+    let mut failures: BTreeMap<String, usize> = BTreeMap::new();
+    for (policy, result) in policies.iter().zip(results) {
+        let age = policy.issue_age.get();
+        total.add(policy, result);
+        bands.entry((age / band_width, policy.risk_class.clone()))
+            .or_insert_with(|| SummaryRow { issue_age_band: band_label(age, band_width), risk_class: policy.risk_class.clone(), ..Default::default() })
+            .add(policy, result);
+        if let Err(e) = result {
+            *failures.entry(e.to_string()).or_insert(0) += 1;
+        }
+    }
+    return Ok(BatchSummary { total: total.finish(), bands: bands.into_values().map(SummaryRow::finish).collect(), failures });
+}
+
+// json unless the path ends in .csv, where it is the bands with the total as the last row and no failure messages
+pub fn write_summary(path: &str, summary: &BatchSummary) -> Result<(), ValactError> {
+    if path.ends_with(".csv") {
+        let mut wtr = csv::Writer::from_path(path)?;
+        for row in summary.bands.iter().chain([&summary.total]) {
+            wtr.serialize(row)?;
+        }
+        wtr.flush()?;
+        return Ok(());
+    }
+    std::fs::write(path, serde_json::to_string_pretty(summary)?)?;
+    return Ok(());
+}
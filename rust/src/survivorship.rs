@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rates::RateSet;
+
+// survivorship, last survivor, policies on two insureds that pay on the second death
+// the joint coi rate is the frasier last survivor rate from the two single life coi rates, which takes the lives
+// as independent and weights each year's deaths by the chance each insured is still alive
+//   q = (p1 q1 (1 - p2) + p2 q2 (1 - p1) + p1 q1 p2 q2) / (1 - (1 - p1)(1 - p2))
+// with p1, p2 the chance each has survived from issue to the start of the year and q1, q2 their mortality in it
+// once the first insured has died the policy continues on the survivor's single life coi rate
+// rates are per 1000, an insured past the end of their table is taken as dead
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Insured {
+    #[default]
+    First,
+    Second,
+}
+
+// a what-if first death at the start of policy_year, leaving survivor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FirstDeath {
+    pub policy_year: i32,
+    pub survivor: Insured,
+}
+
+fn mortality(rates_per_1000: &[f64], year: usize) -> f64 {
+    return rates_per_1000.get(year).map_or(1.0, |rate| (rate / 1000.0).clamp(0.0, 1.0));
+}
+
+// frasier last survivor coi per 1000 by policy year for years policy years
+pub fn last_survivor_rates(first: &[f64], second: &[f64], years: usize) -> Vec<f64> {
+    let mut survival_first = 1.0;
+    let mut survival_second = 1.0;
+    let mut rates = Vec::with_capacity(years);
+    for year in 0..years {
+        let q1 = mortality(first, year);
+        let q2 = mortality(second, year);
+        let alive = 1.0 - (1.0 - survival_first) * (1.0 - survival_second);
+        let deaths = survival_first * q1 * (1.0 - survival_second) + survival_second * q2 * (1.0 - survival_first)
+            + survival_first * q1 * survival_second * q2;
+        rates.push(if alive > 0.0 {1000.0 * deaths / alive} else {1000.0});
+        survival_first *= 1.0 - q1;
+        survival_second *= 1.0 - q2;
+    }
+    return rates;
+}
+
+// joint coi per 1000 by policy year, switching to the survivor's rates from a first death
+pub fn survivorship_coi(first: &[f64], second: &[f64], years: usize, first_death: Option<FirstDeath>) -> Vec<f64> {
+    let mut rates = last_survivor_rates(first, second, years);
+    if let Some(death) = first_death {
+        let survivor = match death.survivor {
+            Insured::First => first,
+            Insured::Second => second,
+        };
+        for (year, rate) in rates.iter_mut().enumerate().skip((death.policy_year - 1).max(0) as usize) {
+            *rate = 1000.0 * mortality(survivor, year);
+        }
+    }
+    return rates;
+}
+
+// the first insured's rates with the coi replaced by the joint rate, all other charges stay the first insured's
+pub fn apply_survivorship(rates: &mut RateSet, second_coi: &[f64], first_death: Option<FirstDeath>) {
+    let years = rates.coi_rates.len();
+    rates.coi_rates = survivorship_coi(&rates.coi_rates, second_coi, years, first_death);
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::HashMap, fs::{self, File}, io::Read, path::Path, str::FromStr
+};
+
+use csv::ByteRecord;
+
+use crate::{
+    age::{AttainedAge, IssueAge, PolicyYear},
+    error::ValactError,
+    product::{ProductSpec, TableRates},
+    rates::{
+        cell_coi_key, csv_text_records, is_json, projection_years, read_cell_coi_table, table_records,
+        AARecord, DataDir, GenRCIAPYRecord, IAPYRecord, RateSet, RecordIter, DEFAULT_MATURITY_AGE,
+    },
+    select_ultimate::SelectUltimateTable,
+};
+
+// the rate tables parsed once and indexed by cell, so assembling a policy's rates is a
+// few hash lookups rather than a rescan of every csv
+// versioned tables are loaded as of one date, the tables a policy's rates come from are one RateTables per
+// version in effect (see RateRepository::in_memory_to_maturity)
+// rates by policy year are filled with the same defaults get_rates uses for missing entries
+// the tables RateTables loads, whose versions split policies by issue date
+pub const TABLE_NAMES: [&str; 4] = ["unit_load", "corridor_factors", "coi", "surrender_charge"];
+
+pub struct RateTables {
+    unit_loads: HashMap<IssueAge, Vec<f64>>,
+    // by attained age, shifted to policy year when a policy's rates are assembled
+    corr_facts: HashMap<AttainedAge, f64>,
+    coi_rates: CoiRates,
+    // empty when the product has no surrender_charge table
+    surrender_charges: HashMap<IssueAge, Vec<f64>>,
+    // per cell coi files (xtbml or select and ultimate csv), these take precedence over coi_rates
+    cell_coi: HashMap<(String, String), SelectUltimateTable>,
+    // the directory the tables came from, for any other charge a product reads from a table
+    data_dir: DataDir,
+}
+
+impl RateTables {
+    // tables from a data directory, csv or json as for get_rates, each in the version in effect on the directory's
+    // date, see DataDir::table_path_as_of
+    pub fn load(data_dir: &DataDir) -> Result<RateTables, ValactError> {
+        let surrender_charge_path = data_dir.table_path("surrender_charge");
+        let surrender_charges = if Path::new(&surrender_charge_path).exists() {Some(table_records::<IAPYRecord>(&surrender_charge_path)?)} else {None};
+        let coi_path = data_dir.table_path("coi");
+        let coi_rates = if is_json(&coi_path) {index_coi_records(table_records(&coi_path)?)?} else {read_coi_csv(&coi_path)?};
+        let mut tables = RateTables::from_records(
+            table_records(&data_dir.table_path("unit_load"))?,
+            table_records(&data_dir.table_path("corridor_factors"))?,
+            coi_rates,
+            surrender_charges,
+        )?;
+
+        let mut cell_coi: HashMap<(String, String), SelectUltimateTable> = HashMap::new();
+        for entry in fs::read_dir(data_dir.as_str())? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if let Some(key) = cell_coi_key(name) {
+                cell_coi.insert(key, read_cell_coi_table(&path.to_string_lossy())?);
+            }
+        }
+
+        tables.cell_coi = cell_coi;
+        tables.data_dir = data_dir.clone();
+        return Ok(tables);
+    }
+
+    // tables from csv text rather than files, for callers without a file system (the wasm build)
+    // no per cell coi files so every cell comes from the coi table
+    pub fn from_csv(unit_load: &str, corridor_factors: &str, coi: &str, surrender_charge: Option<&str>) -> Result<RateTables, ValactError> {
+        return RateTables::from_records(
+            csv_text_records("unit_load.csv", unit_load),
+            csv_text_records("corridor_factors.csv", corridor_factors),
+            index_coi_csv(csv_reader(coi.as_bytes()), coi.len() as u64, "coi.csv")?,
+            surrender_charge.map(|text| csv_text_records("surrender_charge.csv", text)),
+        );
+    }
+
+    fn from_records(
+        unit_load: RecordIter<IAPYRecord>,
+        corridor_factors: RecordIter<AARecord>,
+        coi_rates: CoiRates,
+        surrender_charge: Option<RecordIter<IAPYRecord>>,
+    ) -> Result<RateTables, ValactError> {
+        let mut unit_loads: HashMap<IssueAge, Vec<f64>> = HashMap::new();
+        for result in unit_load {
+            let record = result?;
+            set_policy_year(unit_loads.entry(record.issue_age).or_default(), record.policy_year, record.rate);
+        }
+
+        let mut corr_facts: HashMap<AttainedAge, f64> = HashMap::new();
+        for result in corridor_factors {
+            let record = result?;
+            corr_facts.insert(record.attained_age, record.rate);
+        }
+
+        let mut surrender_charges: HashMap<IssueAge, Vec<f64>> = HashMap::new();
+        for result in surrender_charge.into_iter().flatten() {
+            let record = result?;
+            set_policy_year(surrender_charges.entry(record.issue_age).or_default(), record.policy_year, record.rate);
+        }
+
+        return Ok(RateTables { unit_loads, corr_facts, coi_rates, surrender_charges, cell_coi: HashMap::new(), data_dir: DataDir::default() });
+    }
+
+    pub fn get_rates(&self, gender: &str, risk_class: &str, issue_age: IssueAge) -> Result<RateSet, ValactError> {
+        return self.get_rates_to_maturity(gender, risk_class, issue_age, DEFAULT_MATURITY_AGE);
+    }
+
+    pub fn get_rates_to_maturity(&self, gender: &str, risk_class: &str, issue_age: IssueAge, maturity_age: AttainedAge) -> Result<RateSet, ValactError> {
+        let years = projection_years(issue_age, maturity_age)?;
+        let unit_loads = sized(self.unit_loads.get(&issue_age), 0.0, years);
+
+        let mut corr_facts: Vec<f64> = vec![1.0; years];
+        for (attained_age, rate) in &self.corr_facts {
+            if let Some(idx) = attained_age.years_since(issue_age) && idx < years {
+                corr_facts[idx] = *rate;
+            }
+        }
+
+        let coi_rates = match self.cell_coi.get(&(gender.to_string(), risk_class.to_string())) {
+            Some(table) => table.rates_for_issue_age(issue_age, 0.0, years),
+            None => {
+                let key = (gender.to_string(), risk_class.to_string(), issue_age);
+                sized(self.coi_rates.get(&key), 0.0, years)
+            }
+        };
+
+        let surrender_charges = sized(self.surrender_charges.get(&issue_age), 0.0, years);
+
+        let tables = TableRates { unit_loads, corr_facts, coi_rates, surrender_charges };
+        return ProductSpec::default().build_rate_set(&self.data_dir, gender, risk_class, issue_age, maturity_age, tables);
+    }
+}
+
+// tables are stored with as many policy years as they have, gaps filled with 0 like get_rates does
+fn set_policy_year(rates: &mut Vec<f64>, policy_year: PolicyYear, rate: f64) {
+    let idx = policy_year.index();
+    if rates.len() <= idx {
+        rates.resize(idx + 1, 0.0);
+    }
+    rates[idx] = rate;
+}
+
+fn sized(rates: Option<&Vec<f64>>, default: f64, years: usize) -> Vec<f64> {
+    let mut sized = vec![default; years];
+    if let Some(rates) = rates {
+        let n = rates.len().min(years);
+        sized[..n].copy_from_slice(&rates[..n]);
+    }
+    return sized;
+}
+
+type CoiRates = HashMap<(String, String, IssueAge), Vec<f64>>;
+
+fn index_coi_records(records: RecordIter<GenRCIAPYRecord>) -> Result<CoiRates, ValactError> {
+    let mut coi_rates: CoiRates = HashMap::new();
+    for result in records {
+        let record = result?;
+        let key = (record.gender, record.risk_class, record.issue_age);
+        set_policy_year(coi_rates.entry(key).or_default(), record.policy_year, record.rate);
+    }
+    return Ok(coi_rates);
+}
+
+// the coi table covers every gender, class, issue age and duration and can run to hundreds of mb, so rather
+// than deserializing a record per row it is read a row at a time into one ByteRecord and the fields parsed
+// straight from its bytes, with no String for a row of the cell before it; the tables are sorted by cell so
+// a cell's rates are filled without a hash lookup per row, and the index is sized from the file length once
+// the first cell shows how many bytes a cell takes
+// with the mmap feature the file is mapped into memory instead of read through the reader's buffer
+fn read_coi_csv(path: &str) -> Result<CoiRates, ValactError> {
+    let file = File::open(path).map_err(|source| ValactError::File { path: path.to_string(), source })?;
+    let len = file.metadata().map_err(|source| ValactError::File { path: path.to_string(), source })?.len();
+    #[cfg(feature = "mmap")]
+    {
+        // the rate files are not written to while a run is loading them
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|source| ValactError::File { path: path.to_string(), source })?;
+        return index_coi_csv(csv_reader(&map[..]), len, path);
+    }
+    #[cfg(not(feature = "mmap"))]
+    return index_coi_csv(csv_reader(file), len, path);
+}
+
+const READ_BUFFER: usize = 1 << 20;
+
+fn csv_reader<R: Read>(rdr: R) -> csv::Reader<R> {
+    return csv::ReaderBuilder::new().buffer_capacity(READ_BUFFER).from_reader(rdr);
+}
+
+// position of a column by either of the names the record's serde aliases accept
+fn column(headers: &ByteRecord, names: [&str; 2], path: &str) -> Result<usize, ValactError> {
+    return headers.iter().position(|header| names.iter().any(|name| header == name.as_bytes()))
+        .ok_or_else(|| ValactError::Table { path: path.to_string(), message: format!("missing field `{}`", names[0]) });
+}
+
+struct CoiColumns {
+    gender: usize,
+    risk_class: usize,
+    issue_age: usize,
+    policy_year: usize,
+    rate: usize,
+}
+
+fn field<'r>(record: &'r ByteRecord, index: usize, name: &str, path: &str) -> Result<&'r str, ValactError> {
+    let row_error = |message: String| {
+        return ValactError::Row { path: path.to_string(), line: record.position().map_or(0, |p| p.line()), column: Some(name.to_string()), message };
+    };
+    let bytes = record.get(index).ok_or_else(|| row_error("field is missing".to_string()))?;
+    return std::str::from_utf8(bytes).map_err(|e| row_error(e.to_string()));
+}
+
+fn number<T: FromStr>(record: &ByteRecord, index: usize, name: &str, path: &str) -> Result<T, ValactError>
+where
+    T::Err: std::fmt::Display,
+{
+    let text = field(record, index, name, path)?;
+    return text.parse().map_err(|e: T::Err| ValactError::Row {
+        path: path.to_string(),
+        line: record.position().map_or(0, |p| p.line()),
+        column: Some(name.to_string()),
+        message: format!("{:?}: {}", text, e),
+    });
+}
+
+// ages and years straight from the digits, a fair part of the load otherwise goes on checking utf8 and parsing
+fn whole_number(record: &ByteRecord, index: usize, name: &str, path: &str) -> Result<i64, ValactError> {
+    let bytes = record.get(index).unwrap_or_default();
+    if !bytes.is_empty() && bytes.len() <= 6 && bytes.iter().all(u8::is_ascii_digit) {
+        return Ok(bytes.iter().fold(0, |n, digit| 10 * n + i64::from(digit - b'0')));
+    }
+    return number(record, index, name, path);
+}
+
+fn index_coi_csv<R: Read>(mut rdr: csv::Reader<R>, len: u64, path: &str) -> Result<CoiRates, ValactError> {
+    let headers = rdr.byte_headers().map_err(|e| ValactError::csv(path, None, e))?.clone();
+    let columns = CoiColumns {
+        gender: column(&headers, ["gender", "Gender"], path)?,
+        risk_class: column(&headers, ["risk_class", "Risk_Class"], path)?,
+        issue_age: column(&headers, ["issue_age", "Issue_Age"], path)?,
+        policy_year: column(&headers, ["policy_year", "Policy_Year"], path)?,
+        rate: column(&headers, ["rate", "Rate"], path)?,
+    };
+
+    let mut coi_rates: CoiRates = HashMap::new();
+    let mut record = ByteRecord::new();
+    // the cell being filled, put in the index when the rows move on to another
+    let mut current: Option<((String, String, IssueAge), Vec<f64>)> = None;
+    let mut sized = false;
+    while rdr.read_byte_record(&mut record).map_err(|e| ValactError::csv(path, None, e))? {
+        let gender = field(&record, columns.gender, "Gender", path)?;
+        let risk_class = field(&record, columns.risk_class, "Risk_Class", path)?;
+        let issue_age = whole_number(&record, columns.issue_age, "Issue_Age", path)?;
+        let policy_year = whole_number(&record, columns.policy_year, "Policy_Year", path)?;
+        let rate: f64 = number(&record, columns.rate, "Rate", path)?;
+        let row_error = |column: &str, e: ValactError| {
+            return ValactError::Row { path: path.to_string(), line: record.position().map_or(0, |p| p.line()), column: Some(column.to_string()), message: e.to_string() };
+        };
+        let issue_age = IssueAge::try_from(issue_age).map_err(|e| row_error("Issue_Age", e))?;
+        let policy_year = PolicyYear::try_from(policy_year).map_err(|e| row_error("Policy_Year", e))?;
+
+        let same_cell = current.as_ref().is_some_and(|((g, c, a), _)| g == gender && c == risk_class && *a == issue_age);
+        if !same_cell {
+            let key = (gender.to_string(), risk_class.to_string(), issue_age);
+            let years = current.as_ref().map_or(0, |(_, rates)| rates.len());
+            if let Some((key, rates)) = current.take() {
+                if !sized {
+                    // bytes read so far over the one cell they held
+                    let cell_bytes = record.position().map_or(0, |p| p.byte()).max(1);
+                    coi_rates.reserve((len / cell_bytes) as usize + 1);
+                    sized = true;
+                }
+                coi_rates.insert(key, rates);
+            }
+            // a cell whose rows are not all together carries on from where it was
+            let rates = coi_rates.remove(&key).unwrap_or_else(|| Vec::with_capacity(years));
+            current = Some((key, rates));
+        }
+        if let Some((_, rates)) = current.as_mut() {
+            set_policy_year(rates, policy_year, rate);
+        }
+    }
+    if let Some((key, rates)) = current {
+        coi_rates.insert(key, rates);
+    }
+    return Ok(coi_rates);
+}
@@ -0,0 +1,213 @@
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    age::{AttainedAge, IssueAge, PolicyYear},
+    error::ValactError,
+    projection::{solve_for_premium_using, DeathBenefitOption, MonthResult, PremiumPattern, ProjectionConfig, SolveTarget},
+    rates::RateSet,
+    solver::{Method, SolverOptions},
+};
+
+// IRC 7702 guideline premium test and the 7702A seven-pay test
+// guideline premiums are the premiums that endow the face amount at the deemed maturity age using the contract's
+// mortality and expense charges with interest at the greater of the statutory and contract rates
+// the coi rates stand in for the reasonable mortality charges, which assumes they are no higher than 2001 CSO
+
+// statutory minimum interest, annual effective
+pub const GSP_INTEREST: f64 = 0.06;
+pub const GLP_INTEREST: f64 = 0.04;
+// deemed maturity age, the calculations never run past it even when the contract does
+pub const DEEMED_MATURITY_AGE: AttainedAge = AttainedAge::of(100);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GuidelinePremiums {
+    pub single: f64,
+    pub level: f64,
+}
+
+impl GuidelinePremiums {
+    // most that can be paid to the end of policy_year, the greater of the single premium and the sum of level premiums
+    pub fn limit(&self, policy_year: i32) -> f64 {
+        return self.single.max(self.level * f64::from(policy_year));
+    }
+}
+
+// first policy year premiums paid less withdrawals go over the limit
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct GuidelineViolation {
+    pub policy_year: i32,
+    pub premiums_paid: f64,
+    pub limit: f64,
+}
+
+// what to do when a projection pays more than the guideline limit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum GuidelineTest {
+    #[default]
+    Off,
+    Warn,
+    Refuse,
+}
+
+// result of the seven-pay test over a projection
+// a material change restarts the test, seven_pay_premium and test_start_year are for the last test run
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct SevenPayTest {
+    pub seven_pay_premium: f64,
+    pub test_start_year: i32,
+    // policy year the contract became a modified endowment, None if it never did
+    pub mec_year: Option<i32>,
+}
+
+// rates with the interest credited and the naar discount at the greater of the statutory rate and what the contract gives
+fn guideline_rates(rates: &RateSet, interest: f64) -> RateSet {
+    let monthly = f64::powf(1.0 + interest, 1.0 / 12.0) - 1.0;
+    let mut guideline = rates.clone();
+    guideline.maturity_age = rates.maturity_age.min(DEEMED_MATURITY_AGE);
+    guideline.interest_rates = rates.interest_rates.iter().map(|rate| rate.max(monthly)).collect();
+    guideline.naar_discs = guideline.interest_rates.iter().map(|rate| 1.0 / (1.0 + rate)).collect();
+    // the death benefit is the face amount, the corridor is what the test is protecting
+    guideline.corr_facts = vec![0.0; rates.corr_facts.len()];
+    return guideline;
+}
+
+fn endowing_premium(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig) -> Result<f64, ValactError> {
+    // the value at maturity is smooth in the premium once the corridor is gone, brent gets there in a few projections
+    let options = SolverOptions::new(Method::Brent, 0.005);
+    let solve = solve_for_premium_using(rates, issue_age, face_amount, config, SolveTarget::ValueAtMaturity { amount: face_amount }, &options)?;
+    return Ok(solve.premium);
+}
+
+// the single premium is always on a level death benefit, the level premium follows option 2 if the policy has it
+// withdrawals, loans and face changes in config are ignored, adjustments for face changes are not modeled
+pub fn guideline_premiums(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig) -> Result<GuidelinePremiums, ValactError> {
+    if issue_age.at_issue() >= DEEMED_MATURITY_AGE.min(rates.maturity_age) {
+        return Err(format!("issue age {} is at or past the deemed maturity age", issue_age).into());
+    }
+    let base = ProjectionConfig {
+        timestep: config.timestep,
+        ..Default::default()
+    };
+
+    let single_config = ProjectionConfig { premium_pattern: PremiumPattern::SinglePay, ..base.clone() };
+    let single = endowing_premium(&guideline_rates(rates, GSP_INTEREST), issue_age, face_amount, &single_config)?;
+
+    let level_option = match config.death_benefit_option {
+        DeathBenefitOption::Increasing => DeathBenefitOption::Increasing,
+        _ => DeathBenefitOption::Level,
+    };
+    let level_config = ProjectionConfig { death_benefit_option: level_option, ..base };
+    let level = endowing_premium(&guideline_rates(rates, GLP_INTEREST), issue_age, face_amount, &level_config)?;
+
+    return Ok(GuidelinePremiums { single, level });
+}
+
+// checks premiums paid less withdrawals against the limit at the end of each policy year
+pub fn first_violation(months: &[MonthResult], premiums: &GuidelinePremiums) -> Option<GuidelineViolation> {
+    let mut premiums_paid = 0.0;
+    for month in months {
+        premiums_paid += month.premium - month.withdrawal;
+        let limit = premiums.limit(month.policy_year);
+        // a cent of slack so a premium rounded to cents is not a violation of itself
+        if premiums_paid > limit + 0.01 {
+            return Some(GuidelineViolation { policy_year: month.policy_year, premiums_paid, limit });
+        }
+    }
+    return None;
+}
+
+// rates for a contract issued at the start of policy_year, the vectors are by policy year so they start later
+fn rates_from_year(rates: &RateSet, policy_year: usize) -> RateSet {
+    let from = |v: &Vec<f64>| v[policy_year - 1..].to_vec();
+    return RateSet {
+        maturity_age: rates.maturity_age,
+        premium_loads: from(&rates.premium_loads),
+        policy_fees: from(&rates.policy_fees),
+        unit_loads: from(&rates.unit_loads),
+        corr_facts: from(&rates.corr_facts),
+        naar_discs: from(&rates.naar_discs),
+        coi_rates: from(&rates.coi_rates),
+        interest_rates: from(&rates.interest_rates),
+        surrender_charges: from(&rates.surrender_charges),
+    };
+}
+
+// level annual premium for seven years that pays up the face amount, on a level death benefit at the guideline level rate
+pub fn seven_pay_premium(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig) -> Result<f64, ValactError> {
+    return seven_pay_premium_at(rates, issue_age, 1, face_amount, 0.0, config);
+}
+
+// seven-pay premium for a test starting at the start of policy_year, e.g. after a material change
+// the account value at that point is rolled in by reducing the premium in proportion to the net single premium it covers
+fn seven_pay_premium_at(rates: &RateSet, issue_age: IssueAge, policy_year: i32, face_amount: f64, account_value: f64, config: &ProjectionConfig) -> Result<f64, ValactError> {
+    let attained_age = issue_age.attained_age(PolicyYear::try_from(i64::from(policy_year))?)?;
+    if attained_age >= DEEMED_MATURITY_AGE.min(rates.maturity_age) {
+        return Err(format!("attained age {} is at or past the deemed maturity age", attained_age).into());
+    }
+    let rates = guideline_rates(&rates_from_year(rates, policy_year as usize), GLP_INTEREST);
+    let base = ProjectionConfig { timestep: config.timestep, ..Default::default() };
+    let seven_pay_config = ProjectionConfig { premium_pattern: PremiumPattern::NPay(7), ..base.clone() };
+    let seven_pay = endowing_premium(&rates, attained_age.as_issue_age(), face_amount, &seven_pay_config)?;
+    if account_value <= 0.0 {
+        return Ok(seven_pay);
+    }
+    let single_config = ProjectionConfig { premium_pattern: PremiumPattern::SinglePay, ..base };
+    let net_single = endowing_premium(&rates, attained_age.as_issue_age(), face_amount, &single_config)?;
+    return Ok(seven_pay * (1.0 - account_value / net_single).max(0.0));
+}
+
+// 7702A seven-pay test of a projection, amounts paid are premiums less withdrawals
+// a face increase is a material change, the test restarts that year with a new seven-pay premium
+// a face decrease within the seven years retests from the start of the period with the seven-pay premium for the lower face
+pub fn seven_pay_test(rates: &RateSet, issue_age: IssueAge, face_amount: f64, config: &ProjectionConfig, months: &[MonthResult]) -> Result<SevenPayTest, ValactError> {
+    let mut test = SevenPayTest {
+        seven_pay_premium: seven_pay_premium(rates, issue_age, face_amount, config)?,
+        test_start_year: 1,
+        mec_year: None,
+    };
+    let mut tested_face = face_amount;
+    let mut start_value = 0.0;
+    // amounts paid since the test started, one total for each policy year completed
+    let mut paid_by_year: Vec<f64> = Vec::new();
+    let mut paid = 0.0;
+
+    for month in months {
+        let year = month.policy_year;
+        if month.month_in_policy_year == 1 && year > 1 {
+            paid_by_year.push(paid);
+            if month.face_amount > tested_face {
+                test.seven_pay_premium = seven_pay_premium_at(rates, issue_age, year, month.face_amount, month.value_start, config)?;
+                test.test_start_year = year;
+                start_value = month.value_start;
+                paid_by_year.clear();
+                paid = 0.0;
+            } else if month.face_amount < tested_face && year < test.test_start_year + 7 {
+                test.seven_pay_premium = seven_pay_premium_at(rates, issue_age, test.test_start_year, month.face_amount, start_value, config)?;
+                let retest = paid_by_year.iter().enumerate().any(|(i, paid)| *paid > test.seven_pay_premium * (i + 1) as f64 + 0.01);
+                if retest {
+                    test.mec_year = Some(year);
+                    return Ok(test);
+                }
+            }
+            tested_face = month.face_amount;
+        }
+        paid += month.premium - month.withdrawal;
+        let test_years = year - test.test_start_year + 1;
+        // a cent of slack so paying the seven-pay premium rounded to cents is not a failure
+        if test_years <= 7 && paid > test.seven_pay_premium * f64::from(test_years) + 0.01 {
+            test.mec_year = Some(year);
+            return Ok(test);
+        }
+    }
+    return Ok(test);
+}
+
+// flags the months from the year the contract became a modified endowment on
+pub fn mark_mec(months: &mut [MonthResult], test: &SevenPayTest) {
+    if let Some(mec_year) = test.mec_year {
+        for month in months.iter_mut().filter(|m| m.policy_year >= mec_year) {
+            month.mec = true;
+        }
+    }
+}
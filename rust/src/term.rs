@@ -0,0 +1,109 @@
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assumptions::Assumptions, decrements::{expected_cash_flows, ExpectedMonth}, error::ValactError, profit::{profit_by_year, profit_summary, solve_for_profit_target, ProfitSummary, ProfitTarget, YearProfit}, projection::{MonthResult, Timestep}, reserve::{reserves_held, statutory_reserves}, sensitivity::PricingBasis
+};
+
+// level term, a level premium for the level period with the cover ending at the end of it, no account value
+// and nothing paid on surrender
+// the premium is a rate per 1000 of face plus a policy fee, spread evenly over the steps of the year, and the rate
+// is solved so the profit test on the pricing basis hits a target profit margin or irr
+// the profit test holds net level reserves for the term cover at the valuation interest, on the expected mortality
+// as there is no separate valuation table for term here, and commission is paid on the whole premium as target
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct TermProduct {
+    pub level_years: i32,
+    // per policy per year, on top of the rate per 1000
+    pub policy_fee: f64,
+    pub timestep: Timestep,
+}
+
+impl Default for TermProduct {
+    fn default() -> Self {
+        return TermProduct { level_years: 20, policy_fee: 60.0, timestep: Timestep::Monthly };
+    }
+}
+
+impl TermProduct {
+    pub fn annual_premium(&self, face_amount: f64, rate_per_1000: f64) -> f64 {
+        return rate_per_1000 * face_amount / 1000.0 + self.policy_fee;
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TermPricing {
+    pub level_years: i32,
+    pub rate_per_1000: f64,
+    pub annual_premium: f64,
+    pub summary: ProfitSummary,
+    pub years: Vec<YearProfit>,
+}
+
+// the single policy path for the level period, every step at the face amount
+pub fn term_months(face_amount: f64, annual_premium: f64, product: &TermProduct) -> Vec<MonthResult> {
+    let steps_per_year = product.timestep.steps_per_year();
+    let premium = annual_premium / f64::from(steps_per_year);
+    let mut months: Vec<MonthResult> = Vec::with_capacity((product.level_years * steps_per_year) as usize);
+    for step in 0..product.level_years * steps_per_year {
+        months.push(MonthResult {
+            policy_month: step * 12 / steps_per_year + 1,
+            policy_year: step / steps_per_year + 1,
+            month_in_policy_year: (step % steps_per_year) * 12 / steps_per_year + 1,
+            premium,
+            face_amount,
+            death_benefit: face_amount,
+            net_death_benefit: face_amount,
+            ..Default::default()
+        });
+    }
+    return months;
+}
+
+// net level terminal reserve per $1 of face at the end of each year of the level period, deaths at the end of the
+// year and premiums at the start
+pub fn term_reserve_factors(mortality: &[f64], level_years: usize, interest: f64) -> Vec<f64> {
+    let v = 1.0 / (1.0 + interest);
+    let mut benefit = vec![0.0; level_years + 1];
+    let mut annuity = vec![0.0; level_years + 1];
+    for t in (0..level_years).rev() {
+        let q = (mortality[t] / 1000.0).clamp(0.0, 1.0);
+        benefit[t] = v * (q + (1.0 - q) * benefit[t + 1]);
+        annuity[t] = 1.0 + v * (1.0 - q) * annuity[t + 1];
+    }
+    let premium = if annuity[0] > 0.0 {benefit[0] / annuity[0]} else {0.0};
+    return (1..=level_years).map(|t| (benefit[t] - premium * annuity[t]).max(0.0)).collect();
+}
+
+// expected cash flows and profit by year at a premium
+pub fn term_profit(face_amount: f64, annual_premium: f64, product: &TermProduct, basis: &PricingBasis, assumptions: &Assumptions) -> Result<(Vec<ExpectedMonth>, Vec<YearProfit>), ValactError> {
+    let level_years = product.level_years as usize;
+    if product.level_years < 1 || level_years > basis.mortality.len() || level_years > basis.lapses.len() {
+        return Err(format!("level period of {} years is not covered by the pricing basis of {} years", product.level_years, basis.mortality.len()).into());
+    }
+    let months = term_months(face_amount, annual_premium, product);
+    let expected = expected_cash_flows(&months, &basis.mortality, &basis.lapses, product.timestep, assumptions, annual_premium);
+    let factors = term_reserve_factors(&basis.mortality, level_years, assumptions.valuation.interest);
+    let reserves = statutory_reserves(&months, &factors, product.timestep.steps_per_year());
+    let held = reserves_held(&expected, &reserves);
+    let years = profit_by_year(&expected, Some(&held), product.timestep, &assumptions.profit);
+    return Ok((expected, years));
+}
+
+pub fn solve_term_premium(face_amount: f64, product: &TermProduct, basis: &PricingBasis, assumptions: &Assumptions, target: ProfitTarget) -> Result<TermPricing, ValactError> {
+    let hurdle_rate = assumptions.profit.hurdle_rate;
+    let profits_at = |rate_per_1000: f64| -> Result<Vec<YearProfit>, ValactError> {
+        return Ok(term_profit(face_amount, product.annual_premium(face_amount, rate_per_1000), product, basis, assumptions)?.1);
+    };
+    let rate_per_1000 = solve_for_profit_target(profits_at, target, hurdle_rate, (0.01, 1.0))?;
+    let annual_premium = product.annual_premium(face_amount, rate_per_1000);
+    let (_, years) = term_profit(face_amount, annual_premium, product, basis, assumptions)?;
+    return Ok(TermPricing {
+        level_years: product.level_years,
+        rate_per_1000,
+        annual_premium,
+        summary: profit_summary(&years, hurdle_rate),
+        years,
+    });
+}
@@ -0,0 +1,344 @@
+use std::{
+    collections::{BTreeMap, BTreeSet}, fmt, fs::{self, File}, io::BufReader, path::Path
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{age::{AttainedAge, MAX_AGE, MAX_POLICY_YEAR}, error::ValactError, rates::{cell_coi_key, is_json, read_cell_coi_table, DataDir}};
+
+// checks on the rate tables in the data directory, run before a batch so bad data is caught up front rather than
+// as a panic or a silently defaulted rate part way through
+// errors are rows the engine would reject or misread: unparseable rows, negative rates, duplicate keys
+// warnings are rows it would fill in or ignore: gaps in issue ages or policy years (filled with the default),
+// issue ages the coi table has that another table does not, and rows past the maturity age
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Finding {
+    pub table: String,
+    pub severity: Severity,
+    // csv line (the header is line 1), or position in the array for json tables
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TableSummary {
+    pub table: String,
+    pub rows: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ValidationReport {
+    pub data_dir: String,
+    pub maturity_age: AttainedAge,
+    pub tables: Vec<TableSummary>,
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    pub fn errors(&self) -> usize {
+        return self.findings.iter().filter(|f| f.severity == Severity::Error).count();
+    }
+
+    pub fn warnings(&self) -> usize {
+        return self.findings.iter().filter(|f| f.severity == Severity::Warning).count();
+    }
+}
+
+// one block per table with what was found under it, then the totals
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Rate tables in {} checked against maturity age {}", self.data_dir, self.maturity_age)?;
+        for table in &self.tables {
+            let findings: Vec<&Finding> = self.findings.iter().filter(|finding| finding.table == table.table).collect();
+            let status = if findings.is_empty() {"ok".to_string()} else {plural(findings.len(), "finding")};
+            writeln!(f, "{:<28} {:>7} rows, {}", table.table, table.rows, status)?;
+            for finding in findings {
+                let severity = match finding.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                match finding.line {
+                    Some(line) => writeln!(f, "  {}: line {}: {}", severity, line, finding.message)?,
+                    None => writeln!(f, "  {}: {}", severity, finding.message)?,
+                }
+            }
+        }
+        write!(f, "{}, {}", plural(self.errors(), "error"), plural(self.warnings(), "warning"))
+    }
+}
+
+fn plural(count: usize, word: &str) -> String {
+    return format!("{} {}{}", count, word, if count == 1 {""} else {"s"});
+}
+
+// the columns a table is keyed by, rate always follows
+#[derive(Clone, Copy, PartialEq)]
+enum Layout {
+    IssueAgePolicyYear,
+    CellIssueAgePolicyYear,
+    IssueAge,
+    PolicyYear,
+    AttainedAge,
+}
+
+impl Layout {
+    fn columns(self) -> &'static [&'static str] {
+        return match self {
+            Layout::IssueAgePolicyYear => &["Issue_Age", "Policy_Year"],
+            Layout::CellIssueAgePolicyYear => &["Gender", "Risk_Class", "Issue_Age", "Policy_Year"],
+            Layout::IssueAge => &["Issue_Age"],
+            Layout::PolicyYear => &["Policy_Year"],
+            Layout::AttainedAge => &["Attained_Age"],
+        };
+    }
+}
+
+// tables get_rates and the pricing commands read, (name, layout, required)
+const TABLES: [(&str, Layout, bool); 7] = [
+    ("unit_load", Layout::IssueAgePolicyYear, true),
+    ("corridor_factors", Layout::AttainedAge, true),
+    ("coi", Layout::CellIssueAgePolicyYear, true),
+    ("surrender_charge", Layout::IssueAgePolicyYear, false),
+    ("target_premium", Layout::IssueAge, false),
+    ("lapse", Layout::PolicyYear, false),
+    ("wmd", Layout::AttainedAge, false),
+];
+
+// any layout, the columns it does not have are left as None
+#[derive(Deserialize)]
+struct TableRow {
+    #[serde(alias="Gender", default)]
+    gender: Option<String>,
+    #[serde(alias="Risk_Class", default)]
+    risk_class: Option<String>,
+    #[serde(alias="Issue_Age", default)]
+    issue_age: Option<i32>,
+    #[serde(alias="Policy_Year", default)]
+    policy_year: Option<i32>,
+    #[serde(alias="Attained_Age", default)]
+    attained_age: Option<i32>,
+    #[serde(alias="Rate")]
+    rate: f64,
+}
+
+impl TableRow {
+    fn has(&self, column: &str) -> bool {
+        return match column {
+            "Gender" => self.gender.is_some(),
+            "Risk_Class" => self.risk_class.is_some(),
+            "Issue_Age" => self.issue_age.is_some(),
+            "Policy_Year" => self.policy_year.is_some(),
+            _ => self.attained_age.is_some(),
+        };
+    }
+
+    // gender and risk class for the coi table, empty for the others
+    fn cell(&self) -> String {
+        return match (&self.gender, &self.risk_class) {
+            (Some(gender), Some(risk_class)) => format!("{} {}", gender, risk_class),
+            _ => String::new(),
+        };
+    }
+}
+
+type Rows = Vec<(u64, Result<TableRow, String>)>;
+// cell, issue age, policy year and attained age, whichever the layout has
+type RowKey = (String, Option<i32>, Option<i32>, Option<i32>);
+
+fn read_rows(path: &str) -> Result<Rows, ValactError> {
+    if is_json(path) {
+        let values: Vec<serde_json::Value> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        return Ok(values.into_iter().enumerate().map(|(i, value)| (i as u64 + 1, serde_json::from_value(value).map_err(|e| e.to_string()))).collect());
+    }
+    let mut rdr = csv::Reader::from_path(path)?;
+    let headers = rdr.headers()?.clone();
+    let mut rows = Vec::new();
+    for result in rdr.records() {
+        let record = result?;
+        let line = record.position().map_or(0, |p| p.line());
+        rows.push((line, record.deserialize(Some(&headers)).map_err(|e| e.to_string())));
+    }
+    return Ok(rows);
+}
+
+// e.g. 4-6, 9
+fn ranges(values: &BTreeSet<i32>) -> String {
+    let mut parts = Vec::new();
+    let mut iter = values.iter().copied().peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end + 1)) {
+            end = iter.next().unwrap_or(end);
+        }
+        parts.push(if start == end {start.to_string()} else {format!("{}-{}", start, end)});
+    }
+    return parts.join(", ");
+}
+
+fn missing_between(present: &BTreeSet<i32>, from: i32, to: i32) -> BTreeSet<i32> {
+    return (from..=to).filter(|value| !present.contains(value)).collect();
+}
+
+struct Checked {
+    summary: TableSummary,
+    // issue ages with rows, by cell
+    issue_ages: BTreeMap<String, BTreeSet<i32>>,
+}
+
+fn check_table(file: &str, rows: Rows, layout: Layout, maturity_age: AttainedAge, findings: &mut Vec<Finding>) -> Checked {
+    let mut finding = |severity: Severity, line: Option<u64>, message: String| findings.push(Finding { table: file.to_string(), severity, line, message });
+    let mut summary = TableSummary { table: file.to_string(), rows: rows.len() };
+    let mut issue_ages: BTreeMap<String, BTreeSet<i32>> = BTreeMap::new();
+    if let Some((_, Ok(row))) = rows.first() {
+        let missing: Vec<&str> = layout.columns().iter().copied().filter(|column| !row.has(column)).collect();
+        if !missing.is_empty() {
+            finding(Severity::Error, None, format!("no {} column", missing.join(", ")));
+            summary.rows = 0;
+            return Checked { summary, issue_ages };
+        }
+    }
+
+    // first line each key was seen on, and the policy years by cell and issue age
+    let mut seen: BTreeMap<RowKey, u64> = BTreeMap::new();
+    let mut policy_years: BTreeMap<(String, Option<i32>), BTreeSet<i32>> = BTreeMap::new();
+    let mut past_maturity: Vec<u64> = Vec::new();
+    for (line, row) in rows {
+        let row = match row {
+            Ok(row) => row,
+            Err(message) => {
+                finding(Severity::Error, Some(line), message);
+                continue;
+            }
+        };
+        let cell = row.cell();
+        if row.rate < 0.0 {
+            finding(Severity::Error, Some(line), format!("negative rate {}", row.rate));
+        }
+        if let Some(policy_year) = row.policy_year && policy_year < 1 {
+            finding(Severity::Error, Some(line), format!("policy year {} is before the first", policy_year));
+        }
+        if let Some(policy_year) = row.policy_year && policy_year > i32::from(MAX_POLICY_YEAR) {
+            finding(Severity::Error, Some(line), format!("policy year {} is past the last the engine takes, {}", policy_year, MAX_POLICY_YEAR));
+        }
+        for (name, age) in [("issue age", row.issue_age), ("attained age", row.attained_age)] {
+            if let Some(age) = age && !(0..=i32::from(MAX_AGE)).contains(&age) {
+                finding(Severity::Error, Some(line), format!("{} {} is outside 0 to {}", name, age, MAX_AGE));
+            }
+        }
+        let key = (cell.clone(), row.issue_age, row.policy_year, row.attained_age);
+        if let Some(first) = seen.get(&key) {
+            finding(Severity::Error, Some(line), format!("duplicate of line {}", first));
+        } else {
+            seen.insert(key, line);
+        }
+        if let Some(issue_age) = row.issue_age {
+            issue_ages.entry(cell.clone()).or_default().insert(issue_age);
+        }
+        if let Some(policy_year) = row.policy_year {
+            policy_years.entry((cell, row.issue_age)).or_default().insert(policy_year);
+        }
+        let attained_age = match (row.issue_age, row.policy_year, row.attained_age) {
+            (Some(issue_age), Some(policy_year), _) => Some(issue_age + policy_year - 1),
+            (_, _, Some(attained_age)) => Some(attained_age),
+            _ => None,
+        };
+        if attained_age.is_some_and(|age| age >= i32::from(maturity_age.get())) {
+            past_maturity.push(line);
+        }
+    }
+
+    for (cell, ages) in &issue_ages {
+        if let (Some(&first), Some(&last)) = (ages.first(), ages.last()) {
+            let missing = missing_between(ages, first, last);
+            if !missing.is_empty() {
+                let cell = if cell.is_empty() {String::new()} else {format!(" for {}", cell)};
+                finding(Severity::Warning, None, format!("no rows for issue ages {}{}", ranges(&missing), cell));
+            }
+        }
+    }
+    for ((cell, issue_age), years) in &policy_years {
+        let missing = missing_between(years, 1, years.last().copied().unwrap_or(1));
+        if !missing.is_empty() {
+            let at = match issue_age {
+                Some(age) => format!(" at issue age {}", age),
+                None => String::new(),
+            };
+            let cell = if cell.is_empty() {String::new()} else {format!(" for {}", cell)};
+            finding(Severity::Warning, None, format!("policy years {} missing{}{}, filled with the default", ranges(&missing), cell, at));
+        }
+    }
+    if let Some(&first) = past_maturity.first() {
+        finding(Severity::Warning, Some(first), format!("{} at or past attained age {}, ignored", plural(past_maturity.len(), "row"), maturity_age));
+    }
+    return Checked { summary, issue_ages };
+}
+
+// every table in the data directory, required ones that are missing are errors and optional ones are skipped
+pub fn validate_data_dir(data_dir: &DataDir, maturity_age: AttainedAge) -> Result<ValidationReport, ValactError> {
+    let mut tables = Vec::new();
+    let mut findings = Vec::new();
+    // file and issue ages with rows, by table name
+    let mut issue_ages: BTreeMap<&str, (String, BTreeSet<i32>)> = BTreeMap::new();
+    for (name, layout, required) in TABLES {
+        let path = data_dir.table_path(name);
+        let file = Path::new(&path).file_name().map_or(name.to_string(), |f| f.to_string_lossy().to_string());
+        if !Path::new(&path).exists() {
+            if required {
+                findings.push(Finding { table: file.clone(), severity: Severity::Error, line: None, message: format!("{} not found", path) });
+                tables.push(TableSummary { table: file, rows: 0 });
+            }
+            continue;
+        }
+        let rows = match read_rows(&path) {
+            Ok(rows) => rows,
+            Err(e) => {
+                findings.push(Finding { table: file.clone(), severity: Severity::Error, line: None, message: e.to_string() });
+                tables.push(TableSummary { table: file, rows: 0 });
+                continue;
+            }
+        };
+        let checked = check_table(&file, rows, layout, maturity_age, &mut findings);
+        if matches!(layout, Layout::IssueAgePolicyYear | Layout::CellIssueAgePolicyYear | Layout::IssueAge) {
+            issue_ages.insert(name, (file, checked.issue_ages.into_values().flatten().collect()));
+        }
+        tables.push(checked.summary);
+    }
+
+    // issue ages the coi table sells that another issue age table has nothing for, so the rate defaults
+    if let Some((_, coi_ages)) = issue_ages.get("coi") {
+        for (name, (file, ages)) in &issue_ages {
+            let missing: BTreeSet<i32> = coi_ages.difference(ages).copied().collect();
+            if *name != "coi" && !missing.is_empty() {
+                let message = format!("no rows for issue ages {} that coi has, their rates default to 0", ranges(&missing));
+                findings.push(Finding { table: file.clone(), severity: Severity::Warning, line: None, message });
+            }
+        }
+    }
+
+    // per cell coi files only need to read
+    for entry in fs::read_dir(data_dir.as_str())? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        if cell_coi_key(&name).is_none() {
+            continue;
+        }
+        let rows = match read_cell_coi_table(&path.to_string_lossy()) {
+            Ok(table) => table.select.len(),
+            Err(e) => {
+                findings.push(Finding { table: name.clone(), severity: Severity::Error, line: None, message: e.to_string() });
+                0
+            }
+        };
+        tables.push(TableSummary { table: name, rows });
+    }
+
+    return Ok(ValidationReport { data_dir: data_dir.to_string(), maturity_age, tables, findings });
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{error::ValactError, indexed::{Account, Crediting}, projection::path_rate, scenarios};
+
+// variable universal life, part of the account value invested in separate account funds each earning its own
+// return path rather than a credited rate
+// a fund's path is gross annual effective returns by policy month from the start of the projection, months past
+// the end stay at its last return, and its expense ratio is taken out of the unit value daily
+// the mortality and expense (m&e) charge is an annual rate on what is in the funds, taken daily out of the unit
+// values or monthly as a deduction at the start of the step
+// the funds are accounts beside the fixed account (indexed.rs), premium loads, expense and coi charges, the
+// corridor, loans and riders work as for universal life; fund returns are not floored so the value can fall
+
+const DAYS_PER_YEAR: f64 = 365.0;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Fund {
+    // gross annual effective return for each month
+    pub returns: Vec<f64>,
+    // annual rate charged daily
+    pub expense_ratio: f64,
+}
+
+impl Fund {
+    // growth over months first_month..first_month + months net of the expense ratio, before any m&e
+    pub fn growth(&self, first_month: usize, months: usize) -> f64 {
+        if self.returns.is_empty() {
+            return daily_factor(self.expense_ratio, months);
+        }
+        return (1.0 + path_rate(&self.returns, first_month, months)) * daily_factor(self.expense_ratio, months);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum ChargeFrequency {
+    // out of the unit values every day, so the fund grows net of it
+    #[default]
+    Daily,
+    // a twelfth of the annual rate a month off the fund value at the start of the step
+    Monthly,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct MortalityExpense {
+    pub rate: f64,
+    #[serde(default)]
+    pub frequency: ChargeFrequency,
+}
+
+// a fund value for a step, split into the m&e charge and the return net of fund expenses
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FundStep {
+    pub mortality_expense: f64,
+    pub fund_return: f64,
+}
+
+impl MortalityExpense {
+    // value is what is in the fund at the start of the step, growth the fund's from Fund::growth
+    pub fn step(&self, value: f64, growth: f64, months: usize) -> FundStep {
+        return match self.frequency {
+            ChargeFrequency::Daily => {
+                let mortality_expense = value * growth * (1.0 - daily_factor(self.rate, months));
+                FundStep { mortality_expense, fund_return: value * (growth - 1.0) }
+            }
+            ChargeFrequency::Monthly => {
+                let mortality_expense = value * self.rate * months as f64 / 12.0;
+                FundStep { mortality_expense, fund_return: (value - mortality_expense) * (growth - 1.0) }
+            }
+        };
+    }
+}
+
+// what is left of 1 after an annual rate is charged every day for a number of months
+fn daily_factor(rate: f64, months: usize) -> f64 {
+    return (1.0 - rate / DAYS_PER_YEAR).powf(DAYS_PER_YEAR * months as f64 / 12.0);
+}
+
+// a fund account for each column of a fund returns file, which has the layout of a scenario file (scenarios.rs),
+// a Month or Policy_Year column then a column of gross returns per fund headed with the fund's name
+// allocations are in the order of the columns, expense ratios too or a single ratio for every fund
+pub fn read_fund_accounts(path: &str, allocations: &[f64], expense_ratios: &[f64]) -> Result<Vec<Account>, ValactError> {
+    let funds = scenarios::read_scenarios_csv(path)?;
+    let count = funds.names.len();
+    if allocations.len() != count {
+        return Err(format!("{}: {} funds but {} allocations", path, count, allocations.len()).into());
+    }
+    let expense_ratio = |fund: usize| -> Result<f64, ValactError> {
+        return match expense_ratios {
+            [] => Ok(0.0),
+            [ratio] => Ok(*ratio),
+            ratios if ratios.len() == count => Ok(ratios[fund]),
+            ratios => Err(format!("{}: {} funds but {} expense ratios", path, count, ratios.len()).into()),
+        };
+    };
+    let mut accounts = Vec::with_capacity(count);
+    for (fund, (name, returns)) in funds.names.into_iter().zip(funds.paths).enumerate() {
+        let crediting = Crediting::Variable(Fund { returns, expense_ratio: expense_ratio(fund)? });
+        accounts.push(Account { name, allocation: allocations[fund], crediting });
+    }
+    return Ok(accounts);
+}
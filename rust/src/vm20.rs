@@ -0,0 +1,45 @@
+
+use serde::Serialize;
+
+use crate::{
+    decrements::ExpectedMonth, error::ValactError, projection::Timestep, rates::read_py_carried
+};
+
+// VM-20 deterministic reserve for a model point
+// the expected cash flows are projected on the prescribed assumptions given to the pricing run and the reserve
+// is the present value of benefits, expenses, commissions and premium tax less premiums, discounted at the net
+// asset earned rates of the deterministic scenario; federal income tax is left out as VM-20 requires
+// premiums, expenses, commission and premium tax are at the start of each step and benefits at the end
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct DeterministicReserve {
+    pub pv_benefits: f64,
+    pub pv_expenses: f64,
+    pub pv_premiums: f64,
+    pub reserve: f64,
+}
+
+// net asset earned rates by projection year, annual effective, one Policy_Year and Rate row per year
+// years past the end of the file stay at its last rate
+pub fn read_naer_path(path: &str, years: usize) -> Result<Vec<f64>, ValactError> {
+    return read_py_carried(path, years);
+}
+
+pub fn deterministic_reserve(expected: &[ExpectedMonth], naer: &[f64], timestep: Timestep) -> DeterministicReserve {
+    let steps_per_year = f64::from(timestep.steps_per_year());
+    let mut discount = 1.0;
+    let mut result = DeterministicReserve { pv_benefits: 0.0, pv_expenses: 0.0, pv_premiums: 0.0, reserve: 0.0 };
+
+    for month in expected {
+        let rate = naer[(month.policy_year - 1) as usize];
+        let step_discount = (1.0 + rate).powf(-1.0 / steps_per_year);
+        let expenses = month.acquisition_expense + month.maintenance_expense + month.premium_expense + month.commission + month.premium_tax;
+        let benefits = month.death_benefits + month.surrender_benefits + month.maturity_benefits + month.withdrawal;
+        result.pv_premiums += discount * month.premium;
+        result.pv_expenses += discount * expenses;
+        discount *= step_discount;
+        result.pv_benefits += discount * benefits;
+    }
+    result.reserve = result.pv_benefits + result.pv_expenses - result.pv_premiums;
+    return result;
+}
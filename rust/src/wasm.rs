@@ -0,0 +1,89 @@
+use std::str;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    age::IssueAge,
+    error::ValactError,
+    projection::{at_issue_projection_detail_with, at_issue_projection_with, solve_for_premium_with, DeathBenefitOption, ProjectionConfig},
+    rates::RateSet,
+    tables::RateTables,
+};
+
+// wasm-bindgen wrappers for running illustrations in the browser, built with
+//   wasm-pack build --target web -- --features wasm
+// there is no file system so the rate tables are handed over as the bytes of their csv files,
+// fetched by the page, and parsed once into an Illustrator that every call then uses
+//   const illustrator = new Illustrator(unitLoad, corridorFactors, coi, surrenderCharge);
+//   const premium = illustrator.solve_premium("M", "NS", 35, 100000, 1);
+// death benefit options are 1 level, 2 increasing and 3 return of premium
+// projections come back as json, an array of months with the same columns as the csv output
+
+fn js_error(e: ValactError) -> JsError {
+    return JsError::new(&e.to_string());
+}
+
+fn text<'a>(bytes: &'a [u8], name: &str) -> Result<&'a str, JsError> {
+    return str::from_utf8(bytes).map_err(|e| JsError::new(&format!("{} is not utf-8: {}", name, e)));
+}
+
+// ages come in as plain numbers
+fn to_issue_age(age: u8) -> Result<IssueAge, JsError> {
+    return IssueAge::new(age).map_err(js_error);
+}
+
+fn config_for(death_benefit_option: u8) -> Result<ProjectionConfig, JsError> {
+    let death_benefit_option = match death_benefit_option {
+        1 => DeathBenefitOption::Level,
+        2 => DeathBenefitOption::Increasing,
+        3 => DeathBenefitOption::ReturnOfPremium,
+        option => return Err(JsError::new(&format!("death benefit option {} must be 1, 2 or 3", option))),
+    };
+    return Ok(ProjectionConfig { death_benefit_option, ..Default::default() });
+}
+
+#[wasm_bindgen]
+pub struct Illustrator {
+    tables: RateTables,
+}
+
+#[wasm_bindgen]
+impl Illustrator {
+    // unit_load.csv, corridor_factors.csv and coi.csv, and surrender_charge.csv when the product has one
+    #[wasm_bindgen(constructor)]
+    pub fn new(unit_load: &[u8], corridor_factors: &[u8], coi: &[u8], surrender_charge: Option<Vec<u8>>) -> Result<Illustrator, JsError> {
+        let surrender_charge = match &surrender_charge {
+            Some(bytes) => Some(text(bytes, "surrender_charge")?),
+            None => None,
+        };
+        let tables = RateTables::from_csv(text(unit_load, "unit_load")?, text(corridor_factors, "corridor_factors")?, text(coi, "coi")?, surrender_charge).map_err(js_error)?;
+        return Ok(Illustrator { tables });
+    }
+
+    fn rates(&self, gender: &str, risk_class: &str, issue_age: IssueAge) -> Result<RateSet, JsError> {
+        return self.tables.get_rates(gender, risk_class, issue_age).map_err(js_error);
+    }
+
+    // level annual premium that endows the policy at maturity
+    pub fn solve_premium(&self, gender: &str, risk_class: &str, issue_age: u8, face_amount: f64, death_benefit_option: u8) -> Result<f64, JsError> {
+        let issue_age = to_issue_age(issue_age)?;
+        let rates = self.rates(gender, risk_class, issue_age)?;
+        let solve = solve_for_premium_with(&rates, issue_age, face_amount, &config_for(death_benefit_option)?).map_err(js_error)?;
+        return Ok(solve.premium);
+    }
+
+    // account value at maturity at an annual premium
+    pub fn end_value(&self, gender: &str, risk_class: &str, issue_age: u8, face_amount: f64, death_benefit_option: u8, annual_premium: f64) -> Result<f64, JsError> {
+        let issue_age = to_issue_age(issue_age)?;
+        let rates = self.rates(gender, risk_class, issue_age)?;
+        return at_issue_projection_with(&rates, issue_age, face_amount, annual_premium, &config_for(death_benefit_option)?).map_err(js_error);
+    }
+
+    // month by month projection at an annual premium, as a json array
+    pub fn project(&self, gender: &str, risk_class: &str, issue_age: u8, face_amount: f64, death_benefit_option: u8, annual_premium: f64) -> Result<String, JsError> {
+        let issue_age = to_issue_age(issue_age)?;
+        let rates = self.rates(gender, risk_class, issue_age)?;
+        let months = at_issue_projection_detail_with(&rates, issue_age, face_amount, annual_premium, &config_for(death_benefit_option)?).map_err(js_error)?;
+        return serde_json::to_string(&months).map_err(|e| JsError::new(&e.to_string()));
+    }
+}
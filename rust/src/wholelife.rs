@@ -0,0 +1,165 @@
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assumptions::Assumptions, decrements::{expected_cash_flows, ExpectedMonth}, error::ValactError, profit::{profit_by_year, profit_summary, solve_for_profit_target, ProfitSummary, ProfitTarget, YearProfit}, projection::{MonthResult, Timestep}, reserve::{reserves_held, statutory_reserves}, sensitivity::PricingBasis
+};
+
+// traditional whole life, a fixed premium for life or a limited number of years with the face amount paid on death
+// or as an endowment at maturity, and no account value
+// cash values are net premium reserves at the cash value interest on the valuation mortality, so they build to the
+// face amount at maturity, and the statutory reserve is the net premium reserve at the valuation interest
+// premiums are a rate per 1000 of face plus a policy fee, solved to a target profit margin or irr as for term
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct WholeLifeProduct {
+    // None pays premiums to maturity
+    pub premium_years: Option<i32>,
+    // per policy per year while premiums are paid, on top of the rate per 1000
+    pub policy_fee: f64,
+    // annual effective
+    pub cash_value_interest: f64,
+    pub timestep: Timestep,
+}
+
+impl Default for WholeLifeProduct {
+    fn default() -> Self {
+        return WholeLifeProduct { premium_years: None, policy_fee: 60.0, cash_value_interest: 0.04, timestep: Timestep::Monthly };
+    }
+}
+
+impl WholeLifeProduct {
+    pub fn annual_premium(&self, face_amount: f64, rate_per_1000: f64) -> f64 {
+        return rate_per_1000 * face_amount / 1000.0 + self.policy_fee;
+    }
+
+    pub fn pays_premium(&self, policy_year: i32) -> bool {
+        return self.premium_years.is_none_or(|years| policy_year <= years);
+    }
+}
+
+// cash value schedule at the end of each policy year
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CashValue {
+    #[serde(rename="Policy_Year")]
+    pub policy_year: i32,
+    #[serde(rename="Premium")]
+    pub premium: f64,
+    #[serde(rename="Cash_Value")]
+    pub cash_value: f64,
+    #[serde(rename="Death_Benefit")]
+    pub death_benefit: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WholeLifePricing {
+    pub premium_years: Option<i32>,
+    pub rate_per_1000: f64,
+    pub annual_premium: f64,
+    pub summary: ProfitSummary,
+    pub years: Vec<YearProfit>,
+    pub cash_values: Vec<CashValue>,
+}
+
+// net premium terminal reserve per $1 of face at the end of each policy year, with net premiums for premium_years
+// (or every year) and $1 paid at maturity, deaths at the end of the year and premiums at the start
+pub fn net_premium_reserve_factors(mortality: &[f64], premium_years: Option<i32>, interest: f64) -> Vec<f64> {
+    let v = 1.0 / (1.0 + interest);
+    let years = mortality.len();
+    let paying = premium_years.map_or(years, |n| (n.max(0) as usize).min(years));
+    let mut benefit = vec![1.0; years + 1];
+    let mut annuity = vec![0.0; years + 1];
+    for t in (0..years).rev() {
+        let q = (mortality[t] / 1000.0).clamp(0.0, 1.0);
+        benefit[t] = v * (q + (1.0 - q) * benefit[t + 1]);
+        annuity[t] = if t < paying {1.0 + v * (1.0 - q) * annuity[t + 1]} else {0.0};
+    }
+    if years == 0 {
+        return Vec::new();
+    }
+    let premium = if annuity[0] > 0.0 {benefit[0] / annuity[0]} else {0.0};
+    return (1..=years).map(|t| (benefit[t] - premium * annuity[t]).max(0.0)).collect();
+}
+
+// the single policy path to maturity, cash values interpolated through each policy year from the factors
+pub fn whole_life_months(face_amount: f64, annual_premium: f64, product: &WholeLifeProduct, cash_value_factors: &[f64]) -> Vec<MonthResult> {
+    let steps_per_year = product.timestep.steps_per_year();
+    let steps = cash_value_factors.len() as i32 * steps_per_year;
+    let mut months: Vec<MonthResult> = Vec::with_capacity(steps as usize);
+    for step in 0..steps {
+        let policy_year = step / steps_per_year + 1;
+        let year = (policy_year - 1) as usize;
+        let start = if year == 0 {0.0} else {cash_value_factors[year - 1]};
+        let fraction = f64::from(step % steps_per_year + 1) / f64::from(steps_per_year);
+        let premium = if product.pays_premium(policy_year) {annual_premium / f64::from(steps_per_year)} else {0.0};
+        months.push(MonthResult {
+            policy_month: step * 12 / steps_per_year + 1,
+            policy_year,
+            month_in_policy_year: (step % steps_per_year) * 12 / steps_per_year + 1,
+            premium,
+            face_amount,
+            death_benefit: face_amount,
+            cash_surrender_value: face_amount * (start + (cash_value_factors[year] - start) * fraction),
+            net_death_benefit: face_amount,
+            ..Default::default()
+        });
+    }
+    return months;
+}
+
+pub fn cash_value_schedule(face_amount: f64, annual_premium: f64, product: &WholeLifeProduct, cash_value_factors: &[f64]) -> Vec<CashValue> {
+    return cash_value_factors.iter().enumerate().map(|(year, factor)| {
+        let policy_year = year as i32 + 1;
+        CashValue {
+            policy_year,
+            premium: if product.pays_premium(policy_year) {annual_premium} else {0.0},
+            cash_value: face_amount * factor,
+            death_benefit: face_amount,
+        }
+    }).collect();
+}
+
+// expected cash flows and profit by year at a premium, valuation mortality per 1000 by policy year for the cash
+// values and reserves
+pub fn whole_life_profit(face_amount: f64, annual_premium: f64, product: &WholeLifeProduct, valuation_mortality: &[f64], basis: &PricingBasis, assumptions: &Assumptions) -> Result<(Vec<ExpectedMonth>, Vec<YearProfit>), ValactError> {
+    let years = valuation_mortality.len();
+    if years == 0 || years > basis.mortality.len() || years > basis.lapses.len() {
+        return Err(format!("valuation mortality of {} years is not covered by the pricing basis of {} years", years, basis.mortality.len()).into());
+    }
+    let cash_values = net_premium_reserve_factors(valuation_mortality, product.premium_years, product.cash_value_interest);
+    let months = whole_life_months(face_amount, annual_premium, product, &cash_values);
+    let expected = expected_cash_flows(&months, &basis.mortality, &basis.lapses, product.timestep, assumptions, annual_premium);
+    let factors = net_premium_reserve_factors(valuation_mortality, product.premium_years, assumptions.valuation.interest);
+    let reserves = statutory_reserves(&months, &factors, product.timestep.steps_per_year());
+    let held = reserves_held(&expected, &reserves);
+    let years = profit_by_year(&expected, Some(&held), product.timestep, &assumptions.profit);
+    return Ok((expected, years));
+}
+
+pub fn solve_whole_life_premium(face_amount: f64, product: &WholeLifeProduct, valuation_mortality: &[f64], basis: &PricingBasis, assumptions: &Assumptions, target: ProfitTarget) -> Result<WholeLifePricing, ValactError> {
+    let hurdle_rate = assumptions.profit.hurdle_rate;
+    let profits_at = |rate_per_1000: f64| -> Result<Vec<YearProfit>, ValactError> {
+        return Ok(whole_life_profit(face_amount, product.annual_premium(face_amount, rate_per_1000), product, valuation_mortality, basis, assumptions)?.1);
+    };
+    let rate_per_1000 = solve_for_profit_target(profits_at, target, hurdle_rate, (0.01, 10.0))?;
+    let annual_premium = product.annual_premium(face_amount, rate_per_1000);
+    let (_, years) = whole_life_profit(face_amount, annual_premium, product, valuation_mortality, basis, assumptions)?;
+    let cash_values = net_premium_reserve_factors(valuation_mortality, product.premium_years, product.cash_value_interest);
+    return Ok(WholeLifePricing {
+        premium_years: product.premium_years,
+        rate_per_1000,
+        annual_premium,
+        summary: profit_summary(&years, hurdle_rate),
+        years,
+        cash_values: cash_value_schedule(face_amount, annual_premium, product, &cash_values),
+    });
+}
+
+pub fn write_cash_values_csv(path: &str, cash_values: &[CashValue]) -> Result<(), ValactError> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    for cash_value in cash_values {
+        wtr.serialize(cash_value)?;
+    }
+    wtr.flush()?;
+    return Ok(());
+}
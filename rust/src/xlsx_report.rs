@@ -0,0 +1,64 @@
+
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+
+use crate::{
+    batch::PolicyRecord, error::ValactError, projection::{annual_summary, MonthResult}
+};
+
+const MONEY: &str = "#,##0.00";
+
+fn write_header(sheet: &mut Worksheet, headers: &[&str], bold: &Format) -> Result<(), ValactError> {
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, bold)?;
+        sheet.set_column_width(col as u16, 16)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    return Ok(());
+}
+
+// workbook with an inputs tab and a year by year projection tab for a single policy
+pub fn write_illustration_xlsx(path: &str, inputs: &PolicyRecord, annual_premium: f64, months: &[MonthResult]) -> Result<(), ValactError> {
+    let bold = Format::new().set_bold();
+    let money = Format::new().set_num_format(MONEY);
+
+    let mut workbook = Workbook::new();
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Inputs")?;
+    write_header(sheet, &["Input", "Value"], &bold)?;
+    sheet.set_column_width(0, 20)?;
+    sheet.write_string(1, 0, "Gender")?;
+    sheet.write_string(1, 1, inputs.gender.as_str())?;
+    sheet.write_string(2, 0, "Risk Class")?;
+    sheet.write_string(2, 1, inputs.risk_class.as_str())?;
+    sheet.write_string(3, 0, "Issue Age")?;
+    sheet.write_number(3, 1, inputs.issue_age.get())?;
+    sheet.write_string(4, 0, "Face Amount")?;
+    sheet.write_number_with_format(4, 1, inputs.face_amount, &money)?;
+    sheet.write_string(5, 0, "Annual Premium")?;
+    sheet.write_number_with_format(5, 1, annual_premium, &money)?;
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Projection")?;
+    write_header(sheet, &[
+        "Policy Year", "Attained Age", "Value Start", "Premium", "Premium Load", "Withdrawal", "Expense Charge",
+        "COI Charge", "Interest", "Death Benefit", "Value End", "Loan Balance", "Cash Surrender Value",
+        "Net Death Benefit",
+    ], &bold)?;
+    for (i, year) in annual_summary(months, inputs.issue_age).iter().enumerate() {
+        let row = (i + 1) as u32;
+        sheet.write_number(row, 0, year.policy_year)?;
+        sheet.write_number(row, 1, year.attained_age)?;
+        let values = [
+            year.value_start, year.premium, year.premium_load, year.withdrawal, year.expense_charge,
+            year.coi_charge, year.interest, year.death_benefit, year.value_end, year.loan_balance, year.cash_surrender_value,
+            year.net_death_benefit,
+        ];
+        for (j, value) in values.iter().enumerate() {
+            sheet.write_number_with_format(row, (j + 2) as u16, *value, &money)?;
+        }
+    }
+
+    workbook.save(path)?;
+    return Ok(());
+}
@@ -0,0 +1,173 @@
+/*
+Reader for the XTbML format used by the SOA table manager at mort.soa.org
+
+A file holds one or more tables. A one axis table is an ultimate table by
+attained age, a two axis table is a select table by issue age and duration.
+Select and ultimate tables are usually published as a single file with the
+select table first and the ultimate table second.
+
+Values are stored multiplied by 10^ScalingFactor, the reader divides that back
+out so rates come out per unit of face (e.g. 0.00123 rather than 1.23 per 1000).
+
+*/
+use std::collections::BTreeMap;
+
+use quick_xml::{events::Event, Reader};
+
+use crate::{age::IssueAge, error::ValactError, select_ultimate::SelectUltimateTable};
+
+#[derive(Clone, Debug)]
+pub enum XtbmlValues {
+    // attained age -> rate
+    Ultimate(BTreeMap<i32, f64>),
+    // (issue age, duration) -> rate
+    Select(BTreeMap<(i32, i32), f64>),
+}
+
+#[derive(Clone, Debug)]
+pub struct XtbmlTable {
+    pub description: String,
+    pub scaling_factor: i32,
+    pub values: XtbmlValues,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct XtbmlFile {
+    pub table_identity: String,
+    pub name: String,
+    pub tables: Vec<XtbmlTable>,
+}
+
+impl XtbmlFile {
+    pub fn select_table(&self) -> Option<&BTreeMap<(i32, i32), f64>> {
+        return self.tables.iter().find_map(|t| match &t.values {
+            XtbmlValues::Select(values) => Some(values),
+            _ => None,
+        });
+    }
+
+    pub fn ultimate_table(&self) -> Option<&BTreeMap<i32, f64>> {
+        return self.tables.iter().find_map(|t| match &t.values {
+            XtbmlValues::Ultimate(values) => Some(values),
+            _ => None,
+        });
+    }
+
+    // select table (if any) and ultimate table (if any) combined, rates per unit
+    pub fn to_select_ultimate(&self) -> SelectUltimateTable {
+        let mut table = SelectUltimateTable::default();
+        if let Some(select) = self.select_table() {
+            for ((issue_age, duration), rate) in select {
+                if *duration < 1 {
+                    continue;
+                }
+                let rates = table.select.entry(*issue_age).or_default();
+                if rates.len() < *duration as usize {
+                    rates.resize(*duration as usize, 0.0);
+                }
+                rates[(duration - 1) as usize] = *rate;
+                table.select_period = table.select_period.max(*duration);
+            }
+        }
+        if let Some(ultimate) = self.ultimate_table() {
+            table.ultimate = ultimate.clone();
+        }
+        return table;
+    }
+
+    // rates per unit by policy year for a given issue age, select rates through the select
+    // period and ultimate rates by attained age after that
+    pub fn rates_for_issue_age(&self, issue_age: IssueAge, default: f64, years: usize) -> Vec<f64> {
+        return self.to_select_ultimate().rates_for_issue_age(issue_age, default, years);
+    }
+}
+
+fn attr_i32(e: &quick_xml::events::BytesStart, name: &str) -> Result<Option<i32>, ValactError> {
+    return match e.try_get_attribute(name)? {
+        Some(attr) => Ok(Some(attr.unescape_value()?.trim().parse::<i32>()?)),
+        None => Ok(None),
+    };
+}
+
+// errors say which file they came from
+pub fn read_xtbml(path: &str) -> Result<XtbmlFile, ValactError> {
+    return read_xtbml_file(path).map_err(|e| e.in_file(path));
+}
+
+fn read_xtbml_file(path: &str) -> Result<XtbmlFile, ValactError> {
+    let mut reader = Reader::from_file(path)?;
+    reader.config_mut().trim_text(true);
+
+    let mut file = XtbmlFile::default();
+    let mut buf = Vec::new();
+    // element names from the root down to the current element
+    let mut path_stack: Vec<String> = Vec::new();
+    // t attribute of each open Axis element inside Values
+    let mut axis_keys: Vec<Option<i32>> = Vec::new();
+    let mut y_key: Option<i32> = None;
+    // values of the table being read, keyed by the axis keys leading to them
+    let mut description = String::new();
+    let mut scaling_factor = 0;
+    let mut raw: Vec<(Vec<i32>, f64)> = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                match name.as_str() {
+                    "Table" => {
+                        description.clear();
+                        scaling_factor = 0;
+                        raw.clear();
+                    }
+                    "Axis" => axis_keys.push(attr_i32(&e, "t")?),
+                    "Y" => y_key = attr_i32(&e, "t")?,
+                    _ => {}
+                }
+                path_stack.push(name);
+            }
+            Event::End(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                match name.as_str() {
+                    "Axis" => {axis_keys.pop();}
+                    "Y" => y_key = None,
+                    "Table" => {
+                        let scale = 10f64.powi(scaling_factor);
+                        let values = if raw.iter().any(|(keys, _)| keys.len() >= 2) {
+                            XtbmlValues::Select(raw.iter().filter(|(k, _)| k.len() >= 2).map(|(k, v)| ((k[0], k[1]), v / scale)).collect())
+                        } else {
+                            XtbmlValues::Ultimate(raw.iter().filter(|(k, _)| k.len() == 1).map(|(k, v)| (k[0], v / scale)).collect())
+                        };
+                        file.tables.push(XtbmlTable { description: description.clone(), scaling_factor, values });
+                    }
+                    _ => {}
+                }
+                path_stack.pop();
+            }
+            Event::Text(e) => {
+                let text = e.unescape()?.trim().to_string();
+                match path_stack.last().map(|s| s.as_str()) {
+                    Some("TableIdentity") if path_stack.iter().any(|s| s == "ContentClassification") => file.table_identity = text,
+                    Some("TableName") if path_stack.iter().any(|s| s == "ContentClassification") => file.name = text,
+                    Some("TableDescription") => description = text,
+                    Some("ScalingFactor") => scaling_factor = text.parse()?,
+                    // blank cells are allowed in the format, they are skipped
+                    Some("Y") if !text.is_empty() => {
+                        let mut keys: Vec<i32> = axis_keys.iter().flatten().copied().collect();
+                        keys.push(y_key.ok_or("Y element without t attribute")?);
+                        raw.push((keys, text.parse::<f64>()?));
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    if file.tables.is_empty() {
+        return Err(format!("no tables found in {}", path).into());
+    }
+    return Ok(file);
+}
@@ -0,0 +1,74 @@
+/*
+Round trip of batch results through arrow record batches and the arrow ipc file (arrow feature)
+
+Every row read back from an ipc file, written to disk or to bytes, has to be the result it was written from,
+across more than one record batch and for an empty batch run.
+
+*/
+#![cfg(feature = "arrow")]
+
+use std::io::Cursor;
+
+use arrow_array::{cast::AsArray, types::{Float64Type, Int8Type, UInt8Type}, RecordBatch};
+use arrow_ipc::reader::FileReader;
+use illus1::{
+    age::IssueAge, arrow_results, batch::PolicyResult, DeathBenefitOption
+};
+
+fn results(count: usize) -> Vec<PolicyResult> {
+    let options = [DeathBenefitOption::Level, DeathBenefitOption::Increasing, DeathBenefitOption::ReturnOfPremium];
+    return (0..count).map(|i| PolicyResult {
+        gender: if i % 2 == 0 {"M".to_string()} else {"F".to_string()},
+        risk_class: if i % 3 == 0 {"SM".to_string()} else {"NS".to_string()},
+        issue_age: IssueAge::new(20 + (i % 50) as u8).unwrap(),
+        face_amount: 50000.0 * (1 + i % 20) as f64,
+        death_benefit_option: options[i % 3],
+        premium: 100.0 + i as f64 * 1.37,
+        census_row: None,
+    }).collect();
+}
+
+fn rows(batches: &[RecordBatch]) -> Vec<(String, String, u8, f64, i8, f64)> {
+    let mut rows = Vec::new();
+    for batch in batches {
+        let gender = batch.column(0).as_string::<i32>();
+        let risk_class = batch.column(1).as_string::<i32>();
+        let issue_age = batch.column(2).as_primitive::<UInt8Type>();
+        let face_amount = batch.column(3).as_primitive::<Float64Type>();
+        let option = batch.column(4).as_primitive::<Int8Type>();
+        let premium = batch.column(5).as_primitive::<Float64Type>();
+        for i in 0..batch.num_rows() {
+            rows.push((gender.value(i).to_string(), risk_class.value(i).to_string(), issue_age.value(i), face_amount.value(i), option.value(i), premium.value(i)));
+        }
+    }
+    return rows;
+}
+
+fn expected(results: &[PolicyResult]) -> Vec<(String, String, u8, f64, i8, f64)> {
+    return results.iter().map(|r| (r.gender.clone(), r.risk_class.clone(), r.issue_age.get(), r.face_amount, r.death_benefit_option.number(), r.premium)).collect();
+}
+
+#[test]
+fn ipc_bytes_read_back_as_the_results() {
+    for count in [0, 1, 250] {
+        let results = results(count);
+        let bytes = arrow_results::to_ipc_bytes(&results, 64).unwrap();
+        let reader = FileReader::try_new(Cursor::new(bytes), None).unwrap();
+        assert_eq!(reader.schema(), arrow_results::results_schema());
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.len(), count.div_ceil(64).max(1));
+        assert_eq!(rows(&batches), expected(&results));
+    }
+}
+
+#[test]
+fn ipc_file_reads_back_as_the_results() {
+    let results = results(1000);
+    let path = std::env::temp_dir().join(format!("valact-arrow-{}.arrow", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    arrow_results::write_results_ipc(&path, &results, arrow_results::DEFAULT_BATCH_SIZE).unwrap();
+    let reader = FileReader::try_new(std::fs::File::open(&path).unwrap(), None).unwrap();
+    let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(rows(&batches), expected(&results));
+}
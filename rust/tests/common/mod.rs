@@ -0,0 +1,26 @@
+// hand built rate set shared by the integration tests that project a policy without the data tables
+
+use illus1::{age::AttainedAge, RateSet};
+
+pub const ISSUE_AGE: u8 = 45;
+pub const MATURITY_AGE: u8 = 100;
+
+pub fn level(rate: f64) -> Vec<f64> {
+    return vec![rate; usize::from(MATURITY_AGE - ISSUE_AGE)];
+}
+
+// level loads, fees and annual effective interest with a coi curve growing with age, scaled by coi_multiple
+pub fn rates(load: f64, fee: f64, coi_multiple: f64, interest: f64) -> RateSet {
+    let coi_rates = (0..MATURITY_AGE - ISSUE_AGE).map(|t| coi_multiple * 0.5 * (0.08 * f64::from(t)).exp()).collect();
+    return RateSet {
+        maturity_age: AttainedAge::new(MATURITY_AGE).unwrap(),
+        premium_loads: level(load),
+        policy_fees: level(fee),
+        unit_loads: level(1.0),
+        corr_facts: level(1.5),
+        naar_discs: level(1.03f64.powf(-1.0 / 12.0)),
+        coi_rates,
+        interest_rates: level((1.0 + interest).powf(1.0 / 12.0) - 1.0),
+        surrender_charges: level(0.0),
+    };
+}
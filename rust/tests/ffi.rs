@@ -0,0 +1,70 @@
+/*
+Repository handles of the c api (ffi feature)
+
+Each handle reads the tables of its own data directory, so a caller can hold handles on two directories at
+once and every solve uses the tables of the handle it is given.
+
+*/
+#![cfg(feature = "ffi")]
+
+use std::{
+    ffi::CString, fs, path::{Path, PathBuf}, ptr
+};
+
+use illus1::{
+    age::IssueAge, ffi::{valact_repository_free, valact_repository_new, valact_solve_premium, ValactPolicy, ValactRepository, ValactSolve, VALACT_OK},
+    get_rates_to_maturity, rates::DataDir, solve_for_premium_with, ProjectionConfig, DEFAULT_MATURITY_AGE
+};
+
+fn golden_data() -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join("data");
+}
+
+// the golden tables with twice the unit loads, so premiums solved from it differ
+fn doubled_unit_loads() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("valact-ffi-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for entry in fs::read_dir(golden_data()).unwrap() {
+        let path = entry.unwrap().path();
+        fs::copy(&path, dir.join(path.file_name().unwrap())).unwrap();
+    }
+    let unit_loads = fs::read_to_string(golden_data().join("unit_load.csv")).unwrap();
+    let doubled: Vec<String> = unit_loads.lines().enumerate().map(|(i, line)| match line.rsplit_once(',') {
+        Some((cell, rate)) if i > 0 => format!("{},{}", cell, 2.0 * rate.parse::<f64>().unwrap()),
+        _ => line.to_string(),
+    }).collect();
+    fs::write(dir.join("unit_load.csv"), doubled.join("\n")).unwrap();
+    return dir;
+}
+
+fn solve(repository: *const ValactRepository) -> f64 {
+    let (gender, risk_class) = (CString::new("M").unwrap(), CString::new("NS").unwrap());
+    let policy = ValactPolicy { gender: gender.as_ptr(), risk_class: risk_class.as_ptr(), issue_age: 35, face_amount: 100000.0, death_benefit_option: 1 };
+    let mut solve = ValactSolve { premium: 0.0, iterations: 0, converged: 0 };
+    assert_eq!(unsafe { valact_solve_premium(repository, &policy, &mut solve) }, VALACT_OK);
+    return solve.premium;
+}
+
+fn expected_premium(dir: &Path) -> f64 {
+    let issue_age = IssueAge::new(35).unwrap();
+    let rates = get_rates_to_maturity(&DataDir::new(&dir.to_string_lossy()), "M", "NS", issue_age, DEFAULT_MATURITY_AGE).unwrap();
+    return solve_for_premium_with(&rates, issue_age, 100000.0, &ProjectionConfig::default()).unwrap().premium;
+}
+
+#[test]
+fn each_repository_reads_its_own_data_dir() {
+    let (golden, doubled) = (golden_data(), doubled_unit_loads());
+    let (golden_path, doubled_path) = (CString::new(golden.to_string_lossy().as_bytes()).unwrap(), CString::new(doubled.to_string_lossy().as_bytes()).unwrap());
+    let first = unsafe { valact_repository_new(golden_path.as_ptr()) };
+    let second = unsafe { valact_repository_new(doubled_path.as_ptr()) };
+    assert_ne!(first, ptr::null_mut());
+    assert_ne!(second, ptr::null_mut());
+    let (golden_premium, doubled_premium) = (solve(first), solve(second));
+    unsafe {
+        valact_repository_free(first);
+        valact_repository_free(second);
+    }
+    fs::remove_dir_all(&doubled).unwrap();
+    assert_eq!(golden_premium, expected_premium(&golden));
+    assert!(doubled_premium > golden_premium, "doubled unit loads {}, golden {}", doubled_premium, golden_premium);
+}
@@ -0,0 +1,162 @@
+/*
+Golden file regression tests
+
+The rate tables in tests/golden/data are a few cells cut down from ./data and are
+committed with the expected results in tests/golden/expected, so a change to the
+projection or the solve that moves any number shows up here.
+
+Numbers are compared to a relative tolerance rather than exactly so the tests
+survive harmless floating point reordering, anything else must match exactly.
+
+After a deliberate change to the results regenerate the expected files with
+GOLDEN_UPDATE=1 cargo test --test golden
+and check the diff before committing them.
+
+*/
+use std::{
+    error::Error, fs, path::PathBuf
+};
+
+use serde::Serialize;
+
+use illus1::{
+    age::IssueAge, at_issue_projection_detail_with, get_rates_to_maturity, projection::annual_summary, rates::DataDir, solve_for_premium_with,
+    DeathBenefitOption, ProjectionConfig, RateSet, Timestep, DEFAULT_MATURITY_AGE
+};
+
+// relative to the larger of the expected value and 1
+const TOLERANCE: f64 = 1e-9;
+
+fn golden_dir() -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+}
+
+fn age(age: u8) -> IssueAge {
+    return IssueAge::new(age).unwrap();
+}
+
+// every test reads its rates from the golden tables
+fn golden_rates(gender: &str, risk_class: &str, issue_age: IssueAge) -> RateSet {
+    let data_dir = DataDir::new(&golden_dir().join("data").to_string_lossy());
+    return get_rates_to_maturity(&data_dir, gender, risk_class, issue_age, DEFAULT_MATURITY_AGE).unwrap();
+}
+
+fn to_csv<T: Serialize>(rows: &[T]) -> Result<String, Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        wtr.serialize(row)?;
+    }
+    return Ok(String::from_utf8(wtr.into_inner()?)?);
+}
+
+fn close(actual: f64, expected: f64, tolerance: f64) -> bool {
+    return (actual - expected).abs() <= tolerance * expected.abs().max(1.0);
+}
+
+// the first difference between two csvs, numbers within tolerance and everything else exactly
+fn csv_difference(actual: &str, expected: &str, tolerance: f64) -> Option<String> {
+    let rows = |text: &str| -> Vec<Vec<String>> {
+        let mut rdr = csv::ReaderBuilder::new().has_headers(false).from_reader(text.as_bytes());
+        return rdr.records().map(|r| r.unwrap().iter().map(String::from).collect()).collect();
+    };
+    let (actual, expected) = (rows(actual), rows(expected));
+    if actual.len() != expected.len() {
+        return Some(format!("{} rows, expected {}", actual.len(), expected.len()));
+    }
+    let header = &expected[0];
+    for (i, (a, e)) in actual.iter().zip(&expected).enumerate() {
+        if a.len() != e.len() {
+            return Some(format!("row {} has {} columns, expected {}", i, a.len(), e.len()));
+        }
+        for (j, (a, e)) in a.iter().zip(e).enumerate() {
+            let same = match (a.parse::<f64>(), e.parse::<f64>()) {
+                (Ok(a), Ok(e)) => close(a, e, tolerance),
+                _ => a == e,
+            };
+            if !same {
+                return Some(format!("row {} {}: {}, expected {}", i, header[j], a, e));
+            }
+        }
+    }
+    return None;
+}
+
+// compares against tests/golden/expected/name, or rewrites it when GOLDEN_UPDATE is set
+fn check_golden(name: &str, actual: &str) {
+    let path = golden_dir().join("expected").join(name);
+    if std::env::var_os("GOLDEN_UPDATE").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    if let Some(difference) = csv_difference(actual, &expected, TOLERANCE) {
+        panic!("{} differs from the golden file: {}", name, difference);
+    }
+}
+
+#[test]
+fn comparison_allows_tolerance_only_on_numbers() {
+    let expected = "Name,Value\nM,100.0\n";
+    assert_eq!(csv_difference("Name,Value\nM,100.00000000001\n", expected, TOLERANCE), None);
+    assert!(csv_difference("Name,Value\nM,100.001\n", expected, TOLERANCE).is_some());
+    assert!(csv_difference("Name,Value\nF,100.0\n", expected, TOLERANCE).is_some());
+    assert!(csv_difference("Name,Value\n", expected, TOLERANCE).is_some());
+}
+
+#[test]
+fn monthly_projection_matches_golden() {
+    let rates = golden_rates("M", "NS", age(35));
+    let months = at_issue_projection_detail_with(&rates, age(35), 100000.0, 1255.03, &ProjectionConfig::default()).unwrap();
+    check_golden("projection_m_ns_35.csv", &to_csv(&months).unwrap());
+}
+
+#[test]
+fn annual_step_increasing_option_matches_golden() {
+    let rates = golden_rates("F", "SM", age(45));
+    let config = ProjectionConfig { timestep: Timestep::Annual, death_benefit_option: DeathBenefitOption::Increasing, ..Default::default() };
+    let months = at_issue_projection_detail_with(&rates, age(45), 250000.0, 6000.0, &config).unwrap();
+    check_golden("projection_f_sm_45_annual_option2.csv", &to_csv(&months).unwrap());
+}
+
+#[test]
+fn annual_summary_matches_golden() {
+    let rates = golden_rates("M", "NS", age(55));
+    let months = at_issue_projection_detail_with(&rates, age(55), 500000.0, 12000.0, &ProjectionConfig::default()).unwrap();
+    check_golden("summary_m_ns_55.csv", &to_csv(&annual_summary(&months, age(55))).unwrap());
+}
+
+#[derive(Serialize)]
+struct GoldenSolve {
+    #[serde(rename="Gender")]
+    gender: &'static str,
+    #[serde(rename="Risk_Class")]
+    risk_class: &'static str,
+    #[serde(rename="Issue_Age")]
+    issue_age: IssueAge,
+    #[serde(rename="Face_Amount")]
+    face_amount: f64,
+    #[serde(rename="Death_Benefit_Option")]
+    death_benefit_option: DeathBenefitOption,
+    #[serde(rename="Premium")]
+    premium: f64,
+}
+
+#[test]
+fn solved_premiums_match_golden() {
+    let cells = [
+        ("M", "NS", 35, 100000.0, DeathBenefitOption::Level),
+        ("M", "NS", 35, 100000.0, DeathBenefitOption::Increasing),
+        ("M", "NS", 55, 500000.0, DeathBenefitOption::Level),
+        ("F", "SM", 45, 250000.0, DeathBenefitOption::ReturnOfPremium),
+        ("F", "NS", 25, 1000000.0, DeathBenefitOption::Level),
+    ];
+    let mut solves = Vec::new();
+    for (gender, risk_class, issue_age, face_amount, death_benefit_option) in cells {
+        let issue_age = age(issue_age);
+        let rates = golden_rates(gender, risk_class, issue_age);
+        let config = ProjectionConfig { death_benefit_option, ..Default::default() };
+        let premium = solve_for_premium_with(&rates, issue_age, face_amount, &config).unwrap().premium;
+        solves.push(GoldenSolve { gender, risk_class, issue_age, face_amount, death_benefit_option, premium });
+    }
+    check_golden("solves.csv", &to_csv(&solves).unwrap());
+}
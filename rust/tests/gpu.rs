@@ -0,0 +1,135 @@
+/*
+Parity of the gpu backend (gpu feature) with the cpu
+
+The kernel projects in single precision so end values and solved premiums are
+compared to a relative tolerance, against the cpu projecting and solving the
+same policies and scenario paths from the golden tables.
+
+The tests pass without running anything when wgpu finds no adapter, as on a
+build machine without a gpu or software driver.
+
+*/
+#![cfg(feature = "gpu")]
+
+use std::path::PathBuf;
+
+use illus1::{
+    age::IssueAge, at_issue_projection_with, batch::{self, PolicyRecord}, esg::{self, GeneratorParams}, get_rates_to_maturity, gpu::{self, Cell, Gpu, Projections}, rates::DataDir, scenarios,
+    solver::{Method, SolverOptions}, DeathBenefitOption, PremiumMode, PremiumPattern, ProjectionConfig, RateSet, Timestep, ValactError, DEFAULT_MATURITY_AGE
+};
+
+// relative to the larger of the cpu's value and 1
+const VALUE_TOLERANCE: f64 = 1e-4;
+// relative to the cpu's premium, with a couple of cents for small premiums
+const PREMIUM_TOLERANCE: f64 = 1e-5;
+
+fn golden_rates(gender: &str, class: &str, issue_age: IssueAge) -> Result<RateSet, ValactError> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join("data");
+    return get_rates_to_maturity(&DataDir::new(&dir.to_string_lossy()), gender, class, issue_age, DEFAULT_MATURITY_AGE);
+}
+
+fn gpu() -> Option<Gpu> {
+    return match Gpu::new() {
+        Ok(gpu) => {
+            eprintln!("projecting on {}", gpu.adapter());
+            Some(gpu)
+        }
+        Err(e) => {
+            eprintln!("skipping, {}", e);
+            None
+        }
+    };
+}
+
+// gender, class, rates, issue age, face amount and option of each cell
+type TestCell = (&'static str, &'static str, RateSet, IssueAge, f64, DeathBenefitOption);
+
+fn cells() -> Vec<TestCell> {
+    let cells = [("M", "NS", 35, 100000.0), ("M", "NS", 55, 500000.0), ("F", "SM", 45, 250000.0), ("F", "NS", 25, 1000000.0)];
+    let options = [DeathBenefitOption::Level, DeathBenefitOption::Increasing, DeathBenefitOption::ReturnOfPremium];
+    return cells.iter().flat_map(|&(gender, class, age, face)| {
+        let issue_age = IssueAge::new(age).unwrap();
+        let rates = golden_rates(gender, class, issue_age).unwrap();
+        return options.map(|option| (gender, class, rates.clone(), issue_age, face, option));
+    }).collect();
+}
+
+fn close(gpu: f64, cpu: f64) -> bool {
+    return (gpu - cpu).abs() <= VALUE_TOLERANCE * cpu.abs().max(1.0);
+}
+
+fn close_premium(gpu: f64, cpu: f64) -> bool {
+    return (gpu - cpu).abs() <= 0.02 + PREMIUM_TOLERANCE * cpu.abs();
+}
+
+#[test]
+fn end_values_match_the_cpu() {
+    let Some(gpu) = gpu() else {
+        return;
+    };
+    let cells = cells();
+    let configs = [
+        ProjectionConfig::default(),
+        ProjectionConfig { timestep: Timestep::Annual, premium_mode: PremiumMode::Quarterly, ..Default::default() },
+        ProjectionConfig { premium_pattern: PremiumPattern::NPay(10), premium_mode: PremiumMode::Monthly, ..Default::default() },
+        ProjectionConfig { premium_pattern: PremiumPattern::SinglePay, ..Default::default() },
+    ];
+    for config in &configs {
+        let gpu_cells: Vec<Cell> = cells.iter()
+            .map(|(_, _, rates, issue_age, face_amount, option)| Cell { rates, issue_age: *issue_age, face_amount: *face_amount, death_benefit_option: *option, interest_path: &[] })
+            .collect();
+        let projections = Projections::new(&gpu, &gpu_cells, config).unwrap();
+        let premiums: Vec<f64> = cells.iter().map(|(_, _, _, _, face_amount, _)| face_amount / 50.0).collect();
+        let values = projections.end_values(&premiums).unwrap();
+        for ((_, _, rates, issue_age, face_amount, option), (premium, value)) in cells.iter().zip(premiums.iter().zip(values)) {
+            let config = ProjectionConfig { death_benefit_option: *option, ..config.clone() };
+            let expected = at_issue_projection_with(rates, *issue_age, *face_amount, *premium, &config).unwrap();
+            assert!(close(value, expected), "age {} face {} {:?} at {}: gpu {}, cpu {}", issue_age, face_amount, option, premium, value, expected);
+        }
+    }
+}
+
+#[test]
+fn batch_premiums_match_the_cpu() {
+    let Some(gpu) = gpu() else {
+        return;
+    };
+    let policies: Vec<PolicyRecord> = cells().iter().map(|(gender, risk_class, _, issue_age, face_amount, option)| {
+        return PolicyRecord {
+            gender: gender.to_string(),
+            risk_class: risk_class.to_string(),
+            issue_age: *issue_age,
+            face_amount: *face_amount,
+            death_benefit_option: *option,
+            inforce: None,
+            issue_date: None,
+        };
+    }).collect();
+    let rates_for = |policy: &PolicyRecord| golden_rates(&policy.gender, &policy.risk_class, policy.issue_age);
+    let options = SolverOptions::new(Method::Bisection, 0.005);
+    let on_gpu = gpu::solve_policies(&gpu, &policies, &rates_for, &options).unwrap();
+    for (policy, result) in policies.iter().zip(on_gpu) {
+        let gpu_premium = result.unwrap().premium;
+        let cpu_premium = batch::solve_policy_using(policy, &rates_for, &options).unwrap().premium;
+        assert!(close_premium(gpu_premium, cpu_premium), "{:?}: gpu {}, cpu {}", policy, gpu_premium, cpu_premium);
+    }
+}
+
+#[test]
+fn scenarios_match_the_cpu() {
+    let Some(gpu) = gpu() else {
+        return;
+    };
+    let issue_age = IssueAge::new(35).unwrap();
+    let rates = golden_rates("M", "NS", issue_age).unwrap();
+    let set = esg::generate_scenarios(&GeneratorParams::default(), 200, rates.years() * 12).unwrap();
+    let config = ProjectionConfig::default();
+    let on_gpu = gpu::run_scenarios(&gpu, &rates, issue_age, 100000.0, 1500.0, &config, &set).unwrap();
+    let on_cpu = scenarios::run_scenarios(&rates, issue_age, 100000.0, 1500.0, &config, &set).unwrap();
+    assert_eq!(on_gpu.len(), on_cpu.len());
+    for (gpu, cpu) in on_gpu.iter().zip(&on_cpu) {
+        assert_eq!(gpu.name, cpu.name);
+        assert!(close(gpu.end_value, cpu.end_value), "{}: end value gpu {}, cpu {}", cpu.name, gpu.end_value, cpu.end_value);
+        assert!(close_premium(gpu.premium, cpu.premium), "{}: premium gpu {}, cpu {}", cpu.name, gpu.premium, cpu.premium);
+    }
+}
@@ -0,0 +1,50 @@
+/*
+Tests of the illustration actuary calculations on a hand built rate set
+
+The midpoint scale has to sit halfway between the current and guaranteed rates, a premium has to do no
+better on it than on the current basis and no worse than on the guaranteed one, and with no lapses assumed
+the lapse-support test is the self-support test over again.
+
+*/
+mod common;
+
+use common::{level, rates, ISSUE_AGE};
+use illus1::{
+    age::IssueAge, assumptions::Assumptions, at_issue_projection_detail_with, batch::PolicyRecord, illustration, sensitivity::PricingBasis, ProjectionConfig, RateSet
+};
+
+fn current() -> RateSet {
+    return rates(0.05, 60.0, 1.0, 0.05);
+}
+
+fn guaranteed() -> RateSet {
+    return rates(0.08, 180.0, 1.5, 0.02);
+}
+
+#[test]
+fn midpoint_rates_are_halfway() {
+    let midpoint = illustration::midpoint_rates(&current(), &guaranteed());
+    assert!((midpoint.premium_loads[0] - 0.065).abs() < 1e-12);
+    assert!((midpoint.policy_fees[0] - 120.0).abs() < 1e-12);
+    assert!((midpoint.coi_rates[10] - 1.25 * current().coi_rates[10]).abs() < 1e-12);
+    let annual = (1.0 + midpoint.interest_rates[0]).powi(12) - 1.0;
+    assert!((annual - 0.035).abs() < 1e-12, "midpoint interest {}", annual);
+}
+
+#[test]
+fn midpoint_value_lies_between_guaranteed_and_current() {
+    let issue_age = IssueAge::new(ISSUE_AGE).unwrap();
+    let config = ProjectionConfig::default();
+    let end_value = |rates: &RateSet| at_issue_projection_detail_with(rates, issue_age, 100000.0, 2500.0, &config).unwrap()[120].value_end;
+    let midpoint = end_value(&illustration::midpoint_rates(&current(), &guaranteed()));
+    assert!(end_value(&guaranteed()) < midpoint && midpoint < end_value(&current()));
+}
+
+#[test]
+fn without_lapses_lapse_support_is_self_support() {
+    let policy = PolicyRecord { gender: "M".to_string(), risk_class: "NS".to_string(), issue_age: IssueAge::new(ISSUE_AGE).unwrap(), face_amount: 100000.0, death_benefit_option: Default::default(), inforce: None, issue_date: None };
+    let basis = PricingBasis { mortality: current().coi_rates, lapses: level(0.0), target_premium: 2000.0 };
+    let cell = illustration::test_cell(&current(), &guaranteed(), &policy, 2500.0, &ProjectionConfig::default(), &basis, &Assumptions::default()).unwrap();
+    assert_eq!(cell.lapse_supported, !cell.self_supporting);
+    assert_eq!(cell.lapse_support_fail_year, cell.self_support_fail_year);
+}
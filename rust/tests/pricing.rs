@@ -0,0 +1,102 @@
+/*
+Tests of the pricing calculations on a hand built rate set
+
+Expected cash flows, statutory reserves and book profits have to run for an inforce projection that starts
+part way through the policy, without charging acquisition expenses again.
+Net level and CRVM reserve factors, the irr and the release of the reserve at maturity are checked against
+small cases worked by hand, as is the dac tax of expected cash flows starting past the first policy year, whose
+earlier years collected nothing.
+
+*/
+mod common;
+
+use common::{level, rates, ISSUE_AGE, MATURITY_AGE};
+use illus1::{
+    age::IssueAge, assumptions::{Assumptions, ProfitAssumptions, ReserveMethod, ValuationAssumptions}, at_issue_projection_detail_with,
+    decrements::{self, ExpectedMonth}, profit, reserve, InforceState, MonthResult, ProjectionConfig, Timestep
+};
+
+const FACE_AMOUNT: f64 = 100000.0;
+const PREMIUM: f64 = 2500.0;
+
+fn issue_age() -> IssueAge {
+    return IssueAge::new(ISSUE_AGE).unwrap();
+}
+
+#[test]
+fn inforce_projection_prices_from_its_duration() {
+    let rates = rates(0.05, 60.0, 1.0, 0.04);
+    let inforce = InforceState { duration: 18, account_value: 2000.0, ..Default::default() };
+    let config = ProjectionConfig { inforce: Some(inforce), ..Default::default() };
+    let months = at_issue_projection_detail_with(&rates, issue_age(), FACE_AMOUNT, PREMIUM, &config).unwrap();
+    assert_eq!(months[0].policy_year, 2);
+    let expected = decrements::expected_cash_flows(&months, &rates.coi_rates, &level(0.05), Timestep::Monthly, &Assumptions::default(), PREMIUM);
+    assert_eq!(expected.len(), months.len());
+    assert!(expected.iter().all(|month| month.acquisition_expense == 0.0));
+
+    let factors = reserve::reserve_factors(&rates.coi_rates, &ValuationAssumptions::default());
+    let reserves = reserve::statutory_reserves(&months, &factors, 12);
+    let held = reserve::reserves_held(&expected, &reserves);
+    let years = profit::profit_by_year(&expected, Some(&held), Timestep::Monthly, &ProfitAssumptions::default());
+    assert_eq!(years.first().map(|year| year.policy_year), Some(2));
+    assert_eq!(years.len(), usize::from(MATURITY_AGE - ISSUE_AGE) - 1);
+    assert!(years.iter().all(|year| year.book_profit.is_finite()));
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{} is not {}", actual, expected);
+}
+
+// q of 0.1, 0.2 and 1 at no interest, so the benefit is 1 from every year and the annuities due are 2.62, 1.8 and 1
+#[test]
+fn reserve_factors_net_level_and_crvm() {
+    let mortality = [100.0, 200.0, 1000.0];
+    let net_level = reserve::reserve_factors(&mortality, &ValuationAssumptions { method: ReserveMethod::NetLevel, interest: 0.0 });
+    let crvm = reserve::reserve_factors(&mortality, &ValuationAssumptions { method: ReserveMethod::Crvm, interest: 0.0 });
+    for (actual, expected) in net_level.iter().zip([1.0 - 1.8 / 2.62, 1.0 - 1.0 / 2.62, 1.0]) {
+        assert_close(*actual, expected);
+    }
+    // full preliminary term, nothing held at the end of the first year
+    for (actual, expected) in crvm.iter().zip([0.0, 1.0 - 1.0 / 1.8, 1.0]) {
+        assert_close(*actual, expected);
+    }
+}
+
+#[test]
+fn irr_of_known_profits() {
+    // -100 then 110 a year later is 10%, and 144 two years after that 20%
+    let (one_year, two_years) = (profit::irr(&[-100.0, 110.0]).unwrap(), profit::irr(&[-100.0, 0.0, 144.0]).unwrap());
+    assert!((one_year - 0.10).abs() < 1e-6, "{}", one_year);
+    assert!((two_years - 0.20).abs() < 1e-6, "{}", two_years);
+    assert_eq!(profit::irr(&[10.0, 20.0]), None);
+}
+
+#[test]
+fn reserve_released_at_maturity() {
+    let expected = [
+        ExpectedMonth { policy_month: 1, policy_year: 1, inforce_start: 1.0, inforce_end: 1.0, premium: 100.0, ..Default::default() },
+        ExpectedMonth { policy_month: 2, policy_year: 2, inforce_start: 1.0, inforce_end: 1.0, premium: 100.0, maturity_benefits: 210.0, ..Default::default() },
+    ];
+    let assumptions = ProfitAssumptions { earned_rate: 0.0, ..Default::default() };
+    let years = profit::profit_by_year(&expected, Some(&[100.0, 210.0]), Timestep::Annual, &assumptions);
+    assert_close(years[0].book_profit, 0.0);
+    // the maturity benefit is paid out of the 100 held, which is released rather than increased to 210
+    assert_close(years[1].reserve_end, 0.0);
+    assert_close(years[1].increase_in_reserve, -100.0);
+    assert_close(years[1].book_profit, 100.0 - 210.0 + 100.0);
+}
+
+#[test]
+fn expected_cash_flows_past_first_year() {
+    let months: Vec<MonthResult> = (3..=4).map(|year| MonthResult { policy_month: year, policy_year: year, month_in_policy_year: 1, premium: 1000.0, ..Default::default() }).collect();
+    let assumptions = Assumptions::default();
+    let expected = decrements::expected_cash_flows(&months, &[0.0; 4], &[0.0; 4], Timestep::Annual, &assumptions, 1000.0);
+    assert_eq!(expected.iter().map(|month| month.policy_year).collect::<Vec<i32>>(), [3, 4]);
+    assert!(expected.iter().all(|month| month.acquisition_expense == 0.0));
+    // only the premium of years 3 and 4 is capitalized, half a year's amortization in the year it is collected
+    let taxes = &assumptions.taxes;
+    let capitalized = 1000.0 * taxes.dac_rate;
+    let years = f64::from(taxes.dac_amortization_years);
+    assert_close(expected[0].dac_tax, taxes.corporate_tax_rate * (capitalized - capitalized * 0.5 / years));
+    assert_close(expected[1].dac_tax, taxes.corporate_tax_rate * (capitalized - capitalized * 1.5 / years));
+}
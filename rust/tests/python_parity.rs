@@ -0,0 +1,75 @@
+/*
+Parity of the premium solve with the reference python implementation (python-parity feature)
+
+approach1.py at the top of the repo is the implementation this engine was ported from. Its
+solve_for_premium is run on a few cells with python and the premiums compared with the ones
+solved here from the same rate tables, so the two can't drift apart without this failing.
+
+The python side bisects to half a cent and rounds up to a cent that endows, so premiums are
+compared to a couple of cents rather than exactly.
+
+Needs python with polars on the path, or the interpreter in VALACT_PYTHON:
+VALACT_PYTHON=.venv/bin/python cargo test --features python-parity --test python_parity
+
+*/
+#![cfg(feature = "python-parity")]
+
+use std::{
+    path::PathBuf, process::Command
+};
+
+use illus1::{
+    age::IssueAge, get_rates_to_maturity, rates::DataDir, solve_for_premium_with, ProjectionConfig, DEFAULT_MATURITY_AGE
+};
+
+// dollars either way
+const PREMIUM_TOLERANCE: f64 = 0.02;
+
+// solved premium of each gender,class,issue_age,face argument, one to a line
+const SOLVE: &str = "
+import sys
+from approach1 import solve_for_premium
+for cell in sys.argv[1:]:
+    gender, risk_class, issue_age, face_amount = cell.split(',')
+    print(solve_for_premium(gender, risk_class, int(issue_age), int(face_amount))[0])
+";
+
+// approach1.py and the rate tables it reads from its working directory
+fn reference_dir() -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).parent().unwrap().to_path_buf();
+}
+
+fn python_premiums(cells: &[(&str, &str, u8, u32)]) -> Vec<f64> {
+    let python = std::env::var("VALACT_PYTHON").unwrap_or_else(|_| "python3".to_string());
+    let output = Command::new(&python)
+        .current_dir(reference_dir())
+        .arg("-c")
+        .arg(SOLVE)
+        .args(cells.iter().map(|(gender, risk_class, issue_age, face_amount)| format!("{},{},{},{}", gender, risk_class, issue_age, face_amount)))
+        .output()
+        .unwrap_or_else(|e| panic!("running {}: {}", python, e));
+    if !output.status.success() {
+        panic!("approach1.py failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    return String::from_utf8(output.stdout).unwrap().lines().map(|line| line.trim().parse().unwrap()).collect();
+}
+
+#[test]
+fn solved_premiums_match_python() {
+    let data_dir = DataDir::new(&reference_dir().to_string_lossy());
+    let cells = [
+        ("M", "NS", 35, 100000),
+        ("M", "SM", 50, 250000),
+        ("F", "NS", 25, 1000000),
+        ("F", "SM", 65, 500000),
+        ("M", "NS", 80, 100000),
+    ];
+    let expected = python_premiums(&cells);
+    assert_eq!(expected.len(), cells.len(), "approach1.py solved {} of {} cells", expected.len(), cells.len());
+    for ((gender, risk_class, issue_age, face_amount), expected) in cells.into_iter().zip(expected) {
+        let issue_age = IssueAge::new(issue_age).unwrap();
+        let rates = get_rates_to_maturity(&data_dir, gender, risk_class, issue_age, DEFAULT_MATURITY_AGE).unwrap();
+        let premium = solve_for_premium_with(&rates, issue_age, f64::from(face_amount), &ProjectionConfig::default()).unwrap().premium;
+        assert!((premium - expected).abs() <= PREMIUM_TOLERANCE, "{} {} {} {}: premium {}, approach1.py {}", gender, risk_class, issue_age, face_amount, premium, expected);
+    }
+}
@@ -0,0 +1,110 @@
+/*
+Property tests of the premium solve over randomly generated rate sets
+
+The value at maturity has to rise with the premium for the solve to make sense,
+the solved premium has to endow the policy (leave a positive value at maturity)
+and, as the solve rounds to cents, a premium one cent lower must not.
+Solving a few policies together in lockstep has to give each the solve it gets on its own, to the bit.
+
+*/
+use proptest::prelude::*;
+
+use illus1::{
+    age::{AttainedAge, IssueAge}, at_issue_projection, at_issue_projection_with, lanes::{self, Lanes, LANES}, rounding::RoundingPolicy, solve_for_premium, solve_for_premium_using, solve_for_premium_with,
+    solver::{Method, SolverOptions}, DeathBenefitOption, PremiumMode, PremiumPattern, ProjectionConfig, RateSet, SolveTarget, Timestep
+};
+
+// level loads and fees, a coi curve growing with age and jittered year by year, and level naar discount and
+// credited rates, from issue to a random maturity age
+// coi is capped at 400 per 1000, above 1000 / (corridor - 1) a whole year's coi on the corridor naar takes more
+// than the account value so with an annual step no premium can endow
+fn rate_set() -> impl Strategy<Value = (RateSet, IssueAge)> {
+    let levels = (20u8..=70, 90u8..=121, 0.0..0.10f64, 0.0..200.0f64, 0.0..5.0f64, 1.0..3.0f64, 0.0..0.05f64, 0.0..0.06f64);
+    let coi = (0.05..2.0f64, 0.05..0.10f64, prop::collection::vec(0.8..1.2f64, 121));
+    return (levels, coi).prop_map(|((issue_age, maturity_age, load, fee, unit_load, corridor, naar_rate, interest), (coi_base, coi_growth, jitter))| {
+        let years = (maturity_age - issue_age) as usize;
+        let level = |rate: f64| vec![rate; years];
+        let coi_rates = (0..years)
+            .map(|t| (coi_base * (coi_growth * (f64::from(issue_age) + t as f64 - 20.0)).exp() * jitter[t]).min(400.0))
+            .collect();
+        let rates = RateSet {
+            maturity_age: AttainedAge::new(maturity_age).unwrap(),
+            premium_loads: level(load),
+            policy_fees: level(fee),
+            unit_loads: level(unit_load),
+            corr_facts: level(corridor),
+            naar_discs: level((1.0 + naar_rate).powf(-1.0 / 12.0)),
+            coi_rates,
+            interest_rates: level((1.0 + interest).powf(1.0 / 12.0) - 1.0),
+            surrender_charges: level(0.0),
+        };
+        (rates, IssueAge::new(issue_age).unwrap())
+    });
+}
+
+// the options lockstep covers, whatever the policy's death benefit option, step, premium mode and pattern
+fn lockstep_config() -> impl Strategy<Value = ProjectionConfig> {
+    let options = prop_oneof![Just(DeathBenefitOption::Level), Just(DeathBenefitOption::Increasing), Just(DeathBenefitOption::ReturnOfPremium)];
+    let timesteps = prop_oneof![Just(Timestep::Monthly), Just(Timestep::Annual)];
+    let modes = prop_oneof![Just(PremiumMode::Annual), Just(PremiumMode::Quarterly), Just(PremiumMode::Monthly)];
+    let patterns = prop_oneof![Just(PremiumPattern::Level), Just(PremiumPattern::SinglePay), Just(PremiumPattern::NPay(10))];
+    return (options, timesteps, modes, patterns, any::<bool>()).prop_map(|(death_benefit_option, timestep, premium_mode, premium_pattern, amounts)| ProjectionConfig {
+        death_benefit_option,
+        timestep,
+        premium_mode,
+        premium_pattern,
+        rounding: RoundingPolicy { amounts, ..Default::default() },
+        ..Default::default()
+    });
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn higher_premium_gives_higher_value_at_maturity((rates, issue_age) in rate_set(), face_amount in 10000.0..1000000.0, premium in 0.0..50000.0, extra in 1.0..5000.0) {
+        let low = at_issue_projection(&rates, issue_age, face_amount, premium).unwrap();
+        let high = at_issue_projection(&rates, issue_age, face_amount, premium + extra).unwrap();
+        prop_assert!(high > low, "premium {} gives {}, {} more gives {}", premium, low, extra, high);
+    }
+
+    #[test]
+    fn solved_premium_endows_and_a_cent_less_does_not((rates, issue_age) in rate_set(), face_amount in 10000.0..1000000.0) {
+        let premium = solve_for_premium(&rates, issue_age, face_amount).unwrap().premium;
+        let endowed = at_issue_projection(&rates, issue_age, face_amount, premium).unwrap();
+        prop_assert!(endowed > 0.0, "solved premium {} leaves {} at maturity", premium, endowed);
+        let short = at_issue_projection(&rates, issue_age, face_amount, premium - 0.01).unwrap();
+        prop_assert!(short <= 0.0, "premium {} a cent under the solve still leaves {} at maturity", premium - 0.01, short);
+    }
+
+    #[test]
+    fn annual_step_solve_endows_to_the_cent((rates, issue_age) in rate_set(), face_amount in 10000.0..1000000.0) {
+        let config = ProjectionConfig { timestep: Timestep::Annual, ..Default::default() };
+        let premium = solve_for_premium_with(&rates, issue_age, face_amount, &config).unwrap().premium;
+        let value = |premium| at_issue_projection_with(&rates, issue_age, face_amount, premium, &config).unwrap();
+        prop_assert!(value(premium) > 0.0);
+        prop_assert!(value(premium - 0.01) <= 0.0);
+    }
+
+    #[test]
+    fn lockstep_solve_matches_solving_one_at_a_time((rates, issue_age) in rate_set(), config in lockstep_config(), lanes in prop::collection::vec((10000.0..1000000.0f64, 0.5..1.5f64), 1..=LANES)) {
+        // each lane on its own coi scale so no two lanes are the same policy
+        let lane_rates: Vec<RateSet> = lanes.iter().map(|(_, scale)| RateSet { coi_rates: rates.coi_rates.iter().map(|rate| rate * scale).collect(), ..rates.clone() }).collect();
+        let face_amounts: Vec<f64> = lanes.iter().map(|(face, _)| *face).collect();
+        let options = SolverOptions::new(Method::Bisection, 0.005);
+        let together = Lanes::new(&lane_rates.iter().collect::<Vec<&RateSet>>(), &face_amounts, issue_age, &config).unwrap();
+        let solves = lanes::solve_for_premiums(&together, &options).unwrap();
+        for ((rates, face_amount), solve) in lane_rates.iter().zip(&face_amounts).zip(solves) {
+            let alone = solve_for_premium_using(rates, issue_age, *face_amount, &config, SolveTarget::EndowAtMaturity, &options).map_err(|e| e.to_string());
+            match (alone, solve) {
+                (Ok(alone), Ok(solve)) => {
+                    prop_assert_eq!(alone.premium.to_bits(), solve.premium.to_bits(), "premium {} alone, {} in lockstep", alone.premium, solve.premium);
+                    prop_assert_eq!((alone.iterations, alone.evaluations, alone.converged), (solve.iterations, solve.evaluations, solve.converged));
+                    prop_assert_eq!(alone.bracket, solve.bracket);
+                }
+                (Err(alone), Err(solve)) => prop_assert_eq!(alone, solve),
+                (alone, solve) => prop_assert!(false, "alone {:?}, in lockstep {:?}", alone, solve),
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+/*
+Tests of variable universal life fund accounts on a hand built rate set
+
+A fund with no expenses earning the interest rate table's rate has to project as the fixed account does, an m&e
+charge has to come off the fund on top of its return, and a falling fund has to take the account value down
+with it rather than being floored like a credited rate.
+
+*/
+mod common;
+
+use common::ISSUE_AGE;
+use illus1::{
+    age::IssueAge, at_issue_projection_detail_with, indexed::{Account, Crediting},
+    variable::{ChargeFrequency, Fund, MortalityExpense}, ProjectionConfig, RateSet
+};
+
+const INTEREST: f64 = 0.04;
+
+fn rates() -> RateSet {
+    return common::rates(0.05, 60.0, 1.0, INTEREST);
+}
+
+fn fund_config(returns: Vec<f64>, mortality_expense: MortalityExpense) -> ProjectionConfig {
+    let fund = Account { name: "Equity".to_string(), allocation: 1.0, crediting: Crediting::Variable(Fund { returns, expense_ratio: 0.0 }) };
+    return ProjectionConfig { accounts: vec![fund], mortality_expense, ..Default::default() };
+}
+
+fn end_values(config: &ProjectionConfig) -> Vec<f64> {
+    let issue_age = IssueAge::new(ISSUE_AGE).unwrap();
+    let months = at_issue_projection_detail_with(&rates(), issue_age, 100000.0, 3000.0, config).unwrap();
+    return months.iter().map(|month| month.value_end).collect();
+}
+
+#[test]
+fn fund_at_the_fixed_rate_projects_as_the_fixed_account() {
+    let fixed = end_values(&ProjectionConfig::default());
+    let fund = end_values(&fund_config(vec![INTEREST], MortalityExpense::default()));
+    for (month, (fund, fixed)) in fund.iter().zip(&fixed).enumerate().take(240) {
+        assert!((fund - fixed).abs() <= 1e-6 * fixed.abs().max(1.0), "month {}: fund {}, fixed {}", month + 1, fund, fixed);
+    }
+}
+
+#[test]
+fn mortality_expense_and_falling_returns_reduce_the_fund() {
+    let without = end_values(&fund_config(vec![INTEREST], MortalityExpense::default()));
+    for frequency in [ChargeFrequency::Daily, ChargeFrequency::Monthly] {
+        let with = end_values(&fund_config(vec![INTEREST], MortalityExpense { rate: 0.01, frequency }));
+        assert!(with[119] < without[119], "{:?}: {} is not below {}", frequency, with[119], without[119]);
+    }
+    let issue_age = IssueAge::new(ISSUE_AGE).unwrap();
+    let months = at_issue_projection_detail_with(&rates(), issue_age, 100000.0, 3000.0, &fund_config(vec![-0.2], MortalityExpense::default())).unwrap();
+    assert!(months[1].interest < 0.0);
+    assert!(months[1].value_end < months[1].value_start);
+    assert_eq!(months[1].variable_account_value, months[1].value_end);
+}